@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Tracking setup for the generated app, loaded from analytics.ron
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnalyticsConfig {
+    pub provider: AnalyticsProvider,
+    /// Vite modes (`import.meta.env.MODE`) tracking runs in
+    #[serde(default = "default_environments")]
+    pub environments: Vec<String>,
+}
+
+fn default_environments() -> Vec<String> {
+    vec!["production".to_string()]
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum AnalyticsProvider {
+    Ga4 { measurement_id: String },
+    Plausible { domain: String },
+    PostHog {
+        api_key: String,
+        #[serde(default = "default_posthog_host")]
+        host: String,
+    },
+}
+
+fn default_posthog_host() -> String {
+    "https://us.i.posthog.com".to_string()
+}
+
+impl AnalyticsConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: AnalyticsConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+
+    /// npm packages src/analytics.js imports from
+    pub fn npm_packages(&self) -> BTreeSet<String> {
+        match self.provider {
+            AnalyticsProvider::PostHog { .. } => BTreeSet::from(["posthog-js".to_string()]),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    /// src/analytics.js: `initAnalytics()` for the entry and `trackPageView(path)` for the router
+    pub fn analytics_js(&self) -> String {
+        let environments: Vec<String> = self.environments.iter().map(|e| format!("'{}'", e)).collect();
+        let (import, init, track) = match &self.provider {
+            AnalyticsProvider::Ga4 { measurement_id } => (
+                String::new(),
+                format!(
+                    r#"  const script = document.createElement('script');
+  script.async = true;
+  script.src = 'https://www.googletagmanager.com/gtag/js?id={id}';
+  document.head.appendChild(script);
+  window.dataLayer = window.dataLayer || [];
+  window.gtag = function gtag() {{
+    window.dataLayer.push(arguments);
+  }};
+  window.gtag('js', new Date());
+  window.gtag('config', '{id}', {{ send_page_view: false }});
+"#,
+                    id = measurement_id
+                ),
+                "  window.gtag('event', 'page_view', { page_path: path });\n".to_string(),
+            ),
+            AnalyticsProvider::Plausible { domain } => (
+                String::new(),
+                format!(
+                    r#"  const script = document.createElement('script');
+  script.defer = true;
+  script.dataset.domain = '{}';
+  script.src = 'https://plausible.io/js/script.manual.js';
+  document.head.appendChild(script);
+  window.plausible =
+    window.plausible ||
+    function plausible() {{
+      (window.plausible.q = window.plausible.q || []).push(arguments);
+    }};
+"#,
+                    domain
+                ),
+                "  window.plausible('pageview', { u: window.location.origin + path });\n".to_string(),
+            ),
+            AnalyticsProvider::PostHog { api_key, host } => (
+                "import posthog from 'posthog-js';\n\n".to_string(),
+                format!(
+                    "  posthog.init('{}', {{ api_host: '{}', capture_pageview: false }});\n",
+                    api_key, host
+                ),
+                "  posthog.capture('$pageview', { $current_url: window.location.origin + path });\n".to_string(),
+            ),
+        };
+
+        format!(
+            r#"{}const ENABLED = [{}].includes(import.meta.env.MODE);
+
+export function initAnalytics() {{
+  if (!ENABLED) {{
+    return;
+  }}
+{}}}
+
+export function trackPageView(path) {{
+  if (!ENABLED) {{
+    return;
+  }}
+{}}}
+"#,
+            import,
+            environments.join(", "),
+            init,
+            track
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_only_in_the_configured_modes() {
+        let config: AnalyticsConfig = ron::from_str(r#"(provider: Ga4(measurement_id: "G-123"), environments: ["production", "staging"])"#).unwrap();
+        let js = config.analytics_js();
+        assert!(js.starts_with("const ENABLED = ['production', 'staging'].includes(import.meta.env.MODE);"), "{}", js);
+        assert!(js.contains("gtag/js?id=G-123"), "{}", js);
+        assert!(config.npm_packages().is_empty());
+    }
+
+    #[test]
+    fn imports_posthog_from_npm() {
+        let config: AnalyticsConfig = ron::from_str(r#"(provider: PostHog(api_key: "phc_abc"))"#).unwrap();
+        assert_eq!(config.environments, ["production"]);
+        assert_eq!(config.npm_packages(), BTreeSet::from(["posthog-js".to_string()]));
+        let js = config.analytics_js();
+        assert!(js.starts_with("import posthog from 'posthog-js';"), "{}", js);
+        assert!(js.contains("posthog.init('phc_abc', { api_host: 'https://us.i.posthog.com'"), "{}", js);
+    }
+}
@@ -0,0 +1,115 @@
+//! A proto project bundled into one file, so a prototype can be shared between machines or
+//! attached to a ticket and reproduced exactly; see ProtoProject::export
+
+use crate::manifest::BuildManifest;
+use crate::output::GeneratedFile;
+use crate::project::ProtoProject;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+/// The proto files, def files and local assets of a project, with a manifest of their hashes
+/// that import checks before writing anything
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProjectArchive {
+    pub manifest: BuildManifest,
+    /// Path relative to the project root (forward slashes) -> base64 contents
+    pub files: BTreeMap<String, String>,
+}
+
+impl ProjectArchive {
+    /// Bundle `files`, each a path relative to the project root
+    pub fn new(files: &[GeneratedFile]) -> Self {
+        Self {
+            manifest: BuildManifest::new(files),
+            files: files
+                .iter()
+                .map(|file| (file.path.to_string_lossy().replace('\\', "/"), BASE64_STANDARD.encode(&file.contents)))
+                .collect(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default() + "\n"
+    }
+
+    /// The archive as a file to write, e.g. `prototype.protoarchive.json`
+    pub fn file(&self, path: impl AsRef<Path>) -> GeneratedFile {
+        GeneratedFile::text(path.as_ref(), self.to_json())
+    }
+
+    /// The bundled files, checked against the manifest. Fails when a file is missing from
+    /// either side, doesn't match its hash or has a path that would land outside the root.
+    pub fn contents(&self) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        if let Some(path) = self.manifest.files.keys().find(|path| !self.files.contains_key(*path)) {
+            return Err(format!("archive: `{}` is in the manifest but not bundled", path).into());
+        }
+        let mut files = Vec::new();
+        for (path, encoded) in &self.files {
+            let Some(entry) = self.manifest.files.get(path) else {
+                return Err(format!("archive: `{}` is bundled but not in the manifest", path).into());
+            };
+            if !Path::new(path).components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(format!("archive: `{}` is not a path inside the project", path).into());
+            }
+            let file = GeneratedFile { path: path.into(), contents: BASE64_STANDARD.decode(encoded)? };
+            if BuildManifest::new(std::slice::from_ref(&file)).files.get(path) != Some(entry) {
+                return Err(format!("archive: `{}` doesn't match its manifest hash", path).into());
+            }
+            files.push(file);
+        }
+        Ok(files)
+    }
+
+    /// Write the bundled files under `root` and load the project from there. Nothing is written
+    /// unless every file checks out.
+    pub fn import<P: AsRef<Path>>(&self, root: P) -> Result<ProtoProject, Box<dyn std::error::Error>> {
+        for file in self.contents()? {
+            file.write_to(&root)?;
+        }
+        ProtoProject::load(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> ProjectArchive {
+        ProjectArchive::new(&[
+            GeneratedFile::text("index.ron", "(layouts: [], routes: [])"),
+            GeneratedFile { path: "public/logo.png".into(), contents: vec![0x89, b'P', b'N', b'G', 0] },
+        ])
+    }
+
+    #[test]
+    fn round_trips_files_through_json() {
+        let archive = archive();
+        let read: ProjectArchive = serde_json::from_str(&archive.to_json()).unwrap();
+        assert_eq!(read, archive);
+        let contents = read.contents().unwrap();
+        assert_eq!(contents[1].path, Path::new("public/logo.png"));
+        assert_eq!(contents[1].contents, vec![0x89, b'P', b'N', b'G', 0]);
+    }
+
+    #[test]
+    fn rejects_tampered_and_escaping_files() {
+        let mut tampered = archive();
+        tampered.files.insert("index.ron".into(), BASE64_STANDARD.encode("(layouts: [], routes: [], apps: [])"));
+        assert!(tampered.contents().unwrap_err().to_string().contains("doesn't match its manifest hash"));
+
+        let escaping = ProjectArchive::new(&[GeneratedFile::text("../outside.ron", "()")]);
+        assert!(escaping.contents().unwrap_err().to_string().contains("not a path inside the project"));
+
+        let mut unlisted = archive();
+        unlisted.manifest.files.remove("index.ron");
+        assert!(unlisted.contents().unwrap_err().to_string().contains("not in the manifest"));
+    }
+}
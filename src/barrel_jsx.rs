@@ -0,0 +1,157 @@
+use crate::output::GeneratedFile;
+use crate::names::component_ident;
+use crate::ProtoIndex;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// `index.js` re-exporting every generated module in a directory, sorted by export name
+#[derive(Debug, Clone)]
+pub struct BarrelJsx {
+    // Directory the barrel is written to, relative to src/ like Route and Layout paths
+    pub dir: String,
+    pub entries: Vec<BarrelEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarrelEntry {
+    pub name: String,
+    // Module path relative to src/, with extension
+    pub path: String,
+}
+
+impl BarrelJsx {
+    /// Barrels for the views (routes), layouts and components (partials) directories.
+    /// Each barrel goes in the deepest directory shared by its modules; groups sharing a
+    /// directory share one barrel, so no `index.js` overwrites another.
+    pub fn from_proto_index(index: &ProtoIndex) -> Vec<BarrelJsx> {
+        let views = index.routes.iter().map(|r| (component_ident(&r.name), r.path.clone()));
+        let layouts = index.layouts.iter().map(|l| (format!("{}Layout", component_ident(&l.name)), l.path.clone()));
+        let components = index.partials.iter().map(|p| (component_ident(&p.name), p.path.clone()));
+
+        let mut barrels: Vec<BarrelJsx> = Vec::new();
+        for barrel in [
+            Self::from_entries(views.collect()),
+            Self::from_entries(layouts.collect()),
+            Self::from_entries(components.collect()),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            match barrels.iter_mut().find(|other| other.dir == barrel.dir) {
+                Some(other) => {
+                    let pairs = other.entries.drain(..).chain(barrel.entries).map(|e| (e.name, e.path)).collect();
+                    *other = Self::from_entries(pairs).expect("merged barrels have entries");
+                }
+                None => barrels.push(barrel),
+            }
+        }
+        barrels
+    }
+
+    /// Build a barrel from (export name, path) pairs. Paths listed twice are exported once,
+    /// and clashing names get a numeric suffix. None when there is nothing to export.
+    pub fn from_entries(pairs: Vec<(String, String)>) -> Option<BarrelJsx> {
+        let mut seen_paths = BTreeSet::new();
+        let mut used_names = BTreeSet::new();
+        let mut entries = Vec::new();
+        for (name, path) in pairs {
+            let path = path.trim_start_matches("./").to_string();
+            if !seen_paths.insert(path.clone()) {
+                continue;
+            }
+            let mut unique = name.clone();
+            let mut counter = 2;
+            while used_names.contains(&unique) {
+                unique = format!("{}{}", name, counter);
+                counter += 1;
+            }
+            used_names.insert(unique.clone());
+            entries.push(BarrelEntry { name: unique, path });
+        }
+        if entries.is_empty() {
+            return None;
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let dir = common_dir(entries.iter().map(|e| e.path.as_str()));
+        Some(BarrelJsx { dir, entries })
+    }
+
+    /// The barrel as a file under src/
+    pub fn file(&self) -> GeneratedFile {
+        let path = if self.dir.is_empty() {
+            "src/index.js".to_string()
+        } else {
+            format!("src/{}/index.js", self.dir)
+        };
+        GeneratedFile::text(path, self.to_string())
+    }
+}
+
+impl fmt::Display for BarrelJsx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let relative = entry
+                .path
+                .strip_prefix(&self.dir)
+                .unwrap_or(&entry.path)
+                .trim_start_matches('/');
+            writeln!(f, "export {{ default as {} }} from './{}';", entry.name, strip_extension(relative))?;
+        }
+        Ok(())
+    }
+}
+
+/// Deepest directory containing every path: `views/a/Home.jsx` + `views/About.jsx` -> `views`
+fn common_dir<'a>(paths: impl Iterator<Item = &'a str>) -> String {
+    let mut common: Option<Vec<&str>> = None;
+    for path in paths {
+        let mut segments: Vec<&str> = path.split('/').collect();
+        segments.pop();
+        common = Some(match common {
+            None => segments,
+            Some(prev) => prev.into_iter().zip(segments).take_while(|(a, b)| a == b).map(|(a, _)| a).collect(),
+        });
+    }
+    common.unwrap_or_default().join("/")
+}
+
+fn strip_extension(path: &str) -> &str {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') && matches!(ext, "js" | "jsx" | "ts" | "tsx") => stem,
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries.iter().map(|(name, path)| (name.to_string(), path.to_string())).collect()
+    }
+
+    #[test]
+    fn exports_from_the_deepest_shared_directory() {
+        let barrel = BarrelJsx::from_entries(pairs(&[
+            ("Home", "./views/Home.jsx"),
+            ("Post", "views/blog/Post.tsx"),
+            ("Home", "views/Home.jsx"),
+        ]))
+        .unwrap();
+        assert_eq!(barrel.file().path, std::path::Path::new("src/views/index.js"));
+        assert_eq!(
+            barrel.to_string(),
+            "export { default as Home } from './Home';\nexport { default as Post } from './blog/Post';\n"
+        );
+    }
+
+    #[test]
+    fn suffixes_clashing_export_names() {
+        let barrel = BarrelJsx::from_entries(pairs(&[("Card", "a/Card.jsx"), ("Card", "b/Card.jsx")])).unwrap();
+        let names: Vec<&str> = barrel.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Card", "Card2"]);
+        assert_eq!(barrel.dir, "");
+        assert!(BarrelJsx::from_entries(Vec::new()).is_none());
+    }
+}
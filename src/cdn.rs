@@ -0,0 +1,152 @@
+use crate::diagnostics::Diagnostic;
+use crate::output::GeneratedFile;
+use crate::paths::{is_remote_path, normalize_path};
+use crate::view_proto::{AliasDefs, AssetDefs, AssetKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where local image assets are served from once uploaded to a CDN, per build environment,
+/// loaded from cdn.ron
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CdnConfig {
+    /// Base URL for environments without their own; assets are bundled with the app when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Environment name ("staging", "production") -> base URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, String>,
+}
+
+impl CdnConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: CdnConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+
+    pub fn base_url(&self, environment: &str) -> Option<&str> {
+        self.environments.get(environment).or(self.base_url.as_ref()).map(String::as_str)
+    }
+
+    /// Base URLs that aren't absolute http(s) URLs
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let urls = self
+            .base_url
+            .iter()
+            .map(|url| ("cdn".to_string(), url))
+            .chain(self.environments.iter().map(|(env, url)| (format!("cdn > {}", env), url)));
+        for (path, url) in urls {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                diagnostics.push(Diagnostic::error(&path, format!("base URL `{}` must start with http:// or https://", url)));
+            }
+        }
+        diagnostics
+    }
+
+    /// The asset defs for one environment, with local image paths replaced by their CDN URLs
+    /// so views reference them as external URLs instead of importing the files. Unchanged when
+    /// the environment has no base URL.
+    pub fn rewrite(&self, assets: &AssetDefs, aliases: &AliasDefs, environment: &str) -> AssetDefs {
+        let mut rewritten = assets.clone();
+        let Some(base_url) = self.base_url(environment) else {
+            return rewritten;
+        };
+        for asset in &mut rewritten.assets {
+            if let (AssetKind::Image, Some(path)) = (&asset.kind, &mut asset.path)
+                && !is_remote_path(path)
+                && let Some(cdn_path) = cdn_path(aliases, path)
+            {
+                *path = format!("{}/{}", base_url.trim_end_matches('/'), cdn_path);
+            }
+        }
+        rewritten
+    }
+
+    /// Copies of the local images under `cdn/`, laid out as `rewrite`'s URLs expect, for
+    /// uploading. Files are found with AliasDefs::asset_file from `project_root`; paths that
+    /// leave the project are an error.
+    pub fn files<P: AsRef<Path>>(
+        &self,
+        assets: &AssetDefs,
+        aliases: &AliasDefs,
+        project_root: P,
+    ) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        for asset in &assets.assets {
+            if let (AssetKind::Image, Some(path)) = (&asset.kind, &asset.path)
+                && !is_remote_path(path)
+            {
+                let Some(cdn_path) = cdn_path(aliases, path) else {
+                    return Err(format!("asset `{}` ({}) is outside the project", asset.name, path).into());
+                };
+                let source = project_root.as_ref().join(aliases.asset_file(path));
+                let contents = fs::read(&source).map_err(|e| format!("asset `{}` ({}): {}", asset.name, source.display(), e))?;
+                files.push(GeneratedFile {
+                    path: PathBuf::from("cdn").join(cdn_path),
+                    contents,
+                });
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Path of a local asset under the CDN base URL: its file's path from the project root.
+/// None when the file is outside the project, as it would land outside `cdn/`.
+fn cdn_path(aliases: &AliasDefs, path: &str) -> Option<String> {
+    let file = normalize_path(&aliases.asset_file(path).to_string_lossy());
+    let file = file.trim_start_matches("./");
+    if file.is_empty() || file.starts_with('/') || file == ".." || file.starts_with("../") {
+        return None;
+    }
+    Some(file.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets() -> AssetDefs {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(
+            r#"(assets: [
+                (name: "logo", kind: Image, path: "/logo.svg"),
+                (name: "hero", kind: Image, path: "@/assets/hero.png"),
+                (name: "remote", kind: Image, path: "https://example.com/a.png"),
+            ])"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rewrites_local_images_to_the_environment_base_url() {
+        let config = CdnConfig {
+            base_url: Some("https://cdn.example.com/".to_string()),
+            environments: BTreeMap::from([("staging".to_string(), "https://staging.cdn.example.com".to_string())]),
+        };
+        let rewritten = config.rewrite(&assets(), &AliasDefs::project_default(), "production");
+        let paths: Vec<&str> = rewritten.assets.iter().filter_map(|a| a.path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            ["https://cdn.example.com/public/logo.svg", "https://cdn.example.com/src/assets/hero.png", "https://example.com/a.png"]
+        );
+        assert_eq!(config.base_url("staging"), Some("https://staging.cdn.example.com"));
+        assert_eq!(CdnConfig::default().rewrite(&assets(), &AliasDefs::project_default(), "production").assets[0].path.as_deref(), Some("/logo.svg"));
+    }
+
+    #[test]
+    fn rejects_base_urls_that_arent_http() {
+        let config = CdnConfig {
+            base_url: Some("cdn.example.com".to_string()),
+            environments: BTreeMap::from([("staging".to_string(), "https://ok.example.com".to_string())]),
+        };
+        let messages: Vec<String> = config.validate().into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["base URL `cdn.example.com` must start with http:// or https://"]);
+    }
+}
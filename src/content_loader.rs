@@ -0,0 +1,137 @@
+use crate::content_types::{content_json, sorted};
+use crate::output::GeneratedFile;
+use crate::view_jsx::js_string;
+use crate::view_proto::ContentDefs;
+use serde_json::{Map, Value};
+
+/// The content as a JSON data file plus a `useContent()` hook that fetches it, for views
+/// rendered with ViewJsx::with_runtime_content. Copy edits then only touch the data file.
+pub struct ContentLoader<'a> {
+    pub content: &'a ContentDefs,
+    /// Where the app fetches the content from; a root path is served from `public/`
+    pub url: String,
+}
+
+impl<'a> ContentLoader<'a> {
+    pub fn new(content: &'a ContentDefs) -> Self {
+        Self { content, url: "/content.json".to_string() }
+    }
+
+    /// Fetch the content from somewhere else, e.g. a CMS export; no data file is generated
+    /// for URLs outside the app
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Every content key with its value, keys sorted
+    pub fn json(&self) -> String {
+        let data: Map<String, Value> = sorted(&self.content.content)
+            .into_iter()
+            .map(|(key, value)| (key.clone(), content_json(value)))
+            .collect();
+        serde_json::to_string_pretty(&data).unwrap_or_else(|_| "{}".to_string()) + "\n"
+    }
+
+    /// src/content/useContent.js: loads the content once and shares it between components.
+    /// Components get `{}` until it arrives, so content reads render nothing meanwhile.
+    pub fn hook_js(&self) -> String {
+        format!(
+            r#"// Generated from the content defs; edit those instead
+import {{ useEffect, useState }} from 'react';
+
+const CONTENT_URL = {};
+
+let loaded = null;
+let pending = null;
+
+function loadContent() {{
+  if (!pending) {{
+    pending = fetch(CONTENT_URL)
+      .then((response) => {{
+        if (!response.ok) {{
+          throw new Error(`Failed to load ${{CONTENT_URL}}: ${{response.status}}`);
+        }}
+        return response.json();
+      }})
+      .then((content) => {{
+        loaded = content;
+        return content;
+      }});
+  }}
+  return pending;
+}}
+
+export function useContent() {{
+  const [content, setContent] = useState(() => loaded ?? {{}});
+
+  useEffect(() => {{
+    if (loaded) {{
+      return undefined;
+    }}
+    let active = true;
+    loadContent()
+      .then((next) => {{
+        if (active) {{
+          setContent(next);
+        }}
+      }})
+      .catch((error) => {{
+        // Let a later mount try again
+        pending = null;
+        console.error(error);
+      }});
+    return () => {{
+      active = false;
+    }};
+  }}, []);
+
+  return content;
+}}
+"#,
+            js_string(&self.url)
+        )
+    }
+
+    /// The data file under `public/` for root URLs, and src/content/useContent.js
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        let mut files = Vec::new();
+        if let Some(path) = self.url.strip_prefix('/')
+            && !path.starts_with('/')
+            && !path.is_empty()
+        {
+            files.push(GeneratedFile::text(format!("public/{}", path), self.json()));
+        }
+        files.push(GeneratedFile::text("src/content/useContent.js", self.hook_js()));
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn content() -> ContentDefs {
+        ron::from_str(r#"(content: {"title": Str("Hello"), "tags": List([Str("a"), Str("b")])})"#).unwrap()
+    }
+
+    #[test]
+    fn writes_the_content_as_sorted_json() {
+        let content = content();
+        assert_eq!(ContentLoader::new(&content).json(), "{\n  \"tags\": [\n    \"a\",\n    \"b\"\n  ],\n  \"title\": \"Hello\"\n}\n");
+    }
+
+    #[test]
+    fn generates_a_data_file_only_for_urls_inside_the_app() {
+        let content = content();
+        let paths = |loader: ContentLoader| loader.files().into_iter().map(|file| file.path).collect::<Vec<_>>();
+        assert_eq!(
+            paths(ContentLoader::new(&content).with_url("/data/content.json")),
+            [Path::new("public/data/content.json"), Path::new("src/content/useContent.js")]
+        );
+        let remote = ContentLoader::new(&content).with_url("https://cms.example.com/content.json");
+        assert!(remote.hook_js().contains("const CONTENT_URL = \"https://cms.example.com/content.json\";"));
+        assert_eq!(paths(remote), [Path::new("src/content/useContent.js")]);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::names::{component_ident, NameSet};
+use crate::output::GeneratedFile;
+use crate::view_jsx::js_string;
+use crate::view_proto::{ContentDefs, ContentValue, FieldKind, MAX_RELATION_DEPTH};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// TypeScript interfaces for content collections with a schema, plus a typed module exporting
+/// the content, so hand-written code reading generated content is checked by tsc
+pub struct ContentTypes<'a> {
+    pub content: &'a ContentDefs,
+}
+
+// A property of a collection's interface
+struct Field<'a> {
+    name: String,
+    // None for derived fields, which are always strings
+    kind: Option<&'a FieldKind>,
+    optional: bool,
+}
+
+impl<'a> ContentTypes<'a> {
+    pub fn new(content: &'a ContentDefs) -> Self {
+        Self { content }
+    }
+
+    /// Interface name of each collection with a schema, singular: `blogPosts` -> `BlogPost`
+    pub fn type_names(&self) -> BTreeMap<&str, String> {
+        let mut names = NameSet::new();
+        names.reserve("Content");
+        for name in ["RichBlock", "RichSpan", "MarkDef"] {
+            names.reserve(name);
+        }
+        let mut collections: Vec<&String> = self
+            .content
+            .collections
+            .iter()
+            .filter(|(_, config)| !config.schema.is_empty())
+            .map(|(name, _)| name)
+            .collect();
+        collections.sort();
+        collections
+            .into_iter()
+            .map(|name| (name.as_str(), names.unique(&component_ident(&singular(name)))))
+            .collect()
+    }
+
+    /// Schema fields in name order, then derived fields. Each Ref field is followed by the
+    /// `field.other_field` values resolve_relations copies from the referenced record, `depth`
+    /// levels deep like resolve_relations.
+    fn fields(&self, collection: &str, depth: usize) -> Vec<Field<'a>> {
+        let Some(config) = self.content.collections.get(collection) else {
+            return Vec::new();
+        };
+        let mut fields = Vec::new();
+        for (name, schema) in &config.schema {
+            fields.push(Field { name: name.clone(), kind: Some(&schema.kind), optional: schema.optional });
+            if let FieldKind::Ref(target) = &schema.kind
+                && depth > 0
+            {
+                // A dangling or missing key leaves these unset
+                fields.extend(self.fields(target, depth - 1).into_iter().map(|f| Field {
+                    name: format!("{}.{}", name, f.name),
+                    kind: f.kind,
+                    optional: true,
+                }));
+            }
+        }
+        for (name, derived) in &config.derived {
+            if !config.schema.contains_key(name) {
+                let source_required = config.schema.get(derived.source()).is_some_and(|schema| !schema.optional);
+                fields.push(Field { name: name.clone(), kind: None, optional: !source_required });
+            }
+        }
+        fields
+    }
+
+    /// src/content/types.ts
+    pub fn types_ts(&self) -> String {
+        let type_names = self.type_names();
+        let mut output = String::from("// Generated from the content defs; edit those instead\n\n");
+        if self.content.content.values().any(|value| matches!(value, ContentValue::RichText(_))) {
+            output.push_str(RICH_TEXT_TYPES);
+        }
+        for (collection, type_name) in &type_names {
+            output.push_str(&format!("export interface {} {{\n", type_name));
+            for field in self.fields(collection, MAX_RELATION_DEPTH) {
+                let ty = match field.kind {
+                    Some(kind) => ts_type(kind),
+                    None => "string".to_string(),
+                };
+                let optional = if field.optional { "?" } else { "" };
+                output.push_str(&format!("  {}{}: {};\n", property_name(&field.name), optional, ty));
+            }
+            output.push_str("}\n\n");
+        }
+
+        output.push_str("export interface Content {\n");
+        for (key, value) in sorted(&self.content.content) {
+            let ty = match (value, type_names.get(key.as_str())) {
+                (ContentValue::List(_), Some(type_name)) => format!("{}[]", type_name),
+                (value, _) => value_type(value),
+            };
+            output.push_str(&format!("  {}: {};\n", property_name(key), ty));
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    /// src/content/index.ts: the content, and a lookup by key for collections whose schema has their key field
+    pub fn module_ts(&self) -> String {
+        let type_names = self.type_names();
+        let finders: Vec<(&str, &String, &str)> = type_names
+            .iter()
+            .filter_map(|(collection, type_name)| {
+                let key = self.content.collections[*collection].key_field();
+                self.content.collections[*collection].schema.contains_key(key).then_some((*collection, type_name, key))
+            })
+            .collect();
+
+        let mut imports: Vec<&str> = finders.iter().map(|(_, type_name, _)| type_name.as_str()).collect();
+        imports.push("Content");
+        imports.sort();
+        let mut output = format!(
+            "// Generated from the content defs; edit those instead\n\nimport type {{ {} }} from './types';\n\n",
+            imports.join(", ")
+        );
+
+        let data: Map<String, Value> = sorted(&self.content.content)
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match (value, type_names.contains_key(key.as_str())) {
+                    (ContentValue::List(list), true) => self.records_json(key, list),
+                    (value, _) => content_json(value),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&data).unwrap_or_else(|_| "{}".to_string());
+        output.push_str(&format!("export const content: Content = {};\n", json));
+
+        for (collection, type_name, key) in finders {
+            output.push_str(&format!(
+                "\n/** The `{}` record whose `{}` is `key` */\nexport function find{}(key: string): {} | undefined {{\n  return content{}.find((record) => record{} === key);\n}}\n",
+                collection,
+                key,
+                type_name,
+                type_name,
+                property_access(collection),
+                property_access(key)
+            ));
+        }
+        output
+    }
+
+    /// Records with only their declared fields, numbers and booleans converted to match the types
+    fn records_json(&self, collection: &str, list: &[ContentValue]) -> Value {
+        let fields = self.fields(collection, MAX_RELATION_DEPTH);
+        let records = list
+            .iter()
+            .filter_map(|item| match item {
+                ContentValue::Record(record) => Some(record),
+                _ => None,
+            })
+            .map(|record| {
+                let object: Map<String, Value> = fields
+                    .iter()
+                    .filter_map(|field| {
+                        let value = record.get(&field.name)?;
+                        Some((field.name.clone(), field_json(field.kind, value)))
+                    })
+                    .collect();
+                Value::Object(object)
+            })
+            .collect();
+        Value::Array(records)
+    }
+
+    /// src/content/types.ts and src/content/index.ts
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        vec![
+            GeneratedFile::text("src/content/types.ts", self.types_ts()),
+            GeneratedFile::text("src/content/index.ts", self.module_ts()),
+        ]
+    }
+}
+
+// Rich text as content_json serializes RichBlock
+const RICH_TEXT_TYPES: &str = r#"export interface MarkDef {
+  key: string;
+  href: string;
+  blank?: boolean;
+}
+
+export interface RichSpan {
+  text: string;
+  marks?: string[];
+}
+
+export type RichBlock =
+  | {
+      Block: {
+        style?: "Normal" | "H1" | "H2" | "H3" | "H4" | "H5" | "H6" | "Blockquote";
+        list?: "Bullet" | "Number";
+        level: number;
+        children: RichSpan[];
+        mark_defs?: MarkDef[];
+      };
+    }
+  | { Image: { asset: string; alt?: string } };
+
+"#;
+
+fn ts_type(kind: &FieldKind) -> String {
+    match kind {
+        FieldKind::Number => "number".to_string(),
+        FieldKind::Bool => "boolean".to_string(),
+        FieldKind::Enum(allowed) if !allowed.is_empty() => {
+            allowed.iter().map(|value| js_string(value)).collect::<Vec<_>>().join(" | ")
+        }
+        FieldKind::String | FieldKind::Date | FieldKind::Url | FieldKind::Enum(_) | FieldKind::Ref(_) => "string".to_string(),
+    }
+}
+
+/// Type of a content value outside a collection with a schema
+fn value_type(value: &ContentValue) -> String {
+    match value {
+        ContentValue::Str(_) => "string".to_string(),
+        ContentValue::Record(_) => "Record<string, string>".to_string(),
+        ContentValue::RichText(_) => "RichBlock[]".to_string(),
+        ContentValue::List(list) => {
+            let mut types: Vec<String> = list.iter().map(value_type).collect();
+            types.sort();
+            types.dedup();
+            match types.as_slice() {
+                [] => "Record<string, string>[]".to_string(),
+                [only] if !only.contains(' ') => format!("{}[]", only),
+                _ => format!("Array<{}>", types.join(" | ")),
+            }
+        }
+    }
+}
+
+/// A record value as JSON; values the schema doesn't accept stay strings (validate() reports them)
+fn field_json(kind: Option<&FieldKind>, value: &str) -> Value {
+    match kind {
+        Some(FieldKind::Number) => {
+            if let Ok(n) = value.parse::<i64>() {
+                return Value::from(n);
+            }
+            match value.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                Some(n) => Value::Number(n),
+                None => Value::from(value),
+            }
+        }
+        Some(FieldKind::Bool) if value == "true" || value == "false" => Value::Bool(value == "true"),
+        _ => Value::from(value),
+    }
+}
+
+/// Content as JSON: strings, string maps with keys sorted, arrays, and rich text as serialized
+pub(crate) fn content_json(value: &ContentValue) -> Value {
+    match value {
+        ContentValue::Str(s) => Value::from(s.as_str()),
+        ContentValue::Record(record) => {
+            Value::Object(sorted(record).into_iter().map(|(k, v)| (k.clone(), Value::from(v.as_str()))).collect())
+        }
+        ContentValue::List(list) => Value::Array(list.iter().map(content_json).collect()),
+        ContentValue::RichText(blocks) => serde_json::to_value(blocks).unwrap_or(Value::Null),
+    }
+}
+
+pub(crate) fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<(&String, &V)> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// `title`, or `"author.name"` when the name isn't an identifier
+fn property_name(name: &str) -> String {
+    if is_identifier(name) { name.to_string() } else { js_string(name) }
+}
+
+/// `.title`, or `["author.name"]`
+pub(crate) fn property_access(name: &str) -> String {
+    if is_identifier(name) { format!(".{}", name) } else { format!("[{}]", js_string(name)) }
+}
+
+/// `posts` -> `post`, `categories` -> `category`; names not ending in a plural `s` are kept
+fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if name.ends_with("ss") || name.ends_with("us") {
+        name.to_string()
+    } else {
+        name.strip_suffix('s').unwrap_or(name).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content() -> ContentDefs {
+        ron::from_str(
+            r#"(
+                content: {
+                    "title": Str("Hello"),
+                    "categories": List([Record({"id": "news", "featured": "true", "extra": "x"})]),
+                },
+                collections: {
+                    "categories": (schema: {"id": (), "featured": (kind: Bool), "rank": (kind: Number, optional: true)}),
+                },
+            )"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn types_collections_by_their_schema() {
+        let content = content();
+        let types = ContentTypes::new(&content);
+        assert_eq!(types.type_names().get("categories").map(String::as_str), Some("Category"));
+        let ts = types.types_ts();
+        assert!(ts.contains("export interface Category {\n  featured: boolean;\n  id: string;\n  rank?: number;\n}"), "{}", ts);
+        assert!(ts.contains("export interface Content {\n  categories: Category[];\n  title: string;\n}"), "{}", ts);
+    }
+
+    #[test]
+    fn exports_records_with_typed_values_and_a_finder() {
+        let content = content();
+        let ts = ContentTypes::new(&content).module_ts();
+        assert!(ts.contains("import type { Category, Content } from './types';"), "{}", ts);
+        assert!(ts.contains("{\n      \"featured\": true,\n      \"id\": \"news\"\n    }"), "{}", ts);
+        assert!(!ts.contains("extra"), "{}", ts);
+        assert!(ts.contains("export function findCategory(key: string): Category | undefined {\n  return content.categories.find((record) => record.id === key);"), "{}", ts);
+    }
+}
@@ -0,0 +1,410 @@
+//! Date formatting for content values, using moment-style patterns like "MMM D, YYYY"
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+// Longest tokens first so "MMMM" wins over "MMM"
+const TOKENS: [&str; 17] = [
+    "YYYY", "MMMM", "dddd", "MMM", "ddd", "YY", "MM", "DD", "HH", "hh", "mm", "ss", "M", "D", "H", "h", "A",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DateParts {
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    // Offset from UTC in minutes ("Z" is 0); None for dates and local date-times
+    offset: Option<i32>,
+}
+
+impl DateParts {
+    /// Seconds since 1970-01-01T00:00:00Z; dates and local date-times are read as UTC
+    fn timestamp(&self) -> i64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        let seconds = days * 86_400 + i64::from(self.hour) * 3_600 + i64::from(self.minute) * 60 + i64::from(self.second);
+        seconds - i64::from(self.offset.unwrap_or(0)) * 60
+    }
+
+    /// The same instant with a zero offset
+    fn to_utc(self) -> DateParts {
+        if self.offset.is_none_or(|offset| offset == 0) {
+            return self;
+        }
+        let timestamp = self.timestamp();
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(86_400));
+        let seconds = timestamp.rem_euclid(86_400) as u32;
+        DateParts {
+            year,
+            month,
+            day,
+            hour: seconds / 3_600,
+            minute: seconds % 3_600 / 60,
+            second: seconds % 60,
+            offset: Some(0),
+        }
+    }
+}
+
+/// Parse an ISO 8601 date (`2024-03-05`) or date-time (`2024-03-05T14:30:00Z`,
+/// `2024-03-05T14:30:00.000+02:00`)
+fn parse_iso(value: &str) -> Option<DateParts> {
+    let value = value.trim();
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i32 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let (mut hour, mut minute, mut second, mut offset) = (0, 0, 0, None);
+    if let Some(time) = time {
+        let (clock, zone) = match time.find(['Z', 'z', '+', '-']) {
+            Some(at) => (&time[..at], Some(&time[at..])),
+            None => (time, None),
+        };
+        let mut time_parts = clock.split(':');
+        hour = time_parts.next()?.parse().ok()?;
+        minute = time_parts.next().unwrap_or("0").parse().ok()?;
+        // Fractions of a second are dropped
+        let seconds = time_parts.next().unwrap_or("0");
+        second = seconds.split('.').next()?.parse().ok()?;
+        if time_parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        if let Some(zone) = zone {
+            offset = Some(parse_offset(zone)?);
+        }
+    }
+
+    Some(DateParts { year, month, day, hour, minute, second, offset })
+}
+
+/// A zone suffix in minutes east of UTC: "Z", "+02:00", "-0500" or "+02"
+fn parse_offset(zone: &str) -> Option<i32> {
+    if zone.eq_ignore_ascii_case("z") {
+        return Some(0);
+    }
+    let sign = match zone.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(if month <= 2 { year - 1 } else { year });
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month, day)
+}
+
+/// Whether a value parses as an ISO 8601 date or date-time
+pub(crate) fn is_iso_date(value: &str) -> bool {
+    parse_iso(value).is_some()
+}
+
+/// Seconds since the Unix epoch, for ordering dates with and without offsets; dates and
+/// date-times without an offset are read as UTC
+pub(crate) fn iso_timestamp(value: &str) -> Option<i64> {
+    parse_iso(value).map(|date| date.timestamp())
+}
+
+/// Day of week (0 = Sunday), Sakamoto's method
+fn weekday(year: i32, month: u32, day: u32) -> usize {
+    const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if month < 3 { year - 1 } else { year };
+    let w = (y + y / 4 - y / 100 + y / 400 + OFFSETS[month as usize - 1] + day as i32).rem_euclid(7);
+    w as usize
+}
+
+/// Split a pattern into tokens and literal text. Text in [brackets] is always literal.
+fn tokenize(pattern: &str) -> Vec<(bool, String)> {
+    let mut parts = Vec::new();
+    let mut rest = pattern;
+
+    while !rest.is_empty() {
+        if let Some(stripped) = rest.strip_prefix('[') {
+            let end = stripped.find(']').unwrap_or(stripped.len());
+            parts.push((false, stripped[..end].to_string()));
+            rest = stripped.get(end + 1..).unwrap_or("");
+        } else if let Some(token) = TOKENS.iter().find(|t| rest.starts_with(**t)) {
+            parts.push((true, token.to_string()));
+            rest = &rest[token.len()..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            parts.push((false, c.to_string()));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    parts
+}
+
+/// Format an ISO date string with a pattern. Returns None if the value isn't a date.
+/// Date-times are formatted in their own offset, as written.
+pub fn format_date(value: &str, pattern: &str) -> Option<String> {
+    format_parts(parse_iso(value)?, pattern)
+}
+
+/// Format an ISO date string with a pattern after converting it to UTC, for outputs labelled
+/// as UTC such as feed dates. Returns None if the value isn't a date.
+pub(crate) fn format_date_utc(value: &str, pattern: &str) -> Option<String> {
+    format_parts(parse_iso(value)?.to_utc(), pattern)
+}
+
+fn format_parts(date: DateParts, pattern: &str) -> Option<String> {
+    let mut output = String::new();
+
+    for (is_token, text) in tokenize(pattern) {
+        if !is_token {
+            output.push_str(&text);
+            continue;
+        }
+        let hour12 = if date.hour.is_multiple_of(12) { 12 } else { date.hour % 12 };
+        let formatted = match text.as_str() {
+            "YYYY" => date.year.to_string(),
+            "YY" => format!("{:02}", date.year.rem_euclid(100)),
+            "MMMM" => MONTHS[date.month as usize - 1].to_string(),
+            "MMM" => MONTHS[date.month as usize - 1][..3].to_string(),
+            "MM" => format!("{:02}", date.month),
+            "M" => date.month.to_string(),
+            "DD" => format!("{:02}", date.day),
+            "D" => date.day.to_string(),
+            "dddd" => WEEKDAYS[weekday(date.year, date.month, date.day)].to_string(),
+            "ddd" => WEEKDAYS[weekday(date.year, date.month, date.day)][..3].to_string(),
+            "HH" => format!("{:02}", date.hour),
+            "H" => date.hour.to_string(),
+            "hh" => format!("{:02}", hour12),
+            "h" => hour12.to_string(),
+            "mm" => format!("{:02}", date.minute),
+            "ss" => format!("{:02}", date.second),
+            "A" => if date.hour < 12 { "AM" } else { "PM" }.to_string(),
+            _ => text,
+        };
+        output.push_str(&formatted);
+    }
+
+    Some(output)
+}
+
+/// Translate a pattern into an `Intl.DateTimeFormat` options object for runtime values.
+/// Intl decides separators and ordering itself, so only the fields and their widths carry over.
+pub fn intl_options(pattern: &str) -> String {
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    let mut hour12 = None;
+    let mut set = |key: &'static str, value: &'static str| {
+        if !fields.iter().any(|(k, _)| *k == key) {
+            fields.push((key, value));
+        }
+    };
+
+    // Tokens only, so letters inside [bracketed] literals never count
+    for (is_token, text) in tokenize(pattern) {
+        if !is_token {
+            continue;
+        }
+        match text.as_str() {
+            "HH" | "H" => {
+                hour12.get_or_insert(false);
+            }
+            "hh" | "h" => {
+                hour12.get_or_insert(true);
+            }
+            _ => {}
+        }
+        match text.as_str() {
+            "dddd" => set("weekday", "long"),
+            "ddd" => set("weekday", "short"),
+            "YYYY" => set("year", "numeric"),
+            "YY" => set("year", "2-digit"),
+            "MMMM" => set("month", "long"),
+            "MMM" => set("month", "short"),
+            "MM" => set("month", "2-digit"),
+            "M" => set("month", "numeric"),
+            "DD" => set("day", "2-digit"),
+            "D" => set("day", "numeric"),
+            "HH" | "hh" => set("hour", "2-digit"),
+            "H" | "h" => set("hour", "numeric"),
+            "mm" => set("minute", "2-digit"),
+            "ss" => set("second", "2-digit"),
+            _ => {}
+        }
+    }
+
+    if let Some(hour12) = hour12 {
+        fields.push(("hour12", if hour12 { "true" } else { "false" }));
+    }
+
+    let body = fields
+        .iter()
+        .map(|(k, v)| match *v {
+            "true" | "false" => format!("{}: {}", k, v),
+            _ => format!("{}: \"{}\"", k, v),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{{ {} }}", body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_date_times() {
+        let date = parse_iso("2024-03-05").unwrap();
+        assert_eq!(
+            (date.year, date.month, date.day, date.hour, date.offset),
+            (2024, 3, 5, 0, None)
+        );
+
+        let date_time = parse_iso("2024-03-05T14:30:15.250+02:00").unwrap();
+        assert_eq!(
+            (date_time.hour, date_time.minute, date_time.second),
+            (14, 30, 15)
+        );
+        assert_eq!(date_time.offset, Some(120));
+        assert_eq!(parse_iso("2024-03-05T14:30:00Z").unwrap().offset, Some(0));
+        assert_eq!(parse_iso("2024-03-05 14:30").unwrap().offset, None);
+    }
+
+    #[test]
+    fn rejects_out_of_range_dates() {
+        for value in [
+            "2024-02-30",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-03-05T25:00",
+            "2024-03-05T10:61",
+            "March 5",
+            "",
+        ] {
+            assert!(parse_iso(value).is_none(), "{} should not parse", value);
+        }
+        assert!(parse_iso("2024-02-29").is_some());
+    }
+
+    #[test]
+    fn formats_patterns() {
+        assert_eq!(
+            format_date("2024-03-05", "MMM D, YYYY").unwrap(),
+            "Mar 5, 2024"
+        );
+        assert_eq!(
+            format_date("2024-03-05", "dddd, MMMM DD").unwrap(),
+            "Tuesday, March 05"
+        );
+        assert_eq!(
+            format_date("2024-03-05T00:05:00", "h:mm A").unwrap(),
+            "12:05 AM"
+        );
+        assert_eq!(
+            format_date("2024-03-05T14:30:00", "hh:mm A [at] HH").unwrap(),
+            "02:30 PM at 14"
+        );
+        assert!(format_date("not a date", "YYYY").is_none());
+    }
+
+    #[test]
+    fn keeps_the_written_offset_and_converts_to_utc_on_request() {
+        assert_eq!(
+            format_date("2024-03-05T14:30:00.123+02:00", "HH:mm:ss").unwrap(),
+            "14:30:00"
+        );
+        assert_eq!(
+            format_date_utc("2024-03-05T14:30:00+02:00", "YYYY-MM-DD[T]HH:mm:ss[Z]").unwrap(),
+            "2024-03-05T12:30:00Z"
+        );
+        assert_eq!(
+            format_date_utc("2024-03-01T01:30:00+02:00", "YYYY-MM-DD HH:mm").unwrap(),
+            "2024-02-29 23:30"
+        );
+        assert_eq!(
+            format_date_utc("2023-12-31T22:00:00-05:00", "YYYY-MM-DD HH:mm").unwrap(),
+            "2024-01-01 03:00"
+        );
+    }
+
+    #[test]
+    fn timestamps_count_seconds_since_the_epoch() {
+        assert_eq!(iso_timestamp("1970-01-01"), Some(0));
+        assert_eq!(iso_timestamp("2024-03-05T14:30:00Z"), Some(1_709_649_000));
+        assert_eq!(
+            iso_timestamp("2024-03-05T16:30:00+02:00"),
+            iso_timestamp("2024-03-05T14:30:00Z")
+        );
+        for days in [-800_000, -1, 0, 1, 19_000, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn intl_options_ignore_bracketed_literals() {
+        assert_eq!(
+            intl_options("MMM D [at Home] h:mm"),
+            r#"{ month: "short", day: "numeric", hour: "numeric", minute: "2-digit", hour12: true }"#
+        );
+        assert_eq!(
+            intl_options("HH:mm"),
+            r#"{ hour: "2-digit", minute: "2-digit", hour12: false }"#
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Publishing generated output. Targets read the files a BuildManifest lists from the output
+//! root; given the manifest of the previous deploy they only send what changed. The S3 and
+//! IPFS targets drive `curl`, which must be on the PATH.
+
+use crate::manifest::{sha256_hex, BuildManifest};
+use crate::view_proto::image_mime_type;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Somewhere generated output can be published
+pub trait DeployTarget {
+    /// Short name for logs and errors ("s3", "ipfs", "copy")
+    fn name(&self) -> &str;
+
+    /// Publish the files `manifest` lists under `root`. With the manifest of the last deploy,
+    /// only added and changed files are sent and removed ones are deleted where the target can.
+    fn deploy(
+        &self,
+        root: &Path,
+        manifest: &BuildManifest,
+        previous: Option<&BuildManifest>,
+    ) -> Result<DeployReport, Box<dyn std::error::Error>>;
+}
+
+/// What a deploy did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DeployReport {
+    /// Where the deployed prototype can be opened, when the target knows
+    pub url: Option<String>,
+    pub uploaded: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Files left alone because they hadn't changed since the previous deploy
+    pub unchanged: usize,
+}
+
+/// A deploy target as configured in deploy.ron
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum DeployConfig {
+    S3(S3Target),
+    Ipfs(IpfsTarget),
+    Copy(CopyTarget),
+}
+
+impl DeployConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: DeployConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+
+    pub fn target(&self) -> &dyn DeployTarget {
+        match self {
+            DeployConfig::S3(target) => target,
+            DeployConfig::Ipfs(target) => target,
+            DeployConfig::Copy(target) => target,
+        }
+    }
+}
+
+/// An S3 bucket or S3-compatible store (R2, MinIO, Spaces). Requests are signed with
+/// AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY from the environment.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct S3Target {
+    pub bucket: String,
+    pub region: String,
+    /// Endpoint for S3-compatible stores; `https://s3.{region}.amazonaws.com` when unset.
+    /// Objects are addressed path-style, as `{endpoint}/{bucket}/{key}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Key prefix the output is uploaded under, e.g. "previews/landing"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+    /// Address the bucket is served from (a website endpoint or CDN), for the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
+}
+
+impl S3Target {
+    fn endpoint(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+            None => format!("https://s3.{}.amazonaws.com", self.region),
+        }
+    }
+
+    fn object_url(&self, path: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        let key = if prefix.is_empty() { path.to_string() } else { format!("{}/{}", prefix, path) };
+        format!("{}/{}/{}", self.endpoint(), self.bucket, key)
+    }
+}
+
+impl DeployTarget for S3Target {
+    fn name(&self) -> &str {
+        "s3"
+    }
+
+    fn deploy(
+        &self,
+        root: &Path,
+        manifest: &BuildManifest,
+        previous: Option<&BuildManifest>,
+    ) -> Result<DeployReport, Box<dyn std::error::Error>> {
+        let (Ok(key), Ok(secret)) = (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY")) else {
+            return Err("s3: AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set".into());
+        };
+        let sigv4 = format!("aws:amz:{}:s3", self.region);
+        let credentials = config_line("user", &format!("{}:{}", key, secret));
+        let (upload, delete, unchanged) = plan(manifest, previous);
+
+        let mut report = DeployReport { unchanged, ..DeployReport::default() };
+        for path in upload {
+            let source = checked_source(root, manifest, &path)?;
+            curl(
+                self.name(),
+                &[
+                    "-X",
+                    "PUT",
+                    "--aws-sigv4",
+                    &sigv4,
+                    "-H",
+                    &format!("Content-Type: {}", content_type(&path)),
+                    "--upload-file",
+                    &source.to_string_lossy(),
+                    &self.object_url(&path),
+                ],
+                &credentials,
+            )?;
+            report.uploaded.push(path);
+        }
+        for path in delete {
+            curl(self.name(), &["-X", "DELETE", "--aws-sigv4", &sigv4, &self.object_url(&path)], &credentials)?;
+            report.deleted.push(path);
+        }
+        report.url = Some(match &self.public_url {
+            Some(url) => url.clone(),
+            None => self.object_url("index.html"),
+        });
+        Ok(report)
+    }
+}
+
+/// Adds the whole output to an IPFS node through its HTTP API, pinned, as one directory.
+/// Content addressing means every deploy sends every file and gets a new CID.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IpfsTarget {
+    /// Kubo RPC API, or a pinning service exposing the same `/api/v0/add`
+    #[serde(default = "default_ipfs_api")]
+    pub api_url: String,
+    /// Environment variable holding a bearer token for pinning services that need one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// Gateway the report's URL points at
+    #[serde(default = "default_ipfs_gateway")]
+    pub gateway: String,
+}
+
+fn default_ipfs_api() -> String {
+    "http://127.0.0.1:5001".to_string()
+}
+
+fn default_ipfs_gateway() -> String {
+    "https://ipfs.io".to_string()
+}
+
+impl DeployTarget for IpfsTarget {
+    fn name(&self) -> &str {
+        "ipfs"
+    }
+
+    fn deploy(
+        &self,
+        root: &Path,
+        manifest: &BuildManifest,
+        _previous: Option<&BuildManifest>,
+    ) -> Result<DeployReport, Box<dyn std::error::Error>> {
+        let mut args: Vec<String> = vec!["-X".to_string(), "POST".to_string()];
+        let mut credentials = String::new();
+        if let Some(env) = &self.token_env {
+            let token = std::env::var(env).map_err(|_| format!("ipfs: {} must be set", env))?;
+            credentials = config_line("header", &format!("Authorization: Bearer {}", token));
+        }
+        // Every file goes under one `site` directory so the CID covers the whole site. Kubo
+        // expects each directory as its own part ahead of its contents; sorted paths keep
+        // every directory's contents together.
+        let mut directories = std::collections::BTreeSet::new();
+        for path in manifest.files.keys() {
+            let source = checked_source(root, manifest, path)?;
+            let full_path = format!("site/{}", path);
+            for (i, _) in full_path.match_indices('/') {
+                let directory = &full_path[..i];
+                if directories.insert(directory.to_string()) {
+                    args.extend(["-F".to_string(), format!("file=;type=application/x-directory;filename=\"{}\"", directory)]);
+                }
+            }
+            args.extend(["-F".to_string(), format!("file=@\"{}\";filename=\"{}\"", source.to_string_lossy(), full_path)]);
+        }
+        args.push(format!(
+            "{}/api/v0/add?pin=true&cid-version=1&wrap-with-directory=false",
+            self.api_url.trim_end_matches('/')
+        ));
+
+        let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = curl(self.name(), &arg_refs, &credentials)?;
+        // One JSON object per added file or directory; the `site` directory is the root
+        let cid = String::from_utf8_lossy(&output)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find(|entry| entry["Name"] == "site")
+            .and_then(|entry| entry["Hash"].as_str().map(str::to_string))
+            .ok_or("ipfs: the API response has no CID for the site directory")?;
+
+        Ok(DeployReport {
+            url: Some(format!("{}/ipfs/{}/", self.gateway.trim_end_matches('/'), cid)),
+            uploaded: manifest.files.keys().cloned().collect(),
+            deleted: Vec::new(),
+            unchanged: 0,
+        })
+    }
+}
+
+/// Mirror the output into a local directory, such as a mounted share or a static host's
+/// docroot, like `rsync --delete` limited to files the manifests know about
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CopyTarget {
+    pub dest: PathBuf,
+    /// Address the directory is served from, for the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
+}
+
+impl DeployTarget for CopyTarget {
+    fn name(&self) -> &str {
+        "copy"
+    }
+
+    fn deploy(
+        &self,
+        root: &Path,
+        manifest: &BuildManifest,
+        previous: Option<&BuildManifest>,
+    ) -> Result<DeployReport, Box<dyn std::error::Error>> {
+        let (upload, delete, unchanged) = plan(manifest, previous);
+        let mut report = DeployReport { url: self.public_url.clone(), unchanged, ..DeployReport::default() };
+        for path in upload {
+            let source = checked_source(root, manifest, &path)?;
+            let target = self.dest.join(&path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&source, &target).map_err(|e| format!("copy: {}: {}", target.display(), e))?;
+            report.uploaded.push(path);
+        }
+        for path in delete {
+            match fs::remove_file(self.dest.join(&path)) {
+                Ok(()) => report.deleted.push(path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("copy: {}: {}", path, e).into()),
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Paths to send and to delete, and how many are unchanged
+fn plan(manifest: &BuildManifest, previous: Option<&BuildManifest>) -> (Vec<String>, Vec<String>, usize) {
+    match previous {
+        None => (manifest.files.keys().cloned().collect(), Vec::new(), 0),
+        Some(previous) => {
+            let diff = manifest.diff(previous);
+            let mut upload = diff.added;
+            upload.extend(diff.changed);
+            upload.sort();
+            let unchanged = manifest.files.len() - upload.len();
+            (upload, diff.removed, unchanged)
+        }
+    }
+}
+
+/// A manifest file's location on disk, after checking it still has the contents the manifest
+/// recorded so a stale manifest can't publish a half-regenerated build
+fn checked_source(root: &Path, manifest: &BuildManifest, path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let source = root.join(path);
+    let contents = fs::read(&source).map_err(|e| format!("{}: {}", source.display(), e))?;
+    if manifest.files.get(path).is_some_and(|entry| entry.sha256 != sha256_hex(&contents)) {
+        return Err(format!("{} changed since the manifest was written; regenerate before deploying", source.display()).into());
+    }
+    Ok(source)
+}
+
+/// Run curl, failing on HTTP errors, and return what it printed. `config` is passed on stdin
+/// rather than as arguments, so secrets in it don't show up in the process list.
+fn curl(target: &str, args: &[&str], config: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: couldn't run curl: {}", target, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("{}: {}", target, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(output.stdout)
+}
+
+/// A curl config file line setting `option` to `value`
+fn config_line(option: &str, value: &str) -> String {
+    format!("{} = \"{}\"\n", option, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub(crate) fn content_type(path: &str) -> &'static str {
+    if let Some(mime) = image_mime_type(path) {
+        return mime;
+    }
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" | "jsx" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "woff2" => "font/woff2",
+        "woff" => "font/woff",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::GeneratedFile;
+
+    fn write(root: &Path, files: &[GeneratedFile]) -> BuildManifest {
+        for file in files {
+            file.write_to(root).unwrap();
+        }
+        BuildManifest::new(files)
+    }
+
+    #[test]
+    fn copies_only_what_changed_since_the_last_deploy() {
+        let base = std::env::temp_dir().join(format!("degenproto-deploy-{}", std::process::id()));
+        let (out, dest) = (base.join("out"), base.join("dest"));
+        let target = CopyTarget { dest: dest.clone(), public_url: None };
+
+        let first = write(&out, &[GeneratedFile::text("index.html", "<p>1</p>"), GeneratedFile::text("old.js", "1")]);
+        let report = target.deploy(&out, &first, None).unwrap();
+        assert_eq!(report.uploaded, ["index.html", "old.js"]);
+
+        fs::remove_file(out.join("old.js")).unwrap();
+        let second = write(&out, &[GeneratedFile::text("index.html", "<p>2</p>"), GeneratedFile::text("app.css", "p{}")]);
+        let report = target.deploy(&out, &second, Some(&first)).unwrap();
+        assert_eq!(report.uploaded, ["app.css", "index.html"]);
+        assert_eq!(report.deleted, ["old.js"]);
+        assert_eq!(fs::read_to_string(dest.join("index.html")).unwrap(), "<p>2</p>");
+        assert!(!dest.join("old.js").exists());
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn refuses_files_changed_since_the_manifest() {
+        let out = std::env::temp_dir().join(format!("degenproto-deploy-stale-{}", std::process::id()));
+        let manifest = write(&out, &[GeneratedFile::text("index.html", "<p>1</p>")]);
+        fs::write(out.join("index.html"), "<p>edited</p>").unwrap();
+        let error = checked_source(&out, &manifest, "index.html").unwrap_err().to_string();
+        assert!(error.ends_with("changed since the manifest was written; regenerate before deploying"), "{}", error);
+        fs::remove_dir_all(out).unwrap();
+    }
+
+    #[test]
+    fn addresses_s3_objects_path_style_under_the_prefix() {
+        let target: S3Target = ron::from_str(r#"(bucket: "site", region: "eu-west-1", prefix: "/previews/landing/")"#).unwrap();
+        assert_eq!(target.object_url("index.html"), "https://s3.eu-west-1.amazonaws.com/site/previews/landing/index.html");
+        assert_eq!(content_type("app.js"), "text/javascript; charset=utf-8");
+        assert_eq!(config_line("header", r#"Authorization: "x""#), "header = \"Authorization: \\\"x\\\"\"\n");
+    }
+}
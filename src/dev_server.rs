@@ -0,0 +1,345 @@
+//! A local server for iterating on protos: the project is generated into a scratch directory,
+//! its views are served as HTML previews, and open pages reload when a file under the project
+//! changes
+
+use crate::deploy::content_type;
+use crate::diagnostics::Diagnostic;
+use crate::generate::{write_files, GenObserver};
+use crate::output::html_escape;
+use crate::project::ProtoProject;
+use crate::report::BuildReport;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// URL prefix previews link local assets under; served from the project's public/, then its root
+const ASSET_PREFIX: &str = "/assets";
+// Polled by open pages, which reload when it changes
+const VERSION_PATH: &str = "/__dev/version";
+const RELOAD_SCRIPT: &str = "<script>(function(){var v=null;setInterval(function(){fetch('/__dev/version').then(function(r){return r.text()}).then(function(t){if(v!==null&&t!==v)location.reload();v=t}).catch(function(){})},1000)})();</script>";
+
+/// Generates a project into `out_dir` and serves its previews on `addr`, regenerating whenever
+/// a file under `root` changes
+#[derive(Debug, Clone)]
+pub struct DevServer {
+    /// Directory holding index.ron
+    pub root: PathBuf,
+    /// Where sources (`src/`) and previews (`previews/`) are generated
+    pub out_dir: PathBuf,
+    pub addr: String,
+    /// How often the project is checked for changes
+    pub poll_interval: Duration,
+}
+
+// What the last rebuild produced, shared between the watcher and request handlers
+#[derive(Default)]
+struct DevState {
+    // Bumped on every rebuild
+    version: u64,
+    report: BuildReport,
+    // Preview paths under out_dir, forward slashes
+    previews: Vec<String>,
+}
+
+impl DevServer {
+    /// Serve `root` on 127.0.0.1:4173, generating into a directory under the system temp dir
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            out_dir: std::env::temp_dir().join("degenproto-dev"),
+            addr: "127.0.0.1:4173".to_string(),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_out_dir(mut self, out_dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = out_dir.into();
+        self
+    }
+
+    pub fn with_addr(mut self, addr: impl Into<String>) -> Self {
+        self.addr = addr.into();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Load the project and generate it into out_dir: previews of every view, even ones with
+    /// errors, then the sources. Load and build failures end up in the report instead of
+    /// failing, so the index page can show them while the designer fixes the protos.
+    pub fn rebuild(&self) -> BuildReport {
+        self.rebuild_previews().0
+    }
+
+    /// Serve until the process exits, rebuilding whenever a file under root changes
+    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(&self.addr)?;
+        let state = Arc::new(Mutex::new(DevState::default()));
+        self.refresh(&state);
+
+        let watcher = self.clone();
+        let watched = Arc::clone(&state);
+        thread::spawn(move || {
+            let mut last = snapshot(&watcher.root, &watcher.out_dir);
+            loop {
+                thread::sleep(watcher.poll_interval);
+                let current = snapshot(&watcher.root, &watcher.out_dir);
+                if current != last {
+                    last = current;
+                    watcher.refresh(&watched);
+                }
+            }
+        });
+
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let state = Arc::clone(&state);
+            let (root, out_dir) = (self.root.clone(), self.out_dir.clone());
+            thread::spawn(move || handle(stream, &state, &root, &out_dir));
+        }
+        Ok(())
+    }
+
+    fn refresh(&self, state: &Mutex<DevState>) {
+        let (report, previews) = self.rebuild_previews();
+        if let Ok(mut state) = state.lock() {
+            state.version += 1;
+            state.report = report;
+            state.previews = previews;
+        }
+    }
+
+    fn rebuild_previews(&self) -> (BuildReport, Vec<String>) {
+        let project = match ProtoProject::load(&self.root) {
+            Ok(project) => project,
+            Err(e) => return (BuildReport::from_diagnostics(vec![Diagnostic::error("project", e.to_string())]), Vec::new()),
+        };
+        let mut report = BuildReport::default();
+        let previews = project.previews(ASSET_PREFIX, &mut ());
+        let paths = previews.iter().map(|file| file.path.to_string_lossy().replace('\\', "/")).collect();
+        let written = write_files(&previews, &self.out_dir, &mut report).and_then(|_| project.build(&self.out_dir, &mut report));
+        // Validation errors already reached the report through the observer
+        if let Err(e) = written
+            && !report.has_errors()
+        {
+            report.diagnostics("build", &[Diagnostic::error("build", e.to_string())]);
+        }
+        (report, paths)
+    }
+}
+
+// A response status, content type and body
+type Response = (&'static str, &'static str, Vec<u8>);
+
+fn handle(mut stream: TcpStream, state: &Mutex<DevState>, root: &Path, out_dir: &Path) {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 64 * 1024 {
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET" | "HEAD"), Some(target)) => match state.lock() {
+            Ok(state) => respond(target, &state, root, out_dir),
+            Err(_) => ("500 Internal Server Error", "text/plain; charset=utf-8", b"dev server state is poisoned".to_vec()),
+        },
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", Vec::new()),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(&body));
+}
+
+fn respond(target: &str, state: &DevState, root: &Path, out_dir: &Path) -> Response {
+    let path = percent_decode(target.split(['?', '#']).next().unwrap_or("/"));
+    if path == "/" {
+        return ("200 OK", "text/html; charset=utf-8", index_page(state).into_bytes());
+    }
+    if path == VERSION_PATH {
+        return ("200 OK", "text/plain; charset=utf-8", state.version.to_string().into_bytes());
+    }
+    let file = match path.strip_prefix(ASSET_PREFIX).filter(|rest| rest.starts_with('/')) {
+        Some(rest) => inside(&root.join("public"), rest).filter(|file| file.is_file()).or_else(|| inside(root, rest)),
+        None => inside(out_dir, &path),
+    };
+    let Some(contents) = file.and_then(|file| fs::read(file).ok()) else {
+        return ("404 Not Found", "text/plain; charset=utf-8", format!("{} not found", path).into_bytes());
+    };
+    let content_type = content_type(&path);
+    if content_type.starts_with("text/html") {
+        let html = String::from_utf8_lossy(&contents);
+        return ("200 OK", content_type, with_reload_script(&html).into_bytes());
+    }
+    ("200 OK", content_type, contents)
+}
+
+// Links to every preview and the last rebuild's diagnostics
+fn index_page(state: &DevState) -> String {
+    let mut body = String::new();
+    if !state.report.diagnostics.is_empty() {
+        body.push_str("<h2>Diagnostics</h2><ul>");
+        for diagnostic in &state.report.diagnostics {
+            body.push_str(&format!("<li>{}</li>", html_escape(&diagnostic.to_string())));
+        }
+        body.push_str("</ul>");
+    }
+    body.push_str("<h2>Previews</h2><ul>");
+    for preview in &state.previews {
+        let label = preview.trim_start_matches("previews/").trim_end_matches(".html");
+        body.push_str(&format!("<li><a href=\"/{}\">{}</a></li>", html_escape(preview), html_escape(label)));
+    }
+    body.push_str("</ul>");
+    with_reload_script(&format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Previews</title></head><body>{}</body></html>\n",
+        body
+    ))
+}
+
+fn with_reload_script(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &html[..end], RELOAD_SCRIPT, &html[end..]),
+        None => format!("{}{}", html, RELOAD_SCRIPT),
+    }
+}
+
+/// `path` (a URL path) under `base`, or None when a `..` segment would leave it
+fn inside(base: &Path, path: &str) -> Option<PathBuf> {
+    let mut file = base.to_path_buf();
+    for segment in path.split('/').filter(|segment| !segment.is_empty() && *segment != ".") {
+        if segment == ".." || segment.contains('\\') {
+            return None;
+        }
+        file.push(segment);
+    }
+    Some(file)
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Modification time and size of every file under `root`, skipping hidden directories,
+/// node_modules and `out_dir`, so the watcher sees edits, additions and removals
+fn snapshot(root: &Path, out_dir: &Path) -> BTreeMap<PathBuf, (Option<SystemTime>, u64)> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if !name.starts_with('.') && name != "node_modules" && path != out_dir {
+                    dirs.push(path);
+                }
+            } else {
+                files.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/project")
+    }
+
+    #[test]
+    fn generates_previews_and_sources() {
+        let out_dir = std::env::temp_dir().join(format!("degenproto-dev-test-{}", std::process::id()));
+        let server = DevServer::new(fixture()).with_out_dir(&out_dir);
+        let (report, previews) = server.rebuild_previews();
+        let home = fs::read_to_string(out_dir.join("previews/views/Home.html"));
+        let router = out_dir.join("src/router/index.jsx").exists();
+        let _ = fs::remove_dir_all(&out_dir);
+
+        assert!(!report.has_errors(), "{:?}", report.diagnostics);
+        assert!(previews.contains(&"previews/views/Home.html".to_string()));
+        assert!(home.unwrap().contains("src=\"/assets/logo.svg\""));
+        assert!(router);
+    }
+
+    #[test]
+    fn serves_pages_with_the_reload_script_and_assets_from_public() {
+        let out_dir = std::env::temp_dir().join(format!("degenproto-dev-serve-{}", std::process::id()));
+        fs::create_dir_all(out_dir.join("previews")).unwrap();
+        fs::write(out_dir.join("previews/Home.html"), "<html><body>Hi</body></html>").unwrap();
+        let state = DevState { version: 3, previews: vec!["previews/Home.html".to_string()], ..DevState::default() };
+
+        let (status, _, page) = respond("/previews/Home.html?x=1", &state, &fixture(), &out_dir);
+        let (_, _, index) = respond("/", &state, &fixture(), &out_dir);
+        let (_, asset_type, asset) = respond("/assets/logo.svg", &state, &fixture(), &out_dir);
+        let (_, _, version) = respond("/__dev/version", &state, &fixture(), &out_dir);
+        let (escaping, _, _) = respond("/assets/../index.ron", &state, &fixture(), &out_dir);
+        let _ = fs::remove_dir_all(&out_dir);
+
+        assert_eq!(status, "200 OK");
+        assert_eq!(String::from_utf8(page).unwrap(), format!("<html><body>Hi{}</body></html>", RELOAD_SCRIPT));
+        assert!(String::from_utf8(index).unwrap().contains("<a href=\"/previews/Home.html\">Home</a>"));
+        assert_eq!(asset_type, "image/svg+xml");
+        assert!(asset.starts_with(b"<svg"));
+        assert_eq!(version, b"3");
+        assert_eq!(escaping, "404 Not Found");
+    }
+
+    #[test]
+    fn snapshots_change_when_files_do() {
+        let root = std::env::temp_dir().join(format!("degenproto-dev-watch-{}", std::process::id()));
+        fs::create_dir_all(root.join("views")).unwrap();
+        fs::write(root.join("views/home.ron"), "()").unwrap();
+        let before = snapshot(&root, &root.join("out"));
+        fs::create_dir_all(root.join("out")).unwrap();
+        fs::write(root.join("out/ignored.jsx"), "x").unwrap();
+        let unchanged = snapshot(&root, &root.join("out"));
+        fs::write(root.join("views/home.ron"), "(name: \"Home\")").unwrap();
+        let after = snapshot(&root, &root.join("out"));
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(before, unchanged);
+        assert_ne!(before, after);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while validating protos
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Location of the offending element, e.g. `Home > div > Card[2]`
+    pub path: String,
+    /// Proto file the problem is in, when checked as part of a ProtoProject
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            path: path.into(),
+            file: None,
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            path: path.into(),
+            file: None,
+        }
+    }
+
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        write!(f, "{}: {} (at {})", level, self.message, self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_the_file_when_known() {
+        let error = Diagnostic::error("Home > div", "broken").with_file("views/home.ron");
+        assert!(error.is_error());
+        assert_eq!(error.to_string(), "views/home.ron: error: broken (at Home > div)");
+
+        let warning = Diagnostic::warning("Home", "odd");
+        assert!(!warning.is_error());
+        assert_eq!(warning.to_string(), "warning: odd (at Home)");
+    }
+}
@@ -0,0 +1,610 @@
+use crate::ron_format::to_ron_string;
+use crate::view_proto::{Element, ViewProto};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+// Slots that may be left empty; every other slot must hold a node
+const OPTIONAL_SLOTS: &[&str] = &["default", "fallback", "otherwise"];
+
+/// A ViewProto as a flat table of nodes with parent/child links, for GUI editors. Converting
+/// back with to_proto gives the same view. Elements with an editor id keep it as their node id;
+/// the others get a new UUID on every conversion, so call ViewProto::assign_element_ids first
+/// when node ids should survive saving.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EditorDoc {
+    /// The view without its element trees, which live in `nodes`
+    pub view: ViewProto,
+    /// Root node of the main tree
+    pub root: String,
+    /// Local component name -> root node of its tree
+    pub components: BTreeMap<String, String>,
+    /// Region override name -> its top-level nodes
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub regions: BTreeMap<String, Vec<String>>,
+    pub nodes: BTreeMap<String, EditorNode>,
+}
+
+/// One element of an EditorDoc
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EditorNode {
+    /// The element with its child elements taken out
+    pub element: Element,
+    /// None for tree roots and top-level region nodes
+    pub parent: Option<String>,
+    /// Nodes of the element's `children`, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<String>,
+    /// Single-element slots: `template`, `default`, `fallback`, `then`, `otherwise`, `arms.0`, `variants.0`, ...
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub slots: BTreeMap<String, String>,
+}
+
+/// A change to an EditorDoc
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum EditOp {
+    /// Add an element, along with any elements nested in it, to `parent`'s children at `index`,
+    /// or last when unset
+    Insert { parent: String, index: Option<usize>, element: Element },
+    /// Move a node and its subtree to `parent`'s children at `index`, or last when unset
+    Move { node: String, parent: String, index: Option<usize> },
+    /// Replace a node's own fields with `element`'s, keeping its children. The kind of element
+    /// can't change, and children nested in `element` are ignored, except for slots the node
+    /// doesn't have yet (a new Match arm, an `otherwise`), which are added.
+    Update { node: String, element: Element },
+    /// Remove a node and its subtree
+    Delete { node: String },
+}
+
+impl EditorDoc {
+    pub fn from_proto(proto: &ViewProto) -> Self {
+        let mut view = proto.clone();
+        let tree = std::mem::replace(&mut view.tree, placeholder());
+        let components: Vec<(String, Box<Element>)> = view
+            .components
+            .iter_mut()
+            .map(|component| (component.name.clone(), std::mem::replace(&mut component.tree, placeholder())))
+            .collect();
+        let regions = std::mem::take(&mut view.regions);
+
+        let mut doc = EditorDoc {
+            view,
+            root: String::new(),
+            components: BTreeMap::new(),
+            regions: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+        };
+        doc.root = doc.add_element(*tree, None);
+        for (name, tree) in components {
+            let root = doc.add_element(*tree, None);
+            doc.components.insert(name, root);
+        }
+        for (name, elements) in regions {
+            let nodes = elements.into_iter().map(|element| doc.add_element(*element, None)).collect();
+            doc.regions.insert(name, nodes);
+        }
+        doc
+    }
+
+    /// Parse a doc, e.g. one sent back by an editor. Fails unless the node links form proper
+    /// trees: every link resolves, parent links match the children and slots, and every node
+    /// is in exactly one tree, once.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let doc: EditorDoc = serde_json::from_str(json)?;
+        doc.check_links()?;
+        Ok(doc)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The view with its trees rebuilt from the nodes
+    pub fn to_proto(&self) -> Result<ViewProto, Box<dyn std::error::Error>> {
+        let mut view = self.view.clone();
+        *view.tree = self.build(&self.root)?;
+        for component in &mut view.components {
+            let root = self
+                .components
+                .get(&component.name)
+                .ok_or_else(|| format!("component `{}` has no root node", component.name))?;
+            *component.tree = self.build(root)?;
+        }
+        for (name, nodes) in &self.regions {
+            let elements = nodes.iter().map(|node| self.build(node).map(Box::new)).collect::<Result<_, _>>()?;
+            view.regions.insert(name.clone(), elements);
+        }
+        Ok(view)
+    }
+
+    /// to_proto as RON, for writing back to the view's file
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(&self.to_proto()?)?)
+    }
+
+    /// The node's element with its subtree put back in
+    pub fn element(&self, node: &str) -> Result<Element, Box<dyn std::error::Error>> {
+        self.build(node)
+    }
+
+    /// Apply one edit, returning the node it touched (the new node for Insert)
+    pub fn apply(&mut self, op: EditOp) -> Result<String, Box<dyn std::error::Error>> {
+        match op {
+            EditOp::Insert { parent, index, element } => {
+                self.check_container(&parent)?;
+                let node = self.add_element(element, Some(&parent));
+                self.insert_child(&parent, index, &node)?;
+                Ok(node)
+            }
+            EditOp::Move { node, parent, index } => {
+                self.get(&node)?;
+                self.check_container(&parent)?;
+                let mut ancestor = Some(parent.clone());
+                while let Some(current) = ancestor {
+                    if current == node {
+                        return Err(format!("can't move node `{}` into its own subtree", node).into());
+                    }
+                    ancestor = self.get(&current)?.parent.clone();
+                }
+                self.detach(&node)?;
+                self.get_mut(&node)?.parent = Some(parent.clone());
+                self.insert_child(&parent, index, &node)?;
+                Ok(node)
+            }
+            EditOp::Update { node, mut element } => {
+                let existing = &self.get(&node)?.element;
+                if std::mem::discriminant(existing) != std::mem::discriminant(&element) {
+                    return Err(format!("node `{}` is a {}, not a {}", node, existing.label(), element.label()).into());
+                }
+                // Keep the slot placeholders in place so build() can fill them
+                let (_, slots) = take_children(&mut element);
+                let expected: Vec<String> = self.get(&node)?.slots.keys().cloned().collect();
+                if let Some(missing) = expected.iter().find(|slot| !slots.iter().any(|(name, _)| name == *slot)) {
+                    return Err(format!("update of node `{}` drops its `{}` slot", node, missing).into());
+                }
+                // Slots new to the node bring their elements along
+                for (name, child) in slots.into_iter().filter(|(name, _)| !expected.contains(name)) {
+                    let child = self.add_element(child, Some(&node));
+                    self.get_mut(&node)?.slots.insert(name, child);
+                }
+                self.get_mut(&node)?.element = element;
+                Ok(node)
+            }
+            EditOp::Delete { node } => {
+                self.get(&node)?;
+                self.detach(&node)?;
+                self.remove_subtree(&node);
+                Ok(node)
+            }
+        }
+    }
+
+    fn get(&self, node: &str) -> Result<&EditorNode, Box<dyn std::error::Error>> {
+        self.nodes.get(node).ok_or_else(|| format!("unknown node `{}`", node).into())
+    }
+
+    fn get_mut(&mut self, node: &str) -> Result<&mut EditorNode, Box<dyn std::error::Error>> {
+        self.nodes.get_mut(node).ok_or_else(|| format!("unknown node `{}`", node).into())
+    }
+
+    /// Walk every tree from its root, checking each link on the way; see from_json
+    fn check_links(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let roots = std::iter::once(&self.root)
+            .chain(self.components.values())
+            .chain(self.regions.values().flatten());
+        let mut seen = HashSet::new();
+        for root in roots {
+            if let Some(parent) = &self.get(root)?.parent {
+                return Err(format!("node `{}` is the root of a tree but has parent `{}`", root, parent).into());
+            }
+            let mut pending = vec![root];
+            while let Some(node) = pending.pop() {
+                if !seen.insert(node) {
+                    return Err(format!("node `{}` is linked into the trees more than once", node).into());
+                }
+                let entry = self.get(node)?;
+                for child in entry.children.iter().chain(entry.slots.values()) {
+                    match &self.get(child)?.parent {
+                        Some(parent) if parent == node => {}
+                        Some(parent) => {
+                            return Err(format!("node `{}` is a child of `{}` but its parent is `{}`", child, node, parent).into());
+                        }
+                        None => return Err(format!("node `{}` is a child of `{}` but has no parent", child, node).into()),
+                    }
+                    pending.push(child);
+                }
+            }
+        }
+        if let Some(orphan) = self.nodes.keys().find(|node| !seen.contains(node)) {
+            return Err(format!("node `{}` is not in any tree", orphan).into());
+        }
+        Ok(())
+    }
+
+    fn check_container(&self, node: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let element = &self.get(node)?.element;
+        if !has_children_list(element) {
+            return Err(format!("node `{}` ({}) can't have children", node, element.label()).into());
+        }
+        Ok(())
+    }
+
+    /// Add an element and everything nested in it as nodes, returning its node id. An element
+    /// whose id is already taken gets the new node's id, so ids stay unique in to_proto.
+    fn add_element(&mut self, mut element: Element, parent: Option<&str>) -> String {
+        let id = match element.id() {
+            Some(id) if !self.nodes.contains_key(id) => id.to_string(),
+            Some(_) => {
+                let id = Uuid::new_v4().to_string();
+                if let Some(element_id) = element.id_mut() {
+                    *element_id = Some(id.clone());
+                }
+                id
+            }
+            None => Uuid::new_v4().to_string(),
+        };
+        let (children, slots) = take_children(&mut element);
+        // Reserve the id before children pick theirs
+        self.nodes.insert(
+            id.clone(),
+            EditorNode { element, parent: parent.map(str::to_string), children: Vec::new(), slots: BTreeMap::new() },
+        );
+        let children: Vec<String> = children.into_iter().map(|child| self.add_element(child, Some(&id))).collect();
+        let slots: BTreeMap<String, String> =
+            slots.into_iter().map(|(slot, child)| (slot, self.add_element(child, Some(&id)))).collect();
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.children = children;
+            node.slots = slots;
+        }
+        id
+    }
+
+    fn insert_child(&mut self, parent: &str, index: Option<usize>, node: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let children = &mut self.get_mut(parent)?.children;
+        let index = index.unwrap_or(children.len()).min(children.len());
+        children.insert(index, node.to_string());
+        Ok(())
+    }
+
+    /// Unlink a node from its parent, or from the top level of a region
+    fn detach(&mut self, node: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(parent) = self.get(node)?.parent.clone() else {
+            for nodes in self.regions.values_mut() {
+                if let Some(i) = nodes.iter().position(|n| n == node) {
+                    nodes.remove(i);
+                    return Ok(());
+                }
+            }
+            return Err(format!("node `{}` is the root of a tree and can't be moved or deleted", node).into());
+        };
+        let parent = self.get_mut(&parent)?;
+        if let Some(i) = parent.children.iter().position(|n| n == node) {
+            parent.children.remove(i);
+        } else if let Some(slot) = parent.slots.iter().find(|(_, n)| *n == node).map(|(slot, _)| slot.clone()) {
+            if !OPTIONAL_SLOTS.contains(&slot.as_str()) {
+                return Err(format!("node `{}` fills the required `{}` slot of its parent", node, slot).into());
+            }
+            parent.slots.remove(&slot);
+        }
+        Ok(())
+    }
+
+    fn remove_subtree(&mut self, node: &str) {
+        if let Some(removed) = self.nodes.remove(node) {
+            for child in removed.children.iter().chain(removed.slots.values()) {
+                self.remove_subtree(child);
+            }
+        }
+    }
+
+    fn build(&self, node: &str) -> Result<Element, Box<dyn std::error::Error>> {
+        let entry = self.get(node)?;
+        let children = entry.children.iter().map(|child| self.build(child)).collect::<Result<Vec<_>, _>>()?;
+        let mut slots = BTreeMap::new();
+        for (slot, child) in &entry.slots {
+            slots.insert(slot.clone(), self.build(child)?);
+        }
+        let mut element = entry.element.clone();
+        put_children(&mut element, children, slots);
+        Ok(element)
+    }
+}
+
+fn placeholder() -> Box<Element> {
+    Box::new(Element::Fragment { children: Vec::new() })
+}
+
+fn has_children_list(element: &Element) -> bool {
+    matches!(
+        element,
+        Element::Node { .. }
+            | Element::ComponentRef { .. }
+            | Element::Portal { .. }
+            | Element::Animated { .. }
+            | Element::Link { .. }
+            | Element::Fragment { .. }
+            | Element::Region { .. }
+            | Element::Flag { .. }
+            | Element::Show { .. }
+            | Element::Suspense { .. }
+            | Element::ErrorBoundary { .. }
+    )
+}
+
+/// Take an element's children list and slot elements out, leaving placeholders in required slots
+fn take_children(element: &mut Element) -> (Vec<Element>, Vec<(String, Element)>) {
+    let mut slots = Vec::new();
+    let children = match element {
+        Element::Node { children, .. }
+        | Element::ComponentRef { children, .. }
+        | Element::Portal { children, .. }
+        | Element::Animated { children, .. }
+        | Element::Link { children, .. }
+        | Element::Fragment { children }
+        | Element::Region { children, .. }
+        | Element::Flag { children, .. }
+        | Element::Show { children, .. } => std::mem::take(children),
+        Element::Suspense { fallback, children } | Element::ErrorBoundary { fallback, children } => {
+            if let Some(fallback) = fallback.take() {
+                slots.push(("fallback".to_string(), *fallback));
+            }
+            std::mem::take(children)
+        }
+        Element::ContentList { template, .. } | Element::Repeat { template, .. } => {
+            slots.push(("template".to_string(), *std::mem::replace(template, placeholder())));
+            Vec::new()
+        }
+        Element::Match { arms, default, .. } => {
+            for (i, arm) in arms.iter_mut().enumerate() {
+                slots.push((format!("arms.{}", i), *std::mem::replace(&mut arm.template, placeholder())));
+            }
+            if let Some(default) = default.take() {
+                slots.push(("default".to_string(), *default));
+            }
+            Vec::new()
+        }
+        Element::Experiment { variants, .. } => {
+            for (i, variant) in variants.iter_mut().enumerate() {
+                slots.push((format!("variants.{}", i), *std::mem::replace(&mut variant.template, placeholder())));
+            }
+            Vec::new()
+        }
+        Element::If { then, otherwise, .. } => {
+            slots.push(("then".to_string(), *std::mem::replace(then, placeholder())));
+            if let Some(otherwise) = otherwise.take() {
+                slots.push(("otherwise".to_string(), *otherwise));
+            }
+            Vec::new()
+        }
+        Element::Text(_)
+        | Element::Raw(_)
+        | Element::ContentText(_)
+        | Element::Pagination { .. }
+        | Element::Head { .. }
+        | Element::Media { .. }
+        | Element::Embed { .. } => Vec::new(),
+    };
+    (children.into_iter().map(|child| *child).collect(), slots)
+}
+
+/// Inverse of take_children
+fn put_children(element: &mut Element, children: Vec<Element>, mut slots: BTreeMap<String, Element>) {
+    let mut take_slot = |name: &str| slots.remove(name).map(Box::new);
+    match element {
+        Element::Node { children: list, .. }
+        | Element::ComponentRef { children: list, .. }
+        | Element::Portal { children: list, .. }
+        | Element::Animated { children: list, .. }
+        | Element::Link { children: list, .. }
+        | Element::Fragment { children: list }
+        | Element::Region { children: list, .. }
+        | Element::Flag { children: list, .. }
+        | Element::Show { children: list, .. } => *list = children.into_iter().map(Box::new).collect(),
+        Element::Suspense { fallback, children: list } | Element::ErrorBoundary { fallback, children: list } => {
+            *fallback = take_slot("fallback");
+            *list = children.into_iter().map(Box::new).collect();
+        }
+        Element::ContentList { template, .. } | Element::Repeat { template, .. } => {
+            if let Some(slot) = take_slot("template") {
+                *template = slot;
+            }
+        }
+        Element::Match { arms, default, .. } => {
+            for (i, arm) in arms.iter_mut().enumerate() {
+                if let Some(slot) = take_slot(&format!("arms.{}", i)) {
+                    arm.template = slot;
+                }
+            }
+            *default = take_slot("default");
+        }
+        Element::Experiment { variants, .. } => {
+            for (i, variant) in variants.iter_mut().enumerate() {
+                if let Some(slot) = take_slot(&format!("variants.{}", i)) {
+                    variant.template = slot;
+                }
+            }
+        }
+        Element::If { then, otherwise, .. } => {
+            if let Some(slot) = take_slot("then") {
+                *then = slot;
+            }
+            *otherwise = take_slot("otherwise");
+        }
+        Element::Text(_)
+        | Element::Raw(_)
+        | Element::ContentText(_)
+        | Element::Pagination { .. }
+        | Element::Head { .. }
+        | Element::Media { .. }
+        | Element::Embed { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proto() -> ViewProto {
+        ron::from_str(
+            r#"ViewProto(
+                name: "home",
+                components: [(name: "card", tree: Node(tag: "div", id: Some("card"), children: [Text("card")]))],
+                tree: Node(tag: "main", id: Some("main"), children: [
+                    Node(tag: "h1", id: Some("title"), children: [Text("Hello")]),
+                    If(condition: Var("open"), then: Node(tag: "p", id: Some("then"), children: [Text("open")])),
+                    Node(tag: "section", id: Some("section")),
+                ]),
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn node(tag: &str, id: &str) -> Element {
+        ron::from_str(&format!(r#"Node(tag: "{}", id: Some("{}"))"#, tag, id)).unwrap()
+    }
+
+    fn child_tags(doc: &EditorDoc, node: &str) -> Vec<String> {
+        doc.nodes[node]
+            .children
+            .iter()
+            .map(|child| doc.nodes[child].element.label())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let proto = proto();
+        let doc = EditorDoc::from_json(&EditorDoc::from_proto(&proto).to_json().unwrap()).unwrap();
+        assert_eq!(doc.root, "main");
+        assert_eq!(doc.components["card"], "card");
+        assert_eq!(doc.to_ron_string().unwrap(), to_ron_string(&proto).unwrap());
+    }
+
+    #[test]
+    fn rejects_broken_links() {
+        let mut doc = EditorDoc::from_proto(&proto());
+        doc.nodes.get_mut("title").unwrap().parent = Some("section".to_string());
+        assert!(EditorDoc::from_json(&doc.to_json().unwrap()).is_err());
+
+        let mut doc = EditorDoc::from_proto(&proto());
+        doc.nodes
+            .get_mut("section")
+            .unwrap()
+            .children
+            .push("title".to_string());
+        assert!(EditorDoc::from_json(&doc.to_json().unwrap()).is_err());
+
+        let mut doc = EditorDoc::from_proto(&proto());
+        doc.nodes
+            .get_mut("main")
+            .unwrap()
+            .children
+            .push("missing".to_string());
+        assert!(EditorDoc::from_json(&doc.to_json().unwrap()).is_err());
+    }
+
+    #[test]
+    fn inserts_moves_and_deletes_nodes() {
+        let mut doc = EditorDoc::from_proto(&proto());
+        let inserted = doc
+            .apply(EditOp::Insert {
+                parent: "main".to_string(),
+                index: Some(0),
+                element: node("nav", "nav"),
+            })
+            .unwrap();
+        assert_eq!(inserted, "nav");
+        assert_eq!(child_tags(&doc, "main"), ["nav", "h1", "If", "section"]);
+
+        doc.apply(EditOp::Move {
+            node: "title".to_string(),
+            parent: "section".to_string(),
+            index: None,
+        })
+        .unwrap();
+        assert_eq!(child_tags(&doc, "main"), ["nav", "If", "section"]);
+        assert_eq!(doc.nodes["title"].parent.as_deref(), Some("section"));
+
+        doc.apply(EditOp::Delete {
+            node: "section".to_string(),
+        })
+        .unwrap();
+        assert!(!doc.nodes.contains_key("title"));
+        assert_eq!(child_tags(&doc, "main"), ["nav", "If"]);
+        assert!(EditorDoc::from_json(&doc.to_json().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn gives_inserted_duplicates_a_new_id() {
+        let mut doc = EditorDoc::from_proto(&proto());
+        let inserted = doc
+            .apply(EditOp::Insert {
+                parent: "section".to_string(),
+                index: None,
+                element: node("h1", "title"),
+            })
+            .unwrap();
+        assert_ne!(inserted, "title");
+        assert_eq!(doc.nodes[&inserted].element.id(), Some(inserted.as_str()));
+        assert!(EditorDoc::from_json(&doc.to_json().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn refuses_moves_into_a_subtree_and_inserts_into_leaves() {
+        let mut doc = EditorDoc::from_proto(&proto());
+        assert!(
+            doc.apply(EditOp::Move {
+                node: "main".to_string(),
+                parent: "section".to_string(),
+                index: None
+            })
+            .is_err()
+        );
+        let text = doc.nodes["title"].children[0].clone();
+        assert!(
+            doc.apply(EditOp::Insert {
+                parent: text,
+                index: None,
+                element: node("b", "b")
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn updates_keep_children_and_add_new_slots() {
+        let mut doc = EditorDoc::from_proto(&proto());
+        let element: Element = ron::from_str(r#"Node(tag: "h2", id: Some("title"))"#).unwrap();
+        doc.apply(EditOp::Update {
+            node: "title".to_string(),
+            element,
+        })
+        .unwrap();
+        assert_eq!(doc.element("title").unwrap().label(), "h2");
+        assert_eq!(doc.nodes["title"].children.len(), 1);
+
+        let condition = doc.nodes["main"].children[1].clone();
+        let element: Element = ron::from_str(
+            r#"If(condition: Var("open"), then: Text(""), otherwise: Some(Text("closed")))"#,
+        )
+        .unwrap();
+        doc.apply(EditOp::Update {
+            node: condition.clone(),
+            element,
+        })
+        .unwrap();
+        assert!(doc.nodes[&condition].slots.contains_key("otherwise"));
+        assert_eq!(
+            doc.nodes[&doc.nodes[&condition].slots["then"]].element.id(),
+            Some("then")
+        );
+
+        assert!(
+            doc.apply(EditOp::Update {
+                node: "title".to_string(),
+                element: Element::Text("x".to_string())
+            })
+            .is_err()
+        );
+    }
+}
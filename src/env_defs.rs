@@ -0,0 +1,324 @@
+use crate::diagnostics::Diagnostic;
+use crate::output::GeneratedFile;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Environment variables the generated app reads, loaded from env.ron
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EnvDefs {
+    pub vars: Vec<EnvVar>,
+    /// Emit src/env.ts instead of src/env.js
+    #[serde(default)]
+    pub typescript: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvVar {
+    /// Full variable name; Vite only exposes names starting with `VITE_`
+    pub name: String,
+    #[serde(default)]
+    pub kind: EnvKind,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Missing values without a default throw when env.js loads
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Per-profile values written to `.env.{profile}`, by Vite mode
+    #[serde(default)]
+    pub profiles: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EnvKind {
+    #[default]
+    String,
+    Number,
+    Boolean,
+}
+
+impl EnvKind {
+    fn ts_type(&self) -> &'static str {
+        match self {
+            EnvKind::String => "string",
+            EnvKind::Number => "number",
+            EnvKind::Boolean => "boolean",
+        }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            EnvKind::String => true,
+            EnvKind::Number => value.parse::<f64>().is_ok(),
+            EnvKind::Boolean => matches!(value, "true" | "false"),
+        }
+    }
+}
+
+impl EnvVar {
+    /// Property name on the exported `env` object: VITE_API_URL -> apiUrl
+    pub fn key(&self) -> String {
+        let name = self.name.strip_prefix("VITE_").unwrap_or(&self.name);
+        let mut key = String::new();
+        for (i, word) in name.split('_').filter(|w| !w.is_empty()).enumerate() {
+            let word = word.to_lowercase();
+            if i == 0 {
+                key.push_str(&word);
+            } else {
+                let mut chars = word.chars();
+                if let Some(first) = chars.next() {
+                    key.extend(first.to_uppercase());
+                    key.push_str(chars.as_str());
+                }
+            }
+        }
+        key
+    }
+}
+
+impl EnvDefs {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let defs: EnvDefs = options.from_str(&content)?;
+        Ok(defs)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EnvVar> {
+        self.vars.iter().find(|v| v.name == name)
+    }
+
+    /// Profiles mentioned by any variable, e.g. "development", "production"
+    pub fn profiles(&self) -> BTreeSet<String> {
+        self.vars.iter().flat_map(|v| v.profiles.keys().cloned()).collect()
+    }
+
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut seen = BTreeSet::new();
+        let mut keys = BTreeSet::new();
+        for var in &self.vars {
+            let path = format!("env > {}", var.name);
+            if !seen.insert(var.name.as_str()) {
+                diagnostics.push(Diagnostic::error(&path, "duplicate environment variable"));
+            }
+            if !var.name.starts_with("VITE_") {
+                diagnostics.push(Diagnostic::error(&path, "Vite only exposes variables prefixed with `VITE_`"));
+            }
+            if !var.name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') {
+                diagnostics.push(Diagnostic::warning(&path, "use UPPER_SNAKE_CASE for environment variable names"));
+            }
+            if !keys.insert(var.key()) {
+                diagnostics.push(Diagnostic::error(&path, format!("accessor `env.{}` is already taken", var.key())));
+            }
+            if var.required && var.default.is_some() {
+                diagnostics.push(Diagnostic::warning(&path, "required variable has a default, so it can never be missing"));
+            }
+            let values = var
+                .default
+                .iter()
+                .map(|v| ("default".to_string(), v))
+                .chain(var.profiles.iter().map(|(p, v)| (p.clone(), v)));
+            for (source, value) in values {
+                if !var.kind.accepts(value) {
+                    diagnostics.push(Diagnostic::error(
+                        &path,
+                        format!("{} value `{}` is not a valid {:?}", source, value, var.kind),
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// .env.example documenting every variable, with defaults filled in
+    pub fn env_example(&self) -> String {
+        let mut output = String::new();
+        for var in &self.vars {
+            if let Some(description) = &var.description {
+                output.push_str(&format!("# {}\n", description));
+            }
+            let mut notes = vec![format!("{:?}", var.kind).to_lowercase()];
+            if var.required {
+                notes.push("required".to_string());
+            }
+            output.push_str(&format!("# {}\n", notes.join(", ")));
+            output.push_str(&format!("{}={}\n\n", var.name, var.default.as_deref().unwrap_or("")));
+        }
+        output
+    }
+
+    /// `.env.{profile}` with the values declared for that profile
+    pub fn profile_env(&self, profile: &str) -> String {
+        self.vars
+            .iter()
+            .filter_map(|var| var.profiles.get(profile).map(|value| format!("{}={}\n", var.name, value)))
+            .collect()
+    }
+
+    /// Typed accessor reading `import.meta.env` once, so missing required values fail at startup
+    pub fn accessor(&self) -> String {
+        let ts = self.typescript;
+        let mut output = String::new();
+        if ts {
+            output.push_str(
+                "function read(name: string, value: string | undefined, required: boolean, fallback?: string): string | undefined {\n",
+            );
+        } else {
+            output.push_str(
+                "/**\n * @param {string} name\n * @param {string | undefined} value\n * @param {boolean} required\n * @param {string} [fallback]\n * @returns {string | undefined}\n */\nfunction read(name, value, required, fallback) {\n",
+            );
+        }
+        output.push_str(
+            r#"  if (value === undefined || value === '') {
+    if (required && fallback === undefined) {
+      throw new Error(`Missing required environment variable ${name}`);
+    }
+    return fallback;
+  }
+  return value;
+}
+
+"#,
+        );
+
+        // Type of the exported object
+        let fields: Vec<String> = self
+            .vars
+            .iter()
+            .map(|var| {
+                let optional = !var.required && var.default.is_none();
+                let ty = var.kind.ts_type();
+                if optional {
+                    format!("  {}: {} | undefined;\n", var.key(), ty)
+                } else {
+                    format!("  {}: {};\n", var.key(), ty)
+                }
+            })
+            .collect();
+        if ts {
+            output.push_str(&format!("export interface Env {{\n{}}}\n\n", fields.concat()));
+        } else {
+            let fields: String = fields.iter().map(|f| format!(" * {}", f)).collect();
+            output.push_str(&format!("/**\n * @typedef {{{{\n{} * }}}} Env\n */\n\n", fields));
+        }
+
+        if ts {
+            output.push_str("export const env: Env = {\n");
+        } else {
+            output.push_str("/** @type {Env} */\nexport const env = {\n");
+        }
+        for var in &self.vars {
+            if let Some(description) = &var.description {
+                output.push_str(&format!("  /** {} */\n", description));
+            }
+            let fallback = var
+                .default
+                .as_deref()
+                .map(|d| format!(", '{}'", d.replace('\\', "\\\\").replace('\'', "\\'")))
+                .unwrap_or_default();
+            let read = format!("read('{}', import.meta.env.{}, {}{})", var.name, var.name, var.required, fallback);
+            let optional = !var.required && var.default.is_none();
+            let value = match (var.kind, optional) {
+                (EnvKind::String, true) => read,
+                (EnvKind::String, false) => if ts { format!("{} as string", read) } else { read },
+                (EnvKind::Number, true) => format!("toNumber({})", read),
+                (EnvKind::Number, false) => format!("Number({})", read),
+                (EnvKind::Boolean, true) => format!("toBoolean({})", read),
+                (EnvKind::Boolean, false) => format!("{} === 'true'", read),
+            };
+            output.push_str(&format!("  {}: {},\n", var.key(), value));
+        }
+        output.push_str("};\n");
+
+        // Optional numbers and booleans stay undefined when unset
+        let optional_kind = |kind| self.vars.iter().any(|v| v.kind == kind && !v.required && v.default.is_none());
+        if optional_kind(EnvKind::Number) {
+            output.push_str(if ts {
+                "\nfunction toNumber(value: string | undefined): number | undefined {\n  return value === undefined ? undefined : Number(value);\n}\n"
+            } else {
+                "\n/** @param {string | undefined} value */\nfunction toNumber(value) {\n  return value === undefined ? undefined : Number(value);\n}\n"
+            });
+        }
+        if optional_kind(EnvKind::Boolean) {
+            output.push_str(if ts {
+                "\nfunction toBoolean(value: string | undefined): boolean | undefined {\n  return value === undefined ? undefined : value === 'true';\n}\n"
+            } else {
+                "\n/** @param {string | undefined} value */\nfunction toBoolean(value) {\n  return value === undefined ? undefined : value === 'true';\n}\n"
+            });
+        }
+        output
+    }
+
+    /// .env.example, one `.env.{profile}` per profile and the src/env accessor
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        let mut files = vec![GeneratedFile::text(".env.example", self.env_example())];
+        for profile in self.profiles() {
+            files.push(GeneratedFile::text(format!(".env.{}", profile), self.profile_env(&profile)));
+        }
+        let accessor = if self.typescript { "src/env.ts" } else { "src/env.js" };
+        files.push(GeneratedFile::text(accessor, self.accessor()));
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defs(source: &str) -> EnvDefs {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    #[test]
+    fn names_accessors_in_camel_case() {
+        let defs = defs(r#"(vars: [(name: "VITE_API_BASE_URL"), (name: "VITE__RETRY__COUNT", kind: Number)])"#);
+        assert_eq!(defs.vars[0].key(), "apiBaseUrl");
+        assert_eq!(defs.vars[1].key(), "retryCount");
+    }
+
+    #[test]
+    fn rejects_unexposed_names_and_mistyped_values() {
+        let defs = defs(
+            r#"(vars: [
+                (name: "API_URL"),
+                (name: "VITE_RETRIES", kind: Number, default: "three", profiles: {"production": "5"}),
+                (name: "VITE_DEBUG", kind: Boolean, required: true, default: "false"),
+            ])"#,
+        );
+        let messages: Vec<String> = defs.validate().iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "error: Vite only exposes variables prefixed with `VITE_` (at env > API_URL)",
+                "error: default value `three` is not a valid Number (at env > VITE_RETRIES)",
+                "warning: required variable has a default, so it can never be missing (at env > VITE_DEBUG)",
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_one_env_file_per_profile() {
+        let defs = defs(
+            r#"(vars: [
+                (name: "VITE_API_URL", required: true, profiles: {"development": "http://localhost:3000", "production": "https://api.example.com"}),
+                (name: "VITE_TIMEOUT", kind: Number),
+            ], typescript: true)"#,
+        );
+        let paths: Vec<String> = defs.files().iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, [".env.example", ".env.development", ".env.production", "src/env.ts"]);
+        assert_eq!(defs.profile_env("production"), "VITE_API_URL=https://api.example.com\n");
+
+        let accessor = defs.accessor();
+        assert!(accessor.contains("  apiUrl: read('VITE_API_URL', import.meta.env.VITE_API_URL, true) as string,\n"), "{}", accessor);
+        assert!(accessor.contains("  timeout: number | undefined;\n"), "{}", accessor);
+        assert!(accessor.contains("function toNumber(value: string | undefined)"), "{}", accessor);
+    }
+}
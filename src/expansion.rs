@@ -0,0 +1,67 @@
+//! Guard for expanding named definitions into one another (components, base views), so a
+//! self-referential def is reported as a chain instead of overflowing the stack
+
+/// Expansion depth used when none is configured
+pub const DEFAULT_MAX_EXPANSION_DEPTH: usize = 32;
+
+/// Names currently being expanded, outermost first
+#[derive(Debug, Clone)]
+pub(crate) struct ExpansionStack {
+    // What is being expanded, for messages: "component", "view inheritance"
+    kind: &'static str,
+    names: Vec<String>,
+    max_depth: usize,
+}
+
+impl ExpansionStack {
+    pub(crate) fn new(kind: &'static str, max_depth: usize) -> Self {
+        Self { kind, names: Vec::new(), max_depth }
+    }
+
+    /// Start expanding `name`. Fails with the chain when `name` is already being expanded
+    /// ("component cycle: Card → MediaBlock → Card") or the chain would exceed the max depth.
+    pub(crate) fn enter(&mut self, name: &str) -> Result<(), String> {
+        if self.names.iter().any(|n| n == name) {
+            return Err(format!("{} cycle: {} → {}", self.kind, self.names.join(" → "), name));
+        }
+        if self.names.len() >= self.max_depth {
+            return Err(format!(
+                "{} nested deeper than {} levels: {} → {}",
+                self.kind,
+                self.max_depth,
+                self.names.join(" → "),
+                name
+            ));
+        }
+        self.names.push(name.to_string());
+        Ok(())
+    }
+
+    /// Finish expanding the innermost name
+    pub(crate) fn leave(&mut self) {
+        self.names.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_chain_back_to_a_repeated_name() {
+        let mut stack = ExpansionStack::new("component", DEFAULT_MAX_EXPANSION_DEPTH);
+        stack.enter("Card").unwrap();
+        stack.enter("MediaBlock").unwrap();
+        assert_eq!(stack.enter("Card").unwrap_err(), "component cycle: Card → MediaBlock → Card");
+        stack.leave();
+        assert!(stack.enter("Badge").is_ok());
+    }
+
+    #[test]
+    fn stops_at_the_max_depth() {
+        let mut stack = ExpansionStack::new("view inheritance", 2);
+        stack.enter("Base").unwrap();
+        stack.enter("Page").unwrap();
+        assert_eq!(stack.enter("Post").unwrap_err(), "view inheritance nested deeper than 2 levels: Base → Page → Post");
+    }
+}
@@ -0,0 +1,121 @@
+use crate::icons::{encode, open_local_image, square_icon};
+use crate::output::GeneratedFile;
+use crate::view_proto::{AliasDefs, AssetDefs};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::{ExtendedColorType, ImageFormat};
+use std::path::Path;
+
+// Sizes bundled into favicon.ico
+const ICO_SIZES: &[u32] = &[16, 32, 48];
+const PNG_SIZES: &[u32] = &[16, 32];
+const APPLE_TOUCH_SIZE: u32 = 180;
+
+/// favicon.ico, PNG favicons and apple-touch-icon from the asset marked `app_icon`
+pub struct FaviconScaffold {
+    pub asset_defs: AssetDefs,
+    // Resolves the icon asset's import path to a file on disk
+    pub aliases: AliasDefs,
+}
+
+impl FaviconScaffold {
+    pub fn new(asset_defs: AssetDefs) -> Self {
+        Self {
+            asset_defs,
+            aliases: AliasDefs::project_default(),
+        }
+    }
+
+    pub fn with_aliases(mut self, aliases: AliasDefs) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Icon files under public/. `project_root` is where the icon asset's path is resolved from.
+    pub fn files<P: AsRef<Path>>(&self, project_root: P) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let asset = self.asset_defs.app_icon()?;
+        let source = open_local_image(asset, &self.aliases, project_root.as_ref())?;
+
+        let mut frames = Vec::new();
+        for size in ICO_SIZES {
+            let rgba = square_icon(&source, *size).to_rgba8();
+            frames.push(IcoFrame::as_png(&rgba, *size, *size, ExtendedColorType::Rgba8)?);
+        }
+        let mut ico = Vec::new();
+        IcoEncoder::new(&mut ico).encode_images(&frames)?;
+
+        let mut files = vec![GeneratedFile {
+            path: "public/favicon.ico".into(),
+            contents: ico,
+        }];
+        for size in PNG_SIZES {
+            files.push(GeneratedFile {
+                path: format!("public{}", png_url(*size)).into(),
+                contents: encode(&square_icon(&source, *size), ImageFormat::Png)?,
+            });
+        }
+        files.push(GeneratedFile {
+            path: "public/apple-touch-icon.png".into(),
+            contents: encode(&square_icon(&source, APPLE_TOUCH_SIZE), ImageFormat::Png)?,
+        });
+        Ok(files)
+    }
+
+    /// Tags for index.html's <head>
+    pub fn head_tags(&self) -> Vec<String> {
+        let mut tags = vec![r#"<link rel="icon" href="/favicon.ico" sizes="any" />"#.to_string()];
+        for size in PNG_SIZES {
+            tags.push(format!(
+                r#"<link rel="icon" type="image/png" sizes="{}x{}" href="{}" />"#,
+                size,
+                size,
+                png_url(*size)
+            ));
+        }
+        tags.push(r#"<link rel="apple-touch-icon" sizes="180x180" href="/apple-touch-icon.png" />"#.to_string());
+        tags
+    }
+}
+
+fn png_url(size: u32) -> String {
+    format!("/favicon-{}x{}.png", size, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets(source: &str) -> AssetDefs {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    #[test]
+    fn renders_square_icons_from_the_app_icon() {
+        let root = std::env::temp_dir().join(format!("degenproto-favicon-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src/assets")).unwrap();
+        image::RgbaImage::from_pixel(64, 40, image::Rgba([255, 0, 0, 255])).save(root.join("src/assets/logo.png")).unwrap();
+
+        let scaffold = FaviconScaffold::new(assets(r#"(assets: [(name: "logo", kind: Image, path: "@/assets/logo.png", app_icon: true)])"#));
+        let files = scaffold.files(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            ["public/favicon.ico", "public/favicon-16x16.png", "public/favicon-32x32.png", "public/apple-touch-icon.png"]
+        );
+        let touch = image::load_from_memory(&files[3].contents).unwrap();
+        assert_eq!((touch.width(), touch.height()), (180, 180));
+        assert_eq!(image::guess_format(&files[0].contents).unwrap(), ImageFormat::Ico);
+    }
+
+    #[test]
+    fn needs_a_local_image_marked_app_icon() {
+        let remote = FaviconScaffold::new(assets(r#"(assets: [(name: "logo", kind: Image, path: "https://cdn.example.com/logo.png", app_icon: true)])"#));
+        assert_eq!(remote.files(".").unwrap_err().to_string(), "asset `logo` must be a local image");
+        let unmarked = FaviconScaffold::new(assets(r#"(assets: [(name: "logo", kind: Image, path: "./logo.png")])"#));
+        assert_eq!(unmarked.files(".").unwrap_err().to_string(), "no asset is marked `app_icon`");
+    }
+}
@@ -0,0 +1,299 @@
+use crate::diagnostics::Diagnostic;
+use crate::markdown::markdown_plain_text;
+use crate::output::{html_escape, GeneratedFile};
+use crate::view_proto::{ContentDefs, ContentValue, FeedConfig};
+use crate::date_format::{format_date_utc, iso_timestamp};
+use crate::ProtoIndex;
+use std::collections::HashMap;
+
+const RSS_DATE: &str = "ddd, DD MMM YYYY HH:mm:ss [GMT]";
+const ATOM_DATE: &str = "YYYY-MM-DD[T]HH:mm:ss[Z]";
+
+/// rss.xml and atom.xml for every ContentDefs collection with a `feed` config
+pub struct FeedScaffold {
+    /// Public origin the site is served from, e.g. "https://example.com"
+    pub base_url: String,
+}
+
+/// A record ready to publish
+struct FeedItem {
+    title: String,
+    url: String,
+    date: String,
+    // The date as seconds since the epoch, for ordering dates with different offsets
+    timestamp: i64,
+    summary: String,
+}
+
+impl FeedScaffold {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self { base_url: base_url.into() }
+    }
+
+    fn absolute(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    // Sorted so output doesn't depend on map order
+    fn feeds<'a>(&self, content: &'a ContentDefs) -> Vec<(&'a String, &'a FeedConfig)> {
+        let mut feeds: Vec<_> = content.feeds().collect();
+        feeds.sort_by_key(|(name, _)| *name);
+        feeds
+    }
+
+    /// Url path of the directory holding a collection's feed files; "" for the site root
+    fn feed_dir(name: &str, feed: &FeedConfig) -> String {
+        match feed.path.as_deref().unwrap_or(name).trim_matches('/') {
+            "" => String::new(),
+            dir => format!("/{}", dir),
+        }
+    }
+
+    /// Alternate links for index.html's <head>
+    pub fn head_tags(&self, content: &ContentDefs) -> Vec<String> {
+        let mut tags = Vec::new();
+        for (name, feed) in self.feeds(content) {
+            let dir = Self::feed_dir(name, feed);
+            tags.push(format!(
+                r#"<link rel="alternate" type="application/rss+xml" title="{}" href="{}/rss.xml" />"#,
+                html_escape(&feed.title),
+                dir
+            ));
+            tags.push(format!(
+                r#"<link rel="alternate" type="application/atom+xml" title="{}" href="{}/atom.xml" />"#,
+                html_escape(&feed.title),
+                dir
+            ));
+        }
+        tags
+    }
+
+    /// Feed collections nothing routes to, and records that would be left out of their feed
+    pub fn validate(&self, index: &ProtoIndex, content: &ContentDefs) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for (name, feed) in self.feeds(content) {
+            let path = format!("feeds > {}", name);
+            let Some(list) = content.get_list(name) else {
+                diagnostics.push(Diagnostic::error(&path, "feed collection is not a content list"));
+                continue;
+            };
+            if !index.routes.iter().any(|r| r.records.as_ref().is_some_and(|b| &b.collection == name)) {
+                diagnostics.push(Diagnostic::error(&path, "no route binds this collection, so its records have no url"));
+                continue;
+            }
+            for (i, item) in list.iter().enumerate() {
+                let ContentValue::Record(record) = item else {
+                    continue;
+                };
+                let record_path = format!("{}[{}]", path, i);
+                if record_url(index, name, record).is_none() {
+                    diagnostics.push(Diagnostic::warning(&record_path, "no route matches this record; it is left out of the feed"));
+                }
+                if !record.contains_key(&feed.title_field) {
+                    diagnostics.push(Diagnostic::warning(&record_path, format!("missing `{}`; it is left out of the feed", feed.title_field)));
+                }
+                match record.get(&feed.date_field) {
+                    Some(date) if iso_timestamp(date).is_some() => {}
+                    Some(date) => diagnostics.push(Diagnostic::warning(
+                        &record_path,
+                        format!("`{}` value `{}` is not an ISO 8601 date; it is left out of the feed", feed.date_field, date),
+                    )),
+                    None => diagnostics.push(Diagnostic::warning(
+                        &record_path,
+                        format!("missing `{}`; it is left out of the feed", feed.date_field),
+                    )),
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// The feed's records with a title, a valid date and a url, newest first
+    fn items(&self, index: &ProtoIndex, content: &ContentDefs, name: &str, feed: &FeedConfig) -> Vec<FeedItem> {
+        let markdown = content.markdown_fields(name).contains(&feed.summary_field);
+        let mut items: Vec<FeedItem> = content
+            .get_list(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let ContentValue::Record(record) = item else {
+                    return None;
+                };
+                let date = record.get(&feed.date_field)?;
+                let timestamp = iso_timestamp(date)?;
+                let summary = record.get(&feed.summary_field).cloned().unwrap_or_default();
+                Some(FeedItem {
+                    title: record.get(&feed.title_field)?.clone(),
+                    url: self.absolute(&record_url(index, name, record)?),
+                    date: date.clone(),
+                    timestamp,
+                    summary: if markdown { markdown_plain_text(&summary).trim().to_string() } else { summary },
+                })
+            })
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.timestamp));
+        items.truncate(feed.limit);
+        items
+    }
+
+    pub fn rss(&self, index: &ProtoIndex, content: &ContentDefs, name: &str, feed: &FeedConfig) -> String {
+        let items = self.items(index, content, name, feed);
+        let self_url = self.absolute(&format!("{}/rss.xml", Self::feed_dir(name, feed)));
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n  <channel>\n");
+        output.push_str(&format!("    <title>{}</title>\n", html_escape(&feed.title)));
+        output.push_str(&format!("    <link>{}</link>\n", html_escape(&self.absolute(feed.link.as_deref().unwrap_or("/")))));
+        output.push_str(&format!(
+            "    <description>{}</description>\n",
+            html_escape(feed.description.as_deref().unwrap_or(&feed.title))
+        ));
+        output.push_str(&format!(
+            "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\" />\n",
+            html_escape(&self_url)
+        ));
+        if let Some(latest) = items.first().and_then(|item| format_date_utc(&item.date, RSS_DATE)) {
+            output.push_str(&format!("    <lastBuildDate>{}</lastBuildDate>\n", latest));
+        }
+        for item in &items {
+            output.push_str("    <item>\n");
+            output.push_str(&format!("      <title>{}</title>\n", html_escape(&item.title)));
+            output.push_str(&format!("      <link>{}</link>\n", html_escape(&item.url)));
+            output.push_str(&format!("      <guid isPermaLink=\"true\">{}</guid>\n", html_escape(&item.url)));
+            if let Some(date) = format_date_utc(&item.date, RSS_DATE) {
+                output.push_str(&format!("      <pubDate>{}</pubDate>\n", date));
+            }
+            if !item.summary.is_empty() {
+                output.push_str(&format!("      <description>{}</description>\n", html_escape(&item.summary)));
+            }
+            output.push_str("    </item>\n");
+        }
+        output.push_str("  </channel>\n</rss>\n");
+        output
+    }
+
+    pub fn atom(&self, index: &ProtoIndex, content: &ContentDefs, name: &str, feed: &FeedConfig) -> String {
+        let items = self.items(index, content, name, feed);
+        let self_url = self.absolute(&format!("{}/atom.xml", Self::feed_dir(name, feed)));
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        output.push_str(&format!("  <title>{}</title>\n", html_escape(&feed.title)));
+        if let Some(description) = &feed.description {
+            output.push_str(&format!("  <subtitle>{}</subtitle>\n", html_escape(description)));
+        }
+        output.push_str(&format!(
+            "  <link href=\"{}\" rel=\"alternate\" />\n",
+            html_escape(&self.absolute(feed.link.as_deref().unwrap_or("/")))
+        ));
+        output.push_str(&format!("  <link href=\"{}\" rel=\"self\" />\n", html_escape(&self_url)));
+        output.push_str(&format!("  <id>{}</id>\n", html_escape(&self_url)));
+        // Atom requires `updated`; an empty feed falls back to the epoch
+        let updated = items
+            .first()
+            .and_then(|item| format_date_utc(&item.date, ATOM_DATE))
+            .unwrap_or_else(|| "1970-01-01T00:00:00Z".to_string());
+        output.push_str(&format!("  <updated>{}</updated>\n", updated));
+        if let Some(author) = &feed.author {
+            output.push_str(&format!("  <author>\n    <name>{}</name>\n  </author>\n", html_escape(author)));
+        }
+        for item in &items {
+            output.push_str("  <entry>\n");
+            output.push_str(&format!("    <title>{}</title>\n", html_escape(&item.title)));
+            output.push_str(&format!("    <link href=\"{}\" />\n", html_escape(&item.url)));
+            output.push_str(&format!("    <id>{}</id>\n", html_escape(&item.url)));
+            if let Some(date) = format_date_utc(&item.date, ATOM_DATE) {
+                output.push_str(&format!("    <updated>{}</updated>\n", date));
+            }
+            if !item.summary.is_empty() {
+                output.push_str(&format!("    <summary>{}</summary>\n", html_escape(&item.summary)));
+            }
+            output.push_str("  </entry>\n");
+        }
+        output.push_str("</feed>\n");
+        output
+    }
+
+    /// rss.xml and atom.xml under public/ for each feed collection
+    pub fn files(&self, index: &ProtoIndex, content: &ContentDefs) -> Vec<GeneratedFile> {
+        let mut files = Vec::new();
+        for (name, feed) in self.feeds(content) {
+            let dir = format!("public{}", Self::feed_dir(name, feed));
+            files.push(GeneratedFile::text(format!("{}/rss.xml", dir), self.rss(index, content, name, feed)));
+            files.push(GeneratedFile::text(format!("{}/atom.xml", dir), self.atom(index, content, name, feed)));
+        }
+        files
+    }
+}
+
+/// Url of the page showing a record: the expanded route bound to it, or the records route
+/// with its `:param` segments filled in
+fn record_url(index: &ProtoIndex, collection: &str, record: &HashMap<String, String>) -> Option<String> {
+    index
+        .routes
+        .iter()
+        .filter(|route| route.records.as_ref().is_some_and(|r| r.collection == collection))
+        .find_map(|route| match &route.record {
+            Some(bound) => (bound == record).then(|| route.url.clone()),
+            None => route.fill_url(record),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn blog() -> (ProtoIndex, ContentDefs) {
+        let index = ron(r#"(layouts: [], routes: [(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: (collection: "posts", mode: Dynamic))])"#);
+        let content = ron(
+            r#"(
+                content: {"posts": List([
+                    Record({"slug": "old", "title": "Old & busted", "date": "2024-01-05T10:00:00Z"}),
+                    Record({"slug": "new", "title": "New", "date": "2024-03-01T08:30:00+02:00", "summary": "Fresh"}),
+                    Record({"slug": "draft", "title": "Draft"}),
+                ])},
+                collections: {"posts": (feed: (title: "Blog", path: "blog"))},
+            )"#,
+        );
+        (index, content)
+    }
+
+    #[test]
+    fn lists_dated_records_newest_first() {
+        let (index, content) = blog();
+        let scaffold = FeedScaffold::new("https://example.com/");
+        let files = scaffold.files(&index, &content);
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, ["public/blog/rss.xml", "public/blog/atom.xml"]);
+
+        let feed = content.collections["posts"].feed.as_ref().unwrap();
+        let rss = scaffold.rss(&index, &content, "posts", feed);
+        let new = rss.find("<link>https://example.com/blog/new</link>").unwrap();
+        let old = rss.find("<title>Old &amp; busted</title>").unwrap();
+        assert!(new < old, "{}", rss);
+        assert!(!rss.contains("draft"), "{}", rss);
+        assert!(rss.contains("<pubDate>Fri, 01 Mar 2024 06:30:00 GMT</pubDate>"), "{}", rss);
+        assert!(rss.contains(r#"<atom:link href="https://example.com/blog/rss.xml" rel="self""#), "{}", rss);
+
+        let atom = scaffold.atom(&index, &content, "posts", feed);
+        assert!(atom.contains("  <updated>2024-03-01T06:30:00Z</updated>\n"), "{}", atom);
+        assert!(atom.contains("<summary>Fresh</summary>"), "{}", atom);
+    }
+
+    #[test]
+    fn warns_about_records_left_out_of_the_feed() {
+        let (index, content) = blog();
+        let scaffold = FeedScaffold::new("https://example.com");
+        let messages: Vec<String> = scaffold.validate(&index, &content).iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages, ["warning: missing `date`; it is left out of the feed (at feeds > posts[2])"]);
+
+        let unrouted: ProtoIndex = ron("(layouts: [], routes: [])");
+        let messages: Vec<String> = scaffold.validate(&unrouted, &content).iter().map(|d| d.message.clone()).collect();
+        assert_eq!(messages, ["no route binds this collection, so its records have no url"]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Generation steps a project build runs, reporting progress to a GenObserver so long builds
+//! can drive progress bars and editor UIs
+
+use crate::diagnostics::Diagnostic;
+use crate::names::component_ident;
+use crate::output::GeneratedFile;
+use crate::preview::PreviewHtml;
+use crate::ViewJsx;
+use std::path::Path;
+
+/// Callbacks invoked as generation proceeds; every method defaults to doing nothing
+pub trait GenObserver {
+    /// A view is about to be validated and rendered
+    fn view_started(&mut self, _name: &str) {}
+    /// A view was rendered into `file`
+    fn view_finished(&mut self, _name: &str, _file: &GeneratedFile) {}
+    /// Validation of `source` (a view name) found problems
+    fn diagnostics(&mut self, _source: &str, _diagnostics: &[Diagnostic]) {}
+    /// A file was written under the output root
+    fn file_written(&mut self, _path: &Path) {}
+    /// A file was left alone because it already had the generated contents
+    fn file_unchanged(&mut self, _path: &Path) {}
+}
+
+/// Observer that ignores every event
+impl GenObserver for () {}
+
+/// Validate and render each view to `src/{output_path}`, or `src/views/{Name}.jsx` when it has
+/// none. Views with errors are not rendered; the build fails once all views have been checked.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(views = views.len())))]
+pub fn render_views(views: &[ViewJsx], observer: &mut dyn GenObserver) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for view in views {
+        let name = &view.proto.name;
+        observer.view_started(name);
+        let diagnostics = view.validate();
+        if !diagnostics.is_empty() {
+            observer.diagnostics(name, &diagnostics);
+        }
+        let view_errors: Vec<String> = diagnostics.iter().filter(|d| d.is_error()).map(|d| d.to_string()).collect();
+        if !view_errors.is_empty() {
+            errors.extend(view_errors);
+            continue;
+        }
+        let path = match &view.output_path {
+            Some(output_path) => format!("src/{}", output_path.trim_start_matches("./")),
+            None => format!("src/views/{}.jsx", component_ident(name)),
+        };
+        let file = GeneratedFile::text(path, view.to_string());
+        observer.view_finished(name, &file);
+        files.push(file);
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n").into());
+    }
+    Ok(files)
+}
+
+/// A standalone HTML preview of each view under `previews/`, with local assets linked from
+/// `asset_base_url`. Views are previewed even with validation errors, as work in progress.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(views = views.len())))]
+pub fn render_previews(views: &[ViewJsx], asset_base_url: &str, observer: &mut dyn GenObserver) -> Vec<GeneratedFile> {
+    let mut files = Vec::new();
+    for view in views {
+        let name = &view.proto.name;
+        observer.view_started(name);
+        let file = PreviewHtml::new(view).with_asset_base_url(asset_base_url).file();
+        observer.view_finished(name, &file);
+        files.push(file);
+    }
+    files
+}
+
+/// Write every file under `root`, skipping files whose contents haven't changed so a small
+/// edit doesn't make the bundler rebuild the whole app. With the `verify` feature, every
+/// script is parsed first and nothing is written when one has a syntax error.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(files = files.len(), root = %root.as_ref().display())))]
+pub fn write_files<P: AsRef<Path>>(files: &[GeneratedFile], root: P, observer: &mut dyn GenObserver) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "verify")]
+    crate::verify::verify_syntax(files)?;
+    for file in files {
+        if file.write_if_changed(&root)? {
+            observer.file_written(&file.path);
+        } else {
+            observer.file_unchanged(&file.path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl GenObserver for Events {
+        fn view_started(&mut self, name: &str) {
+            self.0.push(format!("started {}", name));
+        }
+        fn view_finished(&mut self, name: &str, file: &GeneratedFile) {
+            self.0.push(format!("finished {} -> {}", name, file.path.display()));
+        }
+        fn diagnostics(&mut self, source: &str, diagnostics: &[Diagnostic]) {
+            self.0.push(format!("{} problem(s) in {}", diagnostics.len(), source));
+        }
+        fn file_written(&mut self, path: &Path) {
+            self.0.push(format!("wrote {}", path.display()));
+        }
+        fn file_unchanged(&mut self, path: &Path) {
+            self.0.push(format!("kept {}", path.display()));
+        }
+    }
+
+    fn view(proto: &str) -> ViewJsx {
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        ViewJsx::new(
+            options.from_str(proto).unwrap(),
+            options.from_str("(components: [])").unwrap(),
+            options.from_str("(assets: [])").unwrap(),
+            options.from_str("(content: {})").unwrap(),
+        )
+    }
+
+    #[test]
+    fn checks_every_view_before_failing() {
+        let views = [
+            view(r#"(name: "Broken", tree: Repeat(count: 100000, template: Node(tag: "li")))"#),
+            view(r#"(name: "about page", tree: Node(tag: "main"))"#),
+        ];
+        let mut events = Events::default();
+        let error = render_views(&views, &mut events).unwrap_err();
+        assert!(error.to_string().contains("repeats 100000 times"), "{}", error);
+        assert_eq!(
+            events.0,
+            ["started Broken", "1 problem(s) in Broken", "started about page", "finished about page -> src/views/AboutPage.jsx"]
+        );
+    }
+
+    #[test]
+    fn skips_files_that_already_match() {
+        let root = std::env::temp_dir().join(format!("degenproto-generate-{}", std::process::id()));
+        let files = [GeneratedFile::text("src/a.js", "a\n")];
+        let mut events = Events::default();
+        write_files(&files, &root, &mut events).unwrap();
+        write_files(&files, &root, &mut events).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(events.0, ["wrote src/a.js", "kept src/a.js"]);
+    }
+
+
+    // Records each span as it is created: its name and fields
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct Spans(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Spans {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            struct Fields(String);
+            impl tracing::field::Visit for Fields {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                    self.0.push_str(&format!(" {}={:?}", field.name(), value));
+                }
+            }
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut spans = self.0.lock().unwrap();
+            spans.push(fields.0);
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+        fn event(&self, _event: &tracing::Event<'_>) {}
+        fn enter(&self, _span: &tracing::span::Id) {}
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn opens_spans_for_each_view() {
+        let spans = Spans::default();
+        let views = [view(r#"(name: "Home", tree: Node(tag: "main"))"#)];
+        tracing::subscriber::with_default(spans.clone(), || render_views(&views, &mut ()).unwrap());
+        assert_eq!(*spans.0.lock().unwrap(), ["render_views views=1", "validate view=Home", "render view=Home"]);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::paths::is_remote_path;
+use crate::view_proto::{AliasDefs, AssetDef, AssetKind};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Open a local image asset, found with AliasDefs::asset_file
+pub(crate) fn open_local_image(
+    asset: &AssetDef,
+    aliases: &AliasDefs,
+    project_root: &Path,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let path = match (&asset.kind, &asset.path) {
+        (AssetKind::Image, Some(path)) if !is_remote_path(path) => path,
+        _ => return Err(format!("asset `{}` must be a local image", asset.name).into()),
+    };
+    Ok(image::open(project_root.join(aliases.asset_file(path)))?)
+}
+
+/// Center-crop to a square and resize to `size` x `size`
+pub(crate) fn square_icon(source: &DynamicImage, size: u32) -> DynamicImage {
+    let side = source.width().min(source.height());
+    let square = source.crop_imm((source.width() - side) / 2, (source.height() - side) / 2, side, side);
+    square.resize_exact(size, size, FilterType::Lanczos3)
+}
+
+pub(crate) fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), format)?;
+    Ok(bytes)
+}
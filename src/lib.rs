@@ -1,46 +1,380 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::Path;
+use ron_format::to_ron_string;
+use view_jsx::encode_uri_component;
 
 mod router_jsx;
 pub use router_jsx::RouterJsx;
 
+mod names;
+
+mod paths;
+
+mod ron_format;
+
+mod expansion;
+pub use expansion::DEFAULT_MAX_EXPANSION_DEPTH;
+
 mod view_proto;
-pub use view_proto::{ViewProto, Import, ImportKind, Element, PropValue, ComponentDef, ComponentDefs, AssetDef, AssetDefs, AssetKind, ContentDefs, ContentValue};
+pub use view_proto::{ViewProto, LocalComponent, PropDecl, StateDecl, Import, ImportKind, Element, MatchArm, Variant, AnimationPreset, EmbedProvider, PropValue, Handler, Unit, ComponentDef, ComponentDefs, PropType, AssetDef, AssetDefs, AssetKind, ContentDefs, ContentValue, CollectionConfig, PaginationConfig, FeedConfig, FieldSchema, FieldKind, DerivedField, RichBlock, BlockStyle, ListKind, RichSpan, MarkDef, ThemeDefs, AliasDefs};
+
+mod markdown;
+
+mod rich_text;
 
 mod view_jsx;
 pub use view_jsx::ViewJsx;
 
+mod view_test_jsx;
+pub use view_test_jsx::ViewTestJsx;
+
+mod preview;
+pub use preview::PreviewHtml;
+
+mod minify;
+pub use minify::{minify_css, minify_html};
+
+mod editor_doc;
+pub use editor_doc::{EditOp, EditorDoc, EditorNode};
+
+mod options;
+pub use options::{BoolProps, ClassMerge, JsxOptions};
+
+mod date_format;
+pub use date_format::format_date;
+
+mod package_json;
+pub use package_json::PackageJson;
+
+mod barrel_jsx;
+pub use barrel_jsx::{BarrelEntry, BarrelJsx};
+
+mod output;
+pub use output::GeneratedFile;
+
+mod manifest;
+pub use manifest::{BuildManifest, ManifestDiff, ManifestEntry};
+
+mod dev_server;
+pub use dev_server::DevServer;
+
+mod archive;
+pub use archive::ProjectArchive;
+
+mod report;
+pub use report::{BuildReport, BuildStats};
+
+mod deploy;
+pub use deploy::{CopyTarget, DeployConfig, DeployReport, DeployTarget, IpfsTarget, S3Target};
+
+mod generate;
+pub use generate::{render_previews, render_views, write_files, GenObserver};
+
+mod project;
+pub use project::ProtoProject;
+
+mod vite_scaffold;
+pub use vite_scaffold::ViteScaffold;
+
+mod pwa;
+pub use pwa::{PwaConfig, PwaScaffold};
+
+mod icons;
+
+mod favicon;
+pub use favicon::FaviconScaffold;
+
+mod seo;
+pub use seo::SeoConfig;
+
+mod analytics;
+pub use analytics::{AnalyticsConfig, AnalyticsProvider};
+
+mod content_types;
+pub use content_types::ContentTypes;
+
+mod content_loader;
+pub use content_loader::ContentLoader;
+
+mod env_defs;
+pub use env_defs::{EnvDefs, EnvKind, EnvVar};
+
+mod cdn;
+pub use cdn::CdnConfig;
+
+mod search;
+pub use search::{SearchConfig, SearchEntry, SearchFields};
+
+mod feeds;
+pub use feeds::FeedScaffold;
+
+mod themes;
+pub use themes::ThemeSet;
+
+mod tenants;
+pub use tenants::{TenantDefs, TenantOverlay, TenantProject};
+
+mod placeholder;
+pub use placeholder::PlaceholderContent;
+
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "verify")]
+pub use verify::{syntax_errors, verify_syntax};
+
+mod diagnostics;
+pub use diagnostics::{Diagnostic, Severity};
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProtoIndex {
     pub layouts: Vec<Layout>,
     pub routes: Vec<Route>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub partials: Vec<Partial>,
+    /// Separate frontends built from this index; see ProtoIndex::app
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<App>,
+}
+
+/// A frontend (marketing site, dashboard, admin) with its own router and output directory,
+/// sharing the index's layouts and partials and the project's ComponentDefs and AssetDefs
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct App {
+    pub name: String,
+    /// Names of the routes the app serves; a route can belong to several apps
+    pub routes: Vec<String>,
+    /// Directory the app is generated into; the app name when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+}
+
+impl App {
+    pub fn dir(&self) -> &str {
+        self.output_dir.as_deref().unwrap_or(&self.name)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Layout {
     pub name: String,
     pub path: String,
+    /// View proto the layout is generated from, relative to the index; see ProtoProject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proto: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Route {
     pub name: String,
     pub url: String,
+    /// View proto the route's view is generated from, relative to the index; see ProtoProject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub proto: Option<String>,
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub layout: Option<String>,
+    /// OpenGraph/Twitter card tags for the route's view
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub social: Option<SocialMeta>,
+    /// Paginated ContentDefs collection; ProtoIndex::expand_pagination adds a route per page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paginate: Option<String>,
+    /// Set on routes produced by expand_pagination
+    #[serde(skip)]
+    pub page: Option<Page>,
+    /// Content collection whose records fill the url's `:param` segments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records: Option<RecordRoute>,
+    /// Set on routes produced by expand_records: the record the view renders
+    #[serde(skip)]
+    pub record: Option<HashMap<String, String>>,
+    /// Feature flag the route is gated behind; see ProtoIndex::with_flags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
+    /// Build environments the route is generated for ("dev", "staging"); every one when empty.
+    /// See ProtoIndex::for_environment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+    /// Data loaded before the view renders, read with useLoaderData; RouterJsx data-router mode only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<RouteLoader>,
+    /// Handles the view's `<Form method="post">` submissions; RouterJsx data-router mode only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<RouteAction>,
+}
+
+impl Route {
+    /// Names of the url's `:param` segments
+    pub fn url_params(&self) -> Vec<&str> {
+        self.url.split('/').filter_map(|segment| segment.strip_prefix(':')).collect()
+    }
+
+    /// The url with every `:param` segment filled from the record's field of the same name,
+    /// percent-encoded so values with spaces, `?` or `#` stay one segment
+    pub fn fill_url(&self, record: &HashMap<String, String>) -> Option<String> {
+        let segments = self
+            .url
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => record.get(param).map(|value| encode_uri_component(value)),
+                None => Some(segment.to_string()),
+            })
+            .collect::<Option<Vec<String>>>()?;
+        Some(segments.join("/"))
+    }
+
+    /// Name the view binds its loader data to, for ViewJsx::with_route_loader: `record` for a
+    /// collection loader matching url params, read with ContentField; `records` for a whole
+    /// collection, read by ContentLists over it; `data` otherwise, read with Var("data.title")
+    pub fn loader_binding(&self) -> Option<&'static str> {
+        match self.loader.as_ref()? {
+            RouteLoader::Collection(_) if !self.url_params().is_empty() => Some("record"),
+            RouteLoader::Collection(_) => Some("records"),
+            RouteLoader::Fetch(_) => Some("data"),
+        }
+    }
+
+    /// Runtime record lookup for a Dynamic records route
+    pub fn record_lookup(&self) -> Option<RecordLookup> {
+        let records = self.records.as_ref().filter(|r| r.mode == RecordMode::Dynamic)?;
+        Some(RecordLookup {
+            collection: records.collection.clone(),
+            params: self.url_params().into_iter().map(str::to_string).collect(),
+        })
+    }
+}
+
+/// Binds a route like `/blog/:slug` to a List in ContentDefs; each param names a record field
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordRoute {
+    pub collection: String,
+    #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
+    pub mode: RecordMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RecordMode {
+    /// One route per record, generated ahead of time
+    #[default]
+    Static,
+    /// A single `:param` route; the view finds its record from useParams at runtime
+    Dynamic,
+}
+
+/// Where a route's loader gets its data
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RouteLoader {
+    /// A ContentDefs collection. Routes with url params get the record whose fields of the
+    /// same names match them, or a 404.
+    Collection(String),
+    /// JSON from an API endpoint; `:param` segments are filled from the route's url params
+    Fetch(String),
+}
+
+/// An API endpoint a route's form submissions are sent to as form data
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RouteAction {
+    /// `:param` segments are filled from the route's url params
+    pub url: String,
+    /// HTTP method, POST when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Where to navigate after a successful submission; the view stays put when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<String>,
+}
+
+/// How a dynamic records view finds its record, for ViewJsx::with_record_lookup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordLookup {
+    pub collection: String,
+    /// Url params matched against the record fields of the same name
+    pub params: Vec<String>,
+}
+
+/// One page of a paginated collection, for ViewJsx::with_page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub collection: String,
+    /// 1-based page number
+    pub number: usize,
+    pub page_size: usize,
+    /// Url of every page, first page first
+    pub urls: Vec<String>,
+}
+
+impl Page {
+    /// Index range of the collection shown on this page
+    pub fn range(&self) -> std::ops::Range<usize> {
+        let start = (self.number - 1) * self.page_size;
+        start..start + self.page_size
+    }
+
+    pub fn prev_url(&self) -> Option<&str> {
+        self.number.checked_sub(2).map(|i| self.urls[i].as_str())
+    }
+
+    pub fn next_url(&self) -> Option<&str> {
+        self.urls.get(self.number).map(String::as_str)
+    }
+}
+
+/// Social preview metadata for a route, read from ContentDefs and AssetDefs
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SocialMeta {
+    /// Content key of the preview title
+    pub title: String,
+    /// Content key of the preview description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Image asset shown in the preview card
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Twitter card type; large image cards need a wide image
+    #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
+    pub twitter_card: TwitterCard,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TwitterCard {
+    Summary,
+    #[default]
+    SummaryLargeImage,
+}
+
+impl TwitterCard {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwitterCard::Summary => "summary",
+            TwitterCard::SummaryLargeImage => "summary_large_image",
+        }
+    }
+
+    /// Smallest image (width, height) the card accepts; OpenGraph needs at least 200x200 too
+    pub fn min_image_size(&self) -> (u32, u32) {
+        match self {
+            TwitterCard::Summary => (200, 200),
+            TwitterCard::SummaryLargeImage => (300, 200),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Partial {
     pub name: String,
     pub path: String,
+    /// View proto the partial is generated from, relative to the index; see ProtoProject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proto: Option<String>,
 }
 
 impl ProtoIndex {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "ProtoIndex", path = %path.as_ref().display()))
+    )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let options = ron::Options::default()
@@ -48,4 +382,449 @@ impl ProtoIndex {
         let index: ProtoIndex = options.from_str(&content)?;
         Ok(index)
     }
+
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
+    /// The index of one app: its routes and the layouts they use. Call before
+    /// expand_pagination and expand_records, which rename the routes they add.
+    pub fn app(&self, name: &str) -> Result<ProtoIndex, Box<dyn std::error::Error>> {
+        let app = self.apps.iter().find(|app| app.name == name).ok_or_else(|| {
+            let known: Vec<&str> = self.apps.iter().map(|app| app.name.as_str()).collect();
+            format!("unknown app `{}`; defined apps: {}", name, known.join(", "))
+        })?;
+        let routes: Vec<Route> = self.routes.iter().filter(|route| app.routes.contains(&route.name)).cloned().collect();
+        let layouts = self
+            .layouts
+            .iter()
+            .filter(|layout| routes.iter().any(|route| route.layout.as_ref() == Some(&layout.name)))
+            .cloned()
+            .collect();
+        Ok(ProtoIndex {
+            layouts,
+            routes,
+            partials: self.partials.clone(),
+            apps: Vec::new(),
+        })
+    }
+
+    /// Apps listing unknown routes, sharing a name or output directory or generated outside the
+    /// project, and routes no app serves
+    pub fn validate_apps(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.apps.is_empty() {
+            return diagnostics;
+        }
+        for (i, app) in self.apps.iter().enumerate() {
+            let path = format!("apps > {}", app.name);
+            let earlier = &self.apps[..i];
+            if earlier.iter().any(|other| other.name == app.name) {
+                diagnostics.push(Diagnostic::error(&path, "another app has the same name"));
+            } else if earlier.iter().any(|other| other.dir() == app.dir()) {
+                diagnostics.push(Diagnostic::error(&path, format!("output directory `{}` is used by another app", app.dir())));
+            }
+            let dir = paths::normalize_path(app.dir());
+            if dir == ".." || dir.starts_with("../") || dir.starts_with('/') {
+                diagnostics.push(Diagnostic::error(&path, format!("output directory `{}` is outside the project", app.dir())));
+            }
+            if app.routes.is_empty() {
+                diagnostics.push(Diagnostic::error(&path, "app has no routes"));
+            }
+            for route in &app.routes {
+                if !self.routes.iter().any(|r| &r.name == route) {
+                    diagnostics.push(Diagnostic::error(&path, format!("unknown route `{}`", route)));
+                }
+            }
+        }
+        for route in &self.routes {
+            if !self.apps.iter().any(|app| app.routes.contains(&route.name)) {
+                diagnostics.push(Diagnostic::warning(
+                    format!("routes > {}", route.name),
+                    "route is not in any app and won't be generated",
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    /// The index for a build profile: routes gated behind a flag not in `flags` are left out
+    pub fn with_flags(&self, flags: &BTreeSet<String>) -> ProtoIndex {
+        let mut index = self.clone();
+        index.routes.retain(|route| route.flag.as_ref().is_none_or(|flag| flags.contains(flag)));
+        index
+    }
+
+    /// The index for one build environment: routes scoped to other environments are left out,
+    /// so debug and playground pages stay out of production output
+    pub fn for_environment(&self, environment: &str) -> ProtoIndex {
+        let mut index = self.clone();
+        index
+            .routes
+            .retain(|route| route.environments.is_empty() || route.environments.iter().any(|env| env == environment));
+        index
+    }
+
+    /// Replace each route with `paginate` set by one route per page of its collection.
+    /// Page 1 keeps the route as is; page n becomes `{name}Page{n}` at the pagination url,
+    /// with its view at `{path stem}Page{n}.jsx`.
+    pub fn expand_pagination(&self, content: &ContentDefs) -> Result<ProtoIndex, Box<dyn std::error::Error>> {
+        let mut routes = Vec::new();
+        for route in &self.routes {
+            let Some(collection) = &route.paginate else {
+                routes.push(route.clone());
+                continue;
+            };
+            let pagination = content
+                .pagination(collection)
+                .ok_or_else(|| format!("route `{}` paginates `{}`, which has no pagination config", route.name, collection))?;
+            if pagination.page_size == 0 {
+                return Err(format!("collection `{}` has a page size of 0", collection).into());
+            }
+            if !pagination.url.contains(":n") {
+                return Err(format!("pagination url `{}` for `{}` has no `:n` segment", pagination.url, collection).into());
+            }
+            let count = content
+                .page_count(collection)
+                .ok_or_else(|| format!("route `{}` paginates `{}`, which is not a list", route.name, collection))?;
+
+            let mut urls = vec![route.url.clone()];
+            urls.extend((2..=count).map(|n| pagination.url.replace(":n", &n.to_string())));
+            for number in 1..=count {
+                let mut page_route = route.clone();
+                if number > 1 {
+                    page_route.name = format!("{}Page{}", route.name, number);
+                    page_route.url = urls[number - 1].clone();
+                    page_route.path = suffixed_path(&route.path, &format!("Page{}", number));
+                }
+                page_route.page = Some(Page {
+                    collection: collection.clone(),
+                    number,
+                    page_size: pagination.page_size,
+                    urls: urls.clone(),
+                });
+                routes.push(page_route);
+            }
+        }
+        Ok(ProtoIndex {
+            layouts: self.layouts.clone(),
+            routes,
+            partials: self.partials.clone(),
+            apps: self.apps.clone(),
+        })
+    }
+
+    /// Replace each Static records route by one route per record of its collection, with the
+    /// `:param` segments filled from the record's fields. `/blog/:slug` named `post` becomes
+    /// `/blog/hello-world` named `postHelloWorld`, with its view at `{path stem}HelloWorld.jsx`.
+    /// Records whose values give the same name or view path (`hello-world` and `hello_world`)
+    /// are an error. Dynamic records routes are kept as they are.
+    pub fn expand_records(&self, content: &ContentDefs) -> Result<ProtoIndex, Box<dyn std::error::Error>> {
+        let mut routes = Vec::new();
+        // Name and view path of each expanded route -> the url it was expanded for
+        let mut expanded: HashMap<String, String> = HashMap::new();
+        for route in &self.routes {
+            let Some(records) = route.records.as_ref().filter(|r| r.mode == RecordMode::Static) else {
+                routes.push(route.clone());
+                continue;
+            };
+            let params = route.url_params();
+            if params.is_empty() {
+                return Err(format!("route `{}` binds records but its url has no `:param` segment", route.name).into());
+            }
+            let list = content
+                .get_list(&records.collection)
+                .ok_or_else(|| format!("route `{}` binds `{}`, which is not a content list", route.name, records.collection))?;
+
+            for (position, item) in list.iter().enumerate() {
+                let ContentValue::Record(record) = item else {
+                    continue;
+                };
+                let mut suffix = String::new();
+                for param in &params {
+                    let value = record
+                        .get(*param)
+                        .ok_or_else(|| format!("a `{}` record has no `{}` field for route `{}`", records.collection, param, route.name))?;
+                    if value.is_empty() || value.contains('/') {
+                        return Err(format!("`{}` value `{}` can't be used as a url segment", param, value).into());
+                    }
+                    // Values with no ASCII letters or digits (`日本`) are named by their position
+                    match pascal_case(value) {
+                        part if part.is_empty() => suffix.push_str(&format!("Record{}", position + 1)),
+                        part => suffix.push_str(&part),
+                    }
+                }
+                let mut record_route = route.clone();
+                record_route.name = format!("{}{}", route.name, suffix);
+                record_route.url = route.fill_url(record).unwrap_or_default();
+                record_route.path = suffixed_path(&route.path, &suffix);
+                record_route.record = Some(record.clone());
+                for key in [&record_route.name, &record_route.path] {
+                    if let Some(other) = expanded.insert(key.clone(), record_route.url.clone()) {
+                        return Err(format!(
+                            "route `{}` expands `{}` and `{}` both to `{}`; give the records distinct {} values",
+                            route.name,
+                            other,
+                            record_route.url,
+                            key,
+                            params.join("/")
+                        )
+                        .into());
+                    }
+                }
+                routes.push(record_route);
+            }
+        }
+        Ok(ProtoIndex {
+            layouts: self.layouts.clone(),
+            routes,
+            partials: self.partials.clone(),
+            apps: self.apps.clone(),
+        })
+    }
+}
+
+// views/Blog.jsx + "Page2" -> views/BlogPage2.jsx
+fn suffixed_path(path: &str, suffix: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => format!("{}{}.{}", stem, suffix, ext),
+        _ => format!("{}{}", path, suffix),
+    }
+}
+
+// "hello-world" -> "HelloWorld"
+fn pascal_case(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(names::capitalize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(routes: &str) -> ProtoIndex {
+        ron::from_str(&format!("(layouts: [], routes: {})", routes)).unwrap()
+    }
+
+    fn posts(slugs: &[&str]) -> ContentDefs {
+        let records: Vec<String> = slugs
+            .iter()
+            .map(|slug| format!(r#"Record({{"slug": "{}", "title": "T"}})"#, slug))
+            .collect();
+        ron::from_str(&format!(
+            r#"(content: {{"posts": List([{}])}})"#,
+            records.join(", ")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn expands_static_records_routes() {
+        let index = index(
+            r#"[(name: "home", url: "/", path: "views/Home.jsx"), (name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts")))]"#,
+        );
+        let expanded = index
+            .expand_records(&posts(&["hello-world", "second"]))
+            .unwrap();
+
+        let routes: Vec<(&str, &str, &str)> = expanded
+            .routes
+            .iter()
+            .map(|r| (r.name.as_str(), r.url.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("home", "/", "views/Home.jsx"),
+                (
+                    "postHelloWorld",
+                    "/blog/hello-world",
+                    "views/PostHelloWorld.jsx"
+                ),
+                ("postSecond", "/blog/second", "views/PostSecond.jsx"),
+            ]
+        );
+        assert_eq!(
+            expanded.routes[1].record.as_ref().unwrap()["slug"],
+            "hello-world"
+        );
+    }
+
+    #[test]
+    fn keeps_dynamic_records_routes() {
+        let index = index(
+            r#"[(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts", mode: Dynamic)))]"#,
+        );
+        let expanded = index.expand_records(&posts(&["a", "b"])).unwrap();
+        assert_eq!(expanded.routes.len(), 1);
+        assert_eq!(expanded.routes[0].url, "/blog/:slug");
+    }
+
+    #[test]
+    fn rejects_records_that_expand_to_the_same_route() {
+        let index = index(
+            r#"[(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts")))]"#,
+        );
+        let error = index
+            .expand_records(&posts(&["hello-world", "hello_world"]))
+            .unwrap_err();
+        assert!(error.to_string().contains("both to"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unusable_url_segments() {
+        let index = index(
+            r#"[(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts")))]"#,
+        );
+        assert!(index.expand_records(&posts(&["a/b"])).is_err());
+        assert!(index.expand_records(&posts(&[""])).is_err());
+    }
+
+    #[test]
+    fn fills_url_params_by_whole_segment() {
+        let route: Route =
+            ron::from_str(r#"(name: "item", url: "/items/:id/:idx", path: "views/Item.jsx")"#)
+                .unwrap();
+        let record = HashMap::from([
+            ("id".to_string(), "7".to_string()),
+            ("idx".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(route.fill_url(&record).as_deref(), Some("/items/7/2"));
+    }
+
+    #[test]
+    fn percent_encodes_record_values_in_urls() {
+        let index = index(
+            r#"[(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts")))]"#,
+        );
+        let expanded = index.expand_records(&posts(&["what? #1 now"])).unwrap();
+        assert_eq!(expanded.routes[0].url, "/blog/what%3F%20%231%20now");
+        assert_eq!(expanded.routes[0].name, "postWhat1Now");
+    }
+
+    #[test]
+    fn names_records_without_ascii_by_position() {
+        let index = index(
+            r#"[(name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: Some((collection: "posts")))]"#,
+        );
+        let expanded = index.expand_records(&posts(&["日本", "ελλάδα"])).unwrap();
+        let names: Vec<(&str, &str)> = expanded.routes.iter().map(|r| (r.name.as_str(), r.path.as_str())).collect();
+        assert_eq!(names, [("postRecord1", "views/PostRecord1.jsx"), ("postRecord2", "views/PostRecord2.jsx")]);
+        assert_eq!(expanded.routes[0].url, "/blog/%E6%97%A5%E6%9C%AC");
+    }
+
+    #[test]
+    fn expands_paginated_routes_with_prev_and_next_urls() {
+        let index = index(r#"[(name: "blog", url: "/blog", path: "views/Blog.jsx", paginate: Some("posts"))]"#);
+        let mut content = posts(&["a", "b", "c", "d", "e"]);
+        content.collections = ron::from_str(r#"{"posts": (pagination: Some((page_size: 2, url: "/blog/page/:n")))}"#).unwrap();
+
+        let expanded = index.expand_pagination(&content).unwrap();
+        let routes: Vec<(&str, &str, &str)> = expanded
+            .routes
+            .iter()
+            .map(|r| (r.name.as_str(), r.url.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("blog", "/blog", "views/Blog.jsx"),
+                ("blogPage2", "/blog/page/2", "views/BlogPage2.jsx"),
+                ("blogPage3", "/blog/page/3", "views/BlogPage3.jsx"),
+            ]
+        );
+        let last = expanded.routes[2].page.as_ref().unwrap();
+        assert_eq!(last.range(), 4..6);
+        assert_eq!(last.prev_url(), Some("/blog/page/2"));
+        assert_eq!(last.next_url(), None);
+        assert_eq!(expanded.routes[0].page.as_ref().unwrap().prev_url(), None);
+
+        content.collections.clear();
+        let error = index.expand_pagination(&content).unwrap_err();
+        assert_eq!(error.to_string(), "route `blog` paginates `posts`, which has no pagination config");
+    }
+
+
+    #[test]
+    fn writes_canonical_ron_that_loads_back() {
+        let source = r#"(layouts: [(name: "main", path: "layouts/Main.jsx")], routes: [(name: "post", url: "/blog/:slug", path: "views/Post.jsx", layout: Some("main"), records: Some((collection: "posts", mode: Static)))])"#;
+        let ron = ron::from_str::<ProtoIndex>(source).unwrap().to_ron_string().unwrap();
+        assert!(ron.starts_with("#![enable(implicit_some)]\n(\n    layouts: [\n"), "{}", ron);
+        assert!(ron.contains("            layout: \"main\",\n            records: (\n                collection: \"posts\",\n            ),\n"), "{}", ron);
+        assert!(!ron.contains("partials") && !ron.contains("mode"), "{}", ron);
+        assert_eq!(ron::from_str::<ProtoIndex>(&ron).unwrap().to_ron_string().unwrap(), ron);
+    }
+
+
+    #[test]
+    fn splits_the_index_into_apps() {
+        let index: ProtoIndex = ron::from_str(
+            r#"(
+                layouts: [(name: "site", path: "layouts/Site.jsx"), (name: "admin", path: "layouts/Admin.jsx")],
+                routes: [
+                    (name: "home", url: "/", path: "views/Home.jsx", layout: Some("site")),
+                    (name: "users", url: "/users", path: "views/Users.jsx", layout: Some("admin")),
+                ],
+                apps: [(name: "marketing", routes: ["home"], output_dir: Some("sites/marketing")), (name: "admin", routes: ["users"])],
+            )"#,
+        )
+        .unwrap();
+        assert!(index.validate_apps().is_empty(), "{:?}", index.validate_apps());
+        assert_eq!(index.apps[0].dir(), "sites/marketing");
+        assert_eq!(index.apps[1].dir(), "admin");
+
+        let admin = index.app("admin").unwrap();
+        assert_eq!(admin.routes.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["users"]);
+        assert_eq!(admin.layouts.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["admin"]);
+        assert!(admin.apps.is_empty());
+        assert_eq!(index.app("docs").unwrap_err().to_string(), "unknown app `docs`; defined apps: marketing, admin");
+    }
+
+    #[test]
+    fn reports_misconfigured_apps() {
+        let mut index = index(
+            r#"[(name: "home", url: "/", path: "views/Home.jsx"), (name: "debug", url: "/debug", path: "views/Debug.jsx")]"#,
+        );
+        index.apps = ron::from_str(
+            r#"[(name: "site", routes: ["home", "about"]), (name: "site", routes: ["home"]), (name: "out", routes: [], output_dir: Some("../out"))]"#,
+        )
+        .unwrap();
+        let messages: Vec<String> = index.validate_apps().into_iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "error: unknown route `about` (at apps > site)",
+                "error: another app has the same name (at apps > site)",
+                "error: output directory `../out` is outside the project (at apps > out)",
+                "error: app has no routes (at apps > out)",
+                "warning: route is not in any app and won't be generated (at routes > debug)",
+            ]
+        );
+    }
+
+
+    #[test]
+    fn leaves_out_routes_behind_disabled_flags() {
+        let index = index(
+            r#"[(name: "home", url: "/", path: "views/Home.jsx"), (name: "checkout", url: "/checkout", path: "views/Checkout.jsx", flag: Some("checkout_v2"))]"#,
+        );
+        let names = |index: ProtoIndex| index.routes.into_iter().map(|r| r.name).collect::<Vec<_>>();
+        assert_eq!(names(index.with_flags(&BTreeSet::new())), ["home"]);
+        assert_eq!(names(index.with_flags(&BTreeSet::from(["checkout_v2".to_string()]))), ["home", "checkout"]);
+    }
+
+
+    #[test]
+    fn keeps_environment_scoped_routes_out_of_other_builds() {
+        let index = index(
+            r#"[(name: "home", url: "/", path: "views/Home.jsx"), (name: "playground", url: "/playground", path: "views/Playground.jsx", environments: ["dev", "staging"])]"#,
+        );
+        let names = |index: ProtoIndex| index.routes.into_iter().map(|r| r.name).collect::<Vec<_>>();
+        assert_eq!(names(index.for_environment("production")), ["home"]);
+        assert_eq!(names(index.for_environment("staging")), ["home", "playground"]);
+    }
 }
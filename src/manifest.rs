@@ -0,0 +1,152 @@
+use crate::output::GeneratedFile;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a generation run produced, for deploy tooling to tell which files changed between builds
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BuildManifest {
+    /// Seconds since the Unix epoch when the build ran
+    pub generated_at: u64,
+    /// Output path (forward slashes) -> entry
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    /// Hex-encoded SHA-256 of the contents
+    pub sha256: String,
+    pub size: u64,
+    /// Proto files the output was generated from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+}
+
+/// Paths that differ between two manifests, each list sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl BuildManifest {
+    /// Hash every file, stamped with the current time
+    pub fn new(files: &[GeneratedFile]) -> Self {
+        let generated_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Self {
+            generated_at,
+            files: files
+                .iter()
+                .map(|file| {
+                    let entry = ManifestEntry {
+                        sha256: sha256_hex(&file.contents),
+                        size: file.contents.len() as u64,
+                        sources: Vec::new(),
+                    };
+                    (manifest_path(&file.path), entry)
+                })
+                .collect(),
+        }
+    }
+
+    /// Record the protos a file was generated from; paths not in the manifest are ignored
+    pub fn with_sources<I, S>(mut self, path: impl AsRef<Path>, sources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if let Some(entry) = self.files.get_mut(&manifest_path(path.as_ref())) {
+            entry.sources.extend(sources.into_iter().map(Into::into));
+        }
+        self
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default() + "\n"
+    }
+
+    /// The manifest as a file to write next to the outputs it describes
+    pub fn file(&self, path: impl Into<PathBuf>) -> GeneratedFile {
+        GeneratedFile::text(path, self.to_json())
+    }
+
+    /// Files added, removed or changed in content since `previous`
+    pub fn diff(&self, previous: &BuildManifest) -> ManifestDiff {
+        let mut diff = ManifestDiff::default();
+        for (path, entry) in &self.files {
+            match previous.files.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old) if old.sha256 != entry.sha256 => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = previous.files.keys().filter(|path| !self.files.contains_key(*path)).cloned().collect();
+        diff
+    }
+}
+
+fn manifest_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+pub(crate) fn sha256_hex(contents: &[u8]) -> String {
+    Sha256::digest(contents).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_by_content_hash() {
+        let previous = BuildManifest::new(&[
+            GeneratedFile::text("src/a.js", "a"),
+            GeneratedFile::text("src/b.js", "b"),
+            GeneratedFile::text("src/gone.js", "x"),
+        ]);
+        let current = BuildManifest::new(&[
+            GeneratedFile::text("src/a.js", "a"),
+            GeneratedFile::text("src/b.js", "b2"),
+            GeneratedFile::text("src/new.js", "n"),
+        ]);
+        assert_eq!(
+            current.diff(&previous),
+            ManifestDiff {
+                added: vec!["src/new.js".to_string()],
+                removed: vec!["src/gone.js".to_string()],
+                changed: vec!["src/b.js".to_string()],
+            }
+        );
+        assert!(current.diff(&current).is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_json_with_sources() {
+        let manifest = BuildManifest::new(&[GeneratedFile::text("src/views/Home.jsx", "abc")])
+            .with_sources("src/views/Home.jsx", ["views/home.ron"])
+            .with_sources("src/missing.js", ["views/other.ron"]);
+        let entry = &manifest.files["src/views/Home.jsx"];
+        assert_eq!(entry.sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(entry.size, 3);
+        assert_eq!(entry.sources, ["views/home.ron"]);
+        assert_eq!(manifest.files.len(), 1);
+
+        let parsed: BuildManifest = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+}
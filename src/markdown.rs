@@ -0,0 +1,231 @@
+use crate::view_proto::{Element, PropValue};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
+
+/// Parse markdown into element children: paragraphs, headings, lists, links, images,
+/// emphasis and code become the matching HTML nodes
+pub(crate) fn markdown_to_elements(markdown: &str) -> Vec<Element> {
+    // Open nodes; the bottom entry collects the top-level blocks
+    let mut stack: Vec<Element> = vec![node("div", HashMap::new())];
+
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Start(tag) => stack.push(start_node(tag)),
+            Event::End(TagEnd::Image) => {
+                // The image's text events are its alt text
+                let mut image = stack.pop().expect("image was opened");
+                if let Element::Node { props, children, .. } = &mut image {
+                    let alt: String = children.drain(..).filter_map(|c| plain_text(&c)).collect();
+                    props.insert("alt".to_string(), PropValue::Str(alt));
+                }
+                push_child(&mut stack, image);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                // <pre><code>...</code></pre>, with the language kept on the pre
+                let mut pre = stack.pop().expect("code block was opened");
+                if let Element::Node { children, .. } = &mut pre {
+                    let mut code = node("code", HashMap::new());
+                    if let Element::Node { children: code_children, .. } = &mut code {
+                        code_children.append(children);
+                    }
+                    children.push(Box::new(code));
+                }
+                push_child(&mut stack, pre);
+            }
+            Event::End(_) => {
+                let finished = stack.pop().expect("tag was opened");
+                push_child(&mut stack, finished);
+            }
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                push_child(&mut stack, Element::Text(text.into_string()));
+            }
+            Event::Code(code) => {
+                let mut code_node = node("code", HashMap::new());
+                if let Element::Node { children, .. } = &mut code_node {
+                    children.push(Box::new(Element::Text(code.into_string())));
+                }
+                push_child(&mut stack, code_node);
+            }
+            Event::SoftBreak => push_child(&mut stack, Element::Text(" ".to_string())),
+            Event::HardBreak => push_child(&mut stack, node("br", HashMap::new())),
+            Event::Rule => push_child(&mut stack, node("hr", HashMap::new())),
+            _ => {}
+        }
+    }
+
+    match stack.pop() {
+        Some(Element::Node { children, .. }) => children.into_iter().map(|child| content_text(*child)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The markdown's text with the markup dropped, blocks separated by spaces
+pub(crate) fn markdown_plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn start_node(tag: Tag) -> Element {
+    let mut props = HashMap::new();
+    let name = match tag {
+        Tag::Paragraph => "p",
+        Tag::Heading { level, .. } => match level {
+            HeadingLevel::H1 => "h1",
+            HeadingLevel::H2 => "h2",
+            HeadingLevel::H3 => "h3",
+            HeadingLevel::H4 => "h4",
+            HeadingLevel::H5 => "h5",
+            HeadingLevel::H6 => "h6",
+        },
+        Tag::BlockQuote(_) => "blockquote",
+        Tag::CodeBlock(kind) => {
+            if let CodeBlockKind::Fenced(lang) = kind
+                && !lang.is_empty()
+            {
+                props.insert("data-language".to_string(), PropValue::Str(lang.into_string()));
+            }
+            "pre"
+        }
+        Tag::List(Some(start)) => {
+            if start != 1 {
+                props.insert("start".to_string(), PropValue::Num(start as f64));
+            }
+            "ol"
+        }
+        Tag::List(None) => "ul",
+        Tag::Item => "li",
+        Tag::Emphasis => "em",
+        Tag::Strong => "strong",
+        Tag::Strikethrough => "del",
+        Tag::Link { dest_url, title, .. } => {
+            if is_safe_href(&dest_url) {
+                props.insert("href".to_string(), PropValue::Str(dest_url.into_string()));
+            }
+            if !title.is_empty() {
+                props.insert("title".to_string(), PropValue::Str(title.into_string()));
+            }
+            "a"
+        }
+        Tag::Image { dest_url, title, .. } => {
+            props.insert("src".to_string(), PropValue::Str(dest_url.into_string()));
+            if !title.is_empty() {
+                props.insert("title".to_string(), PropValue::Str(title.into_string()));
+            }
+            "img"
+        }
+        // Anything else (tables, footnotes, html blocks) keeps its content in a div
+        _ => "div",
+    };
+    node(name, props)
+}
+
+fn node(tag: &str, props: HashMap<String, PropValue>) -> Element {
+    Element::Node {
+        tag: tag.to_string(),
+        class_name: None,
+        props,
+        children: Vec::new(),
+        id: None,
+    }
+}
+
+fn push_child(stack: &mut [Element], child: Element) {
+    if let Some(Element::Node { children, .. }) = stack.last_mut() {
+        // Adjacent text events (split around entities) read better as one run
+        if let (Element::Text(next), Some(last)) = (&child, children.last_mut())
+            && let Element::Text(previous) = last.as_mut()
+        {
+            previous.push_str(next);
+            return;
+        }
+        children.push(Box::new(child));
+    }
+}
+
+fn plain_text(element: &Element) -> Option<String> {
+    match element {
+        Element::Text(text) => Some(text.clone()),
+        Element::Node { children, .. } => Some(children.iter().filter_map(|c| plain_text(c)).collect()),
+        _ => None,
+    }
+}
+
+/// Whether a content link may be followed: http(s), mailto or a relative URL. Links to
+/// anything else, `javascript:` included, are written without their href.
+pub(crate) fn is_safe_href(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside the scheme
+    let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            matches!(url[..i].to_ascii_lowercase().as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// Turn content text runs into ContentText, so they are never translated
+pub(crate) fn content_text(element: Element) -> Element {
+    match element {
+        Element::Text(text) => Element::ContentText(text),
+        Element::Node { tag, class_name, props, children, id } => Element::Node {
+            tag,
+            class_name,
+            props,
+            children: children.into_iter().map(|child| Box::new(content_text(*child))).collect(),
+            id,
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_html_nodes_from_markdown() {
+        let elements = markdown_to_elements("# Title\n\nSome *em* and [a link](https://example.com \"Go\").\n\n```rust\nfn main() {}\n```\n");
+        assert_eq!(elements.len(), 3);
+        let Element::Node { tag, children, .. } = &elements[0] else { panic!("{:?}", elements[0]) };
+        assert_eq!(tag, "h1");
+        assert!(matches!(children[0].as_ref(), Element::ContentText(t) if t == "Title"));
+
+        let Element::Node { children, .. } = &elements[1] else { panic!("{:?}", elements[1]) };
+        let Element::Node { tag, props, .. } = children[3].as_ref() else { panic!("{:?}", children[3]) };
+        assert_eq!(tag, "a");
+        assert!(matches!(&props["href"], PropValue::Str(href) if href == "https://example.com"));
+        assert!(matches!(&props["title"], PropValue::Str(title) if title == "Go"));
+
+        let Element::Node { tag, props, children, .. } = &elements[2] else { panic!("{:?}", elements[2]) };
+        assert_eq!(tag, "pre");
+        assert!(matches!(&props["data-language"], PropValue::Str(lang) if lang == "rust"));
+        assert!(matches!(children[0].as_ref(), Element::Node { tag, .. } if tag == "code"));
+    }
+
+    #[test]
+    fn drops_unsafe_link_targets() {
+        assert!(is_safe_href("https://example.com"));
+        assert!(is_safe_href("mailto:hi@example.com"));
+        assert!(is_safe_href("/blog/post?x=a:b"));
+        assert!(!is_safe_href("javascript:alert(1)"));
+        assert!(!is_safe_href("java\tscript:alert(1)"));
+        assert!(!is_safe_href(" JAVASCRIPT:alert(1)"));
+
+        let elements = markdown_to_elements("[click](javascript:alert(1))");
+        let Element::Node { children, .. } = &elements[0] else { panic!("{:?}", elements[0]) };
+        assert!(matches!(children[0].as_ref(), Element::Node { props, .. } if props.is_empty()));
+    }
+
+    #[test]
+    fn flattens_markdown_to_text() {
+        let text = markdown_plain_text("**Bold** and `code`\n\n- [item](/x)");
+        assert_eq!(text.split_whitespace().collect::<Vec<_>>(), ["Bold", "and", "code", "item"]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Whitespace and comment stripping for static HTML outputs, so exported pages are small
+//! enough to host as they are
+
+// Elements whose contents are kept exactly as written (style is minified as CSS instead)
+const RAW_TEXT_TAGS: &[&str] = &["pre", "textarea", "script", "style"];
+// Elements whitespace next to can't show up on the page
+const BLOCK_TAGS: &[&str] = &[
+    "!doctype", "html", "head", "body", "meta", "link", "title", "base", "style", "script", "noscript", "main",
+    "header", "footer", "nav", "section", "article", "aside", "div", "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul",
+    "ol", "li", "dl", "dt", "dd", "table", "thead", "tbody", "tfoot", "tr", "td", "th", "form", "fieldset",
+    "figure", "figcaption", "blockquote", "hr", "br", "iframe", "video", "audio", "source", "center", "pre",
+];
+
+/// HTML with comments removed and whitespace collapsed. Whitespace next to inline elements
+/// shrinks to one space rather than going away, so text doesn't run together; `pre`,
+/// `textarea` and `script` contents and Outlook conditional comments are kept as written.
+pub fn minify_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut last_tag = String::new();
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map(|i| i + 3).unwrap_or(comment.len());
+            if comment.starts_with("[if") || comment.starts_with("<![endif]") {
+                output.push_str(&rest[..4 + end]);
+            }
+            rest = &comment[end..];
+        } else if rest.starts_with('<') {
+            let end = tag_end(rest);
+            let tag = &rest[..end];
+            output.push_str(&collapse_tag(tag));
+            last_tag = tag_name(tag);
+            rest = &rest[end..];
+
+            let name = last_tag.as_str();
+            if !tag.starts_with("</") && RAW_TEXT_TAGS.contains(&name) {
+                let close = format!("</{}", name);
+                let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                if name == "style" {
+                    output.push_str(&minify_css(&rest[..end]));
+                } else {
+                    output.push_str(&rest[..end]);
+                }
+                rest = &rest[end..];
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let mut text = collapse_whitespace(&rest[..end]);
+            rest = &rest[end..];
+            if output.is_empty() || output.ends_with(' ') || BLOCK_TAGS.contains(&last_tag.as_str()) {
+                text = text.trim_start().to_string();
+            }
+            let next = skip_comments(rest);
+            if next.is_empty() || next.starts_with('<') && BLOCK_TAGS.contains(&tag_name(next).as_str()) {
+                text = text.trim_end().to_string();
+            }
+            output.push_str(&text);
+        }
+    }
+    output
+}
+
+/// CSS with comments removed and the whitespace around punctuation dropped. Selectors keep
+/// the space before a pseudo-class, since `.a :hover` and `.a:hover` select different elements.
+pub fn minify_css(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut pending_space = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' | '\'' => {
+                flush_space(&mut output, &mut pending_space, c);
+                output.push(c);
+                let mut escaped = false;
+                for inner in chars.by_ref() {
+                    output.push(inner);
+                    if escaped {
+                        escaped = false;
+                    } else if inner == '\\' {
+                        escaped = true;
+                    } else if inner == c {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => pending_space = true,
+            ':' => {
+                // Before a `:` in a selector the space is a descendant combinator (`.a :hover`);
+                // in a declaration (`color : red`) it can go
+                if chars.clone().find(|c| matches!(c, '{' | ';' | '}')) != Some('{') {
+                    pending_space = false;
+                }
+                flush_space(&mut output, &mut pending_space, c);
+                output.push(c);
+            }
+            '}' => {
+                if output.ends_with(';') {
+                    output.pop();
+                }
+                pending_space = false;
+                output.push(c);
+            }
+            _ => {
+                flush_space(&mut output, &mut pending_space, c);
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+// A space between two tokens, unless punctuation on either side makes it unnecessary
+fn flush_space(output: &mut String, pending_space: &mut bool, next: char) {
+    if std::mem::take(pending_space)
+        && !output.is_empty()
+        && !output.ends_with(['{', '}', ';', ',', '>', ':'])
+        && !matches!(next, '{' | '}' | ';' | ',' | '>')
+    {
+        output.push(' ');
+    }
+}
+
+/// `html` past any comments (and the whitespace between them) that minifying removes
+fn skip_comments(mut html: &str) -> &str {
+    loop {
+        let trimmed = html.trim_start();
+        match trimmed.strip_prefix("<!--") {
+            Some(comment) if !comment.starts_with("[if") && !comment.starts_with("<![endif]") => {
+                html = comment.find("-->").map(|i| &comment[i + 3..]).unwrap_or("");
+            }
+            _ => return trimmed,
+        }
+    }
+}
+
+/// Byte length of the tag at the start of `html`, quoted `>` included
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Lowercase name of the tag at the start of `html`: `</Div class>` -> `div`
+fn tag_name(html: &str) -> String {
+    html.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// A tag with whitespace runs outside attribute values collapsed, and none around `=` or before `>` and `/>`
+fn collapse_tag(tag: &str) -> String {
+    let mut output = String::with_capacity(tag.len());
+    let mut quote = None;
+    let mut pending_space = false;
+    for c in tag.chars() {
+        match quote {
+            Some(q) => {
+                output.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => pending_space = true,
+            None => {
+                if std::mem::take(&mut pending_space) && !matches!(c, '>' | '/' | '=') && !output.ends_with('=') {
+                    output.push(' ');
+                }
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+        } else {
+            if std::mem::take(&mut pending_space) {
+                output.push(' ');
+            }
+            output.push(c);
+        }
+    }
+    if pending_space {
+        output.push(' ');
+    }
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_comments_and_space_around_punctuation() {
+        assert_eq!(
+            minify_css("/* c */ a , b > c { color : red ; margin: 0 auto ; }"),
+            "a,b>c{color:red;margin:0 auto}"
+        );
+        assert_eq!(
+            minify_css("@media (max-width: 600px) {\n  .x { margin: 0; }\n}\n"),
+            "@media (max-width:600px){.x{margin:0}}"
+        );
+    }
+
+    #[test]
+    fn keeps_the_space_before_a_selector_pseudo_class() {
+        assert_eq!(
+            minify_css(".a :not(.b) { color: red }"),
+            ".a :not(.b){color:red}"
+        );
+        assert_eq!(
+            minify_css("a :hover , b::before { x: y }"),
+            "a :hover,b::before{x:y}"
+        );
+        assert_eq!(
+            minify_css(".a > :first-child { x: y }"),
+            ".a>:first-child{x:y}"
+        );
+    }
+
+    #[test]
+    fn leaves_strings_alone() {
+        assert_eq!(
+            minify_css(r#"a { content : "a  ;  b" ; }"#),
+            r#"a{content:"a  ;  b"}"#
+        );
+        assert_eq!(
+            minify_css(r#"a { content: 'it\'s  /* here */' }"#),
+            r#"a{content:'it\'s  /* here */'}"#
+        );
+    }
+
+    #[test]
+    fn minifies_html_around_inline_and_raw_text_elements() {
+        let html = "<div>\n  <p>\n    Hello   <b>bold</b>   world\n  </p>\n  <pre>  keep\n  this</pre>\n  <!-- gone -->\n</div>";
+        assert_eq!(
+            minify_html(html),
+            "<div><p>Hello <b>bold</b> world</p><pre>  keep\n  this</pre></div>"
+        );
+        assert_eq!(
+            minify_html("<p>a <!-- x --> b</p>\n<!-- y -->\n<p>c</p>"),
+            "<p>a b</p><p>c</p>"
+        );
+        assert_eq!(
+            minify_html("<style> a { color : red ; } </style>"),
+            "<style>a{color:red}</style>"
+        );
+    }
+}
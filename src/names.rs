@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+// Globals generated code relies on; a component named after one would shadow it
+const RESERVED: &[&str] = &[
+    "Array", "Boolean", "Date", "Error", "Function", "Infinity", "Intl", "JSON", "Map", "Math", "NaN", "Number",
+    "Object", "Promise", "Proxy", "React", "Reflect", "RegExp", "Set", "String", "Symbol", "URL", "WeakMap", "WeakSet",
+];
+
+/// A valid JSX component identifier for a route, layout or view name: words are joined in
+/// PascalCase and other characters dropped. `my-page` -> `MyPage`, `about us` -> `AboutUs`,
+/// `404` -> `Component404`, `object` -> `ObjectComponent`.
+pub(crate) fn component_ident(name: &str) -> String {
+    let mut ident: String = name
+        .split(|c: char| !(c.is_alphanumeric() || c == '$'))
+        .filter(|word| !word.is_empty())
+        .map(capitalize)
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_numeric()) {
+        ident.insert_str(0, "Component");
+    }
+    if RESERVED.contains(&ident.as_str()) {
+        ident.push_str("Component");
+    }
+    ident
+}
+
+/// `s` with its first character uppercased
+pub(crate) fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
+
+/// Identifiers already taken in a generated module; hands out unused ones
+#[derive(Debug, Default)]
+pub(crate) struct NameSet {
+    used: HashSet<String>,
+}
+
+impl NameSet {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.used.contains(name)
+    }
+
+    /// Mark a name as taken, e.g. one the module declares itself
+    pub(crate) fn reserve(&mut self, name: impl Into<String>) {
+        self.used.insert(name.into());
+    }
+
+    /// `base` if it is free, otherwise `base2`, `base3`, ...; the result is marked as taken
+    pub(crate) fn unique(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut counter = 2;
+        while self.used.contains(&name) {
+            name = format!("{}{}", base, counter);
+            counter += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_component_identifiers() {
+        assert_eq!(component_ident("my-page"), "MyPage");
+        assert_eq!(component_ident("about us"), "AboutUs");
+        assert_eq!(component_ident("404"), "Component404");
+        assert_eq!(component_ident("object"), "ObjectComponent");
+        assert_eq!(component_ident("--"), "Component");
+    }
+
+    #[test]
+    fn hands_out_unused_names() {
+        let mut names = NameSet::new();
+        names.reserve("Button");
+        assert!(names.contains("Button"));
+        assert_eq!(names.unique("Button"), "Button2");
+        assert_eq!(names.unique("Button"), "Button3");
+        assert_eq!(names.unique("logo"), "logo");
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Options controlling how JSX is generated
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JsxOptions {
+    /// Emit plain `<a href>` tags for Link elements instead of react-router `<Link to>`
+    pub static_links: bool,
+    /// How a base class_name combines with a className prop on the same element
+    pub class_merge: ClassMerge,
+    /// How boolean prop values are written
+    pub bool_props: BoolProps,
+    /// Reject escape hatches such as Expr props during validation
+    pub strict: bool,
+    /// Experiment name -> variant to emit. Experiments not listed pick their variant at runtime.
+    pub experiments: HashMap<String, String>,
+    /// Emit Text elements, text props and Content strings as react-i18next `t("key")` calls
+    pub i18n: bool,
+    /// Emit `Component.propTypes` for the view and its sub-components from their declared props
+    pub prop_types: bool,
+    /// Longest chain of components expanding into one another before validation reports it;
+    /// DEFAULT_MAX_EXPANSION_DEPTH when unset
+    pub max_expansion_depth: Option<usize>,
+    /// Feature flags enabled for this build profile; Flag elements and components gated
+    /// behind any other flag are left out
+    pub flags: BTreeSet<String>,
+    /// Emit element ids as `data-proto-id` attributes, so a visual editor can map the rendered
+    /// DOM back to proto elements. ComponentRefs pass it on as a prop.
+    pub proto_ids: bool,
+    /// BCP 47 locale runtime dates are formatted in with Intl.DateTimeFormat; "en-US" when
+    /// unset, matching the English names build-time dates are written with
+    pub locale: Option<String>,
+}
+
+/// Strategy for combining a component's class_name with a caller's className
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ClassMerge {
+    /// `className={clsx("base classes", override)}`
+    #[default]
+    Clsx,
+    /// The caller's className replaces the base classes
+    Override,
+}
+
+/// Output form for boolean props
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BoolProps {
+    /// `disabled` for true, `disabled={false}` for false
+    #[default]
+    Shorthand,
+    /// `disabled={true}` / `disabled={false}`
+    Explicit,
+    /// `disabled="true"` / `disabled="false"`
+    String,
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file produced by a generator, with a path relative to the output root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+impl GeneratedFile {
+    pub fn text(path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            contents: contents.into().into_bytes(),
+        }
+    }
+
+    /// The same file under `dir`
+    pub fn in_dir(self, dir: impl AsRef<Path>) -> Self {
+        Self {
+            path: dir.as_ref().join(self.path),
+            contents: self.contents,
+        }
+    }
+
+    /// Write the file under `root`, creating parent directories as needed
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "write", skip_all, err, fields(path = %self.path.display())))]
+    pub fn write_to<P: AsRef<Path>>(&self, root: P) -> Result<(), Box<dyn std::error::Error>> {
+        let target = root.as_ref().join(&self.path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, &self.contents)?;
+        Ok(())
+    }
+
+    /// Write the file under `root` unless it already has these contents, leaving its mtime
+    /// alone so dev servers watching the output don't rebuild. Returns whether it was written.
+    pub fn write_if_changed<P: AsRef<Path>>(&self, root: P) -> Result<bool, Box<dyn std::error::Error>> {
+        let target = root.as_ref().join(&self.path);
+        if fs::read(&target).is_ok_and(|existing| existing == self.contents) {
+            return Ok(false);
+        }
+        self.write_to(root)?;
+        Ok(true)
+    }
+}
+
+/// Escape text for an HTML attribute or element body
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_unchanged_files_and_their_mtimes_alone() {
+        let root = std::env::temp_dir().join(format!("degenproto-output-{}", std::process::id()));
+        let file = GeneratedFile::text("src/views/Home.jsx", "export default Home;\n").in_dir("app");
+        let target = root.join("app/src/views/Home.jsx");
+
+        let first = file.write_if_changed(&root).unwrap();
+        let mtime = fs::metadata(&target).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let second = file.write_if_changed(&root).unwrap();
+        let unchanged_mtime = fs::metadata(&target).unwrap().modified().unwrap();
+        let third = GeneratedFile::text("app/src/views/Home.jsx", "export default Page;\n").write_if_changed(&root).unwrap();
+        let contents = fs::read_to_string(&target).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!((first, second, third), (true, false, true));
+        assert_eq!(unchanged_mtime, mtime);
+        assert_eq!(contents, "export default Page;\n");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{RouterJsx, ViewJsx};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Version ranges for packages the generated code is known to work with
+const KNOWN_VERSIONS: &[(&str, &str)] = &[
+    ("clsx", "^2.1.1"),
+    ("framer-motion", "^11.3.0"),
+    ("i18next", "^23.12.0"),
+    ("mobx", "^6.13.0"),
+    ("mobx-react", "^9.1.1"),
+    ("prop-types", "^15.8.1"),
+    ("react", "^18.3.1"),
+    ("react-dom", "^18.3.1"),
+    ("react-helmet", "^6.1.0"),
+    ("react-i18next", "^15.0.0"),
+    ("react-router-dom", "^6.26.0"),
+];
+
+/// Dev dependencies needed to run the tests from ViewTestJsx
+const TEST_DEV_DEPENDENCIES: &[(&str, &str)] = &[
+    ("@testing-library/dom", "^10.4.0"),
+    ("@testing-library/react", "^16.0.0"),
+    ("jsdom", "^24.1.1"),
+    ("vitest", "^2.0.5"),
+];
+
+/// npm dependencies implied by the generated output, written as a package.json fragment
+#[derive(Debug, Clone, Default)]
+pub struct PackageJson {
+    pub dependencies: BTreeMap<String, String>,
+    pub dev_dependencies: BTreeMap<String, String>,
+}
+
+impl PackageJson {
+    /// Add the packages a generated view imports from
+    pub fn add_view(&mut self, view: &ViewJsx) {
+        self.add_packages(view.npm_packages());
+    }
+
+    /// Add the packages the generated router imports from
+    pub fn add_router(&mut self, router: &RouterJsx) {
+        self.add_packages(router.npm_packages());
+    }
+
+    /// Add the dev dependencies generated `*.test.jsx` files need
+    pub fn add_test_dependencies(&mut self) {
+        for (name, version) in TEST_DEV_DEPENDENCIES {
+            self.dev_dependencies.insert(name.to_string(), version.to_string());
+        }
+    }
+
+    /// Add packages by name; ones without a known version range get "latest"
+    pub fn add_packages(&mut self, packages: impl IntoIterator<Item = String>) {
+        for package in packages {
+            let version = KNOWN_VERSIONS
+                .iter()
+                .find(|(name, _)| *name == package)
+                .map(|(_, version)| version.to_string())
+                .unwrap_or_else(|| "latest".to_string());
+            self.dependencies.entry(package).or_insert(version);
+        }
+    }
+
+    /// Merge into an existing package.json. Versions already pinned there are kept.
+    pub fn merge_into(&self, existing: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut root: Value = serde_json::from_str(existing)?;
+        let Some(object) = root.as_object_mut() else {
+            return Err("package.json must contain a JSON object".into());
+        };
+        merge_section(object, "dependencies", &self.dependencies)?;
+        merge_section(object, "devDependencies", &self.dev_dependencies)?;
+        Ok(serde_json::to_string_pretty(&root)? + "\n")
+    }
+
+    pub(crate) fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("dependencies".to_string(), section_value(&self.dependencies));
+        if !self.dev_dependencies.is_empty() {
+            object.insert("devDependencies".to_string(), section_value(&self.dev_dependencies));
+        }
+        Value::Object(object)
+    }
+}
+
+impl fmt::Display for PackageJson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string_pretty(&self.to_value()).map_err(|_| fmt::Error)?;
+        writeln!(f, "{}", json)
+    }
+}
+
+fn section_value(entries: &BTreeMap<String, String>) -> Value {
+    Value::Object(entries.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect())
+}
+
+fn merge_section(
+    object: &mut Map<String, Value>,
+    key: &str,
+    entries: &BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let section = object.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    let Some(section) = section.as_object_mut() else {
+        return Err(format!("`{}` in package.json must be an object", key).into());
+    };
+    for (name, version) in entries {
+        section.entry(name.clone()).or_insert_with(|| Value::String(version.clone()));
+    }
+
+    // Keep the section sorted the way npm writes it
+    let mut sorted: Vec<(String, Value)> = std::mem::take(section).into_iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    section.extend(sorted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_known_version_ranges() {
+        let mut package = PackageJson::default();
+        package.add_packages(["react".to_string(), "left-pad".to_string()]);
+        assert_eq!(package.dependencies["react"], "^18.3.1");
+        assert_eq!(package.dependencies["left-pad"], "latest");
+        assert!(!package.to_string().contains("devDependencies"));
+    }
+
+    #[test]
+    fn keeps_versions_already_pinned() {
+        let mut package = PackageJson::default();
+        package.add_packages(["react".to_string(), "clsx".to_string()]);
+        package.add_test_dependencies();
+        let merged = package
+            .merge_into(r#"{"name": "site", "dependencies": {"react": "18.2.0"}}"#)
+            .unwrap();
+        let merged: Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(merged["name"], "site");
+        assert_eq!(merged["dependencies"]["react"], "18.2.0");
+        assert_eq!(merged["dependencies"]["clsx"], "^2.1.1");
+        assert_eq!(merged["devDependencies"]["vitest"], "^2.0.5");
+
+        assert!(package.merge_into("[]").is_err());
+        assert!(package.merge_into(r#"{"dependencies": []}"#).is_err());
+    }
+}
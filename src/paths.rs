@@ -0,0 +1,119 @@
+//! Import specifiers built from paths written in proto files, which may use Windows separators
+
+// Extensions bundlers and tsc resolve on their own; tsc rejects `.ts`/`.tsx` in specifiers
+pub(crate) const SCRIPT_EXTENSIONS: &[&str] = &[".tsx", ".ts", ".jsx", ".js"];
+
+/// URLs and data URIs, which are used as written rather than imported or read from disk
+pub(crate) fn is_remote_path(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://") || path.starts_with("data:")
+}
+
+/// Forward slashes with empty and `.` segments dropped: `.\assets\\hero.png` -> `./assets/hero.png`.
+/// A leading `./`, `../` or `/` is kept; URLs are returned as they are.
+pub(crate) fn normalize_path(path: &str) -> String {
+    if path.contains("://") {
+        return path.to_string();
+    }
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let joined = segments.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else if path.starts_with("./") && !joined.starts_with("../") {
+        format!("./{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Specifier for a module import (components, views, layouts): normalized, and without a
+/// script extension when it points into the project rather than at a package.
+/// `aliased` paths start with a declared alias.
+pub(crate) fn module_specifier(path: &str, aliased: bool) -> String {
+    let path = normalize_path(path);
+    if !(aliased || path.starts_with('/') || path.starts_with("./") || path.starts_with("../")) {
+        return path;
+    }
+    for extension in SCRIPT_EXTENSIONS {
+        if let Some(stem) = path.strip_suffix(extension)
+            && !stem.is_empty()
+            && !stem.ends_with('/')
+        {
+            return stem.to_string();
+        }
+    }
+    path
+}
+
+/// Specifier for a file import (images): normalized, with `./` added to bare relative paths,
+/// which bundlers would otherwise look up as packages. `aliased` paths start with a declared alias.
+pub(crate) fn file_specifier(path: &str, aliased: bool) -> String {
+    let path = normalize_path(path);
+    if aliased || path.contains("://") || path.starts_with('/') || path.starts_with("./") || path.starts_with("../") {
+        path
+    } else {
+        format!("./{}", path)
+    }
+}
+
+/// Specifier reaching `target` from the file at `from`, both relative to the same root:
+/// (`views/blog/Post.jsx`, `components/Card`) -> `../../components/Card`
+pub(crate) fn relative_specifier(from: &str, target: &str) -> String {
+    let from = normalize_path(from);
+    let target = normalize_path(target);
+    let mut from_dir: Vec<&str> = from.trim_start_matches("./").split('/').collect();
+    from_dir.pop();
+    let target: Vec<&str> = target.trim_start_matches("./").split('/').collect();
+
+    let common = from_dir
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| a == b && **a != "..")
+        .count();
+    let mut segments: Vec<&str> = vec![".."; from_dir.len() - common];
+    segments.extend(&target[common..]);
+    let joined = segments.join("/");
+    if joined.starts_with("../") {
+        joined
+    } else {
+        format!("./{}", joined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_windows_separators_and_dot_segments() {
+        assert_eq!(normalize_path(r".\assets\\hero.png"), "./assets/hero.png");
+        assert_eq!(normalize_path("./components/../views/./Home.jsx"), "./views/Home.jsx");
+        assert_eq!(normalize_path(r"..\shared\Button.jsx"), "../shared/Button.jsx");
+        assert_eq!(normalize_path("/img//logo.png"), "/img/logo.png");
+        assert_eq!(normalize_path("https://cdn.example.com//a.png"), "https://cdn.example.com//a.png");
+
+        assert_eq!(module_specifier(r".\components\Card.jsx", false), "./components/Card");
+        assert_eq!(module_specifier("@/components/Card.tsx", true), "@/components/Card");
+        assert_eq!(module_specifier("lodash.js", false), "lodash.js");
+        assert_eq!(file_specifier(r"assets\logo.png", false), "./assets/logo.png");
+        assert_eq!(file_specifier("@/assets/logo.png", true), "@/assets/logo.png");
+    }
+
+    #[test]
+    fn reaches_targets_relative_to_the_importing_file() {
+        assert_eq!(relative_specifier("views/blog/Post.jsx", "components/Card"), "../../components/Card");
+        assert_eq!(relative_specifier("views/Home.jsx", "views/partials/Nav"), "./partials/Nav");
+        assert_eq!(relative_specifier("main.jsx", "router"), "./router");
+        assert_eq!(relative_specifier(r"views\blog\Post.jsx", "./views/blog/Sidebar"), "./Sidebar");
+    }
+}
@@ -0,0 +1,331 @@
+use crate::view_proto::{AssetDefs, AssetKind, CollectionConfig, ContentDefs, ContentValue, FieldKind};
+use std::collections::HashMap;
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Grace", "Alan", "Linus", "Margaret", "Dennis", "Barbara", "Ken", "Radia", "Tim", "Hedy", "Guido",
+];
+const LAST_NAMES: &[&str] = &[
+    "Lovelace", "Hopper", "Turing", "Torvalds", "Hamilton", "Ritchie", "Liskov", "Thompson", "Perlman", "Berners-Lee",
+    "Lamarr", "van Rossum",
+];
+const WORDS: &[&str] = &[
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod", "tempor",
+    "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim", "ad", "minim", "veniam", "quis",
+    "nostrud", "exercitation", "ullamco", "laboris", "nisi", "aliquip", "ex", "ea", "commodo", "consequat",
+];
+const CITIES: &[&str] = &["Lisbon", "Osaka", "Toronto", "Nairobi", "Oslo", "Montevideo", "Hanoi", "Tallinn"];
+// Field name fragments that make a Url field an image
+const IMAGE_FIELDS: &[&str] = &["image", "photo", "avatar", "thumbnail", "cover", "picture", "logo"];
+
+/// Stand-in data for collections that declare a schema but have no records yet, so layout
+/// work can start before real copy exists. Output is the same for the same seed.
+#[derive(Debug, Clone)]
+pub struct PlaceholderContent {
+    /// Records generated per empty collection
+    pub count: usize,
+    pub seed: u64,
+}
+
+impl Default for PlaceholderContent {
+    fn default() -> Self {
+        Self { count: 6, seed: 0 }
+    }
+}
+
+impl PlaceholderContent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Names of the collections `fill` would generate records for, sorted
+    pub fn empty_collections(&self, content: &ContentDefs) -> Vec<String> {
+        let mut names: Vec<String> = content
+            .collections
+            .iter()
+            .filter(|(name, config)| {
+                !config.schema.is_empty()
+                    && match content.content.get(*name) {
+                        None => true,
+                        Some(ContentValue::List(list)) => list.is_empty(),
+                        Some(_) => false,
+                    }
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The content with every empty collection filled from its schema. Ref fields point at
+    /// records of their target collection, and derived fields and relations are computed
+    /// as ContentDefs::from_file does.
+    pub fn fill(&self, content: &ContentDefs) -> ContentDefs {
+        let mut filled = content.clone();
+        let empty = self.empty_collections(content);
+        for name in &empty {
+            let config = &content.collections[name];
+            let referenced = content
+                .collections
+                .values()
+                .any(|other| other.schema.values().any(|schema| schema.kind == FieldKind::Ref(name.clone())));
+            let records = (0..self.count)
+                .map(|i| ContentValue::Record(self.record(name, config, referenced, i)))
+                .collect();
+            filled.content.insert(name.clone(), ContentValue::List(records));
+        }
+
+        // Refs need the target's keys, which may themselves have just been generated
+        for name in &empty {
+            let config = &content.collections[name];
+            let mut refs: Vec<(&String, Vec<String>)> = Vec::new();
+            for (field, schema) in &config.schema {
+                if let FieldKind::Ref(target) = &schema.kind {
+                    refs.push((field, record_keys(&filled, target)));
+                }
+            }
+            if let Some(ContentValue::List(list)) = filled.content.get_mut(name) {
+                for (i, item) in list.iter_mut().enumerate() {
+                    let ContentValue::Record(record) = item else {
+                        continue;
+                    };
+                    for (field, keys) in &refs {
+                        if !keys.is_empty() {
+                            let pick = self.random(name, i, field) as usize % keys.len();
+                            record.insert(field.to_string(), keys[pick].clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        filled.compute_derived();
+        filled.resolve_relations();
+        filled
+    }
+
+    /// The asset defs with a picsum.photos image for every image asset that has no path yet
+    pub fn fill_assets(&self, assets: &AssetDefs) -> AssetDefs {
+        let mut filled = assets.clone();
+        for asset in &mut filled.assets {
+            if matches!(asset.kind, AssetKind::Image) && asset.path.is_none() {
+                asset.path = Some(picsum_url(&format!("{}-{}", asset.name, self.seed), 1200, 800));
+            }
+        }
+        filled
+    }
+
+    fn record(&self, collection: &str, config: &CollectionConfig, referenced: bool, i: usize) -> HashMap<String, String> {
+        let mut record = HashMap::new();
+        // Other collections' Ref fields match against the key field, so targets get one even
+        // when their schema leaves it out
+        if referenced || config.schema.contains_key(config.key_field()) {
+            record.insert(config.key_field().to_string(), format!("{}-{}", collection, i + 1));
+        }
+        for (field, schema) in &config.schema {
+            let value = match &schema.kind {
+                // Filled once every collection has records
+                FieldKind::Ref(_) => continue,
+                _ if field == config.key_field() => continue,
+                FieldKind::String if config.markdown.contains(field) => self.paragraphs(collection, i, field),
+                kind => self.value(collection, i, field, kind),
+            };
+            record.insert(field.clone(), value);
+        }
+        record
+    }
+
+    fn value(&self, collection: &str, i: usize, field: &str, kind: &FieldKind) -> String {
+        let n = self.random(collection, i, field);
+        let name = field.to_lowercase();
+        match kind {
+            FieldKind::Number if ["price", "cost", "amount", "total"].iter().any(|w| name.contains(w)) => {
+                format!("{}.99", 5 + n % 195)
+            }
+            FieldKind::Number if name.contains("rating") => (1 + n % 5).to_string(),
+            FieldKind::Number => (1 + n % 100).to_string(),
+            FieldKind::Bool => n.is_multiple_of(2).to_string(),
+            FieldKind::Date => format!("2025-{:02}-{:02}", 1 + n % 12, 1 + (n / 12) % 28),
+            FieldKind::Url if IMAGE_FIELDS.iter().any(|w| name.contains(w)) => {
+                picsum_url(&format!("{}-{}-{}-{}", collection, field, i, self.seed), 800, 600)
+            }
+            FieldKind::Url => format!("https://example.com/{}/{}", collection, i + 1),
+            FieldKind::Enum(allowed) if !allowed.is_empty() => allowed[n as usize % allowed.len()].clone(),
+            FieldKind::Enum(_) | FieldKind::Ref(_) => String::new(),
+            FieldKind::String if name.contains("email") => {
+                let (first, last) = self.person(n);
+                format!("{}.{}@example.com", first.to_lowercase(), last.to_lowercase().replace(' ', ""))
+            }
+            FieldKind::String if name.contains("name") || name.contains("author") => {
+                let (first, last) = self.person(n);
+                format!("{} {}", first, last)
+            }
+            FieldKind::String if name.contains("city") || name.contains("location") => CITIES[n as usize % CITIES.len()].to_string(),
+            FieldKind::String if name.contains("slug") => format!("{}-{}", self.words(n, 2).join("-"), i + 1),
+            FieldKind::String if name.contains("price") => format!("${}.99", 5 + n % 195),
+            FieldKind::String if ["title", "heading", "label"].iter().any(|w| name.contains(w)) => {
+                title_case(&self.words(n, 2 + n as usize % 3).join(" "))
+            }
+            FieldKind::String if ["description", "summary", "excerpt", "bio", "body", "content", "text"]
+                .iter()
+                .any(|w| name.contains(w)) =>
+            {
+                self.sentence(n, 12 + n as usize % 10)
+            }
+            FieldKind::String => title_case(&self.words(n, 2).join(" ")),
+        }
+    }
+
+    fn paragraphs(&self, collection: &str, i: usize, field: &str) -> String {
+        (0..3)
+            .map(|p| self.sentence(self.random(collection, i, &format!("{}{}", field, p)), 24))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn person(&self, n: u64) -> (&'static str, &'static str) {
+        (FIRST_NAMES[n as usize % FIRST_NAMES.len()], LAST_NAMES[(n / 7) as usize % LAST_NAMES.len()])
+    }
+
+    fn words(&self, n: u64, count: usize) -> Vec<&'static str> {
+        let mut state = n;
+        (0..count)
+            .map(|_| {
+                state = mix(state);
+                WORDS[state as usize % WORDS.len()]
+            })
+            .collect()
+    }
+
+    fn sentence(&self, n: u64, count: usize) -> String {
+        let mut sentence = self.words(n, count).join(" ");
+        if let Some(first) = sentence.get(..1) {
+            sentence.replace_range(..1, &first.to_uppercase());
+        }
+        sentence.push('.');
+        sentence
+    }
+
+    /// Stable pseudo-random number for one field of one record
+    fn random(&self, collection: &str, i: usize, field: &str) -> u64 {
+        let mut state = mix(self.seed ^ i as u64);
+        for byte in collection.bytes().chain([0]).chain(field.bytes()) {
+            state = mix(state ^ byte as u64);
+        }
+        state
+    }
+}
+
+// splitmix64 finalizer
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn picsum_url(seed: &str, width: u32, height: u32) -> String {
+    let seed: String = seed.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' }).collect();
+    format!("https://picsum.photos/seed/{}/{}/{}", seed, width, height)
+}
+
+fn title_case(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Key field values of a collection's records
+fn record_keys(content: &ContentDefs, collection: &str) -> Vec<String> {
+    let key_field = content.collections.get(collection).map(|c| c.key_field()).unwrap_or("id");
+    content
+        .get_list(collection)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| match item {
+            ContentValue::Record(record) => record.get(key_field).cloned(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn content() -> ContentDefs {
+        ron(r#"(
+            content: {"posts": List([]), "authors": List([Record({"id": "jane", "name": "Jane"})])},
+            collections: {
+                "posts": (schema: {"title": (), "price": (kind: Number), "cover": (kind: Url), "status": (kind: Enum(["live", "draft"])), "author": (kind: Ref("authors"))}),
+                "authors": (schema: {"id": (), "name": ()}),
+                "tags": (),
+            },
+        )"#)
+    }
+
+    #[test]
+    fn fills_empty_collections_from_their_schema() {
+        let content = content();
+        let placeholder = PlaceholderContent::new().with_count(3);
+        assert_eq!(placeholder.empty_collections(&content), ["posts"]);
+
+        let filled = placeholder.fill(&content);
+        assert!(filled.validate().is_empty(), "{:?}", filled.validate());
+        let posts = filled.get_list("posts").unwrap();
+        assert_eq!(posts.len(), 3);
+        for post in posts {
+            let ContentValue::Record(post) = post else { panic!("{:?}", post) };
+            assert!(post["price"].ends_with(".99"), "{:?}", post);
+            assert!(post["cover"].starts_with("https://picsum.photos/seed/"), "{:?}", post);
+            assert!(["live", "draft"].contains(&post["status"].as_str()), "{:?}", post);
+            assert_eq!(post["author"], "jane");
+            assert_eq!(post["author.name"], "Jane");
+        }
+        assert_eq!(filled.get_list("authors").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn generates_the_same_records_for_the_same_seed() {
+        let content = content();
+        let records = |seed| -> Vec<std::collections::BTreeMap<String, String>> {
+            let filled = PlaceholderContent::new().with_seed(seed).fill(&content);
+            filled
+                .get_list("posts")
+                .unwrap()
+                .iter()
+                .map(|post| match post {
+                    ContentValue::Record(record) => record.clone().into_iter().collect(),
+                    _ => panic!("{:?}", post),
+                })
+                .collect()
+        };
+        assert_eq!(records(7), records(7));
+        assert_ne!(records(7), records(8));
+
+        let assets: AssetDefs = ron(r#"(assets: [(name: "hero", kind: Image), (name: "logo", kind: Image, path: "assets/logo.png")])"#);
+        let filled = PlaceholderContent::new().fill_assets(&assets);
+        assert_eq!(filled.get("hero").unwrap().path.as_deref(), Some("https://picsum.photos/seed/hero-0/1200/800"));
+        assert_eq!(filled.get("logo").unwrap().path.as_deref(), Some("assets/logo.png"));
+    }
+}
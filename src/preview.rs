@@ -0,0 +1,667 @@
+use crate::date_format::format_date;
+use crate::expansion::DEFAULT_MAX_EXPANSION_DEPTH;
+use crate::minify::minify_html;
+use crate::names::component_ident;
+use crate::output::{html_escape, GeneratedFile};
+use crate::paths::{is_remote_path, normalize_path};
+use crate::rich_text::rich_text_elements;
+use crate::view_jsx::{encode_uri_component, merge_markdown, rendered_prop_keys, ViewJsx};
+use crate::view_proto::{AssetKind, ContentValue, Element, EmbedProvider, PropValue};
+use crate::ClassMerge;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+// Elements HTML closes on its own
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+// Elements rendered per page at most, so components that render themselves several times
+// per level stop long before the depth limit would
+const MAX_RENDERED_ELEMENTS: usize = 20_000;
+// Style properties React leaves numbers unitless for
+const UNITLESS_STYLES: &[&str] = &[
+    "flex", "flexGrow", "flexShrink", "fontWeight", "lineHeight", "opacity", "order", "zIndex", "zoom",
+];
+
+/// A standalone HTML page for one view, so designers can review it without running the
+/// React app. Content is inlined, local assets are linked by absolute URL and there is no
+/// router: links keep their route URLs, and values only known at runtime render empty.
+pub struct PreviewHtml<'a> {
+    pub view: &'a ViewJsx,
+    /// Origin serving the project's files, e.g. the Vite dev server
+    pub asset_base_url: String,
+    /// Stylesheets linked from the head, e.g. the app's built CSS
+    pub stylesheets: Vec<String>,
+    /// Critical CSS embedded in the head, so the page is styled before the stylesheets load
+    pub inline_css: Vec<String>,
+    /// Strip comments and collapse whitespace, for previews hosted as they are
+    pub minify: bool,
+}
+
+impl<'a> PreviewHtml<'a> {
+    pub fn new(view: &'a ViewJsx) -> Self {
+        Self {
+            view,
+            asset_base_url: "http://localhost:5173".to_string(),
+            stylesheets: Vec::new(),
+            inline_css: Vec::new(),
+            minify: false,
+        }
+    }
+
+    pub fn with_asset_base_url(mut self, url: impl Into<String>) -> Self {
+        self.asset_base_url = url.into();
+        self
+    }
+
+    pub fn with_stylesheet(mut self, href: impl Into<String>) -> Self {
+        self.stylesheets.push(href.into());
+        self
+    }
+
+    pub fn with_inline_css(mut self, css: impl Into<String>) -> Self {
+        self.inline_css.push(css.into());
+        self
+    }
+
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// `previews/` plus the view's output path with an .html extension, or `previews/{Name}.html`
+    pub fn path(&self) -> String {
+        match &self.view.output_path {
+            Some(output_path) => {
+                let output_path = normalize_path(output_path);
+                let stem = Path::new(output_path.trim_start_matches("./")).with_extension("");
+                format!("previews/{}.html", stem.to_string_lossy())
+            }
+            None => format!("previews/{}.html", component_ident(&self.view.proto.name)),
+        }
+    }
+
+    pub fn file(&self) -> GeneratedFile {
+        GeneratedFile::text(self.path(), self.to_string())
+    }
+
+    /// A local asset path as a URL under asset_base_url; URLs and data URIs are returned as they are
+    fn asset_url(&self, path: &str) -> String {
+        if is_remote_path(path) {
+            return path.to_string();
+        }
+        let base = self.asset_base_url.trim_end_matches('/');
+        let path = normalize_path(path);
+        // Root paths are served from public/, everything else is read from the source root
+        if path.starts_with('/') {
+            return format!("{}{}", base, path);
+        }
+        let file = self.view.alias_defs.asset_file(&path);
+        format!("{}/{}", base, file.to_string_lossy().replace('\\', "/"))
+    }
+}
+
+impl fmt::Display for PreviewHtml<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut renderer = Renderer { preview: self, title: None, meta: Vec::new(), rendered: 0 };
+        let empty = HashMap::new();
+        let root = Scope { record: None, vars: &empty, depth: 0, templates: &[] };
+        // States are previewed with their initial values
+        let vars: HashMap<String, Var> = self
+            .view
+            .proto
+            .props
+            .iter()
+            .filter_map(|decl| Some((decl.name.clone(), renderer.var(decl.default.as_ref()?, root)?)))
+            .chain(
+                self.view
+                    .proto
+                    .states
+                    .iter()
+                    .filter_map(|state| Some((state.name.clone(), renderer.var(&state.initial, root)?))),
+            )
+            .collect();
+        let scope = Scope { record: self.view.record.as_ref(), vars: &vars, depth: 0, templates: &[] };
+        let mut body = String::new();
+        renderer.element(&self.view.proto.tree, scope, &mut body);
+
+        let title = renderer.title.take().unwrap_or_else(|| self.view.proto.name.clone());
+        let mut head = String::new();
+        for tag in &renderer.meta {
+            head.push_str(&format!("    {}\n", tag));
+        }
+        for css in &self.inline_css {
+            head.push_str(&format!("    <style>\n{}\n    </style>\n", css.trim_end()));
+        }
+        for href in &self.stylesheets {
+            head.push_str(&format!("    <link rel=\"stylesheet\" href=\"{}\" />\n", html_escape(href)));
+        }
+        let page = format!(
+            r#"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <title>{}</title>
+{}  </head>
+  <body>
+    {}
+  </body>
+</html>
+"#,
+            html_escape(&title),
+            head,
+            body
+        );
+        if self.minify {
+            f.write_str(&minify_html(&page))
+        } else {
+            f.write_str(&page)
+        }
+    }
+}
+
+// A prop or state value: its text, and whether it holds as a condition
+struct Var {
+    text: String,
+    truthy: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Scope<'s> {
+    // Record ContentField references read from
+    record: Option<&'s HashMap<String, String>>,
+    // Props of the view or local component being rendered
+    vars: &'s HashMap<String, Var>,
+    // Local components entered so far
+    depth: usize,
+    // Component defs whose children_template is being shown, outermost first
+    templates: &'s [&'s str],
+}
+
+struct Renderer<'p, 'a> {
+    preview: &'p PreviewHtml<'a>,
+    // Title and meta tags from Head elements
+    title: Option<String>,
+    meta: Vec<String>,
+    // Elements rendered so far, capped at MAX_RENDERED_ELEMENTS
+    rendered: usize,
+}
+
+impl Renderer<'_, '_> {
+    fn view(&self) -> &ViewJsx {
+        self.preview.view
+    }
+
+    fn element(&mut self, element: &Element, scope: Scope, out: &mut String) {
+        let view = self.preview.view;
+        if self.rendered >= MAX_RENDERED_ELEMENTS {
+            return;
+        }
+        self.rendered += 1;
+        match element {
+            Element::Text(text) | Element::ContentText(text) => out.push_str(&html_escape(text)),
+            // Hand-written markup is usually valid HTML as well
+            Element::Raw(jsx) => out.push_str(jsx),
+
+            Element::Node { tag, class_name, props, children, id } => {
+                let props = view.with_proto_id(id, props);
+                self.node(tag, class_name.as_deref(), &props, children, scope, out)
+            }
+
+            Element::ComponentRef { component, .. } if !view.component_enabled(component) => {}
+
+            Element::ComponentRef { component, props, children, id } => {
+                let props = &*view.with_proto_id(id, props);
+                if let Some(local) = view.proto.components.iter().find(|c| &c.name == component) {
+                    // Recursion ended by runtime props or data can't be followed here, so stop at the depth limit
+                    if scope.depth >= view.options.max_expansion_depth.unwrap_or(DEFAULT_MAX_EXPANSION_DEPTH) {
+                        return;
+                    }
+                    let mut vars: HashMap<String, Var> = local
+                        .props
+                        .iter()
+                        .filter_map(|decl| Some((decl.name.clone(), self.var(decl.default.as_ref()?, scope)?)))
+                        .collect();
+                    for (key, value) in props {
+                        if let Some(var) = self.var(value, scope) {
+                            vars.insert(key.clone(), var);
+                        }
+                    }
+                    let inner = Scope { record: None, vars: &vars, depth: scope.depth + 1, ..scope };
+                    self.element(&local.tree, inner, out);
+                } else if let Some(def) = view.component_defs.get(component) {
+                    let mut merged_props = def.default_props.clone();
+                    merged_props.extend(props.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    // Like the JSX, a use without children shows the def's children_template,
+                    // except inside its own expansion or past the depth limit
+                    let max_depth = view.options.max_expansion_depth.unwrap_or(DEFAULT_MAX_EXPANSION_DEPTH);
+                    match view.children_template(component, children) {
+                        Some(template) if !scope.templates.contains(&component.as_str()) && scope.templates.len() < max_depth => {
+                            let templates: Vec<&str> = scope.templates.iter().copied().chain([component.as_str()]).collect();
+                            let inner = Scope { templates: &templates, ..scope };
+                            self.node(&def.tag, def.class_name.as_deref(), &merged_props, template, inner, out);
+                        }
+                        _ => self.node(&def.tag, def.class_name.as_deref(), &merged_props, children, scope, out),
+                    }
+                } else {
+                    self.node(component, None, props, children, scope, out);
+                }
+            }
+
+            Element::ContentList { source, template } => {
+                let Some(list) = view.content_defs.get_list(source) else {
+                    return;
+                };
+                let range = match &view.page {
+                    Some(page) if &page.collection == source => page.range(),
+                    _ => 0..list.len(),
+                };
+                let markdown_fields = view.content_defs.markdown_fields(source);
+                for item in list.iter().take(range.end).skip(range.start) {
+                    if let ContentValue::Record(record) = item {
+                        let scope = Scope { record: Some(record), ..scope };
+                        if markdown_fields.is_empty() {
+                            self.element(template, scope, out);
+                        } else {
+                            let mut template = template.as_ref().clone();
+                            merge_markdown(&mut template, record, markdown_fields);
+                            self.element(&template, scope, out);
+                        }
+                    }
+                }
+            }
+
+            Element::Pagination { source, prev_label, next_label } => {
+                let Some(page) = view.page.as_ref().filter(|page| &page.collection == source) else {
+                    return;
+                };
+                out.push_str("<nav class=\"pagination\" aria-label=\"Pagination\">");
+                for (url, rel, label) in [(page.prev_url(), "prev", prev_label), (page.next_url(), "next", next_label)] {
+                    if let Some(url) = url {
+                        out.push_str(&format!("<a href=\"{}\" rel=\"{}\">{}</a>", html_escape(url), rel, html_escape(label)));
+                    }
+                }
+                out.push_str("</nav>");
+            }
+
+            Element::Repeat { count, start, index, template } => {
+                let mut record = scope.record.cloned().unwrap_or_default();
+                for i in *start..start.saturating_add(*count) {
+                    record.insert(index.clone(), i.to_string());
+                    self.element(template, Scope { record: Some(&record), ..scope }, out);
+                }
+            }
+
+            Element::Match { source, arms, default } => {
+                let selected = match self.text(source, scope) {
+                    Some(value) => arms
+                        .iter()
+                        .find(|arm| arm.value == value)
+                        .map(|arm| arm.template.as_ref())
+                        .or(default.as_deref()),
+                    // Runtime values are unknown, so show the default, or else the first arm
+                    None => default.as_deref().or(arms.first().map(|arm| arm.template.as_ref())),
+                };
+                if let Some(template) = selected {
+                    self.element(template, scope, out);
+                }
+            }
+
+            Element::Experiment { name, variants } => {
+                if let Some(variant) = view.pinned_variant(name, variants).or(variants.first()) {
+                    self.element(&variant.template, scope, out);
+                }
+            }
+
+            // Runtime conditions are unknown, so their content is shown
+            Element::If { condition, then, otherwise } => {
+                if self.condition(condition, scope).unwrap_or(true) {
+                    self.element(then, scope, out);
+                } else if let Some(otherwise) = otherwise {
+                    self.element(otherwise, scope, out);
+                }
+            }
+
+            Element::Show { condition, children } => {
+                if self.condition(condition, scope).unwrap_or(true) {
+                    for child in children {
+                        self.element(child, scope, out);
+                    }
+                }
+            }
+
+            // Animations are previewed in their final state
+            Element::Animated { class_name, children, .. } => {
+                let class = class_name.as_ref().map(|cn| format!(" class=\"{}\"", html_escape(cn))).unwrap_or_default();
+                out.push_str(&format!("<div{}>", class));
+                self.children(children, scope, out);
+                out.push_str("</div>");
+            }
+
+            Element::Link { route, params, props, children, id } => {
+                let mut link_props = view.with_proto_id(id, props).into_owned();
+                // Params are filled with their previewed values, e.g. a state's initial value
+                let values: HashMap<String, String> = params
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.clone(), self.text(value, scope)?)))
+                    .collect();
+                let url = view.route(route).and_then(|r| r.fill_url(&values));
+                link_props.insert("href".to_string(), PropValue::Str(url.unwrap_or_else(|| "#".to_string())));
+                self.node("a", None, &link_props, children, scope, out);
+            }
+
+            Element::Head { title, meta } => {
+                if let Some(title) = title.as_ref().and_then(|title| self.text(title, scope)) {
+                    self.title = Some(title);
+                }
+                let mut names: Vec<&String> = meta.keys().collect();
+                names.sort();
+                for name in names {
+                    let attr = if name.starts_with("og:") { "property" } else { "name" };
+                    let content = self.text(&meta[name], scope).unwrap_or_default();
+                    self.meta.push(format!(
+                        "<meta {}=\"{}\" content=\"{}\" />",
+                        attr,
+                        html_escape(name),
+                        html_escape(&content)
+                    ));
+                }
+            }
+
+            Element::Media { asset, props, id } => self.media(asset, &view.with_proto_id(id, props), scope, out),
+
+            Element::Embed { provider, config } => self.embed(*provider, config, out),
+
+            Element::Flag { flag, .. } if !view.flag_enabled(flag) => {}
+
+            // Boundaries and portals show their children in place
+            Element::Portal { children, .. }
+            | Element::Suspense { children, .. }
+            | Element::ErrorBoundary { children, .. }
+            | Element::Fragment { children }
+            | Element::Region { children, .. }
+            | Element::Flag { children, .. } => self.children(children, scope, out),
+        }
+    }
+
+    fn children(&mut self, children: &[Box<Element>], scope: Scope, out: &mut String) {
+        for child in children {
+            self.element(child, scope, out);
+        }
+    }
+
+    fn node(
+        &mut self,
+        tag: &str,
+        class_name: Option<&str>,
+        props: &HashMap<String, PropValue>,
+        children: &[Box<Element>],
+        scope: Scope,
+        out: &mut String,
+    ) {
+        // React components can't run here, so they become a marked div around their children
+        let component = tag.starts_with(|c: char| c.is_ascii_uppercase()) || tag.contains('.');
+        let html_tag = if component { "div" } else { tag };
+        out.push_str(&format!("<{}", html_tag));
+        if component {
+            out.push_str(&format!(" data-component=\"{}\"", html_escape(tag)));
+        }
+
+        // Classes are merged the way the JSX output merges them
+        let mut classes: Vec<String> = class_name.map(str::to_string).into_iter().collect();
+        let mut utility_keys: Vec<&String> = props.keys().filter(|k| k.starts_with("tw:")).collect();
+        utility_keys.sort();
+        for key in utility_keys {
+            if let Some(value) = self.text(&props[key], scope) {
+                classes.push(format!("{}-[{}]", &key["tw:".len()..], value.replace(' ', "_")));
+            }
+        }
+        if let Some(class) = props.get("className").and_then(|value| self.text(value, scope)) {
+            if self.view().options.class_merge == ClassMerge::Override {
+                classes.clear();
+            }
+            classes.push(class);
+        }
+        if !classes.is_empty() {
+            out.push_str(&format!(" class=\"{}\"", html_escape(&classes.join(" "))));
+        }
+
+        for key in rendered_prop_keys(props) {
+            let value = &props[key];
+            if matches!(key.as_str(), "text" | "className" | "key" | "ref")
+                || key.starts_with("tw:")
+                || is_event_handler(key)
+            {
+                continue;
+            }
+            let attr = if key == "htmlFor" { "for" } else { key.as_str() };
+            match value {
+                PropValue::Bool(true) => out.push_str(&format!(" {}", attr)),
+                PropValue::Bool(false) | PropValue::Spread(_) => {}
+                PropValue::Style(entries) => {
+                    let css = self.css(entries, scope);
+                    if !css.is_empty() {
+                        out.push_str(&format!(" {}=\"{}\"", attr, html_escape(&css)));
+                    }
+                }
+                _ => {
+                    if let Some(text) = self.text(value, scope) {
+                        out.push_str(&format!(" {}=\"{}\"", attr, html_escape(&text)));
+                    }
+                }
+            }
+        }
+        out.push('>');
+
+        if VOID_TAGS.contains(&html_tag) {
+            return;
+        }
+        match props.get("text") {
+            Some(PropValue::Content(key)) if self.view().content_defs.get_rich_text(key).is_some() => {
+                let blocks = self.view().content_defs.get_rich_text(key).cloned().unwrap_or_default();
+                for element in rich_text_elements(&blocks) {
+                    self.element(&element, scope, out);
+                }
+            }
+            Some(text) => out.push_str(&html_escape(&self.text(text, scope).unwrap_or_default())),
+            None => {}
+        }
+        self.children(children, scope, out);
+        out.push_str(&format!("</{}>", html_tag));
+    }
+
+    fn media(&mut self, asset_name: &str, props: &HashMap<String, PropValue>, scope: Scope, out: &mut String) {
+        let Some(asset) = self.view().asset_defs.get(asset_name) else {
+            return;
+        };
+        let mut media_props = props.clone();
+        match asset.kind {
+            AssetKind::Image => {
+                media_props.insert("src".to_string(), PropValue::Asset(asset_name.to_string()));
+                self.node("img", None, &media_props, &[], scope, out);
+            }
+            AssetKind::Video | AssetKind::Audio => {
+                media_props.insert("src".to_string(), PropValue::Asset(asset_name.to_string()));
+                media_props.entry("controls".to_string()).or_insert(PropValue::Bool(true));
+                let tag = if let AssetKind::Video = asset.kind { "video" } else { "audio" };
+                self.node(tag, None, &media_props, &[], scope, out);
+            }
+            AssetKind::Youtube => {
+                let url = asset.youtube_embed_url().or_else(|| asset.url.clone()).unwrap_or_default();
+                media_props.insert("src".to_string(), PropValue::Str(url));
+                media_props.entry("title".to_string()).or_insert(PropValue::Str(asset_name.to_string()));
+                media_props.entry("allowfullscreen".to_string()).or_insert(PropValue::Bool(true));
+                media_props.entry("style".to_string()).or_insert(PropValue::Str(
+                    "position: absolute; top: 0; left: 0; width: 100%; height: 100%; border: 0".to_string(),
+                ));
+                out.push_str("<div style=\"position: relative; padding-bottom: 56.25%; height: 0; overflow: hidden\">");
+                self.node("iframe", None, &media_props, &[], scope, out);
+                out.push_str("</div>");
+            }
+        }
+    }
+
+    fn embed(&self, provider: EmbedProvider, config: &HashMap<String, String>, out: &mut String) {
+        let get = |key: &str| html_escape(config.get(key).map(String::as_str).unwrap_or(""));
+        let height = |default: &str| html_escape(config.get("height").map(String::as_str).unwrap_or(default));
+        match provider {
+            EmbedProvider::GoogleMaps => out.push_str(&format!(
+                "<iframe src=\"https://www.google.com/maps?q={}&amp;output=embed\" width=\"100%\" height=\"{}\" style=\"border: 0\" loading=\"lazy\" referrerpolicy=\"no-referrer-when-downgrade\" allowfullscreen title=\"Google Map\"></iframe>",
+                encode_uri_component(config.get("query").map(String::as_str).unwrap_or("")),
+                height("450")
+            )),
+            EmbedProvider::Calendly => {
+                let url = config.get("url").map(String::as_str).unwrap_or("");
+                let separator = if url.contains('?') { '&' } else { '?' };
+                out.push_str(&format!(
+                    "<iframe src=\"{}\" width=\"100%\" height=\"{}\" frameborder=\"0\" title=\"Calendly\"></iframe>",
+                    html_escape(&format!("{}{}embed_type=Inline", url, separator)),
+                    height("700")
+                ));
+            }
+            EmbedProvider::StripePricingTable => out.push_str(&format!(
+                "<script async src=\"https://js.stripe.com/v3/pricing-table.js\"></script><stripe-pricing-table pricing-table-id=\"{}\" publishable-key=\"{}\"></stripe-pricing-table>",
+                get("pricing_table_id"),
+                get("publishable_key")
+            )),
+            EmbedProvider::Twitter => out.push_str(&format!(
+                "<blockquote class=\"twitter-tweet\"><a href=\"{}\"></a></blockquote><script async src=\"https://platform.twitter.com/widgets.js\"></script>",
+                get("url")
+            )),
+        }
+    }
+
+    /// A value as text, or None when it's only known at runtime
+    fn text(&self, value: &PropValue, scope: Scope) -> Option<String> {
+        let view = self.view();
+        match value {
+            PropValue::Str(s) => Some(s.clone()),
+            PropValue::Num(n) => Some(n.to_string()),
+            PropValue::Bool(b) => Some(b.to_string()),
+            PropValue::Var(name) => scope.vars.get(name).map(|var| var.text.clone()),
+            PropValue::Expr(_) | PropValue::Spread(_) | PropValue::Ref(_) | PropValue::Style(_) | PropValue::Handler(_) => None,
+            PropValue::Dim(n, unit) => Some(format!("{}{}", n, unit.suffix())),
+            PropValue::Color(color) => Some(view.theme_defs.resolve_color(color).unwrap_or_else(|| color.clone())),
+            PropValue::Date { value: date, format } => {
+                let raw = self.text(date, scope)?;
+                Some(format_date(&raw, format).unwrap_or(raw))
+            }
+            PropValue::Asset(name) => {
+                let asset = view.asset_defs.get(name)?;
+                match asset.kind {
+                    AssetKind::Image => asset.path.as_deref().map(|path| self.preview.asset_url(path)),
+                    _ => asset.url.clone(),
+                }
+            }
+            PropValue::Content(key) => Some(view.content_defs.get_str(key).cloned().unwrap_or_default()),
+            PropValue::ContentField(field) => Some(scope.record.and_then(|record| record.get(field)).cloned().unwrap_or_default()),
+        }
+    }
+
+    /// Whether an If or Show condition holds, reading props and states from the scope;
+    /// None when it's only known at runtime
+    fn condition(&self, condition: &PropValue, scope: Scope) -> Option<bool> {
+        match condition {
+            PropValue::Var(name) => scope.vars.get(name).map(|var| var.truthy),
+            condition => self.view().condition_value(condition, scope.record),
+        }
+    }
+
+    /// A value a prop or state holds, read in the scope it is passed from
+    fn var(&self, value: &PropValue, scope: Scope) -> Option<Var> {
+        let text = self.text(value, scope)?;
+        let truthy = self.condition(value, scope).unwrap_or(!text.is_empty());
+        Some(Var { text, truthy })
+    }
+
+    /// A style object as a CSS declaration list, keys in name order
+    fn css(&self, entries: &HashMap<String, PropValue>, scope: Scope) -> String {
+        let mut keys: Vec<&String> = entries.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .filter_map(|key| {
+                let value = match &entries[key] {
+                    PropValue::Num(n) if *n != 0.0 && !UNITLESS_STYLES.contains(&key.as_str()) => format!("{}px", n),
+                    value => self.text(value, scope)?,
+                };
+                Some(format!("{}: {}", css_property(key), value))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// `onClick`, `onSubmit`, ...; handlers have nothing to attach to in a static page
+fn is_event_handler(key: &str) -> bool {
+    key.strip_prefix("on").is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+/// React style key as a CSS property: `backgroundColor` -> `background-color`, `WebkitMask` -> `-webkit-mask`.
+/// Keys already written as CSS (`background-color`, `--accent`) are kept.
+fn css_property(key: &str) -> String {
+    if key.starts_with("--") || key.contains('-') {
+        return key.to_string();
+    }
+    let mut property = String::new();
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            property.push('-');
+            property.push(c.to_ascii_lowercase());
+        } else {
+            property.push(c);
+        }
+    }
+    property
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn about() -> ViewJsx {
+        ViewJsx::new(
+            ron(r#"(name: "About page", tree: Node(tag: "main", class_name: "page", children: [
+                Node(tag: "h1", props: {"title": Content("title")}, children: [Text("About")]),
+                Node(tag: "img", props: {"src": Asset("logo"), "alt": Str("Logo & co")}),
+                ComponentRef(component: "Card", props: {"title": Str("Hi")}),
+            ]))"#),
+            ron(r#"(components: [(name: "Card", tag: "article", class_name: "card")])"#),
+            ron(r#"(assets: [(name: "logo", kind: Image, path: "./assets/logo.png")])"#),
+            ron(r#"(content: {"title": Str("About <us>")})"#),
+        )
+    }
+
+    #[test]
+    fn renders_a_standalone_page_with_inlined_content() {
+        let view = about();
+        let preview = PreviewHtml::new(&view).with_stylesheet("/dist/app.css");
+        assert_eq!(preview.path(), "previews/AboutPage.html");
+        let html = preview.to_string();
+        assert!(html.contains("    <title>About page</title>\n    <link rel=\"stylesheet\" href=\"/dist/app.css\" />\n  </head>"), "{}", html);
+        assert!(
+            html.contains(
+                r#"<main class="page"><h1 title="About &lt;us&gt;">About</h1><img alt="Logo &amp; co" src="http://localhost:5173/src/assets/logo.png"><article class="card" title="Hi"></article></main>"#
+            ),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn places_previews_next_to_the_view_output_path() {
+        let view = about().with_output_path("./views/company/About.jsx");
+        let preview = PreviewHtml::new(&view).with_asset_base_url("https://staging.example.com/");
+        assert_eq!(preview.path(), "previews/views/company/About.html");
+        let html = preview.to_string();
+        assert!(html.contains(r#"src="https://staging.example.com/src/assets/logo.png""#), "{}", html);
+    }
+}
@@ -0,0 +1,697 @@
+//! A whole proto project: the index, the view protos its routes, layouts and partials point
+//! at, and the shared defs, checked and generated together
+
+use crate::archive::ProjectArchive;
+use crate::diagnostics::Diagnostic;
+use crate::generate::{render_previews, write_files, GenObserver};
+use crate::output::GeneratedFile;
+use crate::report::BuildReport;
+use crate::paths::{is_remote_path, normalize_path, SCRIPT_EXTENSIONS};
+use crate::view_proto::{
+    AliasDefs, AssetDefs, ComponentDefs, ContentDefs, Element, Handler, ImportKind, PropValue, ThemeDefs, ViewProto,
+};
+use crate::{ContentLoader, ProtoIndex, Route, RouterJsx, ViewJsx};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Files ProtoProject::load reads from the project root
+const INDEX_FILE: &str = "index.ron";
+const COMPONENTS_FILE: &str = "component_defs.ron";
+const ASSETS_FILE: &str = "assets_def.ron";
+const CONTENT_FILE: &str = "content_defs.ron";
+const THEME_FILE: &str = "theme_defs.ron";
+const ALIASES_FILE: &str = "alias_defs.ron";
+
+/// Everything one index generates from, loaded by ProtoProject::load
+#[derive(Debug, Clone)]
+pub struct ProtoProject {
+    /// Directory holding index.ron; proto paths in the index are relative to it
+    pub root: PathBuf,
+    pub index: ProtoIndex,
+    pub components: ComponentDefs,
+    pub assets: AssetDefs,
+    pub content: ContentDefs,
+    pub theme: ThemeDefs,
+    pub aliases: AliasDefs,
+    /// View protos by their `proto` path as written in the index, with `extends` resolved
+    pub views: BTreeMap<String, ViewProto>,
+    /// Protos the index points at that couldn't be loaded, with why; validate reports them
+    pub missing: BTreeMap<String, String>,
+    /// Generate a data router, so routes' loaders and actions are generated and views read
+    /// their loader data; see RouterJsx::with_data_router
+    pub data_router: bool,
+    /// Views read content at runtime from a generated ContentLoader instead of having it
+    /// written in; see ViewJsx::with_runtime_content
+    pub runtime_content: bool,
+}
+
+// A view the index generates, and where it's declared
+struct ViewEntry<'a> {
+    // `routes > home`, `layouts > main`, `partials > footer`
+    path: String,
+    proto: &'a str,
+    output_path: &'a str,
+    route: Option<&'a Route>,
+}
+
+impl ViewEntry<'_> {
+    // Whether both entries render the same proto with the same record and page, so one
+    // generated file serves both
+    fn same_view(&self, other: &Self) -> bool {
+        self.proto == other.proto && self.record() == other.record() && self.page() == other.page()
+    }
+
+    fn record(&self) -> Option<&HashMap<String, String>> {
+        self.route.and_then(|route| route.record.as_ref())
+    }
+
+    fn page(&self) -> Option<(&String, usize)> {
+        self.route.and_then(|route| route.page.as_ref()).map(|page| (&page.collection, page.number))
+    }
+}
+
+impl ProtoProject {
+    /// Read `index.ron` from `root` and every proto its routes, layouts and partials name,
+    /// plus whichever of component_defs.ron, assets_def.ron, content_defs.ron, theme_defs.ron
+    /// and alias_defs.ron exist; absent def files count as empty. Protos that can't be read are
+    /// recorded in `missing` rather than failing the load, so validate can report them all.
+    /// Base views named by `extends` are found among the loaded protos, or as `{name}.ron`
+    /// next to the extending proto.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(root = %root.as_ref().display())))]
+    pub fn load<P: AsRef<Path>>(root: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let root = root.as_ref().to_path_buf();
+        let index = ProtoIndex::from_file(root.join(INDEX_FILE)).map_err(|e| format!("{}: {}", INDEX_FILE, e))?;
+
+        let mut project = ProtoProject {
+            components: load_defs(&root, COMPONENTS_FILE, ComponentDefs::from_file)?
+                .unwrap_or(ComponentDefs { components: Vec::new() }),
+            assets: load_defs(&root, ASSETS_FILE, AssetDefs::from_file)?.unwrap_or(AssetDefs { assets: Vec::new() }),
+            content: load_defs(&root, CONTENT_FILE, ContentDefs::from_file)?
+                .unwrap_or(ContentDefs { content: HashMap::new(), collections: HashMap::new() }),
+            theme: load_defs(&root, THEME_FILE, ThemeDefs::from_file)?.unwrap_or_default(),
+            aliases: load_defs(&root, ALIASES_FILE, AliasDefs::from_file)?.unwrap_or_else(AliasDefs::project_default),
+            root,
+            index,
+            views: BTreeMap::new(),
+            missing: BTreeMap::new(),
+            data_router: false,
+            runtime_content: false,
+        };
+
+        let protos: Vec<String> = project.entries(&project.index).into_iter().map(|entry| entry.proto.to_string()).collect();
+        for proto in protos {
+            if project.views.contains_key(&proto) || project.missing.contains_key(&proto) {
+                continue;
+            }
+            match ViewProto::from_file(project.root.join(&proto)) {
+                Ok(view) => {
+                    project.views.insert(proto, view);
+                }
+                Err(e) => {
+                    project.missing.insert(proto, e.to_string());
+                }
+            }
+        }
+
+        let loaded = project.views.clone();
+        for (proto, view) in &mut project.views {
+            if view.extends.is_none() {
+                continue;
+            }
+            let dir = project.root.join(proto).parent().map(Path::to_path_buf).unwrap_or_default();
+            let find_base = |name: &str| {
+                loaded
+                    .values()
+                    .find(|base| base.name == name)
+                    .cloned()
+                    .or_else(|| ViewProto::from_file(dir.join(format!("{}.ron", name))).ok())
+            };
+            match view.resolve_extends(find_base) {
+                Ok(resolved) => *view = resolved,
+                Err(e) => {
+                    project.missing.insert(proto.clone(), e.to_string());
+                }
+            }
+        }
+        for proto in project.missing.keys() {
+            project.views.remove(proto);
+        }
+        Ok(project)
+    }
+
+    pub fn with_data_router(mut self) -> Self {
+        self.data_router = true;
+        self
+    }
+
+    pub fn with_runtime_content(mut self) -> Self {
+        self.runtime_content = true;
+        self
+    }
+
+    /// Check the whole project before anything is generated: the router and apps, protos the
+    /// index points at that couldn't be loaded, and each view as ViewJsx::validate does. Views are
+    /// also checked against the project, which ViewJsx lets through: components that aren't
+    /// local, defined or imported, and assets, content keys and routes that don't exist.
+    /// Entries generating the same file from different protos or records, or a file outside
+    /// `src/`, are errors. With apps, each app is checked as it is generated: against its own
+    /// router and routes. Diagnostics carry the file they were found in.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self.index.validate_apps().into_iter().map(|d| d.with_file(INDEX_FILE)).collect();
+        for (dir, index) in self.app_indexes() {
+            let index = match self.expand(&index) {
+                Ok(expanded) => expanded,
+                Err(e) => {
+                    diagnostics.push(Diagnostic::error("routes", e.to_string()).with_file(INDEX_FILE));
+                    index
+                }
+            };
+            self.validate_app(dir, &index, &mut diagnostics);
+        }
+
+        // Pages, records and apps sharing a proto find the same problems
+        let mut seen = HashSet::new();
+        diagnostics.retain(|d| seen.insert(d.to_string()));
+        diagnostics
+    }
+
+    // The router, output paths and views generated into `dir` from `index`
+    fn validate_app(&self, dir: &str, index: &ProtoIndex, diagnostics: &mut Vec<Diagnostic>) {
+        let router = self.router(index);
+        diagnostics.extend(router.validate().into_iter().map(|d| d.with_file(INDEX_FILE)));
+
+        // Output path -> the first entry generating it
+        let mut outputs: HashMap<String, &ViewEntry> = HashMap::new();
+        let entries = self.entries(index);
+        for entry in &entries {
+            let source = source_path(entry.output_path);
+            if source == ".." || source.starts_with("../") || source.starts_with('/') {
+                diagnostics.push(
+                    Diagnostic::error(&entry.path, format!("output path `{}` is outside src/", entry.output_path))
+                        .with_file(INDEX_FILE),
+                );
+                continue;
+            }
+            match outputs.get(&source) {
+                Some(first) if !first.same_view(entry) => diagnostics.push(
+                    Diagnostic::error(
+                        &entry.path,
+                        format!("generates {} as `{}` does, from a different proto or record", output_file(dir, &source), first.path),
+                    )
+                    .with_file(INDEX_FILE),
+                ),
+                Some(_) => {}
+                None => {
+                    outputs.insert(source, entry);
+                }
+            }
+        }
+
+        for entry in entries {
+            if let Some(problem) = self.missing.get(entry.proto) {
+                diagnostics.push(
+                    Diagnostic::error(&entry.path, format!("proto `{}` could not be loaded: {}", entry.proto, problem))
+                        .with_file(INDEX_FILE),
+                );
+                continue;
+            }
+            let Some(view) = self.view(&entry, index) else {
+                continue;
+            };
+            let mut found = view.validate();
+            self.check_view(&view.proto, &index.routes, &mut found);
+            diagnostics.extend(found.into_iter().map(|d| d.with_file(entry.proto)));
+        }
+    }
+
+    /// validate's diagnostics as a BuildReport, for tools that want them as JSON
+    pub fn check(&self) -> BuildReport {
+        BuildReport::from_diagnostics(self.validate())
+    }
+
+    /// The router and a component for every route, layout and partial with a proto, at their
+    /// paths under `src/`. With apps, each app gets its own router and the views of its routes
+    /// under `{app dir}/src/`, and routes in no app aren't generated. Fails with every error
+    /// validate finds; all diagnostics go to the observer first, by file.
+    pub fn files(&self, observer: &mut dyn GenObserver) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let diagnostics = self.validate();
+        let mut by_file: BTreeMap<&str, Vec<Diagnostic>> = BTreeMap::new();
+        for diagnostic in &diagnostics {
+            by_file.entry(diagnostic.file.as_deref().unwrap_or(INDEX_FILE)).or_default().push(diagnostic.clone());
+        }
+        for (file, diagnostics) in &by_file {
+            observer.diagnostics(file, diagnostics);
+        }
+        let errors: Vec<String> = diagnostics.iter().filter(|d| d.is_error()).map(|d| d.to_string()).collect();
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+
+        let mut files = Vec::new();
+        for (dir, index) in self.app_indexes() {
+            self.app_files(dir, &self.expand(&index)?, &mut files, observer);
+        }
+        Ok(files)
+    }
+
+    // The files generated into `dir` from `index`
+    fn app_files(&self, dir: &str, index: &ProtoIndex, files: &mut Vec<GeneratedFile>, observer: &mut dyn GenObserver) {
+        let router = self.router(index);
+        files.push(GeneratedFile::text(output_file(dir, &router.output_path), router.to_string()));
+        if self.runtime_content {
+            files.extend(ContentLoader::new(&self.content).files().into_iter().map(|file| GeneratedFile {
+                path: Path::new(dir).join(&file.path),
+                contents: file.contents,
+            }));
+        }
+
+        // Already validated, so views are rendered directly rather than through render_views
+        let mut written = HashSet::new();
+        for entry in self.entries(index) {
+            let Some(view) = self.view(&entry, index) else {
+                continue;
+            };
+            let path = output_file(dir, &source_path(entry.output_path));
+            // A view used by several entries is generated once; validate rejects entries sharing
+            // a path with different views
+            if !written.insert(path.clone()) {
+                continue;
+            }
+            observer.view_started(&view.proto.name);
+            let file = GeneratedFile::text(path, view.to_string());
+            observer.view_finished(&view.proto.name, &file);
+            files.push(file);
+        }
+    }
+
+    /// Generate the project into `out_dir`, creating directories as needed and leaving files
+    /// whose contents haven't changed alone. Returns the generated files, e.g. for a BuildManifest.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(out_dir = %out_dir.as_ref().display())))]
+    pub fn build<P: AsRef<Path>>(
+        &self,
+        out_dir: P,
+        observer: &mut dyn GenObserver,
+    ) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let files = self.files(observer)?;
+        write_files(&files, out_dir, observer)?;
+        Ok(files)
+    }
+
+    /// An HTML preview of every route, layout and partial view, as render_previews makes them,
+    /// with local assets linked from `asset_base_url`. Views are previewed even with errors.
+    pub fn previews(&self, asset_base_url: &str, observer: &mut dyn GenObserver) -> Vec<GeneratedFile> {
+        let index = self.expanded_index().unwrap_or_else(|_| self.index.clone());
+        let mut seen = HashSet::new();
+        let views: Vec<ViewJsx> = self
+            .entries(&index)
+            .iter()
+            .filter(|entry| seen.insert(source_path(entry.output_path)))
+            .filter_map(|entry| self.view(entry, &index))
+            .collect();
+        render_previews(&views, asset_base_url, observer)
+    }
+
+    /// The project as a ProjectArchive: index.ron, the def files it has, every proto the index
+    /// names that loaded and the base view files they extend, and the local files of AssetDefs.
+    /// Fails when a file can't be read or lies outside the root, since the archive couldn't
+    /// reproduce the project.
+    pub fn export(&self) -> Result<ProjectArchive, Box<dyn std::error::Error>> {
+        let mut paths = BTreeSet::new();
+        for file in [INDEX_FILE, COMPONENTS_FILE, ASSETS_FILE, CONTENT_FILE, THEME_FILE, ALIASES_FILE] {
+            if self.root.join(file).exists() {
+                paths.insert(file.to_string());
+            }
+        }
+        for proto in self.views.keys() {
+            paths.insert(normalize_path(proto).trim_start_matches("./").to_string());
+            paths.extend(self.base_view_files(proto));
+        }
+        for asset in &self.assets.assets {
+            if let Some(path) = asset.path.as_deref().filter(|path| !is_remote_path(path)) {
+                paths.insert(normalize_path(&self.aliases.asset_file(path).to_string_lossy()));
+            }
+        }
+
+        let mut files = Vec::new();
+        for path in paths {
+            if !Path::new(&path).components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(format!("export: `{}` is outside the project root", path).into());
+            }
+            let contents = fs::read(self.root.join(&path)).map_err(|e| format!("export: {}: {}", path, e))?;
+            files.push(GeneratedFile { path: path.into(), contents });
+        }
+        Ok(ProjectArchive::new(&files))
+    }
+
+    // Files of the base views a proto extends that aren't loaded protos themselves, found as
+    // load finds them: `{name}.ron` next to the extending proto
+    fn base_view_files(&self, proto: &str) -> Vec<String> {
+        let dir = Path::new(proto).parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut files = Vec::new();
+        let mut next = ViewProto::from_file(self.root.join(proto)).ok().and_then(|view| view.extends);
+        while let Some(name) = next.take() {
+            let file = normalize_path(&dir.join(format!("{}.ron", name)).to_string_lossy());
+            let file = file.trim_start_matches("./").to_string();
+            if self.views.values().any(|view| view.name == name) || files.contains(&file) {
+                break;
+            }
+            let Ok(base) = ViewProto::from_file(self.root.join(&file)) else {
+                break;
+            };
+            files.push(file);
+            next = base.extends;
+        }
+        files
+    }
+
+    /// The index with paginated and Static records routes expanded, as generated
+    fn expanded_index(&self) -> Result<ProtoIndex, Box<dyn std::error::Error>> {
+        self.expand(&self.index)
+    }
+
+    fn expand(&self, index: &ProtoIndex) -> Result<ProtoIndex, Box<dyn std::error::Error>> {
+        index.expand_pagination(&self.content)?.expand_records(&self.content)
+    }
+
+    /// What is generated, by the directory it goes in: each app's index under its dir, or the
+    /// whole index at the root when there are no apps
+    fn app_indexes(&self) -> Vec<(&str, ProtoIndex)> {
+        if self.index.apps.is_empty() {
+            return vec![("", self.index.clone())];
+        }
+        // app() only fails for names that aren't in `apps`
+        self.index.apps.iter().filter_map(|app| Some((app.dir(), self.index.app(&app.name).ok()?))).collect()
+    }
+
+    fn router(&self, index: &ProtoIndex) -> RouterJsx {
+        let router = RouterJsx::from_proto_index(index.clone()).with_content(self.content.clone());
+        if self.data_router { router.with_data_router() } else { router }
+    }
+
+    /// Routes, layouts and partials with a proto, in index order
+    fn entries<'a>(&self, index: &'a ProtoIndex) -> Vec<ViewEntry<'a>> {
+        let routes = index.routes.iter().filter_map(|route| {
+            Some(ViewEntry {
+                path: format!("routes > {}", route.name),
+                proto: route.proto.as_deref()?,
+                output_path: &route.path,
+                route: Some(route),
+            })
+        });
+        let layouts = index.layouts.iter().filter_map(|layout| {
+            Some(ViewEntry {
+                path: format!("layouts > {}", layout.name),
+                proto: layout.proto.as_deref()?,
+                output_path: &layout.path,
+                route: None,
+            })
+        });
+        let partials = index.partials.iter().filter_map(|partial| {
+            Some(ViewEntry {
+                path: format!("partials > {}", partial.name),
+                proto: partial.proto.as_deref()?,
+                output_path: &partial.path,
+                route: None,
+            })
+        });
+        routes.chain(layouts).chain(partials).collect()
+    }
+
+    /// The entry's view with the project's defs and, for routes, the route's page, record and social tags
+    fn view(&self, entry: &ViewEntry, index: &ProtoIndex) -> Option<ViewJsx> {
+        let proto = self.views.get(entry.proto)?.clone();
+        let mut view = ViewJsx::new(proto, self.components.clone(), self.assets.clone(), self.content.clone())
+            .with_theme(self.theme.clone())
+            .with_aliases(self.aliases.clone())
+            .with_routes(index.routes.clone())
+            .with_output_path(source_path(entry.output_path));
+        if self.runtime_content {
+            view = view.with_runtime_content();
+        }
+        let Some(route) = entry.route else {
+            return Some(view);
+        };
+        if self.data_router {
+            view = view.with_route_loader(route);
+        }
+        if let Some(social) = &route.social {
+            view = view.with_social(social.clone());
+        }
+        if let Some(page) = &route.page {
+            view = view.with_page(page.clone());
+        }
+        if let (Some(records), Some(record)) = (&route.records, &route.record) {
+            view = view.with_record(&records.collection, record.clone());
+        }
+        if let Some(lookup) = route.record_lookup() {
+            view = view.with_record_lookup(lookup);
+        }
+        Some(view)
+    }
+
+    fn check_view(&self, view: &ViewProto, routes: &[Route], diagnostics: &mut Vec<Diagnostic>) {
+        for component in &view.components {
+            let path = format!("{} > {} > {}", view.name, component.name, component.tree.label());
+            self.check_element(view, &component.tree, &path, routes, diagnostics);
+        }
+        let path = format!("{} > {}", view.name, view.tree.label());
+        self.check_element(view, &view.tree, &path, routes, diagnostics);
+    }
+
+    fn check_element(&self, view: &ViewProto, element: &Element, path: &str, routes: &[Route], diagnostics: &mut Vec<Diagnostic>) {
+        match element {
+            Element::ComponentRef { component, .. } => {
+                let known = view.components.iter().any(|c| &c.name == component)
+                    || self.components.get(component).is_some()
+                    || view.imports.iter().any(|i| &i.name == component && i.kind == ImportKind::Component);
+                if !known {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("unknown component `{}`: not local, in ComponentDefs or imported", component),
+                    ));
+                }
+            }
+            Element::Media { asset, .. } if self.assets.get(asset).is_none() => {
+                diagnostics.push(Diagnostic::error(path, format!("asset `{}` is not defined in AssetDefs", asset)));
+            }
+            Element::ContentList { source, .. } if self.content.get_list(source).is_none() => {
+                diagnostics.push(Diagnostic::error(path, format!("`{}` is not a list in ContentDefs", source)));
+            }
+            Element::Link { route, .. } if !routes.iter().chain(&self.index.routes).any(|r| &r.name == route) => {
+                diagnostics.push(Diagnostic::error(path, format!("link to unknown route `{}`", route)));
+            }
+            _ => {}
+        }
+
+        let mut values: Vec<&PropValue> = element.props().map(|props| props.values().collect()).unwrap_or_default();
+        match element {
+            Element::Head { title, meta } => values.extend(title.iter().chain(meta.values())),
+            Element::Link { params, .. } => values.extend(params.values()),
+            Element::Match { source, .. } | Element::If { condition: source, .. } | Element::Show { condition: source, .. } => {
+                values.push(source)
+            }
+            _ => {}
+        }
+        let mut keys = Vec::new();
+        for value in values {
+            collect_references(value, &mut keys);
+        }
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            match key {
+                Reference::Asset(name) if self.assets.get(name).is_none() => {
+                    diagnostics.push(Diagnostic::error(path, format!("asset `{}` is not defined in AssetDefs", name)));
+                }
+                Reference::Content(key) if self.content.get(key).is_none() => {
+                    diagnostics.push(Diagnostic::error(path, format!("content `{}` is not defined in ContentDefs", key)));
+                }
+                _ => {}
+            }
+        }
+
+        let children = element.child_elements();
+        for (i, child) in children.iter().enumerate() {
+            let child_path = if children.len() > 1 {
+                format!("{} > {}[{}]", path, child.label(), i)
+            } else {
+                format!("{} > {}", path, child.label())
+            };
+            self.check_element(view, child, &child_path, routes, diagnostics);
+        }
+    }
+}
+
+// A def a prop value looks up by name
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Reference<'a> {
+    Asset(&'a str),
+    Content(&'a str),
+}
+
+fn collect_references<'a>(value: &'a PropValue, references: &mut Vec<Reference<'a>>) {
+    match value {
+        PropValue::Asset(name) => references.push(Reference::Asset(name)),
+        PropValue::Content(key) => references.push(Reference::Content(key)),
+        PropValue::Style(style) => {
+            for value in style.values() {
+                collect_references(value, references);
+            }
+        }
+        PropValue::Date { value, .. } | PropValue::Handler(Handler::Set { value, .. }) => collect_references(value, references),
+        PropValue::Handler(Handler::Call { args, .. }) => {
+            for arg in args {
+                collect_references(arg, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A def file under `root`, or None when the project doesn't have one
+fn load_defs<T>(
+    root: &Path,
+    file: &str,
+    load: fn(PathBuf) -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let path = root.join(file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    load(path).map(Some).map_err(|e| format!("{}: {}", file, e).into())
+}
+
+/// A path under `src/` in the directory an app is generated into, `src/views/Home.jsx` or
+/// `admin/src/views/Home.jsx`
+fn output_file(dir: &str, source_path: &str) -> String {
+    match normalize_path(dir).trim_start_matches("./") {
+        "" => format!("src/{}", source_path),
+        dir => format!("{}/src/{}", dir, source_path),
+    }
+}
+
+/// Where a route, layout or partial path is written under `src/`: `./views/Home` -> `views/Home.jsx`
+fn source_path(path: &str) -> String {
+    let path = normalize_path(path);
+    let path = path.trim_start_matches("./");
+    if SCRIPT_EXTENSIONS.iter().any(|extension| path.ends_with(extension)) {
+        path.to_string()
+    } else {
+        format!("{}.jsx", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> ProtoProject {
+        ProtoProject::load(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn validates_a_clean_project_without_diagnostics() {
+        assert!(fixture("project").validate().is_empty());
+    }
+
+    #[test]
+    fn reports_project_problems_in_the_file_they_are_in() {
+        let found: Vec<(Option<String>, String)> = fixture("broken_project")
+            .validate()
+            .into_iter()
+            .map(|d| (d.file, d.message))
+            .collect();
+        let expected = [
+            (
+                "index.ron",
+                "generates src/views/Home.jsx as `routes > home` does, from a different proto or record",
+            ),
+            ("index.ron", "output path `views/../../../outside.jsx` is outside src/"),
+            ("views/home.ron", "link to unknown route `contact`"),
+            (
+                "views/home.ron",
+                "unknown component `Missing`: not local, in ComponentDefs or imported",
+            ),
+        ];
+        for (file, message) in expected {
+            assert!(
+                found.contains(&(Some(file.to_string()), message.to_string())),
+                "missing {}: {}",
+                file,
+                message
+            );
+        }
+        assert!(
+            found
+                .iter()
+                .any(|(file, message)| file.as_deref() == Some("index.ron")
+                    && message.starts_with("proto `views/gone.ron` could not be loaded"))
+        );
+        assert_eq!(found.len(), 5);
+    }
+
+    #[test]
+    fn generates_each_app_under_its_directory() {
+        let project = fixture("apps_project");
+        let diagnostics: Vec<String> = project.validate().into_iter().map(|d| d.message).collect();
+        assert_eq!(diagnostics, ["route is not in any app and won't be generated"]);
+
+        let files = project.files(&mut BuildReport::default()).unwrap();
+        let paths: Vec<String> = files.iter().map(|file| file.path.to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(
+            paths,
+            [
+                "site/src/router/index.jsx",
+                "site/src/views/Home.jsx",
+                "admin/src/router/index.jsx",
+                "admin/src/views/Home.jsx",
+                "admin/src/views/Dashboard.jsx",
+            ]
+        );
+        let admin_router = String::from_utf8(files[2].contents.clone()).unwrap();
+        assert!(admin_router.contains("/dashboard") && !admin_router.contains("/draft"), "{}", admin_router);
+    }
+
+    #[test]
+    fn exports_and_imports_every_file_the_project_reads() {
+        let project = fixture("project");
+        let archive = project.export().unwrap();
+        let paths: Vec<&str> = archive.files.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            [
+                "assets_def.ron",
+                "component_defs.ron",
+                "content_defs.ron",
+                "index.ron",
+                "public/logo.svg",
+                "views/Page.ron",
+                "views/about.ron",
+                "views/footer.ron",
+                "views/home.ron",
+                "views/main.ron",
+            ]
+        );
+
+        let root = std::env::temp_dir().join(format!("degenproto-import-{}", std::process::id()));
+        let imported = archive.import(&root).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert!(imported.validate().is_empty());
+        assert_eq!(imported.views.keys().collect::<Vec<_>>(), project.views.keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn reports_check_results_as_json() {
+        let report = fixture("broken_project").check();
+        assert!(report.has_errors());
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["stats"]["errors"], 5);
+        assert_eq!(json["diagnostics"].as_array().map(Vec::len), Some(5));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::icons::{encode, open_local_image, square_icon};
+use crate::output::GeneratedFile;
+use crate::view_proto::{AliasDefs, AssetDefs, ThemeDefs};
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Installable-app settings, loaded from pwa.ron
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PwaConfig {
+    pub name: String,
+    #[serde(default)]
+    pub short_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Image asset from AssetDefs; square and at least as large as the biggest icon size works best
+    pub icon: String,
+    /// CSS color or `color.*` theme token
+    #[serde(default)]
+    pub theme_color: Option<String>,
+    #[serde(default)]
+    pub background_color: Option<String>,
+    #[serde(default = "default_start_url")]
+    pub start_url: String,
+    #[serde(default = "default_display")]
+    pub display: String,
+    /// Square icon variants generated from the icon asset
+    #[serde(default = "default_icon_sizes")]
+    pub icon_sizes: Vec<u32>,
+}
+
+fn default_start_url() -> String {
+    "/".to_string()
+}
+
+fn default_display() -> String {
+    "standalone".to_string()
+}
+
+fn default_icon_sizes() -> Vec<u32> {
+    vec![192, 512]
+}
+
+impl PwaConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: PwaConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+}
+
+const SERVICE_WORKER: &str = r#"// Caches the app shell and serves same-origin GET requests cache-first
+const CACHE = 'proto-shell-v1';
+const SHELL = ['/', '/index.html', '/manifest.webmanifest'];
+
+self.addEventListener('install', (event) => {
+  event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(SHELL)));
+  self.skipWaiting();
+});
+
+self.addEventListener('activate', (event) => {
+  event.waitUntil(
+    caches.keys().then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+  );
+  self.clients.claim();
+});
+
+self.addEventListener('fetch', (event) => {
+  const url = new URL(event.request.url);
+  if (event.request.method !== 'GET' || url.origin !== self.location.origin) {
+    return;
+  }
+  event.respondWith(
+    caches.match(event.request).then(
+      (cached) =>
+        cached ||
+        fetch(event.request).then((response) => {
+          const copy = response.clone();
+          caches.open(CACHE).then((cache) => cache.put(event.request, copy));
+          return response;
+        })
+    )
+  );
+});
+"#;
+
+const REGISTER_SERVICE_WORKER: &str = r#"export function registerServiceWorker() {
+  if ('serviceWorker' in navigator && import.meta.env.PROD) {
+    window.addEventListener('load', () => {
+      navigator.serviceWorker.register('/sw.js');
+    });
+  }
+}
+"#;
+
+/// Web app manifest, icon variants and service worker for a Vite project
+pub struct PwaScaffold {
+    pub config: PwaConfig,
+    pub asset_defs: AssetDefs,
+    pub theme_defs: ThemeDefs,
+    // Resolves the icon asset's import path to a file on disk
+    pub aliases: AliasDefs,
+}
+
+impl PwaScaffold {
+    pub fn new(config: PwaConfig, asset_defs: AssetDefs) -> Self {
+        Self {
+            config,
+            asset_defs,
+            theme_defs: ThemeDefs::default(),
+            aliases: AliasDefs::project_default(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme_defs: ThemeDefs) -> Self {
+        self.theme_defs = theme_defs;
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: AliasDefs) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// All PWA files. `project_root` is where the icon asset's path is resolved from.
+    pub fn files<P: AsRef<Path>>(&self, project_root: P) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let mut files = vec![
+            GeneratedFile::text("public/manifest.webmanifest", self.manifest()?),
+            GeneratedFile::text("public/sw.js", SERVICE_WORKER),
+            GeneratedFile::text("src/registerServiceWorker.js", REGISTER_SERVICE_WORKER),
+        ];
+        files.extend(self.icons(project_root.as_ref())?);
+        Ok(files)
+    }
+
+    /// Tags for index.html's <head>
+    pub fn head_tags(&self) -> Vec<String> {
+        let mut tags = vec![r#"<link rel="manifest" href="/manifest.webmanifest" />"#.to_string()];
+        if let Some(color) = self.config.theme_color.as_deref().and_then(|c| self.theme_defs.resolve_color(c)) {
+            tags.push(format!(r#"<meta name="theme-color" content="{}" />"#, color));
+        }
+        if let Some(size) = self.config.icon_sizes.iter().min_by_key(|size| size.abs_diff(180)) {
+            tags.push(format!(r#"<link rel="apple-touch-icon" href="{}" />"#, icon_url(*size)));
+        }
+        tags
+    }
+
+    fn manifest(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let config = &self.config;
+        let mut manifest = json!({
+            "name": config.name,
+            "short_name": config.short_name.as_deref().unwrap_or(&config.name),
+            "start_url": config.start_url,
+            "display": config.display,
+        });
+        if let Some(description) = &config.description {
+            manifest["description"] = json!(description);
+        }
+        for (key, value) in [("theme_color", &config.theme_color), ("background_color", &config.background_color)] {
+            if let Some(value) = value {
+                let color = self
+                    .theme_defs
+                    .resolve_color(value)
+                    .ok_or_else(|| format!("unknown theme color `{}` for {}", value, key))?;
+                manifest[key] = json!(color);
+            }
+        }
+        manifest["icons"] = config
+            .icon_sizes
+            .iter()
+            .map(|size| {
+                json!({
+                    "src": icon_url(*size),
+                    "sizes": format!("{}x{}", size, size),
+                    "type": "image/png",
+                    "purpose": "any",
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&manifest)? + "\n")
+    }
+
+    /// Square PNG variants of the icon asset, center-cropped when it isn't square
+    fn icons(&self, project_root: &Path) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+        let icon = &self.config.icon;
+        let asset = self
+            .asset_defs
+            .get(icon)
+            .ok_or_else(|| format!("PWA icon asset `{}` is not defined", icon))?;
+        let source = open_local_image(asset, &self.aliases, project_root)?;
+
+        let mut files = Vec::new();
+        for size in &self.config.icon_sizes {
+            files.push(GeneratedFile {
+                path: format!("public{}", icon_url(*size)).into(),
+                contents: encode(&square_icon(&source, *size), ImageFormat::Png)?,
+            });
+        }
+        Ok(files)
+    }
+}
+
+fn icon_url(size: u32) -> String {
+    format!("/icons/icon-{}x{}.png", size, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn scaffold(config: &str) -> PwaScaffold {
+        PwaScaffold::new(ron(config), ron(r#"(assets: [(name: "logo", kind: Image, path: "./logo.png")])"#))
+            .with_theme(ron(r##"(colors: {"primary": "#0055ff"})"##))
+    }
+
+    #[test]
+    fn writes_a_manifest_with_theme_colors_and_icons() {
+        let root = std::env::temp_dir().join(format!("degenproto-pwa-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        image::RgbaImage::from_pixel(600, 600, image::Rgba([0, 0, 255, 255])).save(root.join("src/logo.png")).unwrap();
+
+        let scaffold = scaffold(r#"(name: "My App", icon: "logo", theme_color: "color.primary", icon_sizes: [48, 192])"#);
+        let files = scaffold.files(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            ["public/manifest.webmanifest", "public/sw.js", "src/registerServiceWorker.js", "public/icons/icon-48x48.png", "public/icons/icon-192x192.png"]
+        );
+        let manifest: serde_json::Value = serde_json::from_slice(&files[0].contents).unwrap();
+        assert_eq!(manifest["short_name"], "My App");
+        assert_eq!(manifest["theme_color"], "#0055ff");
+        assert_eq!(manifest["icons"][1]["sizes"], "192x192");
+        assert_eq!(
+            scaffold.head_tags(),
+            [
+                r#"<link rel="manifest" href="/manifest.webmanifest" />"#,
+                r##"<meta name="theme-color" content="#0055ff" />"##,
+                r#"<link rel="apple-touch-icon" href="/icons/icon-192x192.png" />"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_colors_and_icons() {
+        let unknown_color = scaffold(r#"(name: "App", icon: "logo", background_color: "color.missing")"#);
+        assert_eq!(unknown_color.files(".").unwrap_err().to_string(), "unknown theme color `color.missing` for background_color");
+        let unknown_icon = scaffold(r#"(name: "App", icon: "missing")"#);
+        assert_eq!(unknown_icon.files(".").unwrap_err().to_string(), "PWA icon asset `missing` is not defined");
+    }
+}
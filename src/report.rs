@@ -0,0 +1,116 @@
+//! Machine-readable results of checking or generating a project, so editor plugins and build
+//! systems can read what happened without scraping Display output
+
+use crate::diagnostics::Diagnostic;
+use crate::generate::GenObserver;
+use crate::output::GeneratedFile;
+use serde::Serialize;
+use std::path::Path;
+
+/// What a check or build found and did. Pass it as the GenObserver of a build to fill it in,
+/// then print `to_json()`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildReport {
+    /// Every diagnostic, errors and warnings, in the order they were reported
+    pub diagnostics: Vec<Diagnostic>,
+    /// Output paths (forward slashes) written
+    pub written: Vec<String>,
+    /// Output paths left alone because they already had the generated contents
+    pub unchanged: Vec<String>,
+    pub stats: BuildStats,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BuildStats {
+    /// Views rendered
+    pub views: usize,
+    /// Bytes of generated output across rendered views
+    pub view_bytes: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl BuildReport {
+    /// A report of validation alone, as for a check run
+    pub fn from_diagnostics(diagnostics: Vec<Diagnostic>) -> Self {
+        let mut report = Self::default();
+        report.add_diagnostics(&diagnostics);
+        report
+    }
+
+    /// Whether any error was reported, i.e. the build failed or would fail
+    pub fn has_errors(&self) -> bool {
+        self.stats.errors > 0
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default() + "\n"
+    }
+
+    fn add_diagnostics(&mut self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            if diagnostic.is_error() {
+                self.stats.errors += 1;
+            } else {
+                self.stats.warnings += 1;
+            }
+            self.diagnostics.push(diagnostic.clone());
+        }
+    }
+}
+
+impl GenObserver for BuildReport {
+    fn view_finished(&mut self, _name: &str, file: &GeneratedFile) {
+        self.stats.views += 1;
+        self.stats.view_bytes += file.contents.len();
+    }
+
+    fn diagnostics(&mut self, _source: &str, diagnostics: &[Diagnostic]) {
+        self.add_diagnostics(diagnostics);
+    }
+
+    fn file_written(&mut self, path: &Path) {
+        self.written.push(report_path(path));
+    }
+
+    fn file_unchanged(&mut self, path: &Path) {
+        self.unchanged.push(report_path(path));
+    }
+}
+
+fn report_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn counts_diagnostics_by_severity() {
+        let report = BuildReport::from_diagnostics(vec![
+            Diagnostic::error("Home > div", "broken").with_file("views/home.ron"),
+            Diagnostic::warning("Home > img", "no alt"),
+        ]);
+        assert!(report.has_errors());
+        assert_eq!(report.stats, BuildStats { views: 0, view_bytes: 0, errors: 1, warnings: 1 });
+    }
+
+    #[test]
+    fn serializes_what_a_build_did() {
+        let mut report = BuildReport::default();
+        report.view_finished("Home", &GeneratedFile::text("src/views/Home.jsx", "abc"));
+        report.file_written(Path::new("src/views/Home.jsx"));
+        report.file_unchanged(Path::new("src/Router.jsx"));
+        report.diagnostics("views/home.ron", &[Diagnostic::warning("Home > img", "no alt").with_file("views/home.ron")]);
+
+        let json: Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["written"], serde_json::json!(["src/views/Home.jsx"]));
+        assert_eq!(json["unchanged"], serde_json::json!(["src/Router.jsx"]));
+        assert_eq!(json["stats"]["views"], 1);
+        assert_eq!(json["stats"]["view_bytes"], 3);
+        assert_eq!(json["diagnostics"][0]["severity"], "Warning");
+        assert_eq!(json["diagnostics"][0]["file"], "views/home.ron");
+    }
+}
@@ -0,0 +1,195 @@
+use crate::markdown::{content_text, is_safe_href};
+use crate::view_proto::{BlockStyle, Element, ListKind, MarkDef, PropValue, RichBlock, RichSpan};
+use std::collections::HashMap;
+
+/// Map rich text blocks to semantic elements: styled blocks become p/h1-h6/blockquote,
+/// consecutive list items are grouped into (nested) ul/ol, marks wrap their spans
+pub(crate) fn rich_text_elements(blocks: &[RichBlock]) -> Vec<Element> {
+    let mut output = Vec::new();
+    // Open lists, outermost first
+    let mut lists: Vec<(ListKind, Element)> = Vec::new();
+
+    for block in blocks {
+        match block {
+            RichBlock::Block { list: Some(kind), level, children, mark_defs, .. } => {
+                let level = (*level).max(1);
+                while lists.len() > level {
+                    close_list(&mut lists, &mut output);
+                }
+                if lists.len() == level && lists.last().is_some_and(|(open, _)| open != kind) {
+                    close_list(&mut lists, &mut output);
+                }
+                while lists.len() < level {
+                    let tag = match kind {
+                        ListKind::Bullet => "ul",
+                        ListKind::Number => "ol",
+                    };
+                    lists.push((*kind, node(tag, HashMap::new(), Vec::new())));
+                }
+                let item = node("li", HashMap::new(), spans(children, mark_defs));
+                if let Some((_, Element::Node { children, .. })) = lists.last_mut() {
+                    children.push(Box::new(item));
+                }
+            }
+            RichBlock::Block { style, children, mark_defs, .. } => {
+                while !lists.is_empty() {
+                    close_list(&mut lists, &mut output);
+                }
+                let tag = match style {
+                    BlockStyle::Normal => "p",
+                    BlockStyle::H1 => "h1",
+                    BlockStyle::H2 => "h2",
+                    BlockStyle::H3 => "h3",
+                    BlockStyle::H4 => "h4",
+                    BlockStyle::H5 => "h5",
+                    BlockStyle::H6 => "h6",
+                    BlockStyle::Blockquote => "blockquote",
+                };
+                output.push(node(tag, HashMap::new(), spans(children, mark_defs)));
+            }
+            RichBlock::Image { asset, alt } => {
+                while !lists.is_empty() {
+                    close_list(&mut lists, &mut output);
+                }
+                let mut props = HashMap::new();
+                if let Some(alt) = alt {
+                    props.insert("alt".to_string(), PropValue::Str(alt.clone()));
+                }
+                output.push(Element::Media { asset: asset.clone(), props, id: None });
+            }
+        }
+    }
+    while !lists.is_empty() {
+        close_list(&mut lists, &mut output);
+    }
+    output.into_iter().map(content_text).collect()
+}
+
+/// Close the innermost open list, nesting it in its parent's last item
+fn close_list(lists: &mut Vec<(ListKind, Element)>, output: &mut Vec<Element>) {
+    let Some((_, list)) = lists.pop() else {
+        return;
+    };
+    match lists.last_mut() {
+        Some((_, Element::Node { children, .. })) => match children.last_mut().map(|item| item.as_mut()) {
+            Some(Element::Node { children: item_children, .. }) => item_children.push(Box::new(list)),
+            _ => children.push(Box::new(list)),
+        },
+        _ => output.push(list),
+    }
+}
+
+fn spans(spans: &[RichSpan], mark_defs: &[MarkDef]) -> Vec<Element> {
+    let mut children: Vec<Element> = Vec::new();
+    for span in spans {
+        // The first mark ends up outermost
+        let mut element = Element::Text(span.text.clone());
+        for mark in span.marks.iter().rev() {
+            element = match mark.as_str() {
+                "strong" => node("strong", HashMap::new(), vec![element]),
+                "em" => node("em", HashMap::new(), vec![element]),
+                "code" => node("code", HashMap::new(), vec![element]),
+                "underline" => node("u", HashMap::new(), vec![element]),
+                "strike-through" => node("s", HashMap::new(), vec![element]),
+                key => match mark_defs.iter().find(|def| def.key == key) {
+                    Some(def) => node("a", link_props(def), vec![element]),
+                    // Unknown marks leave the text as is
+                    None => element,
+                },
+            };
+        }
+        // Plain neighbouring spans read better as one run
+        if let (Element::Text(text), Some(last)) = (&element, children.last_mut())
+            && let Element::Text(previous) = last
+        {
+            previous.push_str(text);
+            continue;
+        }
+        children.push(element);
+    }
+    children
+}
+
+fn link_props(def: &MarkDef) -> HashMap<String, PropValue> {
+    let mut props = HashMap::new();
+    if is_safe_href(&def.href) {
+        props.insert("href".to_string(), PropValue::Str(def.href.clone()));
+    }
+    if def.blank {
+        props.insert("target".to_string(), PropValue::Str("_blank".to_string()));
+        props.insert("rel".to_string(), PropValue::Str("noopener noreferrer".to_string()));
+    }
+    props
+}
+
+fn node(tag: &str, props: HashMap<String, PropValue>, children: Vec<Element>) -> Element {
+    Element::Node {
+        tag: tag.to_string(),
+        class_name: None,
+        props,
+        children: children.into_iter().map(Box::new).collect(),
+        id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Elements as HTML-ish markup with props in name order, so assertions read like the output
+    fn markup(elements: &[Element]) -> String {
+        elements.iter().map(|element| match element {
+            Element::Node { tag, props, children, .. } => {
+                let mut attrs: Vec<String> = props
+                    .iter()
+                    .map(|(key, value)| match value {
+                        PropValue::Str(s) => format!(" {}=\"{}\"", key, s),
+                        other => format!(" {}={:?}", key, other),
+                    })
+                    .collect();
+                attrs.sort();
+                let children: Vec<Element> = children.iter().map(|child| (**child).clone()).collect();
+                format!("<{}{}>{}</{}>", tag, attrs.concat(), markup(&children), tag)
+            }
+            Element::ContentText(text) | Element::Text(text) => text.clone(),
+            Element::Media { asset, .. } => format!("[{}]", asset),
+            other => format!("{:?}", other),
+        }).collect()
+    }
+
+    fn blocks(source: &str) -> Vec<RichBlock> {
+        ron::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn nests_list_items_by_level() {
+        let elements = rich_text_elements(&blocks(
+            r#"[
+                Block(style: H2, children: [(text: "Steps")]),
+                Block(list: Some(Number), children: [(text: "One")]),
+                Block(list: Some(Bullet), level: 2, children: [(text: "detail")]),
+                Block(list: Some(Number), children: [(text: "Two")]),
+                Image(asset: "diagram", alt: Some("Flow")),
+            ]"#,
+        ));
+        assert_eq!(
+            markup(&elements),
+            "<h2>Steps</h2><ol><li>One<ul><li>detail</li></ul></li><li>Two</li></ol>[diagram]"
+        );
+        assert!(elements.iter().all(|e| !matches!(e, Element::Text(_))));
+    }
+
+    #[test]
+    fn wraps_marked_spans_and_links() {
+        let elements = rich_text_elements(&blocks(
+            r#"[Block(
+                children: [(text: "Read "), (text: "the docs", marks: ["strong", "l1"]), (text: " or "), (text: "this", marks: ["bad"])],
+                mark_defs: [(key: "l1", href: "https://example.com", blank: true), (key: "bad", href: "javascript:alert(1)")],
+            )]"#,
+        ));
+        assert_eq!(
+            markup(&elements),
+            r#"<p>Read <strong><a href="https://example.com" rel="noopener noreferrer" target="_blank">the docs</a></strong> or <a>this</a></p>"#
+        );
+    }
+}
@@ -0,0 +1,24 @@
+//! Canonical RON output for proto types: pretty, with maps sorted by key and fields left at
+//! their defaults omitted, so a loaded file saves back the same way every time
+
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+
+/// Write a HashMap in key order
+pub(crate) fn sorted_map<S: Serializer, V: Serialize>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+pub(crate) fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Pretty RON that loads back with the IMPLICIT_SOME extension the `from_file` loaders use
+pub(crate) fn to_ron_string<T: Serialize>(value: &T) -> Result<String, ron::Error> {
+    let config = ron::ser::PrettyConfig::new()
+        .indentor("    ".to_string())
+        .extensions(ron::extensions::Extensions::IMPLICIT_SOME);
+    let mut output = ron::ser::to_string_pretty(value, config)?;
+    output.push('\n');
+    Ok(output)
+}
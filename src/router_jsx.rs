@@ -1,22 +1,65 @@
-use crate::{Layout, ProtoIndex, Route};
-use std::collections::{HashMap, HashSet};
+use crate::names::{component_ident, NameSet};
+use crate::view_jsx::js_string;
+use crate::Diagnostic;
+use crate::paths::{module_specifier, relative_specifier};
+use crate::view_proto::{ContentDefs, ContentValue};
+use crate::{Layout, ProtoIndex, Route, RouteLoader};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 
 pub struct RouterJsx {
     pub layouts: Vec<Layout>,
     pub routes: Vec<Route>,
+    /// Call trackPageView from src/analytics.js on every location change
+    pub track_page_views: bool,
+    /// Where the router is written, relative to the source root that route and layout paths
+    /// are relative to; imports are computed from here
+    pub output_path: String,
+    /// When validate finds errors, add a catch-all route to a page listing them
+    pub placeholder: bool,
+    /// Emit a createBrowserRouter data router, with the routes' loaders and actions
+    pub data_router: bool,
+    /// Records for Collection loaders
+    pub content: Option<ContentDefs>,
+}
+
+// Names of a route's generated loader and action functions
+struct RouteHandlers {
+    loader: Option<String>,
+    action: Option<String>,
 }
 
 struct ImportMap {
     // path -> component_name
     path_to_name: HashMap<String, String>,
-    used_names: HashSet<String>,
+    names: NameSet,
 }
 
 impl ImportMap {
     fn new() -> Self {
+        let mut names = NameSet::new();
+        // Declared or imported by the router module itself
+        for name in [
+            "Router",
+            "RouterProblems",
+            "useEffect",
+            "useLocation",
+            "useRoutes",
+            "trackPageView",
+            "router",
+            "routes",
+            "problems",
+            "PageViewTracker",
+            "Outlet",
+            "RouterProvider",
+            "createBrowserRouter",
+            "redirect",
+        ] {
+            names.reserve(name);
+        }
         Self {
             path_to_name: HashMap::new(),
-            used_names: HashSet::new(),
+            names,
         }
     }
 
@@ -25,10 +68,8 @@ impl ImportMap {
             return name.clone();
         }
 
-        let base_name = format!("{}Layout", capitalize(&layout.name));
-        let name = self.unique_name(&base_name);
+        let name = self.names.unique(&format!("{}Layout", component_ident(&layout.name)));
         self.path_to_name.insert(layout.path.clone(), name.clone());
-        self.used_names.insert(name.clone());
         name
     }
 
@@ -37,28 +78,11 @@ impl ImportMap {
             return name.clone();
         }
 
-        let base_name = capitalize(&route.name);
-        let name = self.unique_name(&base_name);
+        let name = self.names.unique(&component_ident(&route.name));
         self.path_to_name.insert(route.path.clone(), name.clone());
-        self.used_names.insert(name.clone());
         name
     }
 
-    fn unique_name(&self, base: &str) -> String {
-        if !self.used_names.contains(base) {
-            return base.to_string();
-        }
-
-        let mut counter = 2;
-        loop {
-            let candidate = format!("{}{}", base, counter);
-            if !self.used_names.contains(&candidate) {
-                return candidate;
-            }
-            counter += 1;
-        }
-    }
-
     fn get(&self, path: &str) -> Option<&String> {
         self.path_to_name.get(path)
     }
@@ -69,10 +93,218 @@ impl RouterJsx {
         Self {
             layouts: index.layouts,
             routes: index.routes,
+            track_page_views: false,
+            output_path: "router/index.jsx".to_string(),
+            placeholder: false,
+            data_router: false,
+            content: None,
+        }
+    }
+
+    /// Emit a data router, so routes' loaders and actions are generated
+    pub fn with_data_router(mut self) -> Self {
+        self.data_router = true;
+        self
+    }
+
+    pub fn with_content(mut self, content: ContentDefs) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    pub fn with_page_view_tracking(mut self) -> Self {
+        self.track_page_views = true;
+        self
+    }
+
+    pub fn with_output_path(mut self, output_path: impl Into<String>) -> Self {
+        self.output_path = output_path.into();
+        self
+    }
+
+    pub fn with_placeholder(mut self) -> Self {
+        self.placeholder = true;
+        self
+    }
+
+    /// Routes whose layout isn't declared (they are left out of the router), an empty
+    /// route list, urls used twice and layouts no route uses
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "validate", skip_all, fields(router = %self.output_path)))]
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.routes.is_empty() {
+            diagnostics.push(Diagnostic::error("router", "no routes are declared, so the router renders nothing"));
+        }
+        let mut urls = HashSet::new();
+        for route in &self.routes {
+            let path = format!("router > {}", route.name);
+            if let Some(layout) = &route.layout
+                && !self.layouts.iter().any(|l| &l.name == layout)
+            {
+                diagnostics.push(Diagnostic::error(
+                    &path,
+                    format!("layout `{}` is not declared, so the route is left out of the router", layout),
+                ));
+            }
+            if !urls.insert(route.url.as_str()) {
+                diagnostics.push(Diagnostic::warning(&path, format!("url `{}` is already routed", route.url)));
+            }
+            self.validate_handlers(route, &path, &mut diagnostics);
+        }
+        for layout in &self.layouts {
+            if !self.routes.iter().any(|r| r.layout.as_ref() == Some(&layout.name)) {
+                diagnostics.push(Diagnostic::warning(format!("router > {}", layout.name), "no route uses this layout"));
+            }
+        }
+        diagnostics
+    }
+
+    /// Loaders and actions: only generated for data routers, reading collections that exist,
+    /// with endpoint params the route's url provides
+    fn validate_handlers(&self, route: &Route, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+        if !self.data_router {
+            if route.loader.is_some() || route.action.is_some() {
+                diagnostics.push(Diagnostic::warning(
+                    path,
+                    "loaders and actions are only generated in data-router mode, so this one is left out",
+                ));
+            }
+            return;
+        }
+        let params = route.url_params();
+        let mut urls = Vec::new();
+        match &route.loader {
+            Some(RouteLoader::Collection(collection)) => {
+                match self.content.as_ref().map(|content| content.get_list(collection)) {
+                    None => diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("loader reads collection `{}` but the router has no content defs", collection),
+                    )),
+                    Some(None) => diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("loader reads `{}`, which is not a content list", collection),
+                    )),
+                    Some(Some(_)) => {}
+                }
+            }
+            Some(RouteLoader::Fetch(url)) => urls.push(("loader", url)),
+            None => {}
+        }
+        if let Some(action) = &route.action {
+            urls.push(("action", &action.url));
+            if let Some(method) = &action.method
+                && !["POST", "PUT", "PATCH", "DELETE"].contains(&method.to_ascii_uppercase().as_str())
+            {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("action method `{}` must be POST, PUT, PATCH or DELETE", method),
+                ));
+            }
+        }
+        for (kind, url) in urls {
+            for segment in url.split('/') {
+                if let Some(param) = segment.strip_prefix(':')
+                    && !params.contains(&param)
+                {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("{} url `{}` uses `:{}`, which the route url doesn't have", kind, url, param),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Loader and action functions for each route that declares them, keyed by route name,
+    /// and their source along with the records Collection loaders read
+    fn route_handlers(&self, names: &mut NameSet) -> (HashMap<String, RouteHandlers>, String) {
+        let mut handlers = HashMap::new();
+        let mut records = BTreeMap::new();
+        let mut functions = String::new();
+        for route in &self.routes {
+            let params = route.url_params();
+            let loader = route.loader.as_ref().map(|loader| {
+                let name = names.unique(&format!("{}Loader", camel_ident(&route.name)));
+                match loader {
+                    RouteLoader::Collection(collection) => {
+                        let records_name = records
+                            .entry(collection.clone())
+                            .or_insert_with(|| names.unique(&format!("{}Records", camel_ident(collection))))
+                            .clone();
+                        if params.is_empty() {
+                            functions.push_str(&format!("\nfunction {}() {{\n  return {};\n}}\n", name, records_name));
+                        } else {
+                            let matches: Vec<String> = params.iter().map(|p| format!("r.{} === params.{}", p, p)).collect();
+                            functions.push_str(&format!(
+                                "\nfunction {}({{ params }}) {{\n  const record = {}.find((r) => {});\n  if (!record) {{\n    throw new Response(\"Not found\", {{ status: 404 }});\n  }}\n  return record;\n}}\n",
+                                name,
+                                records_name,
+                                matches.join(" && ")
+                            ));
+                        }
+                    }
+                    RouteLoader::Fetch(url) => functions.push_str(&format!(
+                        "\nasync function {}({{ params, request }}) {{\n  const response = await fetch({}, {{ signal: request.signal }});\n  if (!response.ok) {{\n    throw new Response(response.statusText, {{ status: response.status }});\n  }}\n  return response.json();\n}}\n",
+                        name,
+                        url_expr(url)
+                    )),
+                }
+                name
+            });
+            let action = route.action.as_ref().map(|action| {
+                let name = names.unique(&format!("{}Action", camel_ident(&route.name)));
+                let method = action.method.as_deref().unwrap_or("POST").to_ascii_uppercase();
+                let result = match &action.redirect {
+                    Some(url) => format!("redirect({})", js_string(url)),
+                    None => "null".to_string(),
+                };
+                functions.push_str(&format!(
+                    "\nasync function {}({{ params, request }}) {{\n  const response = await fetch({}, {{ method: {}, body: await request.formData() }});\n  if (!response.ok) {{\n    throw new Response(response.statusText, {{ status: response.status }});\n  }}\n  return {};\n}}\n",
+                    name,
+                    url_expr(&action.url),
+                    js_string(&method),
+                    result
+                ));
+                name
+            });
+            if loader.is_some() || action.is_some() {
+                handlers.insert(route.name.clone(), RouteHandlers { loader, action });
+            }
         }
+
+        let mut source = String::new();
+        for (collection, records_name) in &records {
+            let list: Vec<BTreeMap<&String, &String>> = self
+                .content
+                .iter()
+                .flat_map(|content| content.get_list(collection))
+                .flatten()
+                .filter_map(|item| match item {
+                    ContentValue::Record(record) => Some(record.iter().collect()),
+                    _ => None,
+                })
+                .collect();
+            let json = serde_json::to_string_pretty(&list).unwrap_or_else(|_| "[]".to_string());
+            source.push_str(&format!("\nconst {} = {};\n", records_name, json));
+        }
+        source.push_str(&functions);
+        (handlers, source)
+    }
+
+    /// Specifier for a view or layout module, from the router's location
+    fn specifier(&self, path: &str) -> String {
+        relative_specifier(&self.output_path, &module_specifier(path, true))
     }
 
-    pub fn to_string(&self) -> String {
+    /// npm packages the generated router imports from
+    pub fn npm_packages(&self) -> BTreeSet<String> {
+        BTreeSet::from(["react".to_string(), "react-router-dom".to_string()])
+    }
+}
+
+impl fmt::Display for RouterJsx {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "render", skip_all, fields(router = %self.output_path)))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut imports = String::new();
         let mut route_elements = String::new();
         let mut import_map = ImportMap::new();
@@ -85,20 +317,42 @@ impl RouterJsx {
             import_map.add_route(route);
         }
 
-        // Import useRoutes
-        imports.push_str("import { useRoutes } from \"react-router-dom\";\n");
+        // Import useRoutes, or the data router APIs
+        if self.data_router {
+            let mut names = vec!["RouterProvider", "createBrowserRouter"];
+            if self.routes.iter().any(|r| r.action.as_ref().is_some_and(|a| a.redirect.is_some())) {
+                names.push("redirect");
+            }
+            if self.track_page_views {
+                imports.push_str("import { useEffect } from \"react\";\n");
+                names.extend(["Outlet", "useLocation"]);
+            }
+            names.sort();
+            imports.push_str(&format!("import {{ {} }} from \"react-router-dom\";\n", names.join(", ")));
+        } else if self.track_page_views {
+            imports.push_str("import { useEffect } from \"react\";\n");
+            imports.push_str("import { useLocation, useRoutes } from \"react-router-dom\";\n");
+        } else {
+            imports.push_str("import { useRoutes } from \"react-router-dom\";\n");
+        }
+        if self.track_page_views {
+            imports.push_str(&format!(
+                "import {{ trackPageView }} from \"{}\";\n",
+                relative_specifier(&self.output_path, "analytics")
+            ));
+        }
 
         // Import layouts
         for layout in &self.layouts {
             let component_name = import_map.get(&layout.path).unwrap();
             imports.push_str(&format!(
-                "import {} from \"../{}\";\n",
+                "import {} from \"{}\";\n",
                 component_name,
-                layout.path
+                self.specifier(&layout.path)
             ));
         }
 
-        imports.push_str("\n");
+        imports.push('\n');
 
         // Import views (deduplicated by path)
         let mut seen_paths: HashSet<String> = HashSet::new();
@@ -110,12 +364,18 @@ impl RouterJsx {
 
             let component_name = import_map.get(&route.path).unwrap();
             imports.push_str(&format!(
-                "import {} from \"../{}\";\n",
+                "import {} from \"{}\";\n",
                 component_name,
-                route.path
+                self.specifier(&route.path)
             ));
         }
 
+        let (handlers, handler_source) = if self.data_router {
+            self.route_handlers(&mut import_map.names)
+        } else {
+            (HashMap::new(), String::new())
+        };
+
         // Build route configuration
         // Group routes by layout
         let mut layout_routes: HashMap<String, Vec<&Route>> = HashMap::new();
@@ -129,61 +389,253 @@ impl RouterJsx {
             }
         }
 
-        route_elements.push_str("  const routes = [\n");
+        // Data routers are created once at module level rather than inside Router
+        let base = if self.data_router { "" } else { "  " };
+        route_elements.push_str(&format!("{}const routes = [\n", base));
 
         // Routes with layouts
         for layout in &self.layouts {
             if let Some(routes) = layout_routes.get(&layout.name) {
                 let layout_component = import_map.get(&layout.path).unwrap();
                 route_elements.push_str(&format!(
-                    "    {{\n      path: \"/\",\n      element: <{} />,\n      children: [\n",
-                    layout_component
+                    "{b}  {{\n{b}    path: \"/\",\n{b}    element: <{} />,\n{b}    children: [\n",
+                    layout_component,
+                    b = base
                 ));
 
                 for route in routes {
                     let component_name = import_map.get(&route.path).unwrap();
-                    route_elements.push_str(&format!(
-                        "        {{\n          path: \"{}\",\n          element: <{} />,\n        }},\n",
-                        route.url,
-                        component_name
-                    ));
+                    let indent = format!("{}      ", base);
+                    route_elements.push_str(&route_entry(route, component_name, handlers.get(&route.name), &indent));
                 }
 
-                route_elements.push_str("      ],\n    },\n");
+                route_elements.push_str(&format!("{b}    ],\n{b}  }},\n", b = base));
             }
         }
 
         // Routes without layouts
         for route in no_layout_routes {
             let component_name = import_map.get(&route.path).unwrap();
+            let indent = format!("{}  ", base);
+            route_elements.push_str(&route_entry(route, component_name, handlers.get(&route.name), &indent));
+        }
+
+        // Catch-all page listing what's wrong with the route config
+        let problems: Vec<Diagnostic> = if self.placeholder {
+            self.validate().into_iter().filter(Diagnostic::is_error).collect()
+        } else {
+            Vec::new()
+        };
+        if !problems.is_empty() {
             route_elements.push_str(&format!(
-                "    {{\n      path: \"{}\",\n      element: <{} />,\n    }},\n",
-                route.url,
-                component_name
+                "{b}  {{\n{b}    path: \"*\",\n{b}    element: <RouterProblems />,\n{b}  }},\n",
+                b = base
             ));
         }
 
-        route_elements.push_str("  ];\n");
+        route_elements.push_str(&format!("{}];\n", base));
+
+        if self.track_page_views && !self.data_router {
+            route_elements.push_str(
+                "\n  const location = useLocation();\n  useEffect(() => {\n    trackPageView(location.pathname + location.search);\n  }, [location]);\n",
+            );
+        }
+
+        if !problems.is_empty() {
+            imports.push_str("\nconst problems = [\n");
+            for problem in &problems {
+                imports.push_str(&format!("  {},\n", js_string(&problem.to_string())));
+            }
+            imports.push_str(
+                r#"];
+
+function RouterProblems() {
+  return (
+    <main style={{ fontFamily: "sans-serif", padding: "2rem" }}>
+      <h1>The route config has problems</h1>
+      <ul>
+        {problems.map((problem) => (
+          <li key={problem}>{problem}</li>
+        ))}
+      </ul>
+    </main>
+  );
+}
+"#,
+            );
+        }
 
-        format!(
-            r#"{}
+        if !self.data_router {
+            return write!(
+                f,
+                r#"{}
 function Router() {{
 {}
   return useRoutes(routes);
 }}
 
+export default Router;
+"#,
+                imports,
+                route_elements
+            );
+        }
+
+        // Page views are tracked from a pathless route wrapping every other one
+        let (tracker, router_routes) = if self.track_page_views {
+            (
+                r#"
+function PageViewTracker() {
+  const location = useLocation();
+  useEffect(() => {
+    trackPageView(location.pathname + location.search);
+  }, [location]);
+
+  return <Outlet />;
+}
+"#,
+                "[\n  {\n    element: <PageViewTracker />,\n    children: routes,\n  },\n]",
+            )
+        } else {
+            ("", "routes")
+        };
+        write!(
+            f,
+            r#"{}{}
+{}{}
+const router = createBrowserRouter({});
+
+function Router() {{
+  return <RouterProvider router={{router}} />;
+}}
+
 export default Router;
 "#,
             imports,
-            route_elements
+            handler_source,
+            route_elements,
+            tracker,
+            router_routes
         )
     }
 }
 
-fn capitalize(s: &str) -> String {
-    let mut chars = s.chars();
+/// One entry of the routes array
+fn route_entry(route: &Route, component_name: &str, handlers: Option<&RouteHandlers>, indent: &str) -> String {
+    let mut entry = format!(
+        "{i}{{\n{i}  path: \"{}\",\n{i}  element: <{} />,\n",
+        route.url,
+        component_name,
+        i = indent
+    );
+    if let Some(handlers) = handlers {
+        if let Some(loader) = &handlers.loader {
+            entry.push_str(&format!("{}  loader: {},\n", indent, loader));
+        }
+        if let Some(action) = &handlers.action {
+            entry.push_str(&format!("{}  action: {},\n", indent, action));
+        }
+    }
+    entry.push_str(&format!("{}}},\n", indent));
+    entry
+}
+
+/// `/api/posts/:slug` as a JS expression, each param read from the loader's `params`
+fn url_expr(url: &str) -> String {
+    if !url.contains(':') {
+        return js_string(url);
+    }
+    let segments: Vec<String> = url
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("${{encodeURIComponent(params.{})}}", param),
+            None => segment.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${"),
+        })
+        .collect();
+    format!("`{}`", segments.join("/"))
+}
+
+/// `blog-post` -> `blogPost`, for functions and constants
+fn camel_ident(name: &str) -> String {
+    let ident = component_ident(name);
+    let mut chars = ident.chars();
     match chars.next() {
-        None => String::new(),
-        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => ident,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(index: &str) -> RouterJsx {
+        RouterJsx::from_proto_index(ron::from_str(index).unwrap())
+    }
+
+    #[test]
+    fn reports_missing_layouts_and_empty_routes() {
+        let messages: Vec<String> = router(r#"(layouts: [], routes: [])"#).validate().iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].contains("no routes are declared"), "{:?}", messages);
+
+        let router = router(
+            r#"(layouts: [(name: "main", path: "layouts/Main.jsx")], routes: [(name: "home", url: "/", path: "views/Home.jsx", layout: Some("site"))])"#,
+        );
+        let diagnostics = router.validate();
+        assert!(diagnostics.iter().any(|d| d.is_error() && d.to_string().contains("layout `site` is not declared")), "{:?}", diagnostics);
+        assert!(diagnostics.iter().any(|d| !d.is_error() && d.to_string().contains("no route uses this layout")), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn lists_problems_on_a_catch_all_page() {
+        let index = r#"(layouts: [], routes: [(name: "home", url: "/", path: "views/Home.jsx", layout: Some("site"))])"#;
+        assert!(!router(index).to_string().contains("RouterProblems"));
+
+        let jsx = router(index).with_placeholder().to_string();
+        assert!(jsx.contains("path: \"*\",\n      element: <RouterProblems />,"), "{}", jsx);
+        assert!(jsx.contains("function RouterProblems()"), "{}", jsx);
+        assert!(jsx.contains("layout `site` is not declared"), "{}", jsx);
+    }
+
+
+    #[test]
+    fn generates_loaders_and_actions_for_data_routes() {
+        let router = router(
+            r#"(layouts: [], routes: [
+                (name: "posts", url: "/posts", path: "views/Posts.jsx", loader: Some(Collection("posts"))),
+                (name: "post", url: "/posts/:slug", path: "views/Post.jsx", loader: Some(Fetch("/api/posts/:slug")), action: Some((url: "/api/posts/:slug/comments", redirect: Some("/posts")))),
+            ])"#,
+        )
+        .with_data_router()
+        .with_content(ron::from_str(r#"(content: {"posts": List([])})"#).unwrap());
+        assert!(router.validate().is_empty(), "{:?}", router.validate());
+        let jsx = router.to_string();
+        assert!(jsx.starts_with("import { RouterProvider, createBrowserRouter, redirect } from \"react-router-dom\";"), "{}", jsx);
+        assert!(jsx.contains("function postsLoader() {\n  return postsRecords;\n}"), "{}", jsx);
+        assert!(jsx.contains("await fetch(`/api/posts/${encodeURIComponent(params.slug)}`, { signal: request.signal });"), "{}", jsx);
+        assert!(jsx.contains("{ method: \"POST\", body: await request.formData() });"), "{}", jsx);
+        assert!(jsx.contains("  return redirect(\"/posts\");\n"), "{}", jsx);
+        assert!(jsx.contains("    element: <Post />,\n    loader: postLoader,\n    action: postAction,\n"), "{}", jsx);
+    }
+
+    #[test]
+    fn reports_loaders_the_router_cant_generate() {
+        let index = r#"(layouts: [], routes: [
+            (name: "post", url: "/posts/:slug", path: "views/Post.jsx", loader: Some(Collection("posts")), action: Some((url: "/api/:id", method: Some("GET")))),
+        ])"#;
+        let messages = |router: RouterJsx| router.validate().into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(
+            messages(router(index)),
+            ["loaders and actions are only generated in data-router mode, so this one is left out"]
+        );
+        assert_eq!(
+            messages(router(index).with_data_router()),
+            [
+                "loader reads collection `posts` but the router has no content defs",
+                "action method `GET` must be POST, PUT, PATCH or DELETE",
+                "action url `/api/:id` uses `:id`, which the route url doesn't have",
+            ]
+        );
     }
 }
@@ -0,0 +1,253 @@
+use crate::markdown::markdown_plain_text;
+use crate::output::GeneratedFile;
+use crate::view_proto::{excerpt, ContentDefs, ContentValue};
+use crate::{ProtoIndex, RecordMode, Route};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Client-side search settings, loaded from search.ron
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchConfig {
+    /// Record fields indexed for records routes, by collection
+    #[serde(default)]
+    pub collections: BTreeMap<String, SearchFields>,
+    #[serde(default = "default_excerpt_length")]
+    pub excerpt_length: usize,
+    /// Also emit src/components/Search.jsx
+    #[serde(default = "default_component")]
+    pub component: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchFields {
+    pub title: String,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+}
+
+fn default_excerpt_length() -> usize {
+    160
+}
+
+fn default_component() -> bool {
+    true
+}
+
+/// One searchable page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchEntry {
+    pub title: String,
+    pub url: String,
+    pub excerpt: String,
+}
+
+const SEARCH_COMPONENT: &str = r#"import React, { useMemo, useState } from 'react';
+import { Link } from 'react-router-dom';
+
+let indexPromise;
+
+function loadIndex() {
+  indexPromise = indexPromise || fetch('/search-index.json').then((response) => response.json());
+  return indexPromise;
+}
+
+function Search() {
+  const [entries, setEntries] = useState([]);
+  const [query, setQuery] = useState('');
+
+  const results = useMemo(() => {
+    const terms = query.toLowerCase().split(/\s+/).filter(Boolean);
+    if (terms.length === 0) {
+      return [];
+    }
+    return entries
+      .map((entry) => {
+        const title = entry.title.toLowerCase();
+        const excerpt = entry.excerpt.toLowerCase();
+        let score = 0;
+        for (const term of terms) {
+          if (title.includes(term)) {
+            score += 2;
+          } else if (excerpt.includes(term)) {
+            score += 1;
+          } else {
+            return null;
+          }
+        }
+        return { entry, score };
+      })
+      .filter(Boolean)
+      .sort((a, b) => b.score - a.score)
+      .slice(0, 10)
+      .map(({ entry }) => entry);
+  }, [entries, query]);
+
+  return (
+    <div className="search" role="search">
+      <input
+        type="search"
+        placeholder="Search"
+        aria-label="Search"
+        value={query}
+        onFocus={() => loadIndex().then(setEntries)}
+        onChange={(event) => setQuery(event.target.value)}
+      />
+      {results.length > 0 && (
+        <ul className="search-results">
+          {results.map((entry) => (
+            <li key={entry.url}>
+              <Link to={entry.url}>{entry.title}</Link>
+              {entry.excerpt && <p>{entry.excerpt}</p>}
+            </li>
+          ))}
+        </ul>
+      )}
+    </div>
+  );
+}
+
+export default Search;
+"#;
+
+impl SearchConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: SearchConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+
+    /// An entry per page. Records routes (expanded or dynamic) get one per record from the
+    /// configured fields; other routes use their social title and description, or their name.
+    /// Routes with unfilled `:param` segments and pages after the first are skipped.
+    pub fn entries(&self, index: &ProtoIndex, content: &ContentDefs) -> Vec<SearchEntry> {
+        let mut entries = Vec::new();
+        for route in &index.routes {
+            if route.page.as_ref().is_some_and(|page| page.number > 1) {
+                continue;
+            }
+            let collection = route.records.as_ref().map(|records| records.collection.as_str());
+            let fields = collection.and_then(|c| self.collections.get(c).map(|fields| (c, fields)));
+
+            match (&route.record, fields) {
+                (Some(record), Some((collection, fields))) => {
+                    entries.push(self.record_entry(route.url.clone(), record, collection, fields, content));
+                }
+                (None, Some((collection, fields)))
+                    if route.records.as_ref().is_some_and(|r| r.mode == RecordMode::Dynamic) =>
+                {
+                    for item in content.get_list(collection).into_iter().flatten() {
+                        if let ContentValue::Record(record) = item
+                            && let Some(url) = route.fill_url(record)
+                        {
+                            entries.push(self.record_entry(url, record, collection, fields, content));
+                        }
+                    }
+                }
+                _ if route.url_params().is_empty() => entries.push(self.route_entry(route, content)),
+                _ => {}
+            }
+        }
+        entries
+    }
+
+    fn record_entry(
+        &self,
+        url: String,
+        record: &HashMap<String, String>,
+        collection: &str,
+        fields: &SearchFields,
+        content: &ContentDefs,
+    ) -> SearchEntry {
+        let text = fields.excerpt.as_ref().and_then(|field| record.get(field)).map(|value| {
+            let is_markdown = fields
+                .excerpt
+                .as_ref()
+                .is_some_and(|field| content.markdown_fields(collection).contains(field));
+            if is_markdown { markdown_plain_text(value) } else { value.clone() }
+        });
+        SearchEntry {
+            title: record.get(&fields.title).cloned().unwrap_or_default(),
+            url,
+            excerpt: excerpt(text.as_deref().unwrap_or_default(), self.excerpt_length),
+        }
+    }
+
+    fn route_entry(&self, route: &Route, content: &ContentDefs) -> SearchEntry {
+        let social = route.social.as_ref();
+        let title = social
+            .and_then(|social| content.get_str(&social.title))
+            .cloned()
+            .unwrap_or_else(|| route.name.clone());
+        let description = social
+            .and_then(|social| social.description.as_ref())
+            .and_then(|key| content.get_str(key));
+        SearchEntry {
+            title,
+            url: route.url.clone(),
+            excerpt: excerpt(description.map(String::as_str).unwrap_or_default(), self.excerpt_length),
+        }
+    }
+
+    pub fn index_json(&self, index: &ProtoIndex, content: &ContentDefs) -> String {
+        serde_json::to_string_pretty(&self.entries(index, content)).unwrap_or_default() + "\n"
+    }
+
+    /// public/search-index.json, plus src/components/Search.jsx when enabled
+    pub fn files(&self, index: &ProtoIndex, content: &ContentDefs) -> Vec<GeneratedFile> {
+        let mut files = vec![GeneratedFile::text("public/search-index.json", self.index_json(index, content))];
+        if self.component {
+            files.push(GeneratedFile::text("src/components/Search.jsx", SEARCH_COMPONENT));
+        }
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    #[test]
+    fn indexes_pages_and_records() {
+        let index: ProtoIndex = ron(
+            r#"(layouts: [], routes: [
+                (name: "home", url: "/", path: "views/Home.jsx", social: (title: "home.title", description: "home.summary")),
+                (name: "about", url: "/about", path: "views/About.jsx"),
+                (name: "post", url: "/blog/:slug", path: "views/Post.jsx", records: (collection: "posts", mode: Dynamic)),
+                (name: "user", url: "/users/:id", path: "views/User.jsx"),
+            ])"#,
+        );
+        let content: ContentDefs = ron(
+            r#"(
+                content: {
+                    "home.title": Str("Welcome"),
+                    "home.summary": Str("Everything about mugs"),
+                    "posts": List([Record({"slug": "glaze", "title": "Glazing", "body": "How to **glaze** a mug"})]),
+                },
+                collections: {"posts": (markdown: ["body"])},
+            )"#,
+        );
+        let config: SearchConfig = ron(r#"(collections: {"posts": (title: "title", excerpt: "body")}, excerpt_length: 12, component: false)"#);
+        let entry = |title: &str, url: &str, excerpt: &str| SearchEntry { title: title.into(), url: url.into(), excerpt: excerpt.into() };
+        assert_eq!(
+            config.entries(&index, &content),
+            [
+                entry("Welcome", "/", "Everything…"),
+                entry("about", "/about", ""),
+                entry("Glazing", "/blog/glaze", "How to glaze…"),
+            ]
+        );
+        let paths: Vec<String> = config.files(&index, &content).iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, ["public/search-index.json"]);
+    }
+}
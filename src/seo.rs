@@ -0,0 +1,173 @@
+use crate::output::{html_escape, GeneratedFile};
+use crate::{Diagnostic, ProtoIndex, Route};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Project-wide SEO settings, loaded from seo.ron
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SeoConfig {
+    /// Public origin the site is served from, e.g. "https://example.com"
+    pub base_url: String,
+    /// Meta tags every page starts with, by name ("description", "author", ...)
+    #[serde(default)]
+    pub default_meta: BTreeMap<String, String>,
+    /// Extra paths crawlers should skip
+    #[serde(default)]
+    pub disallow: Vec<String>,
+    /// Names of routes kept out of search results; they get no canonical URL
+    #[serde(default)]
+    pub noindex: Vec<String>,
+}
+
+impl SeoConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let config: SeoConfig = options.from_str(&content)?;
+        Ok(config)
+    }
+
+    /// A base URL that isn't an absolute http(s) URL, and disallowed paths that aren't absolute
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let host = self.base_url.strip_prefix("https://").or_else(|| self.base_url.strip_prefix("http://"));
+        if host.is_none_or(|host| host.is_empty() || host.starts_with('/')) {
+            diagnostics.push(Diagnostic::error(
+                "seo",
+                format!("base URL `{}` must be an absolute http:// or https:// URL", self.base_url),
+            ));
+        }
+        for path in &self.disallow {
+            if !path.starts_with('/') {
+                diagnostics.push(Diagnostic::error("seo > disallow", format!("path `{}` must start with /", path)));
+            }
+        }
+        diagnostics
+    }
+
+    /// An absolute URL for a site path, under base_url
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+
+    /// Canonical URL for a route. None for noindex routes and routes with `:param` or `*`
+    /// segments, whose URL is only known at runtime.
+    pub fn canonical_url(&self, route: &Route) -> Option<String> {
+        let dynamic = route.url.split('/').any(|s| s.starts_with(':') || s.contains('*'));
+        if self.noindex.contains(&route.name) || dynamic {
+            return None;
+        }
+        Some(self.url(&route.url))
+    }
+
+    /// sitemap.xml listing every route with a canonical URL. Records routes are listed once
+    /// expanded, so pass the index from ProtoIndex::expand_records.
+    pub fn sitemap_xml(&self, index: &ProtoIndex) -> String {
+        let mut urls: Vec<String> = index.routes.iter().filter_map(|route| self.canonical_url(route)).collect();
+        urls.sort();
+        urls.dedup();
+
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+        for url in &urls {
+            output.push_str(&format!("  <url><loc>{}</loc></url>\n", html_escape(url)));
+        }
+        output.push_str("</urlset>\n");
+        output
+    }
+
+    /// Default meta tags for index.html's <head>
+    pub fn head_tags(&self) -> Vec<String> {
+        self.default_meta
+            .iter()
+            .map(|(name, content)| {
+                format!(r#"<meta name="{}" content="{}" />"#, html_escape(name), html_escape(content))
+            })
+            .collect()
+    }
+
+    /// robots.txt disallowing the configured paths and noindex routes, pointing at the sitemap
+    pub fn robots_txt(&self, index: &ProtoIndex) -> String {
+        let mut disallowed: Vec<String> = self.disallow.clone();
+        for route in &index.routes {
+            if self.noindex.contains(&route.name) {
+                // `:param` segments match anything
+                let pattern: Vec<&str> = route
+                    .url
+                    .split('/')
+                    .map(|segment| if segment.starts_with(':') { "*" } else { segment })
+                    .collect();
+                disallowed.push(pattern.join("/"));
+            }
+        }
+        disallowed.sort();
+        disallowed.dedup();
+
+        let mut output = String::from("User-agent: *\n");
+        for path in &disallowed {
+            output.push_str(&format!("Disallow: {}\n", path));
+        }
+        output.push_str("Allow: /\n");
+        output.push_str(&format!("\nSitemap: {}\n", self.url("sitemap.xml")));
+        output
+    }
+
+    pub fn files(&self, index: &ProtoIndex) -> Vec<GeneratedFile> {
+        vec![
+            GeneratedFile::text("public/robots.txt", self.robots_txt(index)),
+            GeneratedFile::text("public/sitemap.xml", self.sitemap_xml(index)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn index() -> ProtoIndex {
+        ron(
+            r#"(layouts: [], routes: [
+                (name: "home", url: "/", path: "views/Home.jsx"),
+                (name: "about", url: "/about", path: "views/About.jsx"),
+                (name: "account", url: "/account/:id", path: "views/Account.jsx"),
+                (name: "post", url: "/blog/:slug", path: "views/Post.jsx"),
+            ])"#,
+        )
+    }
+
+    #[test]
+    fn lists_only_canonical_routes_in_the_sitemap() {
+        let seo: SeoConfig = ron(r#"(base_url: "https://example.com/", noindex: ["about"])"#);
+        assert_eq!(
+            seo.sitemap_xml(&index()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  <url><loc>https://example.com/</loc></url>\n</urlset>\n"
+        );
+    }
+
+    #[test]
+    fn disallows_noindex_routes_in_robots_txt() {
+        let seo: SeoConfig = ron(r#"(base_url: "https://example.com", disallow: ["/admin"], noindex: ["account"], default_meta: {"author": "Ann & Bo"})"#);
+        assert_eq!(
+            seo.robots_txt(&index()),
+            "User-agent: *\nDisallow: /account/*\nDisallow: /admin\nAllow: /\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+        assert_eq!(seo.head_tags(), [r#"<meta name="author" content="Ann &amp; Bo" />"#]);
+    }
+
+    #[test]
+    fn requires_an_absolute_base_url() {
+        let seo: SeoConfig = ron(r#"(base_url: "example.com", disallow: ["admin"])"#);
+        let messages: Vec<String> = seo.validate().into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["base URL `example.com` must be an absolute http:// or https:// URL", "path `admin` must start with /"]);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::diagnostics::Diagnostic;
+use crate::output::GeneratedFile;
+use crate::view_proto::{AssetDef, AssetDefs, ContentDefs, ContentValue, ThemeDefs};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Per-tenant overrides layered over one shared proto project, loaded from tenants.ron
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TenantDefs {
+    #[serde(default)]
+    pub tenants: BTreeMap<String, TenantOverlay>,
+}
+
+/// What a tenant changes; everything else comes from the base project
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TenantOverlay {
+    /// Content values replacing the base values with the same key
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub content: HashMap<String, ContentValue>,
+    /// Assets replacing the base assets with the same name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<AssetDef>,
+    /// Theme colors replacing the base colors with the same token
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub colors: HashMap<String, String>,
+    /// Directory the tenant's site is generated into; the tenant name when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+}
+
+/// The base project's defs with one tenant's overrides applied
+#[derive(Debug, Clone)]
+pub struct TenantProject {
+    pub name: String,
+    pub output_dir: String,
+    pub content: ContentDefs,
+    pub assets: AssetDefs,
+    pub theme: ThemeDefs,
+}
+
+impl TenantDefs {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let defs: TenantDefs = options.from_str(&content)?;
+        Ok(defs)
+    }
+
+    /// Overrides the base project has nothing to override (likely typos), and content or
+    /// assets whose kind differs from the value they replace
+    pub fn validate(&self, content: &ContentDefs, assets: &AssetDefs, theme: &ThemeDefs) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for (name, overlay) in &self.tenants {
+            let path = format!("tenants > {}", name);
+
+            let mut keys: Vec<&String> = overlay.content.keys().collect();
+            keys.sort();
+            for key in keys {
+                match content.get(key) {
+                    None => diagnostics.push(Diagnostic::warning(
+                        &path,
+                        format!("content `{}` is not in the base project", key),
+                    )),
+                    Some(base) if content_kind(base) != content_kind(&overlay.content[key]) => {
+                        diagnostics.push(Diagnostic::error(
+                            &path,
+                            format!(
+                                "content `{}` is a {} in the base project but a {} here",
+                                key,
+                                content_kind(base),
+                                content_kind(&overlay.content[key])
+                            ),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            for asset in &overlay.assets {
+                match assets.get(&asset.name) {
+                    None => diagnostics.push(Diagnostic::warning(
+                        &path,
+                        format!("asset `{}` is not in the base project", asset.name),
+                    )),
+                    Some(base) if std::mem::discriminant(&base.kind) != std::mem::discriminant(&asset.kind) => {
+                        diagnostics.push(Diagnostic::error(
+                            &path,
+                            format!("asset `{}` is a {:?} in the base project but a {:?} here", asset.name, base.kind, asset.kind),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            let mut tokens: Vec<&String> = overlay.colors.keys().filter(|t| !theme.colors.contains_key(*t)).collect();
+            tokens.sort();
+            for token in tokens {
+                diagnostics.push(Diagnostic::warning(&path, format!("color `{}` is not in the base theme", token)));
+            }
+        }
+        diagnostics
+    }
+
+    /// The base defs with the named tenant's overrides applied. Derived fields and relations
+    /// are recomputed for replaced collections, as ContentDefs::from_file does.
+    pub fn resolve(
+        &self,
+        name: &str,
+        content: &ContentDefs,
+        assets: &AssetDefs,
+        theme: &ThemeDefs,
+    ) -> Result<TenantProject, Box<dyn std::error::Error>> {
+        let overlay = self.tenants.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.tenants.keys().map(String::as_str).collect();
+            format!("unknown tenant `{}`; defined tenants: {}", name, known.join(", "))
+        })?;
+
+        let mut content = content.clone();
+        content.content.extend(overlay.content.clone());
+        content.compute_derived();
+        let errors: Vec<String> = content
+            .validate()
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| format!("tenant `{}`: {}", name, d))
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+        content.resolve_relations();
+
+        let mut assets = assets.clone();
+        for asset in &overlay.assets {
+            match assets.assets.iter_mut().find(|a| a.name == asset.name) {
+                Some(base) => *base = asset.clone(),
+                None => assets.assets.push(asset.clone()),
+            }
+        }
+
+        let mut theme = theme.clone();
+        theme.colors.extend(overlay.colors.clone());
+
+        Ok(TenantProject {
+            name: name.to_string(),
+            output_dir: overlay.output_dir.clone().unwrap_or_else(|| name.to_string()),
+            content,
+            assets,
+            theme,
+        })
+    }
+}
+
+impl TenantProject {
+    /// Move generated files under the tenant's output directory
+    pub fn place(&self, files: Vec<GeneratedFile>) -> Vec<GeneratedFile> {
+        files.into_iter().map(|file| file.in_dir(&self.output_dir)).collect()
+    }
+}
+
+fn content_kind(value: &ContentValue) -> &'static str {
+    match value {
+        ContentValue::Str(_) => "Str",
+        ContentValue::Record(_) => "Record",
+        ContentValue::List(_) => "List",
+        ContentValue::RichText(_) => "RichText",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn base() -> (ContentDefs, AssetDefs, ThemeDefs) {
+        (
+            ron(r#"(content: {"title": Str("Base"), "tagline": Str("Shared")})"#),
+            ron(r#"(assets: [(name: "logo", kind: Image, path: "assets/logo.png")])"#),
+            ron(r##"(colors: {"primary": "#000", "accent": "#fff"})"##),
+        )
+    }
+
+    #[test]
+    fn layers_tenant_overrides_over_the_base_project() {
+        let (content, assets, theme) = base();
+        let tenants: TenantDefs = ron(
+            r##"(tenants: {"acme": (content: {"title": Str("Acme")}, assets: [(name: "logo", kind: Image, path: "assets/acme.png")], colors: {"primary": "#f00"}, output_dir: "sites/acme")})"##,
+        );
+        let project = tenants.resolve("acme", &content, &assets, &theme).unwrap();
+        assert!(matches!(project.content.get("title"), Some(ContentValue::Str(title)) if title == "Acme"));
+        assert!(matches!(project.content.get("tagline"), Some(ContentValue::Str(tagline)) if tagline == "Shared"));
+        assert_eq!(project.assets.get("logo").unwrap().path.as_deref(), Some("assets/acme.png"));
+        assert_eq!(project.theme.colors["primary"], "#f00");
+        assert_eq!(project.theme.colors["accent"], "#fff");
+        let placed = project.place(vec![GeneratedFile::text("index.html", "")]);
+        assert_eq!(placed[0].path, Path::new("sites/acme/index.html"));
+
+        let error = tenants.resolve("globex", &content, &assets, &theme).unwrap_err();
+        assert_eq!(error.to_string(), "unknown tenant `globex`; defined tenants: acme");
+    }
+
+    #[test]
+    fn reports_overrides_the_base_project_lacks() {
+        let (content, assets, theme) = base();
+        let tenants: TenantDefs = ron(
+            r##"(tenants: {"acme": (content: {"titel": Str("Acme"), "title": List([])}, colors: {"secondary": "#0f0"})})"##,
+        );
+        let messages: Vec<String> = tenants.validate(&content, &assets, &theme).into_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            [
+                "content `titel` is not in the base project",
+                "content `title` is a Str in the base project but a List here",
+                "color `secondary` is not in the base theme",
+            ]
+        );
+    }
+}
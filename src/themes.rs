@@ -0,0 +1,239 @@
+use crate::diagnostics::Diagnostic;
+use crate::output::GeneratedFile;
+use crate::view_jsx::js_string;
+use crate::ThemeDefs;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+const STORAGE_KEY: &str = "theme";
+
+/// Named themes for white-label variants of the same protos, loaded from themes.ron.
+/// Either build the project once per theme with `build_each`, or build it once with
+/// `runtime_theme` and let visitors switch through `files` and `head_tags`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ThemeSet {
+    /// Theme name -> tokens; names are used as directory names and `data-theme` values
+    #[serde(default)]
+    pub themes: BTreeMap<String, ThemeDefs>,
+    /// Theme shown before a visitor picks one; the first by name when unset
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl ThemeSet {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let set: ThemeSet = options.from_str(&content)?;
+        Ok(set)
+    }
+
+    pub fn default_name(&self) -> Option<&str> {
+        self.default.as_deref().or_else(|| self.themes.keys().next().map(String::as_str))
+    }
+
+    /// The default theme's tokens, for outputs that need literal colors (PWA manifest)
+    pub fn default_theme(&self) -> Option<&ThemeDefs> {
+        self.themes.get(self.default_name()?)
+    }
+
+    /// Unusable theme names, an unknown default, and color tokens only some themes define
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.themes.is_empty() {
+            diagnostics.push(Diagnostic::error("themes", "no themes defined"));
+            return diagnostics;
+        }
+        if let Some(default) = &self.default
+            && !self.themes.contains_key(default)
+        {
+            diagnostics.push(Diagnostic::error("themes", format!("default theme `{}` is not defined", default)));
+        }
+
+        let tokens = self.tokens();
+        let default = self.default_name();
+        for (name, theme) in &self.themes {
+            let path = format!("themes > {}", name);
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                diagnostics.push(Diagnostic::error(&path, "theme names may only use letters, digits, `-` and `_`"));
+            }
+            let missing: Vec<&str> = tokens.iter().filter(|t| !theme.colors.contains_key(**t)).map(|t| t.as_str()).collect();
+            if !missing.is_empty() {
+                let fallback = if Some(name.as_str()) == default { "are left unset" } else { "fall back to the default theme" };
+                diagnostics.push(Diagnostic::warning(
+                    &path,
+                    format!("colors defined by other themes {} here: {}", fallback, missing.join(", ")),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    // Every color token any theme defines
+    fn tokens(&self) -> BTreeSet<&String> {
+        self.themes.values().flat_map(|theme| theme.colors.keys()).collect()
+    }
+
+    /// Run `build` for every theme and put each theme's files under `{name}/`
+    pub fn build_each<F>(&self, mut build: F) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>>
+    where
+        F: FnMut(&str, &ThemeDefs) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>>,
+    {
+        let mut files = Vec::new();
+        for (name, theme) in &self.themes {
+            files.extend(build(name, theme)?.into_iter().map(|file| file.in_dir(name)));
+        }
+        Ok(files)
+    }
+
+    /// Tokens resolving to CSS variables (`color.primary` -> `var(--color-primary)`), so a single
+    /// build follows whichever theme is active. Pass to ViewJsx::with_theme.
+    pub fn runtime_theme(&self) -> ThemeDefs {
+        ThemeDefs {
+            colors: self.tokens().into_iter().map(|token| (token.clone(), format!("var({})", css_variable(token)))).collect(),
+        }
+    }
+
+    /// Custom properties for each theme, selected by `data-theme` on <html>.
+    /// The default theme also applies to :root, so tokens a theme lacks fall back to it;
+    /// it comes first because :root and `[data-theme]` are equally specific.
+    pub fn css(&self) -> String {
+        let default = self.default_name();
+        let mut themes: Vec<(&String, &ThemeDefs)> = self.themes.iter().collect();
+        themes.sort_by_key(|(name, _)| Some(name.as_str()) != default);
+        let mut output = String::new();
+        for (name, theme) in themes {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            if Some(name.as_str()) == default {
+                output.push_str(":root,\n");
+            }
+            output.push_str(&format!("[data-theme=\"{}\"] {{\n", name));
+            let colors: BTreeMap<&String, &String> = theme.colors.iter().collect();
+            for (token, value) in colors {
+                output.push_str(&format!("  {}: {};\n", css_variable(token), value));
+            }
+            output.push_str("}\n");
+        }
+        output
+    }
+
+    /// Stylesheet link, plus a script applying the saved theme before first paint
+    pub fn head_tags(&self) -> Vec<String> {
+        vec![
+            r#"<link rel="stylesheet" href="/themes.css" />"#.to_string(),
+            format!(
+                "<script>(function () {{ var themes = {}; var saved = localStorage.getItem('{}'); document.documentElement.dataset.theme = themes.indexOf(saved) >= 0 ? saved : {}; }})();</script>",
+                self.names_array(),
+                STORAGE_KEY,
+                js_string(self.default_name().unwrap_or_default())
+            ),
+        ]
+    }
+
+    fn names_array(&self) -> String {
+        format!("[{}]", self.themes.keys().map(|name| js_string(name)).collect::<Vec<_>>().join(", "))
+    }
+
+    /// Select element switching `data-theme` and remembering the choice
+    pub fn switcher_jsx(&self) -> String {
+        format!(
+            r#"import React, {{ useEffect, useState }} from 'react';
+
+const THEMES = {};
+
+function ThemeSwitcher() {{
+  const [theme, setTheme] = useState(() => document.documentElement.dataset.theme || {});
+
+  useEffect(() => {{
+    document.documentElement.dataset.theme = theme;
+    localStorage.setItem('{}', theme);
+  }}, [theme]);
+
+  return (
+    <select value={{theme}} onChange={{(event) => setTheme(event.target.value)}} aria-label="Theme">
+      {{THEMES.map((name) => (
+        <option key={{name}} value={{name}}>
+          {{name}}
+        </option>
+      ))}}
+    </select>
+  );
+}}
+
+export default ThemeSwitcher;
+"#,
+            self.names_array(),
+            js_string(self.default_name().unwrap_or_default()),
+            STORAGE_KEY
+        )
+    }
+
+    /// public/themes.css and src/components/ThemeSwitcher.jsx for runtime switching
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        vec![
+            GeneratedFile::text("public/themes.css", self.css()),
+            GeneratedFile::text("src/components/ThemeSwitcher.jsx", self.switcher_jsx()),
+        ]
+    }
+}
+
+/// `--color-{token}`, with characters custom property names can't hold replaced by `-`
+fn css_variable(token: &str) -> String {
+    let name: String = token
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("--color-{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn themes(source: &str) -> ThemeSet {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    #[test]
+    fn switches_themes_through_css_variables() {
+        let set = themes(
+            r##"(themes: {"acme": (colors: {"primary": "#f00"}), "globex": (colors: {"primary": "#00f", "accent": "#0f0"})}, default: "globex")"##,
+        );
+        assert_eq!(set.runtime_theme().colors["primary"], "var(--color-primary)");
+        assert_eq!(
+            set.css(),
+            ":root,\n[data-theme=\"globex\"] {\n  --color-accent: #0f0;\n  --color-primary: #00f;\n}\n\n[data-theme=\"acme\"] {\n  --color-primary: #f00;\n}\n"
+        );
+        assert!(set.head_tags()[1].contains("var themes = [\"acme\", \"globex\"];"), "{:?}", set.head_tags());
+        let messages: Vec<String> = set.validate().into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["colors defined by other themes fall back to the default theme here: accent"]);
+    }
+
+    #[test]
+    fn builds_each_theme_into_its_own_directory() {
+        let set = themes(r##"(themes: {"acme": (colors: {"primary": "#f00"}), "globex": (colors: {"primary": "#00f"})})"##);
+        assert_eq!(set.default_name(), Some("acme"));
+        let files = set
+            .build_each(|name, theme| Ok(vec![GeneratedFile::text("index.css", format!("{} {}", name, theme.colors["primary"]))]))
+            .unwrap();
+        let files: Vec<(String, String)> = files
+            .into_iter()
+            .map(|file| (file.path.display().to_string(), String::from_utf8(file.contents).unwrap()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("acme/index.css".to_string(), "acme #f00".to_string()),
+                ("globex/index.css".to_string(), "globex #00f".to_string()),
+            ]
+        );
+    }
+}
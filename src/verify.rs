@@ -0,0 +1,93 @@
+//! Syntax check for generated scripts, using the tree-sitter JavaScript and TypeScript grammars
+
+use crate::diagnostics::Diagnostic;
+use crate::output::GeneratedFile;
+use tree_sitter::{Language, Node, Parser};
+
+fn language(file: &GeneratedFile) -> Option<Language> {
+    match file.path.extension()?.to_str()? {
+        "js" | "jsx" | "mjs" | "cjs" => Some(tree_sitter_javascript::LANGUAGE.into()),
+        "ts" | "mts" => Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+        "tsx" => Some(tree_sitter_typescript::LANGUAGE_TSX.into()),
+        _ => None,
+    }
+}
+
+/// Syntax errors in a generated script, one per error node, at `path:line:column`.
+/// Files other than .js/.jsx/.ts/.tsx are not checked.
+pub fn syntax_errors(file: &GeneratedFile) -> Result<Vec<Diagnostic>, Box<dyn std::error::Error>> {
+    let Some(language) = language(file) else {
+        return Ok(Vec::new());
+    };
+    let source = String::from_utf8_lossy(&file.contents);
+    let mut parser = Parser::new();
+    parser.set_language(&language)?;
+    let tree = parser
+        .parse(source.as_bytes(), None)
+        .ok_or_else(|| format!("{}: parser gave up", file.path.display()))?;
+
+    let mut diagnostics = Vec::new();
+    collect_errors(tree.root_node(), &source, &file.path.display().to_string(), &mut diagnostics);
+    Ok(diagnostics)
+}
+
+fn collect_errors(node: Node, source: &str, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if node.is_error() || node.is_missing() {
+        let position = node.start_position();
+        let line = source.lines().nth(position.row).unwrap_or_default().trim();
+        let end_row = node.end_position().row;
+        let message = if node.is_missing() {
+            format!("expected `{}` in `{}`", node.kind(), line)
+        } else if end_row > position.row {
+            // Error recovery can swallow whole statements; the mistake is somewhere in the span
+            format!("syntax error in lines {}-{}, starting at `{}`", position.row + 1, end_row + 1, line)
+        } else {
+            format!("syntax error in `{}`", line)
+        };
+        let location = format!("{}:{}:{}", path, position.row + 1, position.column + 1);
+        diagnostics.push(Diagnostic::error(location, message));
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_errors(child, source, path, diagnostics);
+    }
+}
+
+/// Parse every generated script and fail with the first offending file and line
+pub fn verify_syntax(files: &[GeneratedFile]) -> Result<(), Box<dyn std::error::Error>> {
+    for file in files {
+        if let Some(error) = syntax_errors(file)?.into_iter().next() {
+            return Err(format!("{}: {}", error.path, error.message).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_generated_jsx_and_skips_other_files() {
+        let view = GeneratedFile::text("src/views/Home.jsx", "function Home() {\n  return <main className=\"x\">Hi</main>;\n}\n");
+        assert!(syntax_errors(&view).unwrap().is_empty());
+        let typed = GeneratedFile::text("src/env.ts", "export const port: number = 3000;\n");
+        assert!(syntax_errors(&typed).unwrap().is_empty());
+        let json = GeneratedFile::text("public/manifest.webmanifest", "{ not json");
+        assert!(syntax_errors(&json).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_the_file_and_line_of_a_syntax_error() {
+        let files = [
+            GeneratedFile::text("src/ok.js", "export const a = 1;\n"),
+            GeneratedFile::text("src/broken.js", "const a = 1;\nconst b = (2;\n"),
+        ];
+        let error = verify_syntax(&files).unwrap_err().to_string();
+        assert!(error.starts_with("src/broken.js:2:"), "{}", error);
+    }
+}
@@ -1,350 +1,3870 @@
-use crate::view_proto::{AssetDefs, AssetKind, ComponentDefs, ContentDefs, ContentValue, Element, PropValue, ViewProto};
-use std::collections::{HashMap, HashSet};
+use crate::view_proto::{prop_types_assignment, state_setter, AliasDefs, AnimationPreset, AssetDefs, AssetKind, ComponentDefs, ContentDefs, ContentValue, Element, EmbedProvider, Handler, Import, ImportKind, LocalComponent, MatchArm, PropDecl, PropType, PropValue, RichBlock, ThemeDefs, Unit, Variant, ViewProto};
+use crate::content_types::property_access;
+use crate::date_format::{format_date, intl_options};
+use crate::manifest::sha256_hex;
+use crate::markdown::markdown_to_elements;
+use crate::expansion::{ExpansionStack, DEFAULT_MAX_EXPANSION_DEPTH};
+use crate::names::{component_ident, NameSet};
+use crate::paths::{file_specifier, is_remote_path, module_specifier, normalize_path, relative_specifier};
+use crate::rich_text::rich_text_elements;
+use crate::{BoolProps, ClassMerge, Diagnostic, JsxOptions, Page, RecordLookup, Route, RouteLoader, SocialMeta};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+// Repeat counts above this are reported, so a typo like `count: 100000` doesn't quietly
+// generate a huge file
+const MAX_REPEAT_COUNT: usize = 1000;
 
 pub struct ViewJsx {
     pub proto: ViewProto,
     pub component_defs: ComponentDefs,
     pub asset_defs: AssetDefs,
     pub content_defs: ContentDefs,
+    pub theme_defs: ThemeDefs,
+    pub alias_defs: AliasDefs,
+    /// Routes from the ProtoIndex, used to resolve Link elements
+    pub routes: Vec<Route>,
+    /// `<link rel="canonical">` added to the view's head
+    pub canonical_url: Option<String>,
+    /// OpenGraph/Twitter card tags added to the view's head
+    pub social: Option<SocialMeta>,
+    /// Page of a paginated collection this view renders, from ProtoIndex::expand_pagination
+    pub page: Option<Page>,
+    /// Record the main tree's ContentField references read from, for static records routes
+    pub record: Option<HashMap<String, String>>,
+    /// Runtime lookup of the record for a dynamic records route
+    pub record_lookup: Option<RecordLookup>,
+    /// Name the route loader's data is bound to with useLoaderData, from Route::loader_binding
+    pub loader_data: Option<String>,
+    /// Collection a `records` loader binding holds; ContentLists over it map the loaded records
+    pub loader_collection: Option<String>,
+    /// Where the view is written, relative to the source root. When set, relative import
+    /// paths are read from the source root and rewritten to be relative to the view.
+    pub output_path: Option<String>,
+    /// Content strings and lists are read from a ContentLoader's `useContent()` at runtime
+    /// instead of being written into the JSX
+    pub runtime_content: bool,
+    pub options: JsxOptions,
 }
 
 impl ViewJsx {
     pub fn new(proto: ViewProto, component_defs: ComponentDefs, asset_defs: AssetDefs, content_defs: ContentDefs) -> Self {
-        Self { proto, component_defs, asset_defs, content_defs }
+        Self {
+            proto,
+            component_defs,
+            asset_defs,
+            content_defs,
+            theme_defs: ThemeDefs::default(),
+            alias_defs: AliasDefs::project_default(),
+            routes: Vec::new(),
+            canonical_url: None,
+            social: None,
+            page: None,
+            record: None,
+            record_lookup: None,
+            loader_data: None,
+            loader_collection: None,
+            output_path: None,
+            runtime_content: false,
+            options: JsxOptions::default(),
+        }
     }
 
-    pub fn to_string(&self) -> String {
-        let mut output = String::new();
+    pub fn with_theme(mut self, theme_defs: ThemeDefs) -> Self {
+        self.theme_defs = theme_defs;
+        self
+    }
 
-        // Collect all asset references used in the tree
-        let used_assets = self.collect_asset_refs(&self.proto.tree);
+    pub fn with_aliases(mut self, alias_defs: AliasDefs) -> Self {
+        self.alias_defs = alias_defs;
+        self
+    }
 
-        // Collect all component references used in the tree
-        let used_components = self.collect_component_refs(&self.proto.tree);
+    pub fn with_canonical_url(mut self, url: impl Into<String>) -> Self {
+        self.canonical_url = Some(url.into());
+        self
+    }
 
-        // React import
-        output.push_str("import React from 'react';\n");
+    pub fn with_social(mut self, social: SocialMeta) -> Self {
+        self.social = Some(social);
+        self
+    }
 
-        // Observer import if needed
-        if self.proto.observer {
-            output.push_str("import { observer } from \"mobx-react\";\n");
+    pub fn with_page(mut self, page: Page) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Render the main tree for one record of `collection`, as expand_records routes do
+    pub fn with_record(mut self, collection: &str, record: HashMap<String, String>) -> Self {
+        merge_markdown(&mut self.proto.tree, &record, self.content_defs.markdown_fields(collection));
+        self.record = Some(record);
+        self
+    }
+
+    /// Find the record from the url params at runtime. The main tree's ContentField
+    /// references outside ContentList and Repeat templates become `record.<field>`, so
+    /// markdown fields show as plain text.
+    pub fn with_record_lookup(mut self, lookup: RecordLookup) -> Self {
+        bind_record_fields(&mut self.proto.tree);
+        self.record_lookup = Some(lookup);
+        self
+    }
+
+    /// Read content at runtime through `useContent()`, so copy can change without
+    /// regenerating the view. String Content references become `content.<key>` reads and
+    /// ContentLists map over the loaded records. Rich text and Date values are still
+    /// rendered from the content defs, and markdown fields in lists show as plain text.
+    pub fn with_runtime_content(mut self) -> Self {
+        bind_content(&mut self.proto.tree, &self.content_defs);
+        for component in &mut self.proto.components {
+            bind_content(&mut component.tree, &self.content_defs);
         }
+        self.runtime_content = true;
+        self
+    }
 
-        output.push('\n');
+    /// Read the route loader's data with `const <binding> = useLoaderData();`. A `record`
+    /// binding makes the main tree's ContentField references read from it, as with_record_lookup does.
+    pub fn with_loader_data(mut self, binding: impl Into<String>) -> Self {
+        let binding = binding.into();
+        if binding == "record" {
+            bind_record_fields(&mut self.proto.tree);
+        }
+        self.loader_data = Some(binding);
+        self
+    }
 
-        // Auto-generate imports for image assets (skip external URLs)
-        for asset_name in &used_assets {
-            if let Some(asset) = self.asset_defs.get(asset_name) {
-                if let AssetKind::Image = asset.kind {
-                    if let Some(path) = &asset.path {
-                        // Don't import external URLs
-                        if !path.starts_with("http://") && !path.starts_with("https://") {
-                            output.push_str(&format!("import {} from '{}';\n", asset_name, path));
+    /// with_loader_data for the route's loader, if it has one. For a `records` binding,
+    /// ContentLists over the loader's collection map the loaded records instead of the content defs.
+    pub fn with_route_loader(self, route: &Route) -> Self {
+        let Some(binding) = route.loader_binding() else {
+            return self;
+        };
+        let mut view = self.with_loader_data(binding);
+        if let (Some(RouteLoader::Collection(collection)), "records") = (&route.loader, binding) {
+            view.loader_collection = Some(collection.clone());
+        }
+        view
+    }
+
+    /// Output location, e.g. a route's `path`
+    pub fn with_output_path(mut self, output_path: impl Into<String>) -> Self {
+        self.output_path = Some(output_path.into());
+        self
+    }
+
+    pub fn with_routes(mut self, routes: Vec<Route>) -> Self {
+        self.routes = routes;
+        self
+    }
+
+    pub fn with_options(mut self, options: JsxOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Check the tree against the component definitions: every ComponentRef must supply
+    /// its required props, props with a declared type must match it, refs and the states
+    /// handlers change must be declared, expressions must have balanced delimiters, and
+    /// theme colors and linked routes must exist
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "validate", skip_all, fields(view = %self.proto.name)))]
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for decl in &self.proto.props {
+            if let (Some(prop_type), Some(default)) = (&decl.prop_type, &decl.default)
+                && !prop_type.accepts(default)
+            {
+                diagnostics.push(Diagnostic::error(
+                    &self.proto.name,
+                    format!(
+                        "default for prop `{}` expects {:?} but got {}",
+                        decl.name,
+                        prop_type,
+                        default.kind_name()
+                    ),
+                ));
+            }
+            if decl.required && decl.default.is_some() {
+                diagnostics.push(Diagnostic::warning(
+                    &self.proto.name,
+                    format!("prop `{}` is required but also has a default", decl.name),
+                ));
+            }
+        }
+
+        if let Some(social) = &self.social {
+            for key in std::iter::once(&social.title).chain(&social.description) {
+                if self.content_defs.get_str(key).is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        &self.proto.name,
+                        format!("social metadata references unknown content `{}`", key),
+                    ));
+                }
+            }
+            if let Some(image) = &social.image {
+                match self.asset_defs.get(image) {
+                    Some(asset) if matches!(asset.kind, AssetKind::Image) && asset.path.is_some() => {}
+                    Some(_) => diagnostics.push(Diagnostic::error(
+                        &self.proto.name,
+                        format!("social image `{}` is not an image asset with a path", image),
+                    )),
+                    None => diagnostics.push(Diagnostic::error(
+                        &self.proto.name,
+                        format!("social image `{}` is not defined in AssetDefs", image),
+                    )),
+                }
+            }
+        }
+
+        if let Some(lookup) = &self.record_lookup {
+            match self.content_defs.get_list(&lookup.collection) {
+                None => diagnostics.push(Diagnostic::error(
+                    &self.proto.name,
+                    format!("records route reads `{}`, which is not a content list", lookup.collection),
+                )),
+                Some(list) => {
+                    let mut fields = Vec::new();
+                    record_markdown_reads(&self.proto.tree, self.content_defs.markdown_fields(&lookup.collection), &mut fields);
+                    for field in fields {
+                        diagnostics.push(Diagnostic::warning(
+                            &self.proto.name,
+                            format!(
+                                "markdown field `{}` shows as plain text, since `{}` records are looked up at runtime; use a static records route to render it",
+                                field, lookup.collection
+                            ),
+                        ));
+                    }
+                    for param in &lookup.params {
+                        let missing = list
+                            .iter()
+                            .any(|item| !matches!(item, ContentValue::Record(record) if record.contains_key(param)));
+                        if missing {
+                            diagnostics.push(Diagnostic::warning(
+                                &self.proto.name,
+                                format!("some `{}` records have no `{}` field and can't be reached", lookup.collection, param),
+                            ));
                         }
                     }
                 }
             }
         }
 
-        // Auto-generate imports for components with import_path
-        for component_name in &used_components {
-            if let Some(def) = self.component_defs.get(component_name) {
-                if let Some(import_path) = &def.import_path {
-                    output.push_str(&format!("import {} from '{}';\n", def.tag, import_path));
+        if let (Some(lookup), Some(binding)) = (&self.record_lookup, &self.loader_data)
+            && (binding == "record" || binding == "records")
+        {
+            diagnostics.push(Diagnostic::error(
+                &self.proto.name,
+                format!("the route loader and the `{}` record lookup both declare `{}`", lookup.collection, binding),
+            ));
+        }
+
+        let mut seen_ids = HashSet::new();
+        let mut reported_ids = HashSet::new();
+        for id in self.proto.element_ids() {
+            if !seen_ids.insert(id) && reported_ids.insert(id) {
+                diagnostics.push(Diagnostic::error(&self.proto.name, format!("element id `{}` is used more than once", id)));
+            }
+        }
+
+        if self.runtime_content {
+            let props = std::iter::once((self.proto.name.clone(), &self.proto.props)).chain(
+                self.proto.components.iter().map(|c| (format!("{} > {}", self.proto.name, c.name), &c.props)),
+            );
+            for (path, props) in props {
+                if props.iter().any(|decl| decl.name == "content") {
+                    diagnostics.push(Diagnostic::error(
+                        &path,
+                        "prop `content` would shadow the runtime content from useContent()",
+                    ));
                 }
             }
         }
 
-        // Manual imports from proto (fallback for anything not in component_defs)
-        for import in &self.proto.imports {
-            output.push_str(&format!("import {} from '{}';\n", import.name, import.path));
+        for (i, state) in self.proto.states.iter().enumerate() {
+            if self.proto.states[..i].iter().any(|other| other.name == state.name) {
+                diagnostics.push(Diagnostic::error(&self.proto.name, format!("state `{}` is declared more than once", state.name)));
+            } else if self.proto.props.iter().any(|decl| decl.name == state.name) || self.proto.refs.contains(&state.name) {
+                diagnostics.push(Diagnostic::error(
+                    &self.proto.name,
+                    format!("state `{}` has the same name as a prop or ref", state.name),
+                ));
+            }
         }
 
-        output.push('\n');
+        for component in &self.proto.components {
+            let root_path = format!("{} > {} > {}", self.proto.name, component.name, component.tree.label());
+            self.validate_element(&component.tree, &root_path, Some(component), &mut diagnostics);
+        }
 
-        // Function component
-        output.push_str(&format!("function {}() {{\n", self.proto.name));
-        output.push_str("  return (\n");
+        let root_path = format!("{} > {}", self.proto.name, self.proto.tree.label());
+        self.validate_element(&self.proto.tree, &root_path, None, &mut diagnostics);
+        self.validate_expansion(&mut diagnostics);
+        diagnostics
+    }
 
-        // Render the tree
-        let tree_jsx = self.render_element(&self.proto.tree, 4, None);
-        output.push_str(&tree_jsx);
+    /// children_template defs that end up expanding into themselves, or nest deeper than
+    /// JsxOptions::max_expansion_depth, are errors: uses without children are expanded into
+    /// them at generation time.
+    /// Sub-components are functions calling each other at runtime, where props, data and
+    /// conditions end the recursion, so only a cycle with none of those in the way is reported,
+    /// as a warning. Each chain is reported once.
+    fn validate_expansion(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut defs: Vec<&String> = self
+            .component_defs
+            .components
+            .iter()
+            .filter(|def| def.children_template.is_some())
+            .map(|def| &def.name)
+            .collect();
+        defs.sort();
 
-        output.push_str("  );\n");
-        output.push_str("}\n\n");
+        let mut done = HashSet::new();
+        for name in defs {
+            let mut stack = self.expansion_stack();
+            self.expand_component(name, false, &mut stack, &mut done, diagnostics);
+        }
+        let mut done = HashSet::new();
+        for local in &self.proto.components {
+            let mut stack = ExpansionStack::new("component", usize::MAX);
+            self.expand_component(&local.name, true, &mut stack, &mut done, diagnostics);
+        }
+    }
 
-        // Export
+    /// Guard for expanding children_templates, limited to JsxOptions::max_expansion_depth
+    fn expansion_stack(&self) -> ExpansionStack {
+        ExpansionStack::new("component", self.options.max_expansion_depth.unwrap_or(DEFAULT_MAX_EXPANSION_DEPTH))
+    }
+
+    /// Walk what `name` expands into, following sub-components only at runtime; false once
+    /// a chain has been reported
+    fn expand_component<'a>(
+        &'a self,
+        name: &'a str,
+        runtime: bool,
+        stack: &mut ExpansionStack,
+        done: &mut HashSet<&'a str>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> bool {
+        if done.contains(name) {
+            return true;
+        }
+        let body = match self.proto.components.iter().find(|c| c.name == name) {
+            Some(local) if runtime => Some(local.tree.as_ref()),
+            // Generated code calls the sub-component's function instead of expanding it
+            Some(_) => return true,
+            None => self.component_defs.get(name).and_then(|def| def.children_template.as_deref()),
+        };
+        if let Err(message) = stack.enter(name) {
+            diagnostics.push(if runtime {
+                Diagnostic::warning(&self.proto.name, format!("{}; nothing ends the recursion at runtime", message))
+            } else {
+                Diagnostic::error(&self.proto.name, message)
+            });
+            return false;
+        }
+        let mut refs = Vec::new();
+        if let Some(body) = body {
+            collect_unconditional_refs(body, runtime, &mut refs);
+        }
+        // Every name on a reported chain is marked done so the cycle isn't reported again from each member
+        let expanded = refs
+            .into_iter()
+            .all(|component| self.expand_component(component, runtime, stack, done, diagnostics));
+        stack.leave();
+        done.insert(name);
+        expanded
+    }
+
+    /// npm packages the generated module imports from, including peers the imports rely on
+    pub fn npm_packages(&self) -> BTreeSet<String> {
+        let refs = self.used_refs();
+        let mut packages: BTreeSet<String> = ["react", "react-dom"].iter().map(|p| p.to_string()).collect();
         if self.proto.observer {
-            output.push_str(&format!("export default observer({});\n", self.proto.name));
-        } else {
-            output.push_str(&format!("export default {};\n", self.proto.name));
+            packages.insert("mobx-react".to_string());
         }
 
-        output
+        let component_paths = refs
+            .components
+            .iter()
+            .filter_map(|name| self.component_defs.get(name)?.import_path.as_deref());
+        let modules = refs
+            .named_imports
+            .keys()
+            .chain(refs.default_imports.keys())
+            .map(String::as_str)
+            .chain(self.proto.imports.iter().map(|import| import.path.as_str()))
+            .chain(component_paths);
+        packages.extend(
+            modules
+                .map(normalize_path)
+                .filter(|module| self.alias_defs.alias_for(module).is_none())
+                .filter_map(|module| npm_package_name(&module)),
+        );
+
+        // Peer dependencies the generated code never imports directly
+        if packages.contains("mobx-react") {
+            packages.insert("mobx".to_string());
+        }
+        if packages.contains("react-i18next") {
+            packages.insert("i18next".to_string());
+        }
+        packages
     }
 
-    fn collect_asset_refs(&self, element: &Element) -> HashSet<String> {
-        let mut assets = HashSet::new();
-        self.collect_refs_recursive(element, &mut assets, &mut HashSet::new());
-        assets
+    /// Declared aliases the generated imports go through
+    pub fn used_aliases(&self) -> BTreeSet<String> {
+        let refs = self.used_refs();
+        let asset_paths = refs.assets.iter().filter_map(|name| self.asset_defs.get(name)?.path.as_deref());
+        let component_paths = refs
+            .components
+            .iter()
+            .filter_map(|name| self.component_defs.get(name)?.import_path.as_deref());
+        self.proto
+            .imports
+            .iter()
+            .map(|import| import.path.as_str())
+            .chain(asset_paths)
+            .chain(component_paths)
+            .filter_map(|path| self.alias_defs.alias_for(&normalize_path(path)).map(str::to_string))
+            .collect()
     }
 
-    fn collect_component_refs(&self, element: &Element) -> HashSet<String> {
-        let mut components = HashSet::new();
-        self.collect_refs_recursive(element, &mut HashSet::new(), &mut components);
-        components
+    /// Checks that need the asset files on disk: the social image must meet the card's minimum size.
+    /// Local paths are resolved through the alias defs from `project_root`.
+    pub fn validate_files<P: AsRef<Path>>(&self, project_root: P) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let Some(social) = &self.social else {
+            return diagnostics;
+        };
+        let Some(path) = social
+            .image
+            .as_ref()
+            .and_then(|name| self.asset_defs.get(name))
+            .and_then(|asset| asset.path.as_deref())
+        else {
+            return diagnostics;
+        };
+        if is_remote_path(path) {
+            return diagnostics;
+        }
+
+        let file = project_root.as_ref().join(self.alias_defs.asset_file(path));
+        match image::image_dimensions(&file) {
+            Ok((width, height)) => {
+                let (min_width, min_height) = social.twitter_card.min_image_size();
+                if width < min_width || height < min_height {
+                    diagnostics.push(Diagnostic::error(
+                        &self.proto.name,
+                        format!(
+                            "social image `{}` is {}x{}, below the {}x{} minimum for {} cards",
+                            path,
+                            width,
+                            height,
+                            min_width,
+                            min_height,
+                            social.twitter_card.as_str()
+                        ),
+                    ));
+                }
+            }
+            Err(err) => diagnostics.push(Diagnostic::error(
+                &self.proto.name,
+                format!("social image `{}` could not be read: {}", file.display(), err),
+            )),
+        }
+        diagnostics
     }
 
-    fn collect_refs_recursive(
-        &self,
-        element: &Element,
-        assets: &mut HashSet<String>,
-        components: &mut HashSet<String>,
-    ) {
+    /// Translation key -> default text for everything emitted as `t("key")` in i18n mode
+    pub fn translations(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+        for tree in std::iter::once(&self.proto.tree).chain(self.proto.components.iter().map(|c| &c.tree)) {
+            self.collect_tree_translations(tree, &mut entries);
+        }
+        entries
+    }
+
+    /// Translation JSON for one locale. Keys missing from `locale_content` keep the default text.
+    pub fn translations_json(&self, locale_content: Option<&ContentDefs>) -> String {
+        let entries: BTreeMap<String, String> = self
+            .translations()
+            .into_iter()
+            .map(|(key, text)| {
+                let localized = locale_content.and_then(|content| content.get_str(&key)).cloned();
+                (key, localized.unwrap_or(text))
+            })
+            .collect();
+        serde_json::to_string_pretty(&entries).unwrap_or_default() + "\n"
+    }
+
+    // A tree's translations, including the children_templates it expands
+    fn collect_tree_translations(&self, tree: &Element, entries: &mut BTreeMap<String, String>) {
+        self.collect_translations(tree, entries);
+        for template in self.expanded_templates(tree) {
+            self.collect_translations(template, entries);
+        }
+    }
+
+    fn collect_translations(&self, element: &Element, entries: &mut BTreeMap<String, String>) {
+        let add_value = |value: &PropValue, is_text: bool, entries: &mut BTreeMap<String, String>| match value {
+            PropValue::Str(text) if is_text => {
+                entries.insert(self.text_key(text), text.clone());
+            }
+            PropValue::Content(name) => {
+                if let Some(text) = self.content_defs.get_str(name) {
+                    entries.insert(name.clone(), text.clone());
+                }
+            }
+            _ => {}
+        };
+
         match element {
-            Element::Text(_) => {}
-            Element::Node { props, children, .. } => {
-                for value in props.values() {
-                    if let PropValue::Asset(name) = value {
-                        assets.insert(name.clone());
-                    }
+            Element::Text(text) => {
+                entries.insert(self.text_key(text), text.clone());
+            }
+            Element::Head { title: Some(title), .. } => add_value(title, true, entries),
+            Element::Pagination { source, prev_label, next_label } => {
+                if let Some(nav) = self.pagination_nav(source, prev_label, next_label) {
+                    self.collect_translations(&nav, entries);
                 }
-                for child in children {
-                    self.collect_refs_recursive(child, assets, components);
+            }
+            _ => {}
+        }
+        if let Some(props) = element.props() {
+            for (key, value) in props {
+                add_value(value, key == "text", entries);
+            }
+        }
+        if let Element::ComponentRef { component, props, .. } = element
+            && let Some(def) = self.component_defs.get(component)
+        {
+            for (key, value) in &def.default_props {
+                if !props.contains_key(key) {
+                    add_value(value, key == "text", entries);
                 }
             }
-            Element::ComponentRef { component, props, children } => {
-                components.insert(component.clone());
-                for value in props.values() {
-                    if let PropValue::Asset(name) = value {
-                        assets.insert(name.clone());
+        }
+
+        for child in element.child_elements() {
+            self.collect_translations(child, entries);
+        }
+    }
+
+    /// Whether a component tree needs `const content = useContent();`
+    fn uses_runtime_content(&self, tree: &Element) -> bool {
+        self.runtime_content
+            && (reads_runtime_content(tree) || self.expanded_templates(tree).into_iter().any(reads_runtime_content))
+    }
+
+    /// Whether a component tree needs `const { t } = useTranslation();`
+    fn uses_translation(&self, tree: &Element) -> bool {
+        if !self.options.i18n {
+            return false;
+        }
+        let mut entries = BTreeMap::new();
+        self.collect_tree_translations(tree, &mut entries);
+        !entries.is_empty()
+    }
+
+    /// `local` is the sub-component whose tree `element` is in, None for the main tree
+    fn validate_element(&self, element: &Element, path: &str, local: Option<&LocalComponent>, diagnostics: &mut Vec<Diagnostic>) {
+        if let (Element::ContentList { source, .. }, Some(local)) = (element, local)
+            && self.loader_collection.as_ref() == Some(source)
+        {
+            diagnostics.push(Diagnostic::error(
+                path,
+                format!("`{}` can't read the route loader's `records`, which belong to the view; pass them as a prop instead", local.name),
+            ));
+        }
+        if let (
+            Element::If { condition: PropValue::Var(name), .. }
+            | Element::Show { condition: PropValue::Var(name), .. }
+            | Element::Match { source: PropValue::Var(name), .. },
+            Some(local),
+        ) = (element, local)
+            && !local.props.iter().any(|decl| &decl.name == name)
+            && self.proto.states.iter().any(|state| &state.name == name)
+        {
+            diagnostics.push(Diagnostic::error(
+                path,
+                format!("`{}` can't read state `{}`, which belongs to the view; pass it as a prop instead", local.name, name),
+            ));
+        }
+        if let Element::ComponentRef { component, props, .. } = element
+            && !self.proto.components.iter().any(|c| &c.name == component)
+            && let Some(def) = self.component_defs.get(component)
+        {
+            for required in &def.required_props {
+                if !props.contains_key(required) && !def.default_props.contains_key(required) {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("`{}` is missing required prop `{}`", component, required),
+                    ));
+                }
+            }
+
+            let mut names: Vec<&String> = def.prop_types.keys().collect();
+            names.sort();
+            for name in names {
+                let Some(value) = props.get(name).or_else(|| def.default_props.get(name)) else {
+                    continue;
+                };
+                let prop_type = &def.prop_types[name];
+
+                if let PropType::Enum(allowed) = prop_type {
+                    // Content strings are known now, so check them like literals
+                    let literal = match value {
+                        PropValue::Str(s) => Some(s),
+                        PropValue::Content(key) => self.content_defs.get_str(key),
+                        _ => None,
+                    };
+                    if let Some(literal) = literal
+                        && !allowed.contains(literal)
+                    {
+                        diagnostics.push(Diagnostic::error(
+                            path,
+                            format!(
+                                "prop `{}` on `{}` must be one of {} but got \"{}\"",
+                                name,
+                                component,
+                                allowed.iter().map(|a| format!("\"{}\"", a)).collect::<Vec<_>>().join(", "),
+                                literal
+                            ),
+                        ));
+                        continue;
                     }
                 }
-                for child in children {
-                    self.collect_refs_recursive(child, assets, components);
+
+                if !prop_type.accepts(value) {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!(
+                            "prop `{}` on `{}` expects {:?} but got {}",
+                            name,
+                            component,
+                            prop_type,
+                            value.kind_name()
+                        ),
+                    ));
                 }
             }
-            Element::ContentList { template, .. } => {
-                self.collect_refs_recursive(template, assets, components);
+        }
+
+        if let Some(props) = element.props() {
+            for group in case_conflicts(props) {
+                let dropped: Vec<String> = group[1..].iter().map(|key| format!("`{}`", key)).collect();
+                diagnostics.push(Diagnostic::warning(
+                    path,
+                    format!("props {} and `{}` differ only by case; only `{}` is emitted", dropped.join(", "), group[0], group[0]),
+                ));
             }
         }
-    }
 
-    fn render_element(&self, element: &Element, indent: usize, record_ctx: Option<&HashMap<String, String>>) -> String {
-        match element {
-            Element::Text(text) => {
-                let indent_str = " ".repeat(indent);
-                format!("{}{}\n", indent_str, text)
+        let mut exprs: Vec<&String> = Vec::new();
+        if let Some(props) = element.props() {
+            exprs.extend(props.values().filter_map(|value| match value {
+                PropValue::Expr(expr) => Some(expr),
+                _ => None,
+            }));
+        }
+        if let Element::Match { source: PropValue::Expr(expr), .. }
+        | Element::If { condition: PropValue::Expr(expr), .. }
+        | Element::Show { condition: PropValue::Expr(expr), .. } = element
+        {
+            exprs.push(expr);
+        }
+        for expr in exprs {
+            if self.options.strict {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("expression `{}` is not allowed in strict mode", expr),
+                ));
+            } else if let Err(problem) = check_expr_delimiters(expr) {
+                diagnostics.push(Diagnostic::error(path, format!("expression `{}`: {}", expr, problem)));
+            }
+        }
+
+        if let Element::Experiment { name, variants } = element {
+            if variants.is_empty() {
+                diagnostics.push(Diagnostic::warning(path, format!("experiment `{}` has no variants", name)));
+            }
+            for (i, variant) in variants.iter().enumerate() {
+                if variants[..i].iter().any(|other| other.name == variant.name) {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("experiment `{}` declares variant `{}` more than once", name, variant.name),
+                    ));
+                }
             }
+            if let Some(pinned) = self.options.experiments.get(name)
+                && !variants.iter().any(|variant| &variant.name == pinned)
+            {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("experiment `{}` has no variant `{}`", name, pinned),
+                ));
+            }
+        }
 
-            Element::Node { tag, class_name, props, children } => {
-                self.render_node(tag, class_name.as_deref(), props, children, indent, record_ctx)
+        if let Some(PropValue::Content(key)) = element.props().and_then(|props| props.get("text"))
+            && let Some(blocks) = self.content_defs.get_rich_text(key)
+        {
+            for block in blocks {
+                if let RichBlock::Image { asset, .. } = block
+                    && self.asset_defs.get(asset).is_none()
+                {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("rich text `{}` shows image `{}`, which is not defined in AssetDefs", key, asset),
+                    ));
+                }
             }
+        }
 
-            Element::ComponentRef { component, props, children } => {
-                // Look up the component definition
-                if let Some(def) = self.component_defs.get(component) {
-                    // Merge default props with provided props
-                    let mut merged_props = def.default_props.clone();
-                    for (k, v) in props {
-                        merged_props.insert(k.clone(), v.clone());
+        // Rendered with a "#" url, so the output stays valid
+        if let Element::Link { route, params, .. } = element {
+            match self.route(route) {
+                None => diagnostics.push(Diagnostic::error(path, format!("link to unknown route `{}`", route))),
+                Some(target) => {
+                    let url_params = target.url_params();
+                    for param in url_params.iter().filter(|param| !params.contains_key(**param)) {
+                        diagnostics.push(Diagnostic::error(
+                            path,
+                            format!("link to `{}` needs a value for its `:{}` param", route, param),
+                        ));
                     }
+                    let mut unknown: Vec<&String> = params.keys().filter(|name| !url_params.contains(&name.as_str())).collect();
+                    unknown.sort();
+                    for name in unknown {
+                        diagnostics.push(Diagnostic::error(
+                            path,
+                            format!("route `{}` has no `:{}` param; its url is `{}`", route, name, target.url),
+                        ));
+                    }
+                }
+            }
+        }
 
-                    // Add class_name if defined
-                    let class_name = def.class_name.as_deref();
+        if let Element::Repeat { count, start, .. } = element {
+            if start.checked_add(*count).is_none() {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("repeating {} times from {} runs past the largest index", count, start),
+                ));
+            } else if *count > MAX_REPEAT_COUNT {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("repeats {} times, more than the {} a Repeat can render", count, MAX_REPEAT_COUNT),
+                ));
+            }
+        }
 
-                    self.render_node(&def.tag, class_name, &merged_props, children, indent, record_ctx)
-                } else {
-                    // Unknown component - render as-is (might be an imported React component)
-                    self.render_node(component, None, props, children, indent, record_ctx)
+        if let Element::Pagination { source, .. } = element
+            && self.content_defs.pagination(source).is_none()
+        {
+            diagnostics.push(Diagnostic::error(
+                path,
+                format!("`{}` has no pagination config in ContentDefs collections", source),
+            ));
+        }
+
+        if let Some(props) = element.props() {
+            let mut colors = Vec::new();
+            for value in props.values() {
+                collect_colors(value, &mut colors);
+            }
+            for color in colors {
+                if self.theme_defs.resolve_color(color).is_none() {
+                    diagnostics.push(Diagnostic::error(path, format!("unknown theme color `{}`", color)));
                 }
             }
+        }
 
-            Element::ContentList { source, template } => {
-                let mut output = String::new();
-                if let Some(list) = self.content_defs.get_list(source) {
-                    for item in list {
-                        if let ContentValue::Record(record) = item {
-                            output.push_str(&self.render_element(template, indent, Some(record)));
-                        }
+        if let Some(props) = element.props() {
+            let mut keys: Vec<&String> = props.keys().collect();
+            keys.sort();
+            for key in keys {
+                if let PropValue::Handler(handler) = &props[key] {
+                    self.validate_handler(handler, path, local, diagnostics);
+                }
+            }
+        }
+
+        if let Some(props) = element.props() {
+            for value in props.values() {
+                if let PropValue::Ref(name) = value
+                    && !self.proto.refs.contains(name)
+                    && !(self.proto.forward_ref && name == "ref")
+                {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("ref `{}` is not declared in the view's refs", name),
+                    ));
+                }
+            }
+        }
+
+        let children = element.child_elements();
+        for (i, child) in children.iter().enumerate() {
+            let child_path = if children.len() > 1 {
+                format!("{} > {}[{}]", path, child.label(), i)
+            } else {
+                format!("{} > {}", path, child.label())
+            };
+            self.validate_element(child, &child_path, local, diagnostics);
+        }
+    }
+
+    /// Set and Toggle must name a state of the view, which local components can't reach;
+    /// Call should reach an import or prop, or else it's assumed to be a global
+    fn validate_handler(&self, handler: &Handler, path: &str, local: Option<&LocalComponent>, diagnostics: &mut Vec<Diagnostic>) {
+        match handler {
+            Handler::Set { state, .. } | Handler::Toggle(state) => {
+                if let Some(local) = local {
+                    diagnostics.push(Diagnostic::error(
+                        path,
+                        format!(
+                            "`{}` can't change state `{}`, which belongs to the view; pass it a handler prop instead",
+                            local.name, state
+                        ),
+                    ));
+                    return;
+                }
+                match self.proto.states.iter().find(|decl| &decl.name == state) {
+                    None => diagnostics.push(Diagnostic::error(
+                        path,
+                        format!("state `{}` is not declared in the view's states", state),
+                    )),
+                    Some(decl) if matches!(handler, Handler::Toggle(_)) && !matches!(decl.initial, PropValue::Bool(_)) => {
+                        diagnostics.push(Diagnostic::warning(
+                            path,
+                            format!("toggles state `{}`, whose initial value is not a Bool", state),
+                        ))
                     }
+                    Some(_) => {}
+                }
+            }
+            Handler::Call { function, .. } => {
+                let name = function.split(['.', '[']).next().unwrap_or(function);
+                let props = local.map_or(&self.proto.props, |local| &local.props);
+                let known = props.iter().any(|decl| decl.name == name)
+                    || self.proto.imports.iter().any(|import| {
+                        import.name == name || (local.is_none() && import.bindings.iter().any(|binding| binding == name))
+                    })
+                    || (local.is_none() && self.proto.states.iter().any(|state| state.setter() == name));
+                if !known {
+                    diagnostics.push(Diagnostic::warning(
+                        path,
+                        format!("handler calls `{}`, which is not imported or a prop, so it must be a global", function),
+                    ));
                 }
-                output
             }
         }
     }
 
-    fn render_node(
-        &self,
-        tag: &str,
-        class_name: Option<&str>,
-        props: &HashMap<String, PropValue>,
-        children: &[Box<Element>],
-        indent: usize,
-        record_ctx: Option<&HashMap<String, String>>,
-    ) -> String {
-        let indent_str = " ".repeat(indent);
+    /// Look up a route's URL by route name
+    pub(crate) fn route(&self, name: &str) -> Option<&Route> {
+        self.routes.iter().find(|r| r.name == name)
+    }
+
+    /// Where a Link goes: the route's url with its `:param` segments filled from `params`.
+    /// Values known now are filled in with Route::fill_url; Var and Expr values make it a
+    /// template literal encoding them with encodeURIComponent. Unknown routes and missing
+    /// params give "#" so the output stays valid; validate reports them.
+    fn link_target(&self, route: &str, params: &HashMap<String, PropValue>, ctx: RenderCtx) -> PropValue {
+        let Some(route) = self.route(route) else {
+            return PropValue::Str("#".to_string());
+        };
+        if route.url_params().iter().any(|param| !params.contains_key(*param)) {
+            return PropValue::Str("#".to_string());
+        }
+        let is_runtime = |value: &PropValue| matches!(value, PropValue::Var(_) | PropValue::Expr(_));
+        if !params.values().any(is_runtime) {
+            let values = params.iter().map(|(name, value)| (name.clone(), self.prop_value_to_string(value, ctx))).collect();
+            return PropValue::Str(route.fill_url(&values).unwrap_or_else(|| "#".to_string()));
+        }
+        let segments: Vec<String> = route
+            .url
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':').and_then(|param| params.get(param)) {
+                Some(value) if is_runtime(value) => {
+                    format!("${{encodeURIComponent({})}}", self.prop_value_to_expr(value, ctx))
+                }
+                Some(value) => template_text(&encode_uri_component(&self.prop_value_to_string(value, ctx))),
+                None => template_text(segment),
+            })
+            .collect();
+        PropValue::Expr(format!("`{}`", segments.join("/")))
+    }
+
+    /// Elements for a `text` prop reading rich text content
+    fn rich_text_children(&self, props: &HashMap<String, PropValue>) -> Option<Vec<Element>> {
+        let Some(PropValue::Content(key)) = props.get("text") else {
+            return None;
+        };
+        self.content_defs.get_rich_text(key).map(|blocks| rich_text_elements(blocks))
+    }
+
+    /// Records of a content list as a JS array literal, fields in name order
+    fn records_json(&self, collection: &str) -> String {
+        let records: Vec<BTreeMap<&String, &String>> = self
+            .content_defs
+            .get_list(collection)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| match item {
+                ContentValue::Record(record) => Some(record.iter().collect()),
+                _ => None,
+            })
+            .collect();
+        serde_json::to_string_pretty(&records).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Prev/next links for the current page, built as elements so they render like any other link.
+    /// None when the view isn't rendering a page of `source`.
+    fn pagination_nav(&self, source: &str, prev_label: &str, next_label: &str) -> Option<Element> {
+        let page = self.page.as_ref().filter(|page| page.collection == source)?;
+        let (tag, url_prop) = if self.options.static_links { ("a", "href") } else { ("Link", "to") };
+        let link = |url: &str, rel: &str, label: &str| {
+            Box::new(Element::Node {
+                tag: tag.to_string(),
+                class_name: None,
+                props: HashMap::from([
+                    (url_prop.to_string(), PropValue::Str(url.to_string())),
+                    ("rel".to_string(), PropValue::Str(rel.to_string())),
+                ]),
+                children: vec![Box::new(Element::Text(label.to_string()))],
+                id: None,
+            })
+        };
+        let children = page
+            .prev_url()
+            .map(|url| link(url, "prev", prev_label))
+            .into_iter()
+            .chain(page.next_url().map(|url| link(url, "next", next_label)))
+            .collect();
+        Some(Element::Node {
+            tag: "nav".to_string(),
+            class_name: Some("pagination".to_string()),
+            props: HashMap::from([("aria-label".to_string(), PropValue::Str("Pagination".to_string()))]),
+            children,
+            id: None,
+        })
+    }
+}
+
+impl fmt::Display for ViewJsx {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "render", skip_all, fields(view = %self.proto.name)))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut output = String::new();
 
-        // Opening tag
-        output.push_str(&format!("{}<{}", indent_str, tag));
+        // Collect all asset, component, and library references used in the tree
+        let refs = self.used_refs();
+        let used_assets = &refs.assets;
+        let used_components = &refs.components;
 
-        // Add className if present (from component def)
-        if let Some(cn) = class_name {
-            // Check if props override className
-            if !props.contains_key("className") {
-                output.push_str(&format!(" className=\"{}\"", cn));
-            }
+        // React import
+        match refs.named_imports.get("react") {
+            Some(names) => output.push_str(&format!(
+                "import React, {{ {} }} from 'react';\n",
+                names.iter().cloned().collect::<Vec<_>>().join(", ")
+            )),
+            None => output.push_str("import React from 'react';\n"),
         }
 
-        // Render props
-        for (key, value) in props {
-            if key == "text" {
-                // Special "text" prop becomes children text
-                continue;
-            }
-            let prop_str = self.render_prop(key, value, record_ctx);
-            output.push_str(&format!(" {}", prop_str));
+        // Observer import if needed
+        if self.proto.observer {
+            output.push_str("import { observer } from \"mobx-react\";\n");
         }
 
-        // Check for text prop (used as inner text)
-        let text_content = props.get("text").map(|v| self.prop_value_to_string(v, record_ctx));
+        // Default imports from other libraries (clsx, ...)
+        for (module, name) in &refs.default_imports {
+            output.push_str(&format!("import {} from '{}';\n", name, module));
+        }
 
-        let has_children = !children.is_empty() || text_content.is_some();
+        // Named imports from other libraries (react-dom, ...)
+        for (module, names) in &refs.named_imports {
+            if module != "react" {
+                output.push_str(&format!(
+                    "import {{ {} }} from '{}';\n",
+                    names.iter().cloned().collect::<Vec<_>>().join(", "),
+                    module
+                ));
+            }
+        }
 
-        if has_children {
-            output.push_str(">\n");
+        output.push('\n');
 
-            // Render text content if present
-            if let Some(text) = text_content {
-                output.push_str(&format!("{}{}\n", " ".repeat(indent + 2), text));
+        // Auto-generate imports for image assets (skip external URLs)
+        let import_names = self.import_names(&refs);
+        for asset_name in used_assets {
+            if let Some(asset) = self.asset_defs.get(asset_name)
+                && let Some(path) = &asset.path
+                && let Some(ident) = import_names.assets.get(asset_name)
+            {
+                output.push_str(&format!("import {} from '{}';\n", ident, self.file_specifier(path)));
             }
+        }
 
-            // Render children
-            for child in children {
-                output.push_str(&self.render_element(child, indent + 2, record_ctx));
+        // Auto-generate imports for components with import_path; presets sharing a
+        // component import it once
+        let mut imported = BTreeSet::new();
+        for component_name in used_components {
+            if let Some(def) = self.component_defs.get(component_name)
+                && let Some(import_path) = &def.import_path
+                && let Some(ident) = import_names.components.get(component_name)
+                && imported.insert(ident)
+            {
+                output.push_str(&format!("import {} from '{}';\n", ident, self.module_specifier(import_path)));
             }
+        }
 
-            // Closing tag
-            output.push_str(&format!("{}</{}>\n", indent_str, tag));
-        } else {
-            // Self-closing tag
-            output.push_str(" />\n");
+        // Manual imports from proto (fallback for anything not in component_defs)
+        for import in &self.proto.imports {
+            output.push_str(&format!("import {} from '{}';\n", import.name, self.module_specifier(&import.path)));
         }
 
-        output
-    }
+        if self.uses_runtime_content(&self.proto.tree)
+            || self.proto.components.iter().any(|c| self.uses_runtime_content(&c.tree))
+        {
+            output.push_str(&format!(
+                "import {{ useContent }} from '{}';\n",
+                self.module_specifier("./content/useContent")
+            ));
+        }
 
-    fn render_prop(&self, key: &str, value: &PropValue, record_ctx: Option<&HashMap<String, String>>) -> String {
-        match value {
-            PropValue::Str(s) => {
-                format!("{}=\"{}\"", key, s)
+        output.push('\n');
+        let hoisted = RefCell::new(Vec::new());
+        let expansion = RefCell::new(self.expansion_stack());
+        let ctx = RenderCtx {
+            import_names: &import_names,
+            hoisted: self.proto.pure.then_some(&hoisted),
+            expansion: &expansion,
+            record: None,
+        };
+
+        // Records a dynamic records route looks up by url params
+        if let Some(lookup) = &self.record_lookup {
+            output.push_str(&format!("const records = {};\n\n", self.records_json(&lookup.collection)));
+        }
+
+        // Helper components used by the tree
+        for helper in &refs.helpers {
+            output.push_str(helper.source());
+            output.push('\n');
+        }
+
+        // The components are rendered first, hoisting static subtrees as they go
+        let mut body = String::new();
+        // Sub-components declared in the proto
+        for component in &self.proto.components {
+            body.push_str(&format!(
+                "function {}({}) {{\n",
+                component_ident(&component.name),
+                self.render_params(&component.props, ctx)
+            ));
+            let uses_content = self.uses_runtime_content(&component.tree);
+            if uses_content {
+                body.push_str("  const content = useContent();\n");
             }
-            PropValue::Num(n) => {
-                format!("{}={{{}}}", key, n)
+            let uses_translation = self.uses_translation(&component.tree);
+            if uses_translation {
+                body.push_str("  const { t } = useTranslation();\n");
             }
-            PropValue::Bool(b) => {
-                if *b {
-                    key.to_string()
-                } else {
-                    format!("{}={{false}}", key)
-                }
+            if uses_content || uses_translation {
+                body.push('\n');
             }
-            PropValue::Var(var_name) => {
-                format!("{}={{{}}}", key, var_name)
+            body.push_str(&self.render_return(&component.tree, ctx));
+            body.push_str("}\n\n");
+            if let Some(prop_types) = self.prop_types_source(&component_ident(&component.name), &component.props) {
+                body.push_str(&prop_types);
+                body.push('\n');
             }
-            PropValue::Asset(asset_name) => {
-                // Look up asset to determine how to render
-                if let Some(asset) = self.asset_defs.get(asset_name) {
-                    match asset.kind {
-                        AssetKind::Image => {
-                            // Check if it's an external URL
-                            if let Some(path) = &asset.path {
-                                if path.starts_with("http://") || path.starts_with("https://") {
-                                    // External URL - use directly as string
-                                    format!("{}=\"{}\"", key, path)
-                                } else {
-                                    // Local asset - use imported variable reference
-                                    format!("{}={{{}}}", key, asset_name)
-                                }
-                            } else {
-                                format!("{}={{{}}}", key, asset_name)
-                            }
-                        }
-                        AssetKind::Youtube | AssetKind::Video | AssetKind::Audio => {
-                            // URL-based assets use the URL directly
-                            if let Some(url) = &asset.url {
-                                format!("{}=\"{}\"", key, url)
-                            } else {
-                                format!("{}=\"\"", key)
-                            }
-                        }
+        }
+
+        // Function component
+        let view_name = component_ident(&self.proto.name);
+        let params = self.render_params(&self.proto.props, ctx);
+        if self.proto.forward_ref {
+            let params = if params.is_empty() { "props".to_string() } else { params };
+            body.push_str(&format!(
+                "const {} = React.forwardRef(function {}({}, ref) {{\n",
+                view_name, view_name, params
+            ));
+        } else {
+            body.push_str(&format!("function {}({}) {{\n", view_name, params));
+        }
+
+        // Hooks and other statements before the return
+        let hooks: Vec<&Import> = self
+            .proto
+            .imports
+            .iter()
+            .filter(|import| matches!(import.kind, ImportKind::Hook))
+            .collect();
+        if let Some(lookup) = &self.record_lookup {
+            let matches: Vec<String> = lookup.params.iter().map(|p| format!("r.{} === {}", p, p)).collect();
+            body.push_str(&format!("  const {{ {} }} = useParams();\n", lookup.params.join(", ")));
+            body.push_str(&format!("  const record = records.find((r) => {});\n", matches.join(" && ")));
+        }
+        if let Some(binding) = &self.loader_data {
+            body.push_str(&format!("  const {} = useLoaderData();\n", binding));
+        }
+        for hook in &hooks {
+            let args: Vec<String> = hook.args.iter().map(|arg| self.prop_value_to_expr(arg, ctx)).collect();
+            let call = format!("{}({})", hook.name, args.join(", "));
+            if hook.bindings.is_empty() {
+                body.push_str(&format!("  {};\n", call));
+            } else {
+                body.push_str(&format!("  const {{ {} }} = {};\n", hook.bindings.join(", "), call));
+            }
+        }
+        let uses_content = self.uses_runtime_content(&self.proto.tree);
+        if uses_content {
+            body.push_str("  const content = useContent();\n");
+        }
+        let uses_translation = self.uses_translation(&self.proto.tree);
+        if uses_translation {
+            body.push_str("  const { t } = useTranslation();\n");
+        }
+        for state in &self.proto.states {
+            body.push_str(&format!(
+                "  const [{}, {}] = useState({});\n",
+                state.name,
+                state.setter(),
+                self.prop_value_to_expr(&state.initial, ctx)
+            ));
+        }
+        for ref_name in &self.proto.refs {
+            body.push_str(&format!("  const {} = useRef(null);\n", ref_name));
+        }
+        if self.record_lookup.is_some()
+            || self.loader_data.is_some()
+            || !hooks.is_empty()
+            || uses_content
+            || uses_translation
+            || !self.proto.states.is_empty()
+            || !self.proto.refs.is_empty()
+        {
+            body.push('\n');
+        }
+        // After every hook call, so the hook order never changes
+        if self.record_lookup.is_some() {
+            body.push_str("  if (!record) {\n    return null;\n  }\n\n");
+        }
+
+        body.push_str(&self.render_view_return(ctx));
+        if self.proto.forward_ref {
+            body.push_str("});\n\n");
+        } else {
+            body.push_str("}\n\n");
+        }
+
+        if let Some(prop_types) = self.prop_types_source(&view_name, &self.proto.props) {
+            body.push_str(&prop_types);
+            body.push('\n');
+        }
+
+        // Export
+        if self.proto.observer {
+            body.push_str(&format!("export default observer({});\n", view_name));
+        } else if self.proto.memo {
+            body.push_str(&format!("export default React.memo({});\n", view_name));
+        } else {
+            body.push_str(&format!("export default {};\n", view_name));
+        }
+
+        // Static subtrees hoisted out of the render functions
+        for (i, markup) in hoisted.borrow().iter().enumerate() {
+            output.push_str(&format!("const hoisted{} = (\n", i + 1));
+            output.push_str(markup);
+            output.push_str(");\n\n");
+        }
+        output.push_str(&body);
+        f.write_str(&output)
+    }
+}
+
+impl ViewJsx {
+    /// The `return (...)` statement for a component tree
+    fn render_return(&self, tree: &Element, ctx: RenderCtx) -> String {
+        let mut output = String::from("  return (\n");
+
+        // Expression roots need a fragment to stay valid JSX
+        if self.renders_as_expression(tree) {
+            output.push_str("    <>\n");
+            output.push_str(&self.render_element(tree, 6, ctx));
+            output.push_str("    </>\n");
+        } else {
+            let rendered = self.render_element(tree, 4, ctx);
+            // A tree left out by its feature flag
+            if rendered.trim().is_empty() {
+                return "  return null;\n".to_string();
+            }
+            output.push_str(&rendered);
+        }
+
+        output.push_str("  );\n");
+        output
+    }
+
+    /// `Name.propTypes = {...};` when propTypes emission is enabled
+    fn prop_types_source(&self, component: &str, props: &[PropDecl]) -> Option<String> {
+        if !self.options.prop_types {
+            return None;
+        }
+        let entries: Vec<(&str, Option<&PropType>, bool)> = props
+            .iter()
+            .map(|decl| (decl.name.as_str(), decl.prop_type.as_ref(), decl.required))
+            .collect();
+        prop_types_assignment(component, &entries)
+    }
+
+    /// Maximal static Node/ComponentRef children in pure views, counted to reserve `hoistedN` names
+    fn hoisted_subtrees(&self) -> Vec<&Element> {
+        let mut hoisted = Vec::new();
+        if self.proto.pure {
+            for component in &self.proto.components {
+                self.collect_hoisted(&component.tree, &mut hoisted);
+            }
+            self.collect_hoisted(&self.proto.tree, &mut hoisted);
+        }
+        hoisted
+    }
+
+    fn collect_hoisted<'a>(&'a self, element: &'a Element, hoisted: &mut Vec<&'a Element>) {
+        let children: Vec<&Element> = match element {
+            Element::Node { children, .. } | Element::ComponentRef { children, .. } => {
+                children.iter().map(|c| c.as_ref()).collect()
+            }
+            _ => {
+                for child in element.child_elements() {
+                    self.collect_hoisted(child, hoisted);
+                }
+                return;
+            }
+        };
+        for child in children {
+            if self.is_hoistable(child) {
+                hoisted.push(child);
+            } else {
+                self.collect_hoisted(child, hoisted);
+            }
+        }
+    }
+
+    /// A Node or ComponentRef child with content that renders the same markup every time
+    fn is_hoistable(&self, element: &Element) -> bool {
+        let has_content = match element {
+            Element::Node { props, children, .. } | Element::ComponentRef { props, children, .. } => {
+                !children.is_empty() || props.contains_key("text")
+            }
+            _ => false,
+        };
+        has_content && self.is_static(element)
+    }
+
+    /// Render a static child of a pure view into a module-level `hoistedN` constant and return
+    /// its name. Children rendering the same markup share one constant.
+    fn hoist(&self, element: &Element, ctx: RenderCtx) -> Option<String> {
+        let hoisted = ctx.hoisted?;
+        if !self.is_hoistable(element) {
+            return None;
+        }
+        let markup = self.render_element(element, 2, RenderCtx { hoisted: None, ..ctx });
+        // A component left out by its feature flag renders nothing to hoist
+        if markup.trim().is_empty() {
+            return None;
+        }
+        let mut hoisted = hoisted.borrow_mut();
+        let number = match hoisted.iter().position(|existing| *existing == markup) {
+            Some(i) => i + 1,
+            None => {
+                hoisted.push(markup);
+                hoisted.len()
+            }
+        };
+        Some(format!("hoisted{}", number))
+    }
+
+    /// Whether an element renders the same markup on every render
+    fn is_static(&self, element: &Element) -> bool {
+        match element {
+            Element::Text(_) => !self.options.i18n,
+            Element::Raw(_) | Element::ContentText(_) => true,
+            Element::Node { props, children, .. } => {
+                props.values().all(|v| self.is_static_value(v)) && children.iter().all(|c| self.is_static(c))
+            }
+            Element::ComponentRef { component, props, children, .. } => {
+                let def_static = self.component_defs.get(component).is_none_or(|def| {
+                    def.spread.is_none() && def.default_props.values().all(|v| self.is_static_value(v))
+                });
+                // Uses expanding a children_template are rendered in place
+                def_static
+                    && self.children_template(component, children).is_none()
+                    && props.values().all(|v| self.is_static_value(v))
+                    && children.iter().all(|c| self.is_static(c))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_static_value(&self, value: &PropValue) -> bool {
+        match value {
+            PropValue::Str(_)
+            | PropValue::Num(_)
+            | PropValue::Bool(_)
+            | PropValue::Dim(..)
+            | PropValue::Color(_)
+            | PropValue::Asset(_) => true,
+            PropValue::Content(_) => !self.options.i18n,
+            PropValue::Style(entries) => entries.values().all(|v| self.is_static_value(v)),
+            PropValue::Date { value, .. } => self.is_static_value(value),
+            PropValue::Var(_)
+            | PropValue::Expr(_)
+            | PropValue::Ref(_)
+            | PropValue::Spread(_)
+            | PropValue::ContentField(_)
+            | PropValue::Handler(_) => false,
+        }
+    }
+
+    /// The main view's return. View-level head tags go into its Head element,
+    /// or into a Helmet placed before the tree when it has none.
+    fn render_view_return(&self, ctx: RenderCtx) -> String {
+        let tree = &self.proto.tree;
+        let ctx = ctx.with_record(self.record.as_ref());
+        if !self.has_view_head_tags() || contains_head(tree) {
+            return self.render_return(tree, ctx);
+        }
+        let mut output = String::from("  return (\n    <>\n");
+        output.push_str(&self.render_element(&Element::Head { title: None, meta: HashMap::new() }, 6, ctx));
+        output.push_str(&self.render_element(tree, 6, ctx));
+        output.push_str("    </>\n  );\n");
+        output
+    }
+
+    /// Whether the view adds tags to its head, i.e. view_head_tags isn't empty
+    fn has_view_head_tags(&self) -> bool {
+        self.canonical_url.is_some() || self.social.is_some()
+    }
+
+    /// Tags the view adds to its head on top of its own Head element (canonical URL, ...)
+    fn view_head_tags(&self, ctx: RenderCtx) -> Vec<String> {
+        let mut tags = Vec::new();
+        if let Some(url) = &self.canonical_url {
+            tags.push(format!("<link rel=\"canonical\" {} />", string_attr("href", url)));
+        }
+        if let Some(social) = &self.social {
+            let title = self.content_defs.get_str(&social.title);
+            let description = social.description.as_ref().and_then(|key| self.content_defs.get_str(key));
+            let image = social.image.as_ref().map(|name| {
+                let value = PropValue::Asset(name.clone());
+                self.render_prop("content", &value, ctx.with_record(None))
+            });
+
+            tags.push("<meta property=\"og:type\" content=\"website\" />".to_string());
+            if let Some(url) = &self.canonical_url {
+                tags.push(format!("<meta property=\"og:url\" {} />", string_attr("content", url)));
+            }
+            tags.push(format!("<meta name=\"twitter:card\" content=\"{}\" />", social.twitter_card.as_str()));
+            for (suffix, text) in [("title", title), ("description", description)] {
+                if let Some(text) = text {
+                    tags.push(format!("<meta property=\"og:{}\" {} />", suffix, string_attr("content", text)));
+                    tags.push(format!("<meta name=\"twitter:{}\" {} />", suffix, string_attr("content", text)));
+                }
+            }
+            if let Some(image) = image {
+                tags.push(format!("<meta property=\"og:image\" {} />", image));
+                tags.push(format!("<meta name=\"twitter:image\" {} />", image));
+            }
+        }
+        tags
+    }
+
+    /// Destructured props parameter: `{ productId, size = "md" }`
+    fn render_params(&self, props: &[PropDecl], ctx: RenderCtx) -> String {
+        if props.is_empty() {
+            return String::new();
+        }
+        let params: Vec<String> = props
+            .iter()
+            .map(|decl| match &decl.default {
+                Some(default) => format!("{} = {}", decl.name, self.prop_value_to_expr(default, ctx)),
+                None => decl.name.clone(),
+            })
+            .collect();
+        format!("{{ {} }}", params.join(", "))
+    }
+
+    /// Everything the generated module imports, across the main tree and sub-components
+    fn used_refs(&self) -> UsedRefs {
+        let mut refs = UsedRefs::default();
+        for tree in std::iter::once(&self.proto.tree).chain(self.proto.components.iter().map(|c| &c.tree)) {
+            self.collect_refs_recursive(tree, &mut refs);
+            for template in self.expanded_templates(tree) {
+                self.collect_refs_recursive(template, &mut refs);
+            }
+        }
+        if !self.proto.refs.is_empty() {
+            refs.import_named("react", "useRef");
+        }
+        if !self.proto.states.is_empty() {
+            refs.import_named("react", "useState");
+        }
+        if self.options.prop_types
+            && (!self.proto.props.is_empty() || self.proto.components.iter().any(|c| !c.props.is_empty()))
+        {
+            refs.default_imports.insert("prop-types".to_string(), "PropTypes".to_string());
+        }
+        if self.options.i18n && !self.translations().is_empty() {
+            refs.import_named("react-i18next", "useTranslation");
+        }
+        if self.has_view_head_tags() {
+            refs.import_named("react-helmet", "Helmet");
+        }
+        if let Some(image) = self.social.as_ref().and_then(|social| social.image.as_ref()) {
+            refs.assets.insert(image.clone());
+        }
+        if self.record_lookup.is_some() {
+            refs.import_named("react-router-dom", "useParams");
+        }
+        if self.loader_data.is_some() {
+            refs.import_named("react-router-dom", "useLoaderData");
+        }
+        refs
+    }
+
+    fn collect_refs_recursive(&self, element: &Element, refs: &mut UsedRefs) {
+        if let Some(rich_text) = element.props().and_then(|props| self.rich_text_children(props)) {
+            for child in &rich_text {
+                self.collect_refs_recursive(child, refs);
+            }
+        }
+        match element {
+            Element::Text(_) | Element::Raw(_) | Element::ContentText(_) => {}
+            Element::Node { class_name, props, children, .. } => {
+                if (class_name.is_some() || props.keys().any(|k| k.starts_with("tw:")))
+                    && props.contains_key("className")
+                {
+                    self.note_class_merge(refs);
+                }
+                for value in props.values() {
+                    refs.add_prop_value(value);
+                }
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::ComponentRef { component, .. } if !self.component_enabled(component) => {}
+            Element::ComponentRef { component, props, children, .. } => {
+                refs.components.insert(component.clone());
+                if let Some(def) = self.component_defs.get(component)
+                    && (def.class_name.is_some() || props.keys().any(|k| k.starts_with("tw:")))
+                    && (props.contains_key("className") || def.default_props.contains_key("className"))
+                {
+                    self.note_class_merge(refs);
+                }
+                for value in props.values() {
+                    refs.add_prop_value(value);
+                }
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::ContentList { template, .. } | Element::Repeat { template, .. } => {
+                self.collect_refs_recursive(template, refs);
+            }
+            Element::Pagination { source, prev_label, next_label } => {
+                if let Some(nav) = self.pagination_nav(source, prev_label, next_label) {
+                    if !self.options.static_links {
+                        refs.import_named("react-router-dom", "Link");
                     }
-                } else {
-                    // Unknown asset, treat as variable
-                    format!("{}={{{}}}", key, asset_name)
+                    self.collect_refs_recursive(&nav, refs);
                 }
             }
-            PropValue::Content(content_name) => {
-                // Look up content and inline it as a string
-                if let Some(text) = self.content_defs.get_str(content_name) {
-                    format!("{}=\"{}\"", key, text)
-                } else {
-                    format!("{}=\"\"", key)
+            Element::Match { arms, default, .. } => {
+                for arm in arms {
+                    self.collect_refs_recursive(&arm.template, refs);
+                }
+                if let Some(default) = default {
+                    self.collect_refs_recursive(default, refs);
                 }
             }
-            PropValue::ContentField(field_name) => {
-                // Look up field in current record context
-                if let Some(record) = record_ctx {
-                    if let Some(value) = record.get(field_name) {
-                        format!("{}=\"{}\"", key, value)
-                    } else {
-                        format!("{}=\"\"", key)
+            Element::If { .. } | Element::Show { .. } => {
+                for child in element.child_elements() {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Portal { children, .. } => {
+                refs.import_named("react-dom", "createPortal");
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Suspense { fallback, children } => {
+                refs.import_named("react", "Suspense");
+                if let Some(fallback) = fallback {
+                    self.collect_refs_recursive(fallback, refs);
+                }
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::ErrorBoundary { fallback, children } => {
+                refs.helpers.insert(Helper::ErrorBoundary);
+                if let Some(fallback) = fallback {
+                    self.collect_refs_recursive(fallback, refs);
+                }
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Animated { children, .. } => {
+                refs.import_named("framer-motion", "motion");
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Link { props, children, .. } => {
+                if !self.options.static_links {
+                    refs.import_named("react-router-dom", "Link");
+                }
+                for value in props.values() {
+                    refs.add_prop_value(value);
+                }
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Media { asset, props, .. } => {
+                refs.assets.insert(asset.clone());
+                for value in props.values() {
+                    refs.add_prop_value(value);
+                }
+            }
+            Element::Embed { provider, .. } => {
+                if let EmbedProvider::StripePricingTable | EmbedProvider::Twitter = provider {
+                    refs.import_named("react", "useEffect");
+                    refs.helpers.insert(Helper::ExternalScript);
+                }
+            }
+            Element::Fragment { children } | Element::Region { children, .. } => {
+                for child in children {
+                    self.collect_refs_recursive(child, refs);
+                }
+            }
+            Element::Flag { flag, children } => {
+                if self.flag_enabled(flag) {
+                    for child in children {
+                        self.collect_refs_recursive(child, refs);
                     }
-                } else {
-                    format!("{}=\"\"", key)
+                }
+            }
+            Element::Experiment { name, variants } => match self.pinned_variant(name, variants) {
+                Some(variant) => self.collect_refs_recursive(&variant.template, refs),
+                None => {
+                    refs.helpers.insert(Helper::Experiments);
+                    for variant in variants {
+                        self.collect_refs_recursive(&variant.template, refs);
+                    }
+                }
+            },
+            Element::Head { title, meta } => {
+                refs.import_named("react-helmet", "Helmet");
+                for value in title.iter().chain(meta.values()) {
+                    refs.add_prop_value(value);
                 }
             }
         }
     }
 
-    fn prop_value_to_string(&self, value: &PropValue, record_ctx: Option<&HashMap<String, String>>) -> String {
-        match value {
-            PropValue::Str(s) => s.clone(),
-            PropValue::Num(n) => n.to_string(),
-            PropValue::Bool(b) => b.to_string(),
-            PropValue::Var(var_name) => format!("{{{}}}", var_name),
-            PropValue::Asset(asset_name) => {
-                if let Some(asset) = self.asset_defs.get(asset_name) {
-                    match asset.kind {
-                        AssetKind::Image => {
-                            // Check if external URL
-                            if let Some(path) = &asset.path {
-                                if path.starts_with("http://") || path.starts_with("https://") {
-                                    path.clone()
-                                } else {
-                                    format!("{{{}}}", asset_name)
-                                }
-                            } else {
-                                format!("{{{}}}", asset_name)
-                            }
-                        }
-                        _ => asset.url.clone().unwrap_or_default(),
-                    }
-                } else {
-                    format!("{{{}}}", asset_name)
-                }
+    fn note_class_merge(&self, refs: &mut UsedRefs) {
+        if self.options.class_merge == ClassMerge::Clsx {
+            refs.default_imports.insert("clsx".to_string(), "clsx".to_string());
+        }
+    }
+
+    /// Whether an element renders as a `{...}` expression rather than a JSX tag
+    fn renders_as_expression(&self, element: &Element) -> bool {
+        if let Element::Flag { flag, children } = element
+            && let [child] = children.as_slice()
+            && self.flag_enabled(flag)
+        {
+            return self.renders_as_expression(child);
+        }
+        if let Element::Experiment { name, variants } = element {
+            return match self.pinned_variant(name, variants) {
+                Some(variant) => self.renders_as_expression(&variant.template),
+                None => !variants.is_empty(),
+            };
+        }
+        if let Element::ContentList { .. } = element {
+            return self.runtime_content;
+        }
+        if let Element::If { condition, then, otherwise } = element {
+            return match self.condition_value(condition, None) {
+                Some(true) => self.renders_as_expression(then),
+                Some(false) => otherwise.as_deref().is_some_and(|otherwise| self.renders_as_expression(otherwise)),
+                None => true,
+            };
+        }
+        if let Element::Show { condition, children } = element {
+            return match self.condition_value(condition, None) {
+                Some(true) => !matches!(children.as_slice(), [only] if !self.renders_as_expression(only)),
+                Some(false) => false,
+                None => true,
+            };
+        }
+        matches!(
+            element,
+            Element::Match { source: PropValue::Var(_) | PropValue::Expr(_), .. } | Element::Portal { .. }
+        )
+    }
+
+    pub(crate) fn flag_enabled(&self, flag: &str) -> bool {
+        self.options.flags.contains(flag)
+    }
+
+    /// Whether uses of a component are rendered: false for ComponentDefs behind a disabled flag
+    pub(crate) fn component_enabled(&self, component: &str) -> bool {
+        self.proto.components.iter().any(|c| c.name == component)
+            || self
+                .component_defs
+                .get(component)
+                .and_then(|def| def.flag.as_deref())
+                .is_none_or(|flag| self.flag_enabled(flag))
+    }
+
+    /// The def's children_template, for a use of `component` with no children of its own
+    pub(crate) fn children_template(&self, component: &str, children: &[Box<Element>]) -> Option<&[Box<Element>]> {
+        if !children.is_empty() || self.proto.components.iter().any(|c| c.name == component) {
+            return None;
+        }
+        self.component_defs.get(component)?.children_template.as_ref().map(std::slice::from_ref)
+    }
+
+    /// children_templates a tree expands into, following templates into templates, each once
+    fn expanded_templates<'a>(&'a self, element: &'a Element) -> Vec<&'a Element> {
+        fn collect<'a>(view: &'a ViewJsx, element: &'a Element, seen: &mut HashSet<&'a str>, templates: &mut Vec<&'a Element>) {
+            if let Element::ComponentRef { component, children, .. } = element
+                && let Some([template]) = view.children_template(component, children)
+                && seen.insert(component)
+            {
+                templates.push(template);
+                collect(view, template, seen, templates);
             }
-            PropValue::Content(content_name) => {
-                self.content_defs.get_str(content_name).cloned().unwrap_or_default()
+            for child in element.child_elements() {
+                collect(view, child, seen, templates);
             }
-            PropValue::ContentField(field_name) => {
-                if let Some(record) = record_ctx {
-                    record.get(field_name).cloned().unwrap_or_default()
+        }
+        let mut templates = Vec::new();
+        collect(self, element, &mut HashSet::new(), &mut templates);
+        templates
+    }
+
+    /// Variant chosen for an experiment through JsxOptions, if any
+    pub(crate) fn pinned_variant<'a>(&self, name: &str, variants: &'a [Variant]) -> Option<&'a Variant> {
+        let pinned = self.options.experiments.get(name)?;
+        variants.iter().find(|variant| &variant.name == pinned)
+    }
+
+    /// Identifiers the view's imported assets and components are bound to. Names the module declares
+    /// itself, generated and manual imports and the view's locals come first; a component
+    /// colliding with them is imported as `{tag}Component`, an asset colliding with anything
+    /// as `{name}Img`.
+    fn import_names(&self, refs: &UsedRefs) -> ImportNames {
+        let mut names = NameSet::new();
+        names.reserve(component_ident(&self.proto.name));
+        for component in &self.proto.components {
+            names.reserve(component_ident(&component.name));
+        }
+        for import in &self.proto.imports {
+            names.reserve(import.name.clone());
+            for binding in &import.bindings {
+                names.reserve(binding.clone());
+            }
+        }
+        for name in ["React", "observer", "useContent", "content", "t", "records", "record"] {
+            names.reserve(name);
+        }
+        for name in refs.default_imports.values().chain(refs.named_imports.values().flatten()) {
+            names.reserve(name.clone());
+        }
+        for helper in &refs.helpers {
+            names.reserve(helper.name());
+        }
+        for i in 0..self.hoisted_subtrees().len() {
+            names.reserve(format!("hoisted{}", i + 1));
+        }
+        if let Some(lookup) = &self.record_lookup {
+            for param in &lookup.params {
+                names.reserve(param.clone());
+            }
+        }
+        if let Some(binding) = &self.loader_data {
+            names.reserve(binding.clone());
+        }
+        for state in &self.proto.states {
+            names.reserve(state.name.clone());
+            names.reserve(state.setter());
+        }
+        for ref_name in &self.proto.refs {
+            names.reserve(ref_name.clone());
+        }
+
+        let mut components = HashMap::new();
+        let mut by_import: HashMap<(&str, &str), String> = HashMap::new();
+        for def in refs.components.iter().filter_map(|name| self.component_defs.get(name)) {
+            let Some(path) = &def.import_path else {
+                continue;
+            };
+            let ident = by_import
+                .entry((def.tag.as_str(), path.as_str()))
+                .or_insert_with(|| {
+                    if names.contains(&def.tag) {
+                        names.unique(&format!("{}Component", def.tag))
+                    } else {
+                        names.unique(&def.tag)
+                    }
+                })
+                .clone();
+            components.insert(def.name.clone(), ident);
+        }
+
+        let mut assets = HashMap::new();
+        for asset in refs.assets.iter().filter_map(|name| self.asset_defs.get(name)) {
+            if let AssetKind::Image = asset.kind
+                && let Some(path) = &asset.path
+                // External URLs and inlined images are used directly, not imported
+                && !is_remote_path(path)
+            {
+                let ident = if names.contains(&asset.name) {
+                    names.unique(&format!("{}Img", asset.name))
                 } else {
-                    String::new()
-                }
+                    names.unique(&asset.name)
+                };
+                assets.insert(asset.name.clone(), ident);
             }
         }
+        ImportNames { assets, components }
+    }
+
+    /// A component or manual import path as written in the generated module
+    fn module_specifier(&self, path: &str) -> String {
+        let aliased = self.alias_defs.alias_for(&normalize_path(path)).is_some();
+        let specifier = module_specifier(path, aliased);
+        match &self.output_path {
+            // Packages never start with a dot
+            Some(output_path) if specifier.starts_with('.') => relative_specifier(output_path, &specifier),
+            _ => specifier,
+        }
+    }
+
+    /// An image asset path as written in the generated module
+    fn file_specifier(&self, path: &str) -> String {
+        let aliased = self.alias_defs.alias_for(&normalize_path(path)).is_some();
+        let specifier = file_specifier(path, aliased);
+        match &self.output_path {
+            Some(output_path) if specifier.starts_with('.') => relative_specifier(output_path, &specifier),
+            _ => specifier,
+        }
+    }
+
+    /// Identifier a local image asset is imported as
+    fn asset_ident(&self, name: &str, ctx: RenderCtx) -> String {
+        ctx.import_names.assets.get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
+
+    /// Tag a component preset renders as: its import's identifier, or the def's tag
+    fn component_ident(&self, name: &str, ctx: RenderCtx) -> String {
+        match ctx.import_names.components.get(name).cloned() {
+            Some(ident) => ident,
+            None => self.component_defs.get(name).map(|def| def.tag.clone()).unwrap_or_else(|| name.to_string()),
+        }
+    }
+
+    fn render_element(&self, element: &Element, indent: usize, ctx: RenderCtx) -> String {
+        match element {
+            Element::Text(text) if self.options.i18n => {
+                format!("{}{{t({})}}\n", " ".repeat(indent), js_string(&self.text_key(text)))
+            }
+            Element::Text(text) => format!("{}{}\n", " ".repeat(indent), jsx_text(text)),
+            Element::Raw(jsx) => format!("{}{}\n", " ".repeat(indent), jsx),
+            Element::ContentText(text) => format!("{}{}\n", " ".repeat(indent), jsx_text(text)),
+
+            Element::Node { tag, class_name, props, children, id } => {
+                let props = self.with_proto_id(id, props);
+                self.render_node(tag, NodeBase { class_name: class_name.as_deref(), spread: None }, &props, children, indent, ctx)
+            }
+
+            Element::ComponentRef { component, .. } if !self.component_enabled(component) => String::new(),
+
+            Element::ComponentRef { component, props, children, id } => {
+                let props = &*self.with_proto_id(id, props);
+                // Sub-components declared in the proto are rendered by name
+                if self.proto.components.iter().any(|c| &c.name == component) {
+                    return self.render_node(&component_ident(component), NodeBase::default(), props, children, indent, ctx);
+                }
+
+                // Look up the component definition
+                if let Some(def) = self.component_defs.get(component) {
+                    // Merge default props with provided props
+                    let mut merged_props = def.default_props.clone();
+                    for (k, v) in props {
+                        merged_props.insert(k.clone(), v.clone());
+                    }
+
+                    // Add class_name if defined
+                    let class_name = def.class_name.as_deref();
+
+                    let tag = self.component_ident(component, ctx);
+                    let base = NodeBase { class_name, spread: def.spread.as_deref() };
+                    // A use without children gets the def's children_template, unless that
+                    // expands into itself or too deep; validate reports those
+                    let template = self
+                        .children_template(component, children)
+                        .filter(|_| ctx.expansion.borrow_mut().enter(component).is_ok());
+                    let Some(template) = template else {
+                        return self.render_node(&tag, base, &merged_props, children, indent, ctx);
+                    };
+                    let output = self.render_node(&tag, base, &merged_props, template, indent, ctx);
+                    ctx.expansion.borrow_mut().leave();
+                    output
+                } else {
+                    // Unknown component - render as-is (might be an imported React component)
+                    self.render_node(component, NodeBase::default(), props, children, indent, ctx)
+                }
+            }
+
+            Element::ContentList { source, template }
+                if self.runtime_content || self.loader_collection.as_ref() == Some(source) =>
+            {
+                self.render_runtime_list(source, template, indent, ctx)
+            }
+
+            Element::ContentList { source, template } => {
+                let mut output = String::new();
+                if let Some(list) = self.content_defs.get_list(source) {
+                    // Only this page's slice of a paginated collection
+                    let range = match &self.page {
+                        Some(page) if &page.collection == source => page.range(),
+                        _ => 0..list.len(),
+                    };
+                    let markdown_fields = self.content_defs.markdown_fields(source);
+                    for item in list.iter().take(range.end).skip(range.start) {
+                        if let ContentValue::Record(record) = item {
+                            if markdown_fields.is_empty() {
+                                output.push_str(&self.render_element(template, indent, ctx.with_record(Some(record))));
+                            } else {
+                                let mut template = template.as_ref().clone();
+                                merge_markdown(&mut template, record, markdown_fields);
+                                output.push_str(&self.render_element(&template, indent, ctx.with_record(Some(record))));
+                            }
+                        }
+                    }
+                }
+                output
+            }
+
+            Element::Pagination { source, prev_label, next_label } => self
+                .pagination_nav(source, prev_label, next_label)
+                .map(|nav| self.render_element(&nav, indent, ctx))
+                .unwrap_or_default(),
+
+            Element::Repeat { count, start, index, template } => {
+                let mut output = String::new();
+                // Keep any enclosing record fields visible alongside the index
+                let mut record = ctx.record.cloned().unwrap_or_default();
+                for i in *start..start.saturating_add(*count) {
+                    record.insert(index.clone(), i.to_string());
+                    output.push_str(&self.render_element(template, indent, ctx.with_record(Some(&record))));
+                }
+                output
+            }
+
+            Element::Match { source, arms, default } => {
+                if let PropValue::Var(expr) = source {
+                    return self.render_match_chain(expr, arms, default.as_deref(), indent, ctx);
+                }
+                if let PropValue::Expr(expr) = source {
+                    let expr = format!("({})", expr);
+                    return self.render_match_chain(&expr, arms, default.as_deref(), indent, ctx);
+                }
+
+                // Content values are known now, so only the selected template is emitted
+                let value = self.prop_value_to_string(source, ctx);
+                let selected = arms
+                    .iter()
+                    .find(|arm| arm.value == value)
+                    .map(|arm| arm.template.as_ref())
+                    .or(default.as_deref());
+                match selected {
+                    Some(template) => self.render_element(template, indent, ctx),
+                    None => String::new(),
+                }
+            }
+
+            Element::If { condition, then, otherwise } => match self.condition_value(condition, ctx.record) {
+                Some(true) => self.render_element(then, indent, ctx),
+                Some(false) => otherwise
+                    .as_deref()
+                    .map(|otherwise| self.render_element(otherwise, indent, ctx))
+                    .unwrap_or_default(),
+                None => {
+                    let indent_str = " ".repeat(indent);
+                    let mut output = format!("{}{{{} ? (\n", indent_str, self.prop_value_to_expr(condition, ctx));
+                    output.push_str(&self.render_branch(then, indent + 2, ctx));
+                    match otherwise {
+                        Some(otherwise) => {
+                            output.push_str(&format!("{}) : (\n", indent_str));
+                            output.push_str(&self.render_branch(otherwise, indent + 2, ctx));
+                            output.push_str(&format!("{})}}\n", indent_str));
+                        }
+                        None => output.push_str(&format!("{}) : null}}\n", indent_str)),
+                    }
+                    output
+                }
+            },
+
+            Element::Show { condition, children } => match self.condition_value(condition, ctx.record) {
+                Some(true) => children.iter().map(|child| self.render_element(child, indent, ctx)).collect(),
+                Some(false) => String::new(),
+                None => {
+                    let indent_str = " ".repeat(indent);
+                    format!(
+                        "{}{{{} && (\n{}{})}}\n",
+                        indent_str,
+                        self.prop_value_to_expr(condition, ctx),
+                        self.render_children_branch(children, indent + 2, ctx),
+                        indent_str
+                    )
+                }
+            },
+
+            Element::Portal { target, children } => {
+                let indent_str = " ".repeat(indent);
+                let content = self.render_children_branch(children, indent + 2, ctx);
+                format!(
+                    "{}{{createPortal(\n{},\n{}  document.getElementById({})\n{})}}\n",
+                    indent_str,
+                    content.trim_end(),
+                    indent_str,
+                    js_string(target),
+                    indent_str
+                )
+            }
+
+            Element::Suspense { fallback, children } => {
+                let indent_str = " ".repeat(indent);
+                let fallback_expr = match fallback {
+                    Some(fallback) => self.render_element_expr(fallback, indent, ctx),
+                    None => "null".to_string(),
+                };
+                let mut output = format!("{}<Suspense fallback={{{}}}>\n", indent_str, fallback_expr);
+                for child in children {
+                    output.push_str(&self.render_element(child, indent + 2, ctx));
+                }
+                output.push_str(&format!("{}</Suspense>\n", indent_str));
+                output
+            }
+
+            Element::ErrorBoundary { fallback, children } => {
+                let indent_str = " ".repeat(indent);
+                let mut output = format!("{}<ProtoErrorBoundary", indent_str);
+                if let Some(fallback) = fallback {
+                    output.push_str(&format!(
+                        " fallback={{{}}}",
+                        self.render_element_expr(fallback, indent, ctx)
+                    ));
+                }
+                output.push_str(">\n");
+                for child in children {
+                    output.push_str(&self.render_element(child, indent + 2, ctx));
+                }
+                output.push_str(&format!("{}</ProtoErrorBoundary>\n", indent_str));
+                output
+            }
+
+            Element::Animated { preset, class_name, children } => {
+                let indent_str = " ".repeat(indent);
+                let mut output = format!("{}<motion.div", indent_str);
+                if let Some(cn) = class_name {
+                    output.push_str(&format!(" {}", string_attr("className", cn)));
+                }
+                output.push_str(&format!(" {}>\n", animation_props(*preset)));
+
+                for child in children {
+                    if let AnimationPreset::StaggerChildren = preset {
+                        // Each child animates on its own so the container can stagger them
+                        let child_indent = " ".repeat(indent + 2);
+                        output.push_str(&format!("{}<motion.div variants={{{}}}>\n", child_indent, STAGGER_ITEM_VARIANTS));
+                        output.push_str(&self.render_element(child, indent + 4, ctx));
+                        output.push_str(&format!("{}</motion.div>\n", child_indent));
+                    } else {
+                        output.push_str(&self.render_element(child, indent + 2, ctx));
+                    }
+                }
+
+                output.push_str(&format!("{}</motion.div>\n", indent_str));
+                output
+            }
+
+            Element::Link { route, params, props, children, id } => {
+                let (tag, url_prop) = if self.options.static_links {
+                    ("a", "href")
+                } else {
+                    ("Link", "to")
+                };
+                let mut link_props = self.with_proto_id(id, props).into_owned();
+                link_props.insert(url_prop.to_string(), self.link_target(route, params, ctx));
+                self.render_node(tag, NodeBase::default(), &link_props, children, indent, ctx)
+            }
+
+            Element::Head { title, meta } => {
+                let indent_str = " ".repeat(indent);
+                let inner_indent = " ".repeat(indent + 2);
+                let mut output = format!("{}<Helmet>\n", indent_str);
+
+                if let Some(title) = title {
+                    output.push_str(&format!(
+                        "{}<title>{}</title>\n",
+                        inner_indent,
+                        self.text_to_jsx(title, ctx)
+                    ));
+                }
+
+                let mut names: Vec<&String> = meta.keys().collect();
+                names.sort();
+                for name in names {
+                    // OpenGraph tags use `property`, everything else uses `name`
+                    let attr = if name.starts_with("og:") { "property" } else { "name" };
+                    output.push_str(&format!(
+                        "{}<meta {}=\"{}\" {} />\n",
+                        inner_indent,
+                        attr,
+                        name,
+                        self.render_prop("content", &meta[name], ctx)
+                    ));
+                }
+
+                for tag in self.view_head_tags(ctx) {
+                    output.push_str(&format!("{}{}\n", inner_indent, tag));
+                }
+
+                output.push_str(&format!("{}</Helmet>\n", indent_str));
+                output
+            }
+
+            Element::Media { asset, props, id } => {
+                self.render_media(asset, &self.with_proto_id(id, props), indent, ctx)
+            }
+
+            Element::Embed { provider, config } => self.render_embed(*provider, config, indent, ctx),
+
+            Element::Flag { flag, .. } if !self.flag_enabled(flag) => String::new(),
+
+            // A region with a single child needs no wrapper, and an empty one renders nothing
+            Element::Region { children, .. } | Element::Flag { children, .. } if children.len() <= 1 => children
+                .first()
+                .map(|child| self.render_element(child, indent, ctx))
+                .unwrap_or_default(),
+
+            Element::Experiment { name, variants } => {
+                if let Some(variant) = self.pinned_variant(name, variants) {
+                    return self.render_element(&variant.template, indent, ctx);
+                }
+                let Some((control, rest)) = variants.split_first() else {
+                    return String::new();
+                };
+                let subject = format!("protoExperimentVariant({}, {})", js_string(name), js_string(&control.name));
+                let arms: Vec<MatchArm> = rest
+                    .iter()
+                    .map(|variant| MatchArm { value: variant.name.clone(), template: variant.template.clone() })
+                    .collect();
+                self.render_match_chain(&subject, &arms, Some(&control.template), indent, ctx)
+            }
+
+            Element::Fragment { children } | Element::Region { children, .. } | Element::Flag { children, .. } => {
+                let indent_str = " ".repeat(indent);
+                let mut output = format!("{}<>\n", indent_str);
+                for child in children {
+                    output.push_str(&self.render_element(child, indent + 2, ctx));
+                }
+                output.push_str(&format!("{}</>\n", indent_str));
+                output
+            }
+        }
+    }
+
+    /// Render an element for use inside an attribute expression (`fallback={...}`).
+    /// Single-line output is inlined; anything longer is placed on its own lines.
+    fn render_element_expr(&self, element: &Element, indent: usize, ctx: RenderCtx) -> String {
+        let rendered = self.render_branch(element, indent + 2, ctx);
+        if rendered.trim_end().lines().count() <= 1 {
+            rendered.trim().to_string()
+        } else {
+            format!("\n{}{}", rendered, " ".repeat(indent))
+        }
+    }
+
+    /// Render an element used as a JS expression, wrapping anything that isn't a single tag in a fragment
+    fn render_branch(&self, element: &Element, indent: usize, ctx: RenderCtx) -> String {
+        match element {
+            Element::ComponentRef { component, .. } if !self.component_enabled(component) => {
+                format!("{}null\n", " ".repeat(indent))
+            }
+            Element::Node { .. } | Element::ComponentRef { .. } => self.render_element(element, indent, ctx),
+            _ => {
+                let indent_str = " ".repeat(indent);
+                format!(
+                    "{}<>\n{}{}</>\n",
+                    indent_str,
+                    self.render_element(element, indent + 2, ctx),
+                    indent_str
+                )
+            }
+        }
+    }
+
+    /// Children used as one JS expression: a single tag as is, anything else in a fragment
+    fn render_children_branch(&self, children: &[Box<Element>], indent: usize, ctx: RenderCtx) -> String {
+        match children {
+            [only] => self.render_branch(only, indent, ctx),
+            _ => {
+                let indent_str = " ".repeat(indent);
+                let mut content = format!("{}<>\n", indent_str);
+                for child in children {
+                    content.push_str(&self.render_element(child, indent + 2, ctx));
+                }
+                content.push_str(&format!("{}</>\n", indent_str));
+                content
+            }
+        }
+    }
+
+    /// Whether an If or Show condition holds, when that's known at generation time: content,
+    /// record fields and literals. None for Var and Expr conditions, which are checked at runtime.
+    pub(crate) fn condition_value(&self, condition: &PropValue, record_ctx: Option<&HashMap<String, String>>) -> Option<bool> {
+        match condition {
+            PropValue::Var(_) | PropValue::Expr(_) | PropValue::Ref(_) | PropValue::Spread(_) | PropValue::Handler(_) => None,
+            PropValue::Date { value, .. } if matches!(**value, PropValue::Var(_) | PropValue::Expr(_)) => None,
+            // As JS treats them: only empty strings are false, so "false" and "0" hold
+            PropValue::Bool(value) => Some(*value),
+            PropValue::Num(n) => Some(*n != 0.0 && !n.is_nan()),
+            PropValue::Content(key) => Some(match self.content_defs.get(key) {
+                Some(ContentValue::Str(text)) => !text.is_empty(),
+                Some(ContentValue::List(list)) => !list.is_empty(),
+                Some(ContentValue::Record(record)) => !record.is_empty(),
+                Some(ContentValue::RichText(blocks)) => !blocks.is_empty(),
+                None => false,
+            }),
+            condition => {
+                // Import identifiers don't change whether the text is empty
+                let import_names = ImportNames::default();
+                let expansion = RefCell::new(self.expansion_stack());
+                let ctx = RenderCtx { import_names: &import_names, hoisted: None, expansion: &expansion, record: record_ctx };
+                Some(!self.prop_value_to_string(condition, ctx).is_empty())
+            }
+        }
+    }
+
+    /// An event handler as an arrow function; it takes `event` when its body reads it
+    fn handler_expr(&self, handler: &Handler, ctx: RenderCtx) -> String {
+        let body = match handler {
+            Handler::Set { state, value } => format!("{}({})", state_setter(state), self.prop_value_to_expr(value, ctx)),
+            Handler::Toggle(state) => format!("{}((current) => !current)", state_setter(state)),
+            Handler::Call { function, args } => {
+                let args: Vec<String> = args.iter().map(|arg| self.prop_value_to_expr(arg, ctx)).collect();
+                format!("{}({})", function, args.join(", "))
+            }
+        };
+        let params = if mentions_identifier(&body, "event") { "(event)" } else { "()" };
+        format!("{} => {}", params, body)
+    }
+
+    /// `{(content.posts ?? []).map((record, index) => ...)}` over the records loaded at runtime,
+    /// or over the `records` loader data for the loader's collection
+    fn render_runtime_list(&self, source: &str, template: &Element, indent: usize, ctx: RenderCtx) -> String {
+        let indent_str = " ".repeat(indent);
+        // Only this page's slice of a paginated collection
+        let slice = match &self.page {
+            Some(page) if page.collection == source => {
+                let range = page.range();
+                format!(".slice({}, {})", range.start, range.end)
+            }
+            _ => String::new(),
+        };
+        let list = match &self.loader_collection {
+            Some(collection) if collection == source => "records".to_string(),
+            _ => format!("content{}", property_access(source)),
+        };
+        let mut template = template.clone();
+        bind_record_fields(&mut template);
+        format!(
+            "{}{{({} ?? []){}.map((record, index) => (\n{}  <React.Fragment key={{index}}>\n{}{}  </React.Fragment>\n{}))}}\n",
+            indent_str,
+            list,
+            slice,
+            indent_str,
+            self.render_element(&template, indent + 4, ctx.with_record(None)),
+            indent_str,
+            indent_str
+        )
+    }
+
+    /// Props with `data-proto-id` added when JsxOptions::proto_ids is set and the element has an id
+    pub(crate) fn with_proto_id<'a>(
+        &self,
+        id: &Option<String>,
+        props: &'a HashMap<String, PropValue>,
+    ) -> Cow<'a, HashMap<String, PropValue>> {
+        match id {
+            Some(id) if self.options.proto_ids => {
+                let mut props = props.clone();
+                props.insert("data-proto-id".to_string(), PropValue::Str(id.clone()));
+                Cow::Owned(props)
+            }
+            _ => Cow::Borrowed(props),
+        }
+    }
+
+    fn render_match_chain(
+        &self,
+        subject: &str,
+        arms: &[MatchArm],
+        default: Option<&Element>,
+        indent: usize,
+        ctx: RenderCtx,
+    ) -> String {
+        let indent_str = " ".repeat(indent);
+        let mut output = format!("{}{{", indent_str);
+
+        for arm in arms {
+            output.push_str(&format!("{} === {} ? (\n", subject, js_string(&arm.value)));
+            output.push_str(&self.render_branch(&arm.template, indent + 2, ctx));
+            output.push_str(&format!("{}) : ", indent_str));
+        }
+
+        match default {
+            Some(template) => {
+                output.push_str("(\n");
+                output.push_str(&self.render_branch(template, indent + 2, ctx));
+                output.push_str(&format!("{})}}\n", indent_str));
+            }
+            None => output.push_str("null}\n"),
+        }
+
+        output
+    }
+
+    fn render_media(
+        &self,
+        asset_name: &str,
+        props: &HashMap<String, PropValue>,
+        indent: usize,
+        ctx: RenderCtx,
+    ) -> String {
+        let Some(asset) = self.asset_defs.get(asset_name) else {
+            return String::new();
+        };
+
+        let mut media_props = props.clone();
+        media_props.insert("src".to_string(), PropValue::Asset(asset_name.to_string()));
+
+        match asset.kind {
+            AssetKind::Image => self.render_node("img", NodeBase::default(), &media_props, &[], indent, ctx),
+            AssetKind::Video | AssetKind::Audio => {
+                media_props.entry("controls".to_string()).or_insert(PropValue::Bool(true));
+                let tag = if let AssetKind::Video = asset.kind { "video" } else { "audio" };
+                self.render_node(tag, NodeBase::default(), &media_props, &[], indent, ctx)
+            }
+            AssetKind::Youtube => {
+                // 16:9 wrapper so the player scales with its container
+                let indent_str = " ".repeat(indent);
+                media_props.entry("title".to_string()).or_insert(PropValue::Str(asset_name.to_string()));
+                media_props.entry("allow".to_string()).or_insert(PropValue::Str(
+                    "accelerometer; autoplay; clipboard-write; encrypted-media; gyroscope; picture-in-picture".to_string(),
+                ));
+                media_props.entry("allowFullScreen".to_string()).or_insert(PropValue::Bool(true));
+                media_props.entry("style".to_string()).or_insert(PropValue::Style(HashMap::from([
+                    ("position".to_string(), PropValue::Str("absolute".to_string())),
+                    ("top".to_string(), PropValue::Num(0.0)),
+                    ("left".to_string(), PropValue::Num(0.0)),
+                    ("width".to_string(), PropValue::Dim(100.0, Unit::Percent)),
+                    ("height".to_string(), PropValue::Dim(100.0, Unit::Percent)),
+                    ("border".to_string(), PropValue::Num(0.0)),
+                ])));
+                format!(
+                    "{}<div style={{{{ position: \"relative\", paddingBottom: \"56.25%\", height: 0, overflow: \"hidden\" }}}}>\n{}{}</div>\n",
+                    indent_str,
+                    self.render_node("iframe", NodeBase::default(), &media_props, &[], indent + 2, ctx),
+                    indent_str
+                )
+            }
+        }
+    }
+
+    fn render_embed(&self, provider: EmbedProvider, config: &HashMap<String, String>, indent: usize, ctx: RenderCtx) -> String {
+        let indent_str = " ".repeat(indent);
+        let inner_indent = " ".repeat(indent + 2);
+        let get = |key: &str| config.get(key).map(String::as_str).unwrap_or("");
+
+        match provider {
+            EmbedProvider::GoogleMaps => {
+                let src = format!("https://www.google.com/maps?q={}&output=embed", encode_uri_component(get("query")));
+                format!(
+                    "{}<iframe {} width=\"100%\" {} style={{{{ border: 0 }}}} loading=\"lazy\" referrerPolicy=\"no-referrer-when-downgrade\" {} title=\"Google Map\" />\n",
+                    indent_str,
+                    string_attr("src", &src),
+                    string_attr("height", config.get("height").map(String::as_str).unwrap_or("450")),
+                    self.render_prop("allowFullScreen", &PropValue::Bool(true), ctx)
+                )
+            }
+            EmbedProvider::Calendly => {
+                let url = get("url");
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!(
+                    "{}<iframe {} width=\"100%\" {} frameBorder=\"0\" title=\"Calendly\" />\n",
+                    indent_str,
+                    string_attr("src", &format!("{}{}embed_type=Inline", url, separator)),
+                    string_attr("height", config.get("height").map(String::as_str).unwrap_or("700"))
+                )
+            }
+            EmbedProvider::StripePricingTable => format!(
+                "{}<>\n{}<ProtoExternalScript src=\"https://js.stripe.com/v3/pricing-table.js\" />\n{}<stripe-pricing-table {} {}></stripe-pricing-table>\n{}</>\n",
+                indent_str,
+                inner_indent,
+                inner_indent,
+                string_attr("pricing-table-id", get("pricing_table_id")),
+                string_attr("publishable-key", get("publishable_key")),
+                indent_str
+            ),
+            EmbedProvider::Twitter => format!(
+                "{}<>\n{}<blockquote className=\"twitter-tweet\">\n{}  <a {}></a>\n{}</blockquote>\n{}<ProtoExternalScript src=\"https://platform.twitter.com/widgets.js\" />\n{}</>\n",
+                indent_str,
+                inner_indent,
+                inner_indent,
+                string_attr("href", get("url")),
+                inner_indent,
+                inner_indent,
+                indent_str
+            ),
+        }
+    }
+
+    fn render_node(
+        &self,
+        tag: &str,
+        base: NodeBase,
+        props: &HashMap<String, PropValue>,
+        children: &[Box<Element>],
+        indent: usize,
+        ctx: RenderCtx,
+    ) -> String {
+        let indent_str = " ".repeat(indent);
+        let mut output = String::new();
+
+        // Opening tag
+        output.push_str(&format!("{}<{}", indent_str, tag));
+
+        // Tailwind utilities from "tw:<utility>" color props join the base classes
+        let mut utility_keys: Vec<&String> = props.keys().filter(|k| k.starts_with("tw:")).collect();
+        utility_keys.sort();
+        let utility_classes: Vec<String> = utility_keys
+            .iter()
+            .map(|key| {
+                let utility = &key["tw:".len()..];
+                let value = self.prop_value_to_string(&props[*key], ctx);
+                format!("{}-[{}]", utility, value.replace(' ', "_"))
+            })
+            .collect();
+        let base_classes = match (base.class_name, utility_classes.is_empty()) {
+            (class_name, true) => class_name.map(str::to_string),
+            (None, false) => Some(utility_classes.join(" ")),
+            (Some(cn), false) => Some(format!("{} {}", cn, utility_classes.join(" "))),
+        };
+
+        // Add className if present (from component def)
+        let mut merged_class = false;
+        if let Some(cn) = base_classes.as_deref() {
+            match props.get("className") {
+                None => output.push_str(&format!(" {}", string_attr("className", cn))),
+                Some(override_value) if self.options.class_merge == ClassMerge::Clsx => {
+                    output.push_str(&format!(
+                        " className={{clsx({}, {})}}",
+                        js_string(cn),
+                        self.prop_value_to_expr(override_value, ctx)
+                    ));
+                    merged_class = true;
+                }
+                // ClassMerge::Override - the className prop below replaces the base classes
+                Some(_) => {}
+            }
+        }
+
+        // Spreads go first so explicitly set props override spread values
+        if let Some(spread) = base.spread {
+            output.push_str(&format!(" {{...{}}}", spread));
+        }
+        let keys = rendered_prop_keys(props);
+        for key in &keys {
+            let value = &props[*key];
+            if let PropValue::Spread(_) = value {
+                output.push_str(&format!(" {}", self.render_prop("", value, ctx)));
+            }
+        }
+
+        // Render props
+        for key in keys {
+            let value = &props[key];
+            if let PropValue::Spread(_) = value {
+                continue;
+            }
+            if key == "text" {
+                // Special "text" prop becomes children text
+                continue;
+            }
+            if (merged_class && key == "className") || key.starts_with("tw:") {
+                continue;
+            }
+            let prop_str = self.render_prop(key, value, ctx);
+            output.push_str(&format!(" {}", prop_str));
+        }
+
+        // Check for text prop (used as inner text); rich text content renders as elements instead
+        let rich_text = self.rich_text_children(props);
+        let text_content = match rich_text {
+            Some(_) => None,
+            None => props.get("text").map(|v| self.text_to_jsx(v, ctx)),
+        };
+
+        let has_children = !children.is_empty() || text_content.is_some() || rich_text.is_some();
+
+        if has_children {
+            output.push_str(">\n");
+
+            // Render text content if present
+            if let Some(text) = text_content {
+                output.push_str(&format!("{}{}\n", " ".repeat(indent + 2), text));
+            }
+            for element in rich_text.iter().flatten() {
+                output.push_str(&self.render_element(element, indent + 2, ctx));
+            }
+
+            // Render children
+            for child in children {
+                match self.hoist(child, ctx) {
+                    Some(name) => output.push_str(&format!("{}{{{}}}\n", " ".repeat(indent + 2), name)),
+                    None => output.push_str(&self.render_element(child, indent + 2, ctx)),
+                }
+            }
+
+            // Closing tag
+            output.push_str(&format!("{}</{}>\n", indent_str, tag));
+        } else {
+            // Self-closing tag
+            output.push_str(" />\n");
+        }
+
+        output
+    }
+
+    fn render_prop(&self, key: &str, value: &PropValue, ctx: RenderCtx) -> String {
+        match value {
+            PropValue::Str(s) => {
+                string_attr(key, s)
+            }
+            PropValue::Num(n) => {
+                format!("{}={{{}}}", key, n)
+            }
+            PropValue::Bool(b) => match self.options.bool_props {
+                BoolProps::Shorthand if *b => key.to_string(),
+                BoolProps::Shorthand | BoolProps::Explicit => format!("{}={{{}}}", key, b),
+                BoolProps::String => format!("{}=\"{}\"", key, b),
+            },
+            PropValue::Var(var_name) => {
+                format!("{}={{{}}}", key, var_name)
+            }
+            PropValue::Spread(object) => {
+                format!("{{...{}}}", object)
+            }
+            PropValue::Ref(ref_name) => {
+                format!("{}={{{}}}", key, ref_name)
+            }
+            PropValue::Handler(handler) => {
+                format!("{}={{{}}}", key, self.handler_expr(handler, ctx))
+            }
+            PropValue::Expr(expr) => {
+                format!("{}={{{}}}", key, expr)
+            }
+            PropValue::Dim(..) => {
+                string_attr(key, &self.prop_value_to_string(value, ctx))
+            }
+            PropValue::Style(_) => {
+                format!("{}={{{}}}", key, self.prop_value_to_expr(value, ctx))
+            }
+            PropValue::Color(_) => {
+                string_attr(key, &self.prop_value_to_string(value, ctx))
+            }
+            PropValue::Date { value: date, .. } => match date.as_ref() {
+                PropValue::Var(_) | PropValue::Expr(_) => {
+                    format!("{}={{{}}}", key, self.prop_value_to_expr(value, ctx))
+                }
+                _ => string_attr(key, &self.prop_value_to_string(value, ctx)),
+            },
+            PropValue::Asset(asset_name) => {
+                // Look up asset to determine how to render
+                if let Some(asset) = self.asset_defs.get(asset_name) {
+                    match asset.kind {
+                        AssetKind::Image => {
+                            // Check if it's an external URL
+                            if let Some(path) = &asset.path {
+                                if is_remote_path(path) {
+                                    // External URL - use directly as string
+                                    string_attr(key, path)
+                                } else {
+                                    // Local asset - use imported variable reference
+                                    format!("{}={{{}}}", key, self.asset_ident(asset_name, ctx))
+                                }
+                            } else {
+                                format!("{}={{{}}}", key, asset_name)
+                            }
+                        }
+                        AssetKind::Youtube if key == "src" => {
+                            // A player src needs the embed URL, not the watch page
+                            let url = asset.youtube_embed_url().or_else(|| asset.url.clone()).unwrap_or_default();
+                            string_attr(key, &url)
+                        }
+                        AssetKind::Youtube | AssetKind::Video | AssetKind::Audio => {
+                            // URL-based assets use the URL directly
+                            if let Some(url) = &asset.url {
+                                string_attr(key, url)
+                            } else {
+                                format!("{}=\"\"", key)
+                            }
+                        }
+                    }
+                } else {
+                    // Unknown asset, treat as variable
+                    format!("{}={{{}}}", key, asset_name)
+                }
+            }
+            PropValue::Content(content_name) if self.options.i18n && self.content_defs.get_str(content_name).is_some() => {
+                format!("{}={{t({})}}", key, js_string(content_name))
+            }
+            PropValue::Content(content_name) => {
+                // Look up content and inline it as a string
+                if let Some(text) = self.content_defs.get_str(content_name) {
+                    string_attr(key, text)
+                } else {
+                    format!("{}=\"\"", key)
+                }
+            }
+            PropValue::ContentField(field_name) => {
+                // Look up field in current record context
+                if let Some(record) = ctx.record {
+                    if let Some(value) = record.get(field_name) {
+                        string_attr(key, value)
+                    } else {
+                        format!("{}=\"\"", key)
+                    }
+                } else {
+                    format!("{}=\"\"", key)
+                }
+            }
+        }
+    }
+
+    /// Render a prop value as a JS expression (what would appear inside `{...}`)
+    fn prop_value_to_expr(&self, value: &PropValue, ctx: RenderCtx) -> String {
+        match value {
+            PropValue::Num(n) => n.to_string(),
+            PropValue::Bool(b) => b.to_string(),
+            PropValue::Var(name) | PropValue::Spread(name) | PropValue::Ref(name) => name.clone(),
+            PropValue::Expr(expr) => format!("({})", expr),
+            PropValue::Handler(handler) => self.handler_expr(handler, ctx),
+            PropValue::Dim(..) | PropValue::Color(_) => js_string(&self.prop_value_to_string(value, ctx)),
+            PropValue::Style(entries) => {
+                let mut keys: Vec<&String> = entries.keys().collect();
+                keys.sort();
+                let body = keys
+                    .iter()
+                    .map(|k| format!("{}: {}", style_key(k), self.prop_value_to_expr(&entries[*k], ctx)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if body.is_empty() { "{}".to_string() } else { format!("{{ {} }}", body) }
+            }
+            PropValue::Date { value: date, format } => match date.as_ref() {
+                PropValue::Var(_) | PropValue::Expr(_) => format!(
+                    "new Intl.DateTimeFormat({}, {}).format(new Date({}))",
+                    js_string(self.options.locale.as_deref().unwrap_or("en-US")),
+                    intl_options(format),
+                    self.prop_value_to_expr(date, ctx)
+                ),
+                _ => js_string(&self.prop_value_to_string(value, ctx)),
+            },
+            PropValue::Asset(_) => {
+                // Imported assets are identifiers; everything else resolves to a URL string
+                let text = self.prop_value_to_string(value, ctx);
+                match text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+                    Some(ident) => ident.to_string(),
+                    None => js_string(&text),
+                }
+            }
+            PropValue::Str(_) | PropValue::Content(_) | PropValue::ContentField(_) => {
+                js_string(&self.prop_value_to_string(value, ctx))
+            }
+        }
+    }
+
+    /// Text shown as element children; translatable strings become `{t("key")}` in i18n mode
+    fn text_to_jsx(&self, value: &PropValue, ctx: RenderCtx) -> String {
+        if self.options.i18n {
+            match value {
+                PropValue::Str(text) => return format!("{{t({})}}", js_string(&self.text_key(text))),
+                PropValue::Content(name) if self.content_defs.get_str(name).is_some() => {
+                    return format!("{{t({})}}", js_string(name));
+                }
+                _ => {}
+            }
+        }
+        match value {
+            // Already `{...}` expressions
+            PropValue::Var(_) | PropValue::Expr(_) => self.prop_value_to_string(value, ctx),
+            PropValue::Date { value: date, .. } if matches!(**date, PropValue::Var(_) | PropValue::Expr(_)) => {
+                self.prop_value_to_string(value, ctx)
+            }
+            PropValue::Asset(_) => format!("{{{}}}", self.prop_value_to_expr(value, ctx)),
+            _ => jsx_text(&self.prop_value_to_string(value, ctx)),
+        }
+    }
+
+    /// Translation key for literal text: the view name plus the first few words, made unique by
+    /// a hash of the whole text, `Home.welcome_back_3f2a9c1e`
+    fn text_key(&self, text: &str) -> String {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .take(6)
+            .map(|word| word.to_ascii_lowercase())
+            .collect();
+        let words = if words.is_empty() { "text".to_string() } else { words.join("_") };
+        format!("{}.{}_{}", self.proto.name, words, &sha256_hex(text.as_bytes())[..8])
+    }
+
+    fn prop_value_to_string(&self, value: &PropValue, ctx: RenderCtx) -> String {
+        match value {
+            PropValue::Str(s) => s.clone(),
+            PropValue::Num(n) => n.to_string(),
+            PropValue::Bool(b) => b.to_string(),
+            PropValue::Var(var_name) => format!("{{{}}}", var_name),
+            PropValue::Expr(expr) => format!("{{{}}}", expr),
+            PropValue::Dim(n, unit) => format!("{}{}", n, unit.suffix()),
+            // A style object has no text form
+            PropValue::Style(_) => String::new(),
+            // Unknown tokens are left as written; validate() reports them
+            PropValue::Color(color) => self.theme_defs.resolve_color(color).unwrap_or_else(|| color.clone()),
+            PropValue::Date { value: date, format } => match date.as_ref() {
+                PropValue::Var(_) | PropValue::Expr(_) => {
+                    format!("{{{}}}", self.prop_value_to_expr(value, ctx))
+                }
+                _ => {
+                    // Values that aren't ISO dates are shown unchanged
+                    let raw = self.prop_value_to_string(date, ctx);
+                    format_date(&raw, format).unwrap_or(raw)
+                }
+            },
+            // Spreads, refs and handlers have no text form
+            PropValue::Spread(_) | PropValue::Ref(_) | PropValue::Handler(_) => String::new(),
+            PropValue::Asset(asset_name) => {
+                if let Some(asset) = self.asset_defs.get(asset_name) {
+                    match asset.kind {
+                        AssetKind::Image => {
+                            // Check if external URL
+                            if let Some(path) = &asset.path {
+                                if is_remote_path(path) {
+                                    path.clone()
+                                } else {
+                                    format!("{{{}}}", self.asset_ident(asset_name, ctx))
+                                }
+                            } else {
+                                format!("{{{}}}", asset_name)
+                            }
+                        }
+                        _ => asset.url.clone().unwrap_or_default(),
+                    }
+                } else {
+                    format!("{{{}}}", asset_name)
+                }
+            }
+            PropValue::Content(content_name) => {
+                self.content_defs.get_str(content_name).cloned().unwrap_or_default()
+            }
+            PropValue::ContentField(field_name) => {
+                if let Some(record) = ctx.record {
+                    record.get(field_name).cloned().unwrap_or_default()
+                } else {
+                    String::new()
+                }
+            }
+        }
+    }
+}
+
+const STAGGER_ITEM_VARIANTS: &str = "{ hidden: { opacity: 0, y: 16 }, visible: { opacity: 1, y: 0 } }";
+
+/// motion.div props for an animation preset
+fn animation_props(preset: AnimationPreset) -> &'static str {
+    match preset {
+        AnimationPreset::FadeIn => {
+            "initial={{ opacity: 0 }} animate={{ opacity: 1 }} transition={{ duration: 0.4 }}"
+        }
+        AnimationPreset::SlideUp => {
+            "initial={{ opacity: 0, y: 24 }} animate={{ opacity: 1, y: 0 }} transition={{ duration: 0.4, ease: \"easeOut\" }}"
+        }
+        AnimationPreset::StaggerChildren => {
+            "initial=\"hidden\" animate=\"visible\" variants={{ hidden: {}, visible: { transition: { staggerChildren: 0.1 } } }}"
+        }
+    }
+}
+
+/// References gathered from a tree before rendering, used to build the import block
+#[derive(Default)]
+struct UsedRefs {
+    assets: BTreeSet<String>,
+    components: BTreeSet<String>,
+    // module -> named exports, e.g. "react-dom" -> {"createPortal"}
+    named_imports: BTreeMap<String, BTreeSet<String>>,
+    // module -> default import name, e.g. "clsx" -> "clsx"
+    default_imports: BTreeMap<String, String>,
+    helpers: BTreeSet<Helper>,
+}
+
+/// Support code emitted once per file above the view component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Helper {
+    ErrorBoundary,
+    ExternalScript,
+    Experiments,
+}
+
+impl Helper {
+    /// Module-level name the helper declares
+    fn name(&self) -> &'static str {
+        match self {
+            Helper::ErrorBoundary => "ProtoErrorBoundary",
+            Helper::ExternalScript => "ProtoExternalScript",
+            Helper::Experiments => "protoExperimentVariant",
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            Helper::ErrorBoundary => {
+                r#"class ProtoErrorBoundary extends React.Component {
+  constructor(props) {
+    super(props);
+    this.state = { hasError: false };
+  }
+
+  static getDerivedStateFromError() {
+    return { hasError: true };
+  }
+
+  render() {
+    if (this.state.hasError) {
+      return this.props.fallback ?? null;
+    }
+    return this.props.children;
+  }
+}
+"#
+            }
+            Helper::ExternalScript => {
+                r#"function ProtoExternalScript({ src }) {
+  useEffect(() => {
+    if (document.querySelector(`script[src="${src}"]`)) {
+      return;
+    }
+    const script = document.createElement("script");
+    script.src = src;
+    script.async = true;
+    document.body.appendChild(script);
+  }, [src]);
+  return null;
+}
+"#
+            }
+            Helper::Experiments => {
+                r#"// Variant comes from `?exp.<name>=<variant>`, then window.__EXPERIMENTS__, then the control
+function protoExperimentVariant(name, control) {
+  if (typeof window === "undefined") {
+    return control;
+  }
+  const fromQuery = new URLSearchParams(window.location.search).get(`exp.${name}`);
+  return fromQuery ?? window.__EXPERIMENTS__?.[name] ?? control;
+}
+"#
+            }
+        }
+    }
+}
+
+/// Components referenced anywhere under `element`, except inside a Match, If or Show decided at runtime,
+/// which may be what stops a recursive component. At runtime the items of a ContentList or Repeat
+/// can run out as well.
+fn collect_unconditional_refs<'a>(element: &'a Element, runtime: bool, refs: &mut Vec<&'a str>) {
+    match element {
+        Element::Match { source: PropValue::Var(_) | PropValue::Expr(_), .. }
+        | Element::If { condition: PropValue::Var(_) | PropValue::Expr(_), .. }
+        | Element::Show { condition: PropValue::Var(_) | PropValue::Expr(_), .. } => return,
+        Element::ContentList { .. } | Element::Repeat { .. } if runtime => return,
+        Element::ComponentRef { component, .. } => refs.push(component),
+        _ => {}
+    }
+    for child in element.child_elements() {
+        collect_unconditional_refs(child, runtime, refs);
+    }
+}
+
+/// Style object key for a CSS property: `background-color` -> `backgroundColor`, custom
+/// properties quoted as written (`"--accent"`), anything else that isn't an identifier quoted
+fn style_key(key: &str) -> String {
+    if key.starts_with("--") {
+        return js_string(key);
+    }
+    // React spells vendor prefixes as WebkitTransition, except for ms
+    let key = key.strip_prefix("-ms-").map(|rest| format!("ms-{}", rest)).unwrap_or_else(|| key.to_string());
+    let mut camel = String::new();
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '-' {
+            upper = !camel.is_empty() || key.starts_with('-');
+        } else if upper {
+            camel.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    let is_ident = camel.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && camel.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_ident { camel } else { js_string(&camel) }
+}
+
+/// Prop keys that differ only by case (`onclick`/`onClick`), grouped with the key that is
+/// emitted first: the one with the most capitals, as React's camelCase names have
+fn case_conflicts(props: &HashMap<String, PropValue>) -> Vec<Vec<&String>> {
+    let mut groups: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for key in props.keys() {
+        groups.entry(key.to_lowercase()).or_default().push(key);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by_key(|key| (std::cmp::Reverse(key.chars().filter(char::is_ascii_uppercase).count()), key.as_str()));
+            group
+        })
+        .collect()
+}
+
+/// Prop keys in a stable order, without the keys case_conflicts drops
+pub(crate) fn rendered_prop_keys(props: &HashMap<String, PropValue>) -> Vec<&String> {
+    let dropped: Vec<&String> = case_conflicts(props).into_iter().flat_map(|group| group.into_iter().skip(1)).collect();
+    let mut keys: Vec<&String> = props.keys().filter(|key| !dropped.contains(key)).collect();
+    keys.sort();
+    keys
+}
+
+/// Identifiers assets and component presets are imported as, by asset and preset name
+#[derive(Default)]
+struct ImportNames {
+    assets: HashMap<String, String>,
+    components: HashMap<String, String>,
+}
+
+/// What an element renders with besides its props: a component def's base classes and the
+/// object it spreads onto every use
+#[derive(Clone, Copy, Default)]
+struct NodeBase<'a> {
+    class_name: Option<&'a str>,
+    spread: Option<&'a str>,
+}
+
+/// What rendering an element reads besides the view, passed down explicitly: the import
+/// identifiers resolved once per render, the `hoistedN` constants, the children_templates
+/// being expanded and the record being rendered
+#[derive(Clone, Copy)]
+struct RenderCtx<'a> {
+    import_names: &'a ImportNames,
+    // Markup of each `hoistedN` constant at N - 1; None where nothing is hoisted (views that
+    // aren't pure, and inside a hoisted subtree)
+    hoisted: Option<&'a RefCell<Vec<String>>>,
+    // children_templates being expanded
+    expansion: &'a RefCell<ExpansionStack>,
+    record: Option<&'a HashMap<String, String>>,
+}
+
+impl<'a> RenderCtx<'a> {
+    fn with_record(self, record: Option<&'a HashMap<String, String>>) -> Self {
+        Self { record, ..self }
+    }
+}
+
+impl UsedRefs {
+    /// Record assets referenced by a prop value, including inside style objects
+    fn add_prop_value(&mut self, value: &PropValue) {
+        match value {
+            PropValue::Asset(name) => {
+                self.assets.insert(name.clone());
+            }
+            PropValue::Style(entries) => {
+                for entry in entries.values() {
+                    self.add_prop_value(entry);
+                }
+            }
+            PropValue::Handler(Handler::Set { value, .. }) => self.add_prop_value(value),
+            PropValue::Handler(Handler::Call { args, .. }) => {
+                for arg in args {
+                    self.add_prop_value(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn import_named(&mut self, module: &str, name: &str) {
+        self.named_imports.entry(module.to_string()).or_default().insert(name.to_string());
+    }
+}
+
+/// Package a bare module specifier belongs to: `@scope/pkg/sub` -> `@scope/pkg`.
+/// Relative paths and `@/` or `~/` aliases aren't packages.
+pub(crate) fn npm_package_name(module: &str) -> Option<String> {
+    if module.starts_with('.') || module.starts_with('/') || module.starts_with("@/") || module.starts_with('~') {
+        return None;
+    }
+    let mut segments = module.split('/');
+    let first = segments.next().filter(|s| !s.is_empty())?;
+    if first.starts_with('@') {
+        let name = segments.next().filter(|s| !s.is_empty())?;
+        Some(format!("{}/{}", first, name))
+    } else {
+        Some(first.to_string())
+    }
+}
+
+fn contains_head(element: &Element) -> bool {
+    matches!(element, Element::Head { .. }) || element.child_elements().into_iter().any(contains_head)
+}
+
+/// Gather Color values from a prop value, including inside style objects
+fn collect_colors<'a>(value: &'a PropValue, colors: &mut Vec<&'a String>) {
+    match value {
+        PropValue::Color(color) => colors.push(color),
+        PropValue::Style(entries) => {
+            for entry in entries.values() {
+                collect_colors(entry, colors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check that brackets in a JS expression are balanced, skipping over string literals
+fn check_expr_delimiters(expr: &str) -> Result<(), String> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut chars = expr.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.pop() != Some(expected) {
+                    return Err(format!("unexpected `{}`", c));
+                }
+            }
+            '"' | '\'' | '`' => {
+                let quote = c;
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(next) if next == quote => break,
+                        Some(_) => {}
+                        None => return Err(format!("unterminated string starting with `{}`", quote)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match stack.pop() {
+        Some(open) => Err(format!("unclosed `{}`", open)),
+        None => Ok(()),
+    }
+}
+
+/// Render a string attribute, falling back to a JS string expression when the
+/// value contains characters that JSX would misread in a quoted attribute
+fn string_attr(key: &str, value: &str) -> String {
+    if value.contains('"') || value.contains('&') {
+        format!("{}={{{}}}", key, js_string(value))
+    } else {
+        format!("{}=\"{}\"", key, value)
+    }
+}
+
+/// Text for inside a JS template literal
+fn template_text(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${")
+}
+
+/// Percent-encode a value for use in a URL query string or path segment
+pub(crate) fn encode_uri_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Replace `text` props reading a markdown field with the parsed markdown as leading children.
+/// Nested ContentList and Repeat templates bind their own records and are left alone.
+pub(crate) fn merge_markdown(element: &mut Element, record: &HashMap<String, String>, fields: &[String]) {
+    match element {
+        Element::ContentList { .. } | Element::Repeat { .. } => return,
+        Element::Node { props, children, .. } | Element::ComponentRef { props, children, .. } => {
+            if let Some(PropValue::ContentField(field)) = props.get("text")
+                && fields.contains(field)
+            {
+                let markdown = record.get(field).map(String::as_str).unwrap_or_default();
+                let mut merged: Vec<Box<Element>> = markdown_to_elements(markdown).into_iter().map(Box::new).collect();
+                merged.append(children);
+                *children = merged;
+                props.remove("text");
+            }
+        }
+        _ => {}
+    }
+    for child in element.child_elements_mut() {
+        merge_markdown(child, record, fields);
+    }
+}
+
+/// Markdown fields a records lookup view shows through `text` props, which bind_record_fields
+/// turned into `record.<field>` reads
+fn record_markdown_reads<'a>(element: &Element, fields: &'a [String], found: &mut Vec<&'a String>) {
+    match element {
+        Element::ContentList { .. } | Element::Repeat { .. } => return,
+        Element::Node { props, .. } | Element::ComponentRef { props, .. } => {
+            if let Some(PropValue::Var(read)) = props.get("text")
+                && let Some(field) = fields.iter().find(|field| read.strip_prefix("record.") == Some(field.as_str()))
+                && !found.contains(&field)
+            {
+                found.push(field);
+            }
+        }
+        _ => {}
+    }
+    for child in element.child_elements() {
+        record_markdown_reads(child, fields, found);
+    }
+}
+
+/// Turn ContentField references into `record.<field>` reads, leaving ContentList and
+/// Repeat templates alone since they bind their own records
+fn bind_record_fields(element: &mut Element) {
+    fn bind_value(value: &mut PropValue) {
+        match value {
+            // Related fields are stored flat under dotted names
+            PropValue::ContentField(field) if field.contains('.') => {
+                *value = PropValue::Var(format!("record[{}]", js_string(field)))
+            }
+            PropValue::ContentField(field) => *value = PropValue::Var(format!("record.{}", field)),
+            PropValue::Date { value, .. } | PropValue::Handler(Handler::Set { value, .. }) => bind_value(value),
+            PropValue::Style(style) => style.values_mut().for_each(bind_value),
+            PropValue::Handler(Handler::Call { args, .. }) => args.iter_mut().for_each(bind_value),
+            _ => {}
+        }
+    }
+
+    match element {
+        Element::ContentList { .. } | Element::Repeat { .. } => return,
+        Element::Node { props, .. } | Element::ComponentRef { props, .. } | Element::Media { props, .. } => {
+            props.values_mut().for_each(bind_value)
+        }
+        Element::Link { props, params, .. } => props.values_mut().chain(params.values_mut()).for_each(bind_value),
+        Element::Head { title, meta } => title.iter_mut().chain(meta.values_mut()).for_each(bind_value),
+        Element::Match { source, .. }
+        | Element::If { condition: source, .. }
+        | Element::Show { condition: source, .. } => bind_value(source),
+        _ => {}
+    }
+    for child in element.child_elements_mut() {
+        bind_record_fields(child);
+    }
+}
+
+/// Turn string Content references into reads of the runtime `content` object. Rich text
+/// stays inlined, as do Date values since they can't be formatted before the content loads.
+fn bind_content(element: &mut Element, content_defs: &ContentDefs) {
+    let bind_value = |value: &mut PropValue| {
+        let mut values: Vec<&mut PropValue> = match value {
+            PropValue::Style(style) => style.values_mut().collect(),
+            value => vec![value],
+        };
+        for value in values.iter_mut() {
+            if let PropValue::Content(key) = &**value
+                && content_defs.get_str(key).is_some()
+            {
+                **value = PropValue::Var(format!("content{}", property_access(key)));
+            }
+        }
+    };
+
+    match element {
+        Element::Node { props, .. } | Element::ComponentRef { props, .. } | Element::Media { props, .. } => {
+            props.values_mut().for_each(bind_value)
+        }
+        Element::Link { props, params, .. } => props.values_mut().chain(params.values_mut()).for_each(bind_value),
+        Element::Head { title, meta } => title.iter_mut().chain(meta.values_mut()).for_each(bind_value),
+        Element::Match { source, .. }
+        | Element::If { condition: source, .. }
+        | Element::Show { condition: source, .. } => bind_value(source),
+        _ => {}
+    }
+    for child in element.child_elements_mut() {
+        bind_content(child, content_defs);
+    }
+}
+
+/// Whether a tree bound by bind_content reads the runtime content
+fn reads_runtime_content(element: &Element) -> bool {
+    fn is_content_read(value: &PropValue) -> bool {
+        match value {
+            PropValue::Var(expr) => expr.starts_with("content.") || expr.starts_with("content["),
+            PropValue::Style(style) => style.values().any(is_content_read),
+            _ => false,
+        }
+    }
+
+    let reads = match element {
+        Element::ContentList { .. } => true,
+        Element::Head { title, meta } => title.iter().chain(meta.values()).any(is_content_read),
+        Element::Match { source, .. }
+        | Element::If { condition: source, .. }
+        | Element::Show { condition: source, .. } => is_content_read(source),
+        element => element.props().is_some_and(|props| props.values().any(is_content_read)),
+    };
+    reads || element.child_elements().into_iter().any(reads_runtime_content)
+}
+
+/// Whether `code` uses `name` as a whole identifier rather than as part of another one
+fn mentions_identifier(code: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    code.match_indices(name).any(|(i, _)| {
+        !code[..i].ends_with(|c: char| is_ident(c) || c == '.') && !code[i + name.len()..].starts_with(is_ident)
+    })
+}
+
+/// Text as a JSX child. Runs JSX would read as markup, entities or expressions, or whose
+/// edge whitespace it would drop, become a `{"..."}` string expression.
+pub(crate) fn jsx_text(text: &str) -> String {
+    let needs_expression = text.contains(['{', '}', '<', '>', '&', '\n'])
+        || text.starts_with(char::is_whitespace)
+        || text.ends_with(char::is_whitespace);
+    if needs_expression {
+        format!("{{{}}}", js_string(text))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Quote a string as a JS string literal
+pub(crate) fn js_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn view(proto: &str) -> ViewJsx {
+        ViewJsx::new(ron(proto), ron("(components: [])"), ron("(assets: [])"), ron("(content: {})"))
+    }
+
+    // The JSX with whitespace runs collapsed to one space, so assertions don't depend on indentation
+    fn compact(view: &ViewJsx) -> String {
+        view.to_string().split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn messages(view: &ViewJsx) -> Vec<String> {
+        view.validate().into_iter().map(|d| d.message).collect()
+    }
+
+    fn with_post_route(view: ViewJsx) -> ViewJsx {
+        view.with_routes(vec![
+            ron(r#"(name: "home", url: "/", path: "views/Home.jsx")"#),
+            ron(r#"(name: "post", url: "/blog/:slug", path: "views/Post.jsx")"#),
+        ])
+    }
+
+    #[test]
+    fn fills_link_params_known_at_generation_time() {
+        let view = with_post_route(view(
+            r#"(name: "Home", tree: Link(route: "post", params: {"slug": Str("what? now")}, children: [Text("Read")]))"#,
+        ));
+        assert!(view.validate().is_empty());
+        assert!(compact(&view).contains(r#"<Link to="/blog/what%3F%20now">"#), "{}", view);
+    }
+
+    #[test]
+    fn encodes_runtime_link_params_in_a_template_literal() {
+        let view = with_post_route(view(
+            r#"(name: "Home", props: [(name: "slug")], tree: Link(route: "post", params: {"slug": Var("slug")}))"#,
+        ));
+        assert!(view.validate().is_empty(), "{:?}", view.validate());
+        assert!(compact(&view).contains("<Link to={`/blog/${encodeURIComponent(slug)}`}"), "{}", view);
+    }
+
+    #[test]
+    fn reports_missing_and_unknown_link_params() {
+        let view = with_post_route(view(
+            r#"(name: "Home", tree: Node(tag: "div", children: [Link(route: "post"), Link(route: "home", params: {"id": Str("1")})]))"#,
+        ));
+        assert_eq!(
+            messages(&view),
+            ["link to `post` needs a value for its `:slug` param", "route `home` has no `:id` param; its url is `/`"]
+        );
+        assert!(compact(&view).contains(r##"<Link to="#" />"##), "{}", view);
+    }
+
+    #[test]
+    fn repeats_a_template_with_its_index() {
+        let view = view(
+            r#"(name: "Grid", tree: Node(tag: "ul", children: [
+                Repeat(count: 3, start: 1, template: Node(tag: "li", props: {"text": ContentField("index")})),
+            ]))"#,
+        );
+        assert!(view.validate().is_empty());
+        assert!(compact(&view).contains("<ul> <li> 1 </li> <li> 2 </li> <li> 3 </li> </ul>"), "{}", view);
+    }
+
+    #[test]
+    fn rejects_repeats_too_long_to_render() {
+        let huge = view(r#"(name: "Grid", tree: Repeat(count: 100000, template: Node(tag: "li")))"#);
+        assert_eq!(messages(&huge), ["repeats 100000 times, more than the 1000 a Repeat can render"]);
+        let overflowing = view(r#"(name: "Grid", tree: Repeat(count: 2, start: 18446744073709551615, template: Node(tag: "li")))"#);
+        assert_eq!(messages(&overflowing), ["repeating 2 times from 18446744073709551615 runs past the largest index"]);
+    }
+
+    #[test]
+    fn leaves_plain_text_as_is() {
+        assert_eq!(jsx_text("Hello world"), "Hello world");
+        assert_eq!(jsx_text("it's \"quoted\""), "it's \"quoted\"");
+    }
+
+    #[test]
+    fn wraps_text_jsx_would_misread() {
+        assert_eq!(jsx_text("a {b} c"), r#"{"a {b} c"}"#);
+        assert_eq!(jsx_text("1 < 2 && 3 > 2"), r#"{"1 < 2 && 3 > 2"}"#);
+        assert_eq!(jsx_text("line\nbreak"), r#"{"line\nbreak"}"#);
+    }
+
+    #[test]
+    fn keeps_edge_whitespace_jsx_would_drop() {
+        assert_eq!(jsx_text(" trailing "), r#"{" trailing "}"#);
+        assert_eq!(jsx_text("\t\"tab\""), r#"{"\t\"tab\""}"#);
+    }
+
+    #[test]
+    fn hoists_only_the_static_subtrees_it_renders() {
+        let view = view(
+            r#"(name: "Home", pure: true, tree: Node(tag: "div", children: [
+                If(condition: Bool(false), then: Node(tag: "section", children: [Node(tag: "p", props: {"text": Str("gone")})])),
+                Node(tag: "p", props: {"text": Str("kept")}),
+                Node(tag: "p", props: {"text": Str("kept")}),
+            ]))"#,
+        );
+        let jsx = view.to_string();
+        assert!(!jsx.contains("gone") && !jsx.contains("hoisted2"), "{}", jsx);
+        assert!(compact(&view).contains("const hoisted1 = ( <p> kept </p> );"), "{}", jsx);
+        assert_eq!(jsx.matches("{hoisted1}").count(), 2, "{}", jsx);
+    }
+
+    fn with_templates(proto: &str, components: &str) -> ViewJsx {
+        ViewJsx::new(ron(proto), ron(components), ron("(assets: [])"), ron("(content: {})"))
+    }
+
+    #[test]
+    fn expands_children_templates_into_uses_without_children() {
+        let view = with_templates(
+            r#"(name: "Home", tree: Node(tag: "main", children: [
+                ComponentRef(component: "Card"),
+                ComponentRef(component: "Card", children: [Text("Own")]),
+            ]))"#,
+            r#"(components: [
+                (name: "Card", tag: "div", class_name: "card", children_template: ComponentRef(component: "Badge")),
+                (name: "Badge", tag: "Badge", import_path: "./components/Badge", children_template: Text("Empty")),
+            ])"#,
+        );
+        assert!(view.validate().is_empty(), "{:?}", view.validate());
+        let jsx = compact(&view);
+        assert!(jsx.contains("import Badge from './components/Badge';"), "{}", view);
+        assert!(jsx.contains(r#"<div className="card"> <Badge> Empty </Badge> </div>"#), "{}", view);
+        assert!(jsx.contains(r#"<div className="card"> Own </div>"#), "{}", view);
+    }
+
+    #[test]
+    fn stops_expanding_a_children_template_cycle() {
+        let view = with_templates(
+            r#"(name: "Home", tree: ComponentRef(component: "A"))"#,
+            r#"(components: [
+                (name: "A", tag: "section", children_template: ComponentRef(component: "B")),
+                (name: "B", tag: "article", children_template: ComponentRef(component: "A")),
+            ])"#,
+        );
+        assert_eq!(messages(&view), ["component cycle: A → B → A"]);
+        assert!(compact(&view).contains("<section> <article> <section /> </article> </section>"), "{}", view);
+    }
+
+    #[test]
+    fn names_imports_after_the_defs_the_view_uses() {
+        let view = ViewJsx::new(
+            ron(r#"(name: "Home", tree: Node(tag: "div", children: [ComponentRef(component: "PrimaryButton"), Media(asset: "logo")]))"#),
+            ron(r#"(components: [
+                (name: "LegacyButton", tag: "Button", import_path: "./legacy/Button"),
+                (name: "PrimaryButton", tag: "Button", import_path: "./components/Button"),
+            ])"#),
+            ron(r#"(assets: [(name: "Button", kind: Image, path: "./unused.png"), (name: "logo", kind: Image, path: "./logo.png")])"#),
+            ron("(content: {})"),
+        );
+        let jsx = view.to_string();
+        assert!(jsx.contains("import Button from './components/Button';"), "{}", jsx);
+        assert!(jsx.contains("import logo from './logo.png';"), "{}", jsx);
+        assert!(!jsx.contains("legacy") && !jsx.contains("unused"), "{}", jsx);
+        assert!(compact(&view).contains("<Button />"), "{}", jsx);
+    }
+
+    #[test]
+    fn escapes_text_children_and_string_attributes() {
+        let view = view(
+            r#"(name: "Home", tree: Node(tag: "p", props: {"text": Str("a {b} <c>"), "title": Str("say \"hi\""), "className": Str("x\"y")}))"#,
+        );
+        let jsx = compact(&view);
+        assert!(jsx.contains(r#"{"a {b} <c>"}"#), "{}", view);
+        assert!(jsx.contains(r#"title={"say \"hi\""}"#), "{}", view);
+        assert!(jsx.contains(r#"className={"x\"y"}"#), "{}", view);
+    }
+
+    #[test]
+    fn checks_prop_types_at_component_refs() {
+        let view = with_templates(
+            r#"(name: "Home", tree: Node(tag: "div", children: [
+                ComponentRef(component: "Button", props: {"size": Str("xl"), "disabled": Str("yes")}),
+                ComponentRef(component: "Button", props: {"size": Str("sm"), "disabled": Var("busy")}),
+            ]))"#,
+            r#"(components: [(name: "Button", tag: "button", prop_types: {"size": Enum(["sm", "lg"]), "disabled": Bool})])"#,
+        );
+        assert_eq!(
+            messages(&view),
+            ["prop `disabled` on `Button` expects Bool but got Str", r#"prop `size` on `Button` must be one of "sm", "lg" but got "xl""#]
+        );
+    }
+
+    #[test]
+    fn writes_plain_anchors_for_static_links() {
+        let proto = r#"(name: "Home", tree: Link(route: "post", params: {"slug": Str("hi")}, children: [Text("Read")]))"#;
+        let routed = with_post_route(view(proto));
+        assert!(compact(&routed).contains("import { Link } from 'react-router-dom';"), "{}", routed);
+
+        let static_view = with_post_route(view(proto)).with_options(JsxOptions { static_links: true, ..Default::default() });
+        let jsx = compact(&static_view);
+        assert!(jsx.contains(r#"<a href="/blog/hi"> Read </a>"#), "{}", static_view);
+        assert!(!jsx.contains("react-router"), "{}", static_view);
+    }
+
+    #[test]
+    fn merges_base_and_caller_classes() {
+        let proto = r#"(name: "Home", tree: ComponentRef(component: "Card", props: {"className": Var("extra")}))"#;
+        let components = r#"(components: [(name: "Card", tag: "div", class_name: "card")])"#;
+        let merged = with_templates(proto, components);
+        let jsx = compact(&merged);
+        assert!(jsx.contains("import clsx from 'clsx';"), "{}", merged);
+        assert!(jsx.contains(r#"<div className={clsx("card", extra)} />"#), "{}", merged);
+
+        let overridden = with_templates(proto, components).with_options(JsxOptions { class_merge: ClassMerge::Override, ..Default::default() });
+        let jsx = compact(&overridden);
+        assert!(!jsx.contains("clsx"), "{}", overridden);
+        assert!(jsx.contains("<div className={extra} />"), "{}", overridden);
+    }
+
+    #[test]
+    fn writes_boolean_props_in_the_configured_form() {
+        let proto = r#"(name: "Home", tree: Node(tag: "input", props: {"disabled": Bool(true), "required": Bool(false)}))"#;
+        let rendered = |bool_props| compact(&view(proto).with_options(JsxOptions { bool_props, ..Default::default() }));
+        assert!(rendered(BoolProps::Shorthand).contains("<input disabled required={false} />"));
+        assert!(rendered(BoolProps::Explicit).contains("<input disabled={true} required={false} />"));
+        assert!(rendered(BoolProps::String).contains(r#"<input disabled="true" required="false" />"#));
+    }
+
+    #[test]
+    fn chains_match_arms_into_conditionals() {
+        let runtime = view(
+            r#"(name: "Home", props: [(name: "status")], tree: Match(source: Var("status"), arms: [
+                (value: "ok", template: Node(tag: "p", children: [Text("Fine")])),
+                (value: "err", template: Node(tag: "p", children: [Text("Bad")])),
+            ], default: Node(tag: "p", children: [Text("?")])))"#,
+        );
+        assert!(
+            compact(&runtime).contains(r#"{status === "ok" ? ( <p> Fine </p> ) : status === "err" ? ( <p> Bad </p> ) : ( <p> ? </p> )}"#),
+            "{}",
+            runtime
+        );
+
+        let known = view(
+            r#"(name: "Home", tree: Match(source: Str("err"), arms: [
+                (value: "ok", template: Node(tag: "p", children: [Text("Fine")])),
+                (value: "err", template: Node(tag: "p", children: [Text("Bad")])),
+            ]))"#,
+        );
+        let jsx = compact(&known);
+        assert!(jsx.contains("return ( <p> Bad </p> );") && !jsx.contains("Fine"), "{}", known);
+    }
+
+    #[test]
+    fn renders_portals_into_their_target() {
+        let view = view(r#"(name: "Home", tree: Portal(target: "modal-root", children: [Node(tag: "dialog")]))"#);
+        let jsx = compact(&view);
+        assert!(jsx.contains("import { createPortal } from 'react-dom';"), "{}", view);
+        assert!(jsx.contains(r#"{createPortal( <dialog />, document.getElementById("modal-root") )}"#), "{}", view);
+    }
+
+    #[test]
+    fn wraps_children_in_suspense_with_a_fallback() {
+        let view = view(r#"(name: "Home", tree: Suspense(fallback: Node(tag: "p", children: [Text("Loading")]), children: [Node(tag: "main")]))"#);
+        let jsx = compact(&view);
+        assert!(jsx.contains("import React, { Suspense } from 'react';"), "{}", view);
+        assert!(jsx.contains("<Suspense fallback={ <p> Loading </p> }> <main /> </Suspense>"), "{}", view);
+    }
+
+    #[test]
+    fn declares_the_error_boundary_class_once() {
+        let view = view(
+            r#"(name: "Home", tree: Node(tag: "div", children: [
+                ErrorBoundary(fallback: Node(tag: "p", children: [Text("Oops")]), children: [Node(tag: "main")]),
+                ErrorBoundary(children: [Node(tag: "aside")]),
+            ]))"#,
+        );
+        let jsx = compact(&view);
+        assert_eq!(jsx.matches("class ProtoErrorBoundary extends React.Component").count(), 1, "{}", view);
+        assert!(jsx.contains("static getDerivedStateFromError() { return { hasError: true }; }"), "{}", view);
+        assert!(jsx.contains("<ProtoErrorBoundary fallback={ <p> Oops </p> }> <main /> </ProtoErrorBoundary>"), "{}", view);
+        assert!(jsx.contains("<ProtoErrorBoundary> <aside /> </ProtoErrorBoundary>"), "{}", view);
+    }
+
+    #[test]
+    fn animates_with_framer_motion_presets() {
+        let fade = view(r#"(name: "Home", tree: Animated(preset: FadeIn, class_name: "box", children: [Node(tag: "p")]))"#);
+        let jsx = compact(&fade);
+        assert!(jsx.contains("import { motion } from 'framer-motion';"), "{}", fade);
+        assert!(jsx.contains(r#"<motion.div className="box" initial={{ opacity: 0 }} animate={{ opacity: 1 }}"#), "{}", fade);
+
+        let stagger = view(r#"(name: "Home", tree: Animated(preset: StaggerChildren, children: [Node(tag: "p"), Node(tag: "p")]))"#);
+        let jsx = compact(&stagger);
+        assert!(jsx.contains("variants={{ hidden: {}, visible: { transition: { staggerChildren: 0.1 } } }}"), "{}", stagger);
+        assert_eq!(jsx.matches("<motion.div variants={{ hidden: { opacity: 0, y: 16 }").count(), 2, "{}", stagger);
+    }
+
+    #[test]
+    fn renders_head_tags_with_helmet() {
+        let view = view(
+            r#"(name: "Home", tree: Node(tag: "main", children: [Head(title: Str("Home"), meta: {"description": Str("Hi"), "og:image": Str("/a.png")})]))"#,
+        );
+        let jsx = compact(&view);
+        assert!(jsx.contains("import { Helmet } from 'react-helmet';"), "{}", view);
+        assert!(
+            jsx.contains(r#"<Helmet> <title>Home</title> <meta name="description" content="Hi" /> <meta property="og:image" content="/a.png" /> </Helmet>"#),
+            "{}",
+            view
+        );
+    }
+
+    #[test]
+    fn embeds_providers_and_loads_their_scripts_once() {
+        let map = view(r#"(name: "Home", tree: Embed(provider: GoogleMaps, config: {"query": "Berlin"}))"#);
+        assert!(compact(&map).contains(r#"<iframe src={"https://www.google.com/maps?q=Berlin&output=embed"} width="100%" height="450""#), "{}", map);
+
+        let tweet = view(r#"(name: "Home", tree: Embed(provider: Twitter, config: {"url": "https://twitter.com/x/status/1"}))"#);
+        let jsx = compact(&tweet);
+        assert!(jsx.contains(r#"<blockquote className="twitter-tweet"> <a href="https://twitter.com/x/status/1"></a> </blockquote>"#), "{}", tweet);
+        assert!(jsx.contains(r#"<ProtoExternalScript src="https://platform.twitter.com/widgets.js" />"#), "{}", tweet);
+        assert!(jsx.contains("if (document.querySelector(`script[src=\"${src}\"]`)) { return; }"), "{}", tweet);
+    }
+
+    #[test]
+    fn checks_enum_props_against_content_strings() {
+        let view = ViewJsx::new(
+            ron(r#"(name: "Home", tree: Node(tag: "div", children: [
+                ComponentRef(component: "Button", props: {"size": Content("sizes.hero")}),
+                ComponentRef(component: "Button", props: {"size": Content("sizes.small")}),
+                ComponentRef(component: "Button"),
+            ]))"#),
+            ron(r#"(components: [(name: "Button", tag: "button", default_props: {"size": Str("md")}, prop_types: {"size": Enum(["sm", "md"])})])"#),
+            ron("(assets: [])"),
+            ron(r#"(content: {"sizes.hero": Str("huge"), "sizes.small": Str("sm")})"#),
+        );
+        assert_eq!(messages(&view), [r#"prop `size` on `Button` must be one of "sm", "md" but got "huge""#]);
+        assert!(PropType::Enum(vec!["sm".to_string()]).accepts(&PropValue::Var("size".to_string())));
+        assert!(!PropType::Enum(vec!["sm".to_string()]).accepts(&PropValue::Num(1.0)));
+    }
+
+    #[test]
+    fn spreads_props_before_explicit_ones() {
+        let spread = view(r#"(name: "Home", props: [(name: "rest")], tree: Node(tag: "div", props: {"id": Str("x"), "all": Spread("rest")}))"#);
+        assert!(compact(&spread).contains(r#"<div {...rest} id="x" />"#), "{}", spread);
+
+        let component = with_templates(
+            r#"(name: "Home", tree: ComponentRef(component: "Field", props: {"name": Str("email")}))"#,
+            r#"(components: [(name: "Field", tag: "input", spread: "inputProps")])"#,
+        );
+        assert!(compact(&component).contains(r#"<input {...inputProps} name="email" />"#), "{}", component);
+    }
+
+    #[test]
+    fn forwards_refs_and_declares_local_ones() {
+        let forwarded = view(
+            r#"(name: "Home", forward_ref: true, refs: ["inputRef"], tree: Node(tag: "div", children: [
+                Node(tag: "input", props: {"ref": Ref("ref")}),
+                Node(tag: "input", props: {"ref": Ref("inputRef")}),
+            ]))"#,
+        );
+        assert!(forwarded.validate().is_empty(), "{:?}", forwarded.validate());
+        let jsx = compact(&forwarded);
+        assert!(jsx.contains("import React, { useRef } from 'react';"), "{}", forwarded);
+        assert!(jsx.contains("const Home = React.forwardRef(function Home(props, ref) { const inputRef = useRef(null);"), "{}", forwarded);
+        assert!(jsx.contains("<input ref={ref} /> <input ref={inputRef} />"), "{}", forwarded);
+
+        let undeclared = view(r#"(name: "Home", tree: Node(tag: "input", props: {"ref": Ref("ref")}))"#);
+        assert_eq!(messages(&undeclared), ["ref `ref` is not declared in the view's refs"]);
+    }
+
+    #[test]
+    fn emits_expressions_verbatim_and_checks_their_delimiters() {
+        let inline = view(
+            r#"(name: "Home", props: [(name: "items")], tree: Node(tag: "p", props: {"hidden": Expr("items.length === 0"), "text": Expr("items.length")}))"#,
+        );
+        assert!(inline.validate().is_empty(), "{:?}", inline.validate());
+        assert!(compact(&inline).contains("<p hidden={items.length === 0}> {items.length} </p>"), "{}", inline);
+
+        let unclosed = view(r#"(name: "Home", tree: Node(tag: "p", props: {"hidden": Expr("(a")}))"#);
+        assert_eq!(messages(&unclosed), ["expression `(a`: unclosed `(`"]);
+        let strict = view(r#"(name: "Home", tree: Node(tag: "p", props: {"hidden": Expr("a")}))"#)
+            .with_options(JsxOptions { strict: true, ..Default::default() });
+        assert_eq!(messages(&strict), ["expression `a` is not allowed in strict mode"]);
+    }
+
+    #[test]
+    fn writes_dimensions_with_their_units() {
+        let view = view(r#"(name: "Home", tree: Node(tag: "p", props: {"style": Style({"fontSize": Dim(2, Rem), "width": Dim(50, Percent), "zIndex": Num(3)})}))"#);
+        assert!(compact(&view).contains(r#"<p style={{ fontSize: "2rem", width: "50%", zIndex: 3 }} />"#), "{}", view);
+        assert_eq!(Unit::Vh.suffix(), "vh");
+    }
+
+    #[test]
+    fn resolves_color_tokens_through_the_theme() {
+        let theme: ThemeDefs = ron(r##"(colors: {"primary": "#ff00aa"})"##);
+        let themed = view(
+            r##"(name: "Home", tree: Node(tag: "p", props: {"style": Style({"color": Color("color.primary"), "background": Color("#fff")}), "tw:bg": Color("color.primary")}))"##,
+        )
+        .with_theme(theme.clone());
+        assert!(themed.validate().is_empty(), "{:?}", themed.validate());
+        assert!(compact(&themed).contains(r##"<p className="bg-[#ff00aa]" style={{ background: "#fff", color: "#ff00aa" }} />"##), "{}", themed);
+
+        let unknown = view(r#"(name: "Home", tree: Node(tag: "p", props: {"color": Color("color.missing")}))"#).with_theme(theme);
+        assert_eq!(messages(&unknown), ["unknown theme color `color.missing`"]);
+    }
+
+    #[test]
+    fn destructures_view_props_with_defaults() {
+        let view = view(
+            r#"(name: "Card", props: [(name: "title", required: true), (name: "size", default: Str("md"))], tree: Node(tag: "h2", props: {"text": Var("title"), "className": Var("size")}))"#,
+        );
+        assert!(view.validate().is_empty(), "{:?}", view.validate());
+        assert!(compact(&view).contains(r#"function Card({ title, size = "md" }) { return ( <h2 className={size}> {title} </h2> ); }"#), "{}", view);
+    }
+
+    #[test]
+    fn emits_local_components_above_the_view() {
+        let view = view(
+            r#"(name: "Home", components: [(name: "Badge", props: [(name: "label")], tree: Node(tag: "span", props: {"text": Var("label")}))],
+                tree: Node(tag: "div", children: [ComponentRef(component: "Badge", props: {"label": Str("New")})]))"#,
+        );
+        assert!(view.validate().is_empty(), "{:?}", view.validate());
+        let jsx = compact(&view);
+        let badge = jsx.find("function Badge({ label }) { return ( <span> {label} </span> ); }").expect(&jsx);
+        let home = jsx.find(r#"function Home() { return ( <div> <Badge label="New" /> </div> ); }"#).expect(&jsx);
+        assert!(badge < home, "{}", view);
+    }
+
+    #[test]
+    fn calls_hooks_and_binds_their_results() {
+        let view = view(
+            r#"(name: "Home", imports: [(name: "useAuth", path: "../hooks/useAuth", kind: hook, bindings: ["user"], args: [Str("strict")])],
+                tree: Node(tag: "p", props: {"text": Var("user")}))"#,
+        );
+        assert!(view.validate().is_empty(), "{:?}", view.validate());
+        let jsx = compact(&view);
+        assert!(jsx.contains("import useAuth from '../hooks/useAuth';"), "{}", view);
+        assert!(jsx.contains(r#"function Home() { const { user } = useAuth("strict"); return ( <p> {user} </p> ); }"#), "{}", view);
+    }
+
+    #[test]
+    fn picks_experiment_variants_at_runtime_unless_pinned() {
+        let proto = r#"(name: "Home", tree: Experiment(name: "hero", variants: [
+            (name: "a", template: Node(tag: "h1", props: {"text": Str("A")})),
+            (name: "b", template: Node(tag: "h2", props: {"text": Str("B")})),
+        ]))"#;
+        let runtime = view(proto);
+        let jsx = compact(&runtime);
+        assert!(jsx.contains("function protoExperimentVariant(name, control) {"), "{}", runtime);
+        assert!(jsx.contains(r#"{protoExperimentVariant("hero", "a") === "b" ? ( <h2> B </h2> ) : ( <h1> A </h1> )}"#), "{}", runtime);
+
+        let pinned = view(proto).with_options(JsxOptions {
+            experiments: HashMap::from([("hero".to_string(), "b".to_string())]),
+            ..Default::default()
+        });
+        let jsx = compact(&pinned);
+        assert!(jsx.contains("return ( <h2> B </h2> );"), "{}", pinned);
+        assert!(!jsx.contains("protoExperimentVariant") && !jsx.contains("<h1>"), "{}", pinned);
+    }
+
+    #[test]
+    fn translates_text_through_use_translation() {
+        let view = view(r#"(name: "Home", tree: Node(tag: "main", children: [Text("Hello"), Node(tag: "p", props: {"text": Str("Hello")})]))"#)
+            .with_options(JsxOptions { i18n: true, ..Default::default() });
+        let jsx = compact(&view);
+        assert!(jsx.contains("import { useTranslation } from 'react-i18next';"), "{}", view);
+        assert!(jsx.contains(r#"const { t } = useTranslation(); return ( <main> {t("Home.hello_185f8db3")} <p> {t("Home.hello_185f8db3")} </p> </main> );"#), "{}", view);
+        assert_eq!(view.translations().into_iter().collect::<Vec<_>>(), [("Home.hello_185f8db3".to_string(), "Hello".to_string())]);
+    }
+
+    #[test]
+    fn assigns_prop_types_from_declared_props() {
+        let view = view(
+            r#"(name: "Card", props: [(name: "title", prop_type: String, required: true), (name: "size", prop_type: Enum(["sm", "lg"]))],
+                tree: Node(tag: "h2", props: {"text": Var("title")}))"#,
+        )
+        .with_options(JsxOptions { prop_types: true, ..Default::default() });
+        let jsx = view.to_string();
+        assert!(jsx.contains("import PropTypes from 'prop-types';\n"), "{}", jsx);
+        assert!(
+            jsx.contains("Card.propTypes = {\n  title: PropTypes.string.isRequired,\n  size: PropTypes.oneOf([\"sm\", \"lg\"]),\n};\n"),
+            "{}",
+            jsx
+        );
+    }
+
+    #[test]
+    fn adds_social_cards_to_the_head() {
+        let social = ViewJsx::new(
+            ron(r#"(name: "Home", tree: Node(tag: "main"))"#),
+            ron("(components: [])"),
+            ron(r#"(assets: [(name: "card", kind: Image, path: "./card.png")])"#),
+            ron(r#"(content: {"home.title": Str("Welcome"), "home.summary": Str("Hi & bye")})"#),
+        )
+        .with_canonical_url("https://example.com/")
+        .with_social(ron(r#"(title: "home.title", description: "home.summary", image: "card")"#));
+        assert!(social.validate().is_empty(), "{:?}", social.validate());
+        let jsx = compact(&social);
+        assert!(jsx.contains(r#"<meta property="og:url" content="https://example.com/" />"#), "{}", social);
+        assert!(jsx.contains(r#"<meta name="twitter:card" content="summary_large_image" />"#), "{}", social);
+        assert!(jsx.contains(r#"<meta property="og:description" content={"Hi & bye"} />"#), "{}", social);
+        assert!(jsx.contains("<meta property=\"og:image\" content={card} />"), "{}", social);
+
+        let root = std::env::temp_dir().join(format!("degenproto-social-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        image::RgbaImage::new(250, 250).save(root.join("src/card.png")).unwrap();
+        let sizes: Vec<String> = social.validate_files(&root).into_iter().map(|d| d.message).collect();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(sizes, ["social image `./card.png` is 250x250, below the 300x200 minimum for summary_large_image cards"]);
+    }
+
+    #[test]
+    fn reports_unknown_social_content_and_images() {
+        let view = view(r#"(name: "Home", tree: Node(tag: "main"))"#).with_social(ron(r#"(title: "missing", image: "nope")"#));
+        assert_eq!(
+            messages(&view),
+            ["social metadata references unknown content `missing`", "social image `nope` is not defined in AssetDefs"]
+        );
+    }
+
+    #[test]
+    fn imports_relative_to_the_output_path() {
+        let view = ViewJsx::new(
+            ron(r#"(name: "Post", tree: Node(tag: "div", children: [ComponentRef(component: "Card"), Media(asset: "logo")]))"#),
+            ron(r#"(components: [(name: "Card", tag: "Card", import_path: "./components/Card.jsx")])"#),
+            ron(r#"(assets: [(name: "logo", kind: Image, path: "./assets/logo.png")])"#),
+            ron("(content: {})"),
+        )
+        .with_output_path("views/blog/Post.jsx");
+        let jsx = view.to_string();
+        assert!(jsx.contains("import logo from '../../assets/logo.png';\nimport Card from '../../components/Card';\n"), "{}", jsx);
+    }
+
+    #[test]
+    fn names_components_with_valid_identifiers() {
+        let view = with_templates(
+            r#"(name: "404 page", tree: Node(tag: "main", children: [ComponentRef(component: "promo-banner")]))"#,
+            r#"(components: [(name: "promo-banner", tag: "PromoBanner", import_path: "./components/PromoBanner")])"#,
+        );
+        let jsx = compact(&view);
+        assert!(jsx.contains("import PromoBanner from './components/PromoBanner';"), "{}", view);
+        assert!(jsx.contains("function Component404Page() { return ( <main> <PromoBanner /> </main> ); }"), "{}", view);
+        assert!(jsx.contains("export default Component404Page;"), "{}", view);
+    }
+
+
+    #[test]
+    fn emits_one_of_props_that_differ_only_by_case() {
+        let view = view(r#"(name: "Form", tree: Node(tag: "button", props: {"onclick": Expr("save"), "onClick": Expr("submit"), "type": Str("button")}))"#);
+        let jsx = compact(&view);
+        assert!(jsx.contains("<button onClick={submit} type=\"button\" />"), "{}", jsx);
+        assert!(!jsx.contains("onclick"), "{}", jsx);
+        assert_eq!(messages(&view), ["props `onclick` and `onClick` differ only by case; only `onClick` is emitted"]);
+    }
+
+
+    #[test]
+    fn strips_elements_and_components_behind_disabled_flags() {
+        let proto = r#"(name: "Cart", tree: Node(tag: "main", children: [
+            Flag(flag: "checkout_v2", children: [Node(tag: "h2", children: [Text("New checkout")])]),
+            ComponentRef(component: "Upsell"),
+        ]))"#;
+        let components = r#"(components: [(name: "Upsell", tag: "Upsell", import_path: "./components/Upsell", flag: "upsell")])"#;
+
+        let jsx = compact(&with_templates(proto, components));
+        assert!(jsx.contains("return ( <main> </main> );"), "{}", jsx);
+        assert!(!jsx.contains("Upsell"), "{}", jsx);
+
+        let flags = ["checkout_v2".to_string(), "upsell".to_string()].into();
+        let jsx = compact(&with_templates(proto, components).with_options(JsxOptions { flags, ..Default::default() }));
+        assert!(jsx.contains("import Upsell from './components/Upsell';"), "{}", jsx);
+        assert!(jsx.contains("<main> <h2> New checkout </h2> <Upsell /> </main>"), "{}", jsx);
+    }
+
+
+    #[test]
+    fn uses_inlined_images_without_importing_them() {
+        let view = ViewJsx::new(
+            ron(r#"(name: "Home", tree: Node(tag: "img", props: {"src": Asset("icon")}))"#),
+            ron("(components: [])"),
+            ron(r#"(assets: [(name: "icon", kind: Image, path: "data:image/svg+xml;base64,PHN2Zy8+")])"#),
+            ron("(content: {})"),
+        );
+        let jsx = compact(&view);
+        assert!(jsx.contains(r#"<img src="data:image/svg+xml;base64,PHN2Zy8+" />"#), "{}", jsx);
+        assert!(!jsx.contains("import icon"), "{}", jsx);
+    }
+
+
+    #[test]
+    fn reads_loader_data_with_use_loader_data() {
+        let view = view(r#"(name: "Post", tree: Node(tag: "h1", children: [Raw("{post.title}")]))"#).with_loader_data("post");
+        let jsx = compact(&view);
+        assert!(jsx.contains("import { useLoaderData } from 'react-router-dom';"), "{}", jsx);
+        assert!(jsx.contains("function Post() { const post = useLoaderData(); return ( <h1> {post.title} </h1> ); }"), "{}", jsx);
+    }
+
+
+    #[test]
+    fn emits_element_ids_as_data_attributes_when_enabled() {
+        let proto = r#"(name: "Home", tree: Node(tag: "main", id: "e1", children: [ComponentRef(component: "Card", id: "card")]))"#;
+        let components = r#"(components: [(name: "Card", tag: "Card", import_path: "./components/Card")])"#;
+        assert!(!compact(&with_templates(proto, components)).contains("data-proto-id"));
+
+        let jsx = compact(&with_templates(proto, components).with_options(JsxOptions { proto_ids: true, ..Default::default() }));
+        assert!(jsx.contains(r#"<main data-proto-id="e1"> <Card data-proto-id="card" /> </main>"#), "{}", jsx);
+    }
+
+
+    #[test]
+    fn declares_state_and_renders_handlers_and_conditionals() {
+        let view = view(
+            r#"(name: "Faq", states: [(name: "open", initial: Bool(false)), (name: "query", initial: Str(""))], tree: Node(tag: "section", children: [
+                Node(tag: "button", props: {"onClick": Handler(Toggle("open"))}, children: [Text("Toggle")]),
+                Node(tag: "input", props: {"value": Var("query"), "onChange": Handler(Set(state: "query", value: Expr("event.target.value")))}),
+                If(condition: Var("open"), then: Node(tag: "p", children: [Text("Answer")]), otherwise: Node(tag: "p", children: [Text("Closed")])),
+                Show(condition: Expr("query.length > 0"), children: [Node(tag: "span", children: [Text("Searching")])]),
+                If(condition: Bool(false), then: Node(tag: "b")),
+            ]))"#,
+        );
+        assert!(messages(&view).is_empty(), "{:?}", messages(&view));
+        let jsx = compact(&view);
+        assert!(jsx.starts_with("import React, { useState } from 'react';"), "{}", jsx);
+        assert!(jsx.contains("const [open, setOpen] = useState(false); const [query, setQuery] = useState(\"\");"), "{}", jsx);
+        assert!(jsx.contains("<button onClick={() => setOpen((current) => !current)}>"), "{}", jsx);
+        assert!(jsx.contains("<input onChange={(event) => setQuery((event.target.value))} value={query} />"), "{}", jsx);
+        assert!(jsx.contains("{open ? ( <p> Answer </p> ) : ( <p> Closed </p> )}"), "{}", jsx);
+        assert!(jsx.contains("{(query.length > 0) && ( <span> Searching </span> )} </section>"), "{}", jsx);
+    }
+
+    #[test]
+    fn reports_handlers_on_undeclared_or_mistyped_state() {
+        let view = view(
+            r#"(name: "Faq", states: [(name: "query", initial: Str(""))], tree: Node(tag: "div", children: [
+                Node(tag: "button", props: {"onClick": Handler(Toggle("query"))}),
+                Node(tag: "button", props: {"onClick": Handler(Set(state: "open", value: Bool(true)))}),
+                Node(tag: "button", props: {"onClick": Handler(Call(function: "subscribe"))}),
+            ]))"#,
+        );
+        assert_eq!(
+            messages(&view),
+            [
+                "toggles state `query`, whose initial value is not a Bool",
+                "state `open` is not declared in the view's states",
+                "handler calls `subscribe`, which is not imported or a prop, so it must be a global",
+            ]
+        );
     }
 }
@@ -1,7 +1,16 @@
+use crate::date_format::{format_date, is_iso_date};
+use crate::markdown::markdown_plain_text;
+use crate::paths::{is_remote_path, normalize_path};
+use crate::ron_format::to_ron_string;
+use crate::expansion::{ExpansionStack, DEFAULT_MAX_EXPANSION_DEPTH};
+use crate::diagnostics::Diagnostic;
+use crate::names::capitalize;
+use crate::view_jsx::js_string;
+use base64::prelude::{Engine, BASE64_STANDARD};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// A prop value - can be string, number, bool, or asset/variable reference
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -13,32 +22,102 @@ pub enum PropValue {
     Asset(String),       // Asset reference - looked up in AssetDefs
     Content(String),     // Content reference - looked up in ContentDefs
     ContentField(String), // Field reference within a ContentList context
+    Spread(String),      // Spread an object into the props: {...rest} (the prop key is ignored)
+    Ref(String),         // A ref declared in ViewProto.refs, or "ref" for the forwarded ref
+    Expr(String),        // JS expression emitted verbatim inside braces: items.length === 0
+    Dim(f64, Unit),      // Dimensioned number: Dim(2, Rem) -> "2rem"
+    Style(#[serde(serialize_with = "crate::ron_format::sorted_map")] HashMap<String, PropValue>), // Inline style object: style={{ fontSize: "2rem" }}
+    /// Color literal ("#ff00aa") or theme token ("color.primary") resolved through ThemeDefs.
+    /// Under a "tw:<utility>" key it becomes a Tailwind arbitrary class, e.g. "tw:bg" -> bg-[#ff00aa]
+    Color(String),
+    /// Date formatted with a pattern like "MMM D, YYYY" - at generation time for content values,
+    /// or with Intl.DateTimeFormat at runtime for Var/Expr values
+    Date {
+        value: Box<PropValue>,
+        format: String,
+    },
+    /// Event handler such as onClick, emitted as an arrow function
+    Handler(Handler),
+}
+
+/// What an event handler prop does
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Handler {
+    /// Set a state declared in ViewProto::states: `() => setOpen(true)`.
+    /// The value can read the event as Expr("event.target.value").
+    Set { state: String, value: Box<PropValue> },
+    /// Flip a boolean state: `() => setOpen((current) => !current)`
+    Toggle(String),
+    /// Call a function the view imports or takes as a prop: `() => subscribe("newsletter")`
+    Call {
+        function: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<PropValue>,
+    },
+}
+
+/// CSS unit for dimensioned prop values
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Unit {
+    Px,
+    Rem,
+    Em,
+    Percent,
+    Vw,
+    Vh,
+}
+
+impl Unit {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Rem => "rem",
+            Unit::Em => "em",
+            Unit::Percent => "%",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+        }
+    }
 }
 
 /// An element in the tree
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Element {
-    /// Plain text content
+    /// Plain text content, escaped so `{`, `<` and `&` show up as written
     Text(String),
 
+    /// JSX emitted as written and never translated, for intentional markup or expressions
+    Raw(String),
+
+    /// Text from markdown or rich text content: escaped like Text but never translated.
+    /// Made while rendering, so it is not part of the proto format.
+    #[serde(skip)]
+    ContentText(String),
+
     /// HTML/JSX node
     Node {
         tag: String,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         class_name: Option<String>,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
         props: HashMap<String, PropValue>,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
         children: Vec<Box<Element>>,
+        /// Stable id for visual editors; see ViewProto::find_element and JsxOptions::proto_ids
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
     },
 
     /// Reference to a component definition
     ComponentRef {
         component: String,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
         props: HashMap<String, PropValue>,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
         children: Vec<Box<Element>>,
+        /// Stable id for visual editors; see ViewProto::find_element and JsxOptions::proto_ids
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
     },
 
     /// Iterate over a content list
@@ -46,6 +125,329 @@ pub enum Element {
         source: String,           // Key in ContentDefs (must be a List)
         template: Box<Element>,   // Template using ContentField references
     },
+
+    /// Previous/next links for the current page of a paginated ContentList source
+    Pagination {
+        source: String,           // Collection key in ContentDefs with pagination configured
+        #[serde(default = "default_prev_label")]
+        prev_label: String,
+        #[serde(default = "default_next_label")]
+        next_label: String,
+    },
+
+    /// Render a template a fixed number of times (skeleton grids, placeholder cards)
+    Repeat {
+        count: usize,
+        #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
+        start: usize,             // First index value, so the range is start..start + count
+        #[serde(default = "default_repeat_index")]
+        index: String,            // Name the index is bound to, read with ContentField
+        template: Box<Element>,
+    },
+
+    /// Pick a template by matching a value against arms.
+    /// Content values are matched at generation time; a Var or Expr source becomes a JSX conditional chain.
+    Match {
+        source: PropValue,
+        arms: Vec<MatchArm>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<Box<Element>>,
+    },
+
+    /// Render children into a DOM node outside the layout (modals, toasts)
+    Portal {
+        target: String,           // Id of the DOM element to render into
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// Suspense boundary around lazily loaded or async children
+    Suspense {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<Box<Element>>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// Error boundary so a failing subtree renders the fallback instead of breaking the page
+    ErrorBoundary {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<Box<Element>>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// framer-motion wrapper applying an animation preset to its children
+    Animated {
+        preset: AnimationPreset,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        class_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// Link to a route by name - the URL is looked up in the ProtoIndex routes
+    Link {
+        route: String,
+        /// Values for the route url's `:param` segments, by param name
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+        params: HashMap<String, PropValue>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+        props: HashMap<String, PropValue>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+        /// Stable id for visual editors; see ViewProto::find_element and JsxOptions::proto_ids
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+
+    /// Document head for this view (title and meta tags), rendered with react-helmet
+    Head {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<PropValue>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+        meta: HashMap<String, PropValue>,  // "description", "og:image", ... -> content
+    },
+
+    /// Render an asset with the element that fits its kind (img, video, audio, YouTube iframe)
+    Media {
+        asset: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+        props: HashMap<String, PropValue>,
+        /// Stable id for visual editors; see ViewProto::find_element and JsxOptions::proto_ids
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+
+    /// Third-party embed expanded from a built-in provider
+    Embed {
+        provider: EmbedProvider,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+        config: HashMap<String, String>,
+    },
+
+    /// Children rendered side by side without a wrapping tag (<>...</>)
+    Fragment {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// Named region of a base view that extending views can replace.
+    /// Renders its own children when not overridden.
+    Region {
+        name: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// Children included only in builds whose JsxOptions::flags enable `flag`, for in-progress
+    /// features that shouldn't appear in every demo build
+    Flag {
+        flag: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+
+    /// A/B alternatives for a subtree. A variant pinned in JsxOptions::experiments is emitted alone;
+    /// otherwise the variant is picked at runtime and the first one is the control.
+    Experiment {
+        name: String,
+        variants: Vec<Variant>,
+    },
+
+    /// `then` while the condition holds, `otherwise` when it doesn't. Var and Expr conditions
+    /// (such as a state) become `{cond ? ... : ...}`; content and literals are decided at generation time.
+    If {
+        condition: PropValue,
+        then: Box<Element>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        otherwise: Option<Box<Element>>,
+    },
+
+    /// Children shown only while the condition holds, `{cond && ...}`; decided like If
+    Show {
+        condition: PropValue,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Box<Element>>,
+    },
+}
+
+/// One alternative of an Experiment element
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Variant {
+    pub name: String,
+    pub template: Box<Element>,
+}
+
+/// Built-in embed providers and the config keys they read
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum EmbedProvider {
+    GoogleMaps,          // query, height
+    Calendly,            // url, height
+    StripePricingTable,  // pricing_table_id, publishable_key
+    Twitter,             // url (link to the post)
+}
+
+impl Element {
+    /// Every element nested directly inside this one (children, templates, arms, fallbacks)
+    pub fn child_elements(&self) -> Vec<&Element> {
+        match self {
+            Element::Text(_)
+            | Element::Raw(_)
+            | Element::ContentText(_)
+            | Element::Head { .. }
+            | Element::Media { .. }
+            | Element::Embed { .. }
+            | Element::Pagination { .. } => Vec::new(),
+            Element::Node { children, .. }
+            | Element::ComponentRef { children, .. }
+            | Element::Portal { children, .. }
+            | Element::Animated { children, .. }
+            | Element::Link { children, .. }
+            | Element::Fragment { children }
+            | Element::Region { children, .. }
+            | Element::Flag { children, .. }
+            | Element::Show { children, .. } => children.iter().map(|c| c.as_ref()).collect(),
+            Element::ContentList { template, .. } | Element::Repeat { template, .. } => vec![template.as_ref()],
+            Element::Experiment { variants, .. } => variants.iter().map(|v| v.template.as_ref()).collect(),
+            Element::If { then, otherwise, .. } => std::iter::once(then.as_ref()).chain(otherwise.as_deref()).collect(),
+            Element::Match { arms, default, .. } => arms
+                .iter()
+                .map(|arm| arm.template.as_ref())
+                .chain(default.as_deref())
+                .collect(),
+            Element::Suspense { fallback, children } | Element::ErrorBoundary { fallback, children } => fallback
+                .as_deref()
+                .into_iter()
+                .chain(children.iter().map(|c| c.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Mutable version of child_elements
+    pub fn child_elements_mut(&mut self) -> Vec<&mut Element> {
+        match self {
+            Element::Text(_)
+            | Element::Raw(_)
+            | Element::ContentText(_)
+            | Element::Head { .. }
+            | Element::Media { .. }
+            | Element::Embed { .. }
+            | Element::Pagination { .. } => Vec::new(),
+            Element::Node { children, .. }
+            | Element::ComponentRef { children, .. }
+            | Element::Portal { children, .. }
+            | Element::Animated { children, .. }
+            | Element::Link { children, .. }
+            | Element::Fragment { children }
+            | Element::Region { children, .. }
+            | Element::Flag { children, .. }
+            | Element::Show { children, .. } => children.iter_mut().map(|c| c.as_mut()).collect(),
+            Element::ContentList { template, .. } | Element::Repeat { template, .. } => vec![template.as_mut()],
+            Element::Experiment { variants, .. } => variants.iter_mut().map(|v| v.template.as_mut()).collect(),
+            Element::If { then, otherwise, .. } => {
+                std::iter::once(then.as_mut()).chain(otherwise.as_deref_mut()).collect()
+            }
+            Element::Match { arms, default, .. } => arms
+                .iter_mut()
+                .map(|arm| arm.template.as_mut())
+                .chain(default.as_deref_mut())
+                .collect(),
+            Element::Suspense { fallback, children } | Element::ErrorBoundary { fallback, children } => fallback
+                .as_deref_mut()
+                .into_iter()
+                .chain(children.iter_mut().map(|c| c.as_mut()))
+                .collect(),
+        }
+    }
+
+    /// The element's editor id; only Node, ComponentRef, Link and Media elements take one
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Element::Node { id, .. }
+            | Element::ComponentRef { id, .. }
+            | Element::Link { id, .. }
+            | Element::Media { id, .. } => id.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn id_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            Element::Node { id, .. }
+            | Element::ComponentRef { id, .. }
+            | Element::Link { id, .. }
+            | Element::Media { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Props set directly on this element, for elements that take props
+    pub fn props(&self) -> Option<&HashMap<String, PropValue>> {
+        match self {
+            Element::Node { props, .. }
+            | Element::ComponentRef { props, .. }
+            | Element::Link { props, .. }
+            | Element::Media { props, .. } => Some(props),
+            _ => None,
+        }
+    }
+
+    /// Short name used when pointing at this element in diagnostics
+    pub fn label(&self) -> String {
+        match self {
+            Element::Text(_) => "Text".to_string(),
+            Element::Raw(_) => "Raw".to_string(),
+            Element::ContentText(_) => "ContentText".to_string(),
+            Element::Node { tag, .. } => tag.clone(),
+            Element::ComponentRef { component, .. } => component.clone(),
+            Element::ContentList { source, .. } => format!("ContentList({})", source),
+            Element::Repeat { .. } => "Repeat".to_string(),
+            Element::Pagination { source, .. } => format!("Pagination({})", source),
+            Element::Match { .. } => "Match".to_string(),
+            Element::Portal { target, .. } => format!("Portal({})", target),
+            Element::Suspense { .. } => "Suspense".to_string(),
+            Element::ErrorBoundary { .. } => "ErrorBoundary".to_string(),
+            Element::Animated { .. } => "Animated".to_string(),
+            Element::Link { route, .. } => format!("Link({})", route),
+            Element::Head { .. } => "Head".to_string(),
+            Element::Media { asset, .. } => format!("Media({})", asset),
+            Element::Fragment { .. } => "Fragment".to_string(),
+            Element::Region { name, .. } => format!("Region({})", name),
+            Element::Flag { flag, .. } => format!("Flag({})", flag),
+            Element::Experiment { name, .. } => format!("Experiment({})", name),
+            Element::Embed { provider, .. } => format!("Embed({:?})", provider),
+            Element::If { .. } => "If".to_string(),
+            Element::Show { .. } => "Show".to_string(),
+        }
+    }
+}
+
+/// Built-in animation presets for Animated elements
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum AnimationPreset {
+    FadeIn,
+    SlideUp,
+    StaggerChildren,  // Children fade/slide in one after another
+}
+
+/// One arm of a Match element
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MatchArm {
+    pub value: String,
+    pub template: Box<Element>,
+}
+
+fn default_repeat_index() -> String {
+    "index".to_string()
+}
+
+fn default_prev_label() -> String {
+    "Previous".to_string()
+}
+
+fn default_next_label() -> String {
+    "Next".to_string()
 }
 
 /// A reusable component definition/preset
@@ -53,18 +455,143 @@ pub enum Element {
 pub struct ComponentDef {
     pub name: String,
     pub tag: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub class_name: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
     pub default_props: HashMap<String, PropValue>,
     /// Props that must be provided when using this component
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub required_props: Vec<String>,
-    #[serde(default)]
+    /// Declared prop types, checked at every ComponentRef site
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub prop_types: HashMap<String, PropType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub children_template: Option<Box<Element>>,
     /// Optional path to a JSX component file (generates an import)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub import_path: Option<String>,
+    /// Object spread onto every use of this component, e.g. "rest" emits {...rest}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spread: Option<String>,
+    /// Feature flag the component is gated behind; uses of it are left out of builds without the flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
+}
+
+/// Declared type of a component prop
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum PropType {
+    String,
+    Number,
+    Bool,
+    Asset,
+    Node,  // Anything renderable as children text
+    Enum(Vec<String>),  // One of a fixed set of strings, e.g. ["sm", "md", "lg"]
+    Func,  // A callback such as onClick
+}
+
+impl PropType {
+    /// Whether a prop value can satisfy this type.
+    /// Variables are only known at runtime, so they always pass.
+    pub fn accepts(&self, value: &PropValue) -> bool {
+        if let (PropType::Enum(allowed), PropValue::Str(s)) = (self, value) {
+            return allowed.contains(s);
+        }
+        matches!(
+            (self, value),
+            (_, PropValue::Var(_) | PropValue::Expr(_) | PropValue::Spread(_))
+                | (
+                    PropType::String,
+                    PropValue::Str(_)
+                        | PropValue::Dim(..)
+                        | PropValue::Date { .. }
+                        | PropValue::Color(_)
+                        | PropValue::Content(_)
+                        | PropValue::ContentField(_)
+                )
+                | (PropType::Number, PropValue::Num(_))
+                | (PropType::Bool, PropValue::Bool(_))
+                | (PropType::Asset, PropValue::Asset(_))
+                | (PropType::Func, PropValue::Handler(_))
+                | (PropType::Enum(_), PropValue::Content(_) | PropValue::ContentField(_))
+                | (
+                    PropType::Node,
+                    PropValue::Str(_)
+                        | PropValue::Num(_)
+                        | PropValue::Dim(..)
+                        | PropValue::Date { .. }
+                        | PropValue::Content(_)
+                        | PropValue::ContentField(_)
+                )
+        )
+    }
+
+    /// Matching `prop-types` validator, e.g. `PropTypes.oneOf(["sm", "md"])`
+    pub fn prop_types_expr(&self) -> String {
+        match self {
+            PropType::String | PropType::Asset => "PropTypes.string".to_string(),
+            PropType::Number => "PropTypes.number".to_string(),
+            PropType::Bool => "PropTypes.bool".to_string(),
+            PropType::Node => "PropTypes.node".to_string(),
+            PropType::Func => "PropTypes.func".to_string(),
+            PropType::Enum(values) => {
+                let values: Vec<String> = values.iter().map(|v| js_string(v)).collect();
+                format!("PropTypes.oneOf([{}])", values.join(", "))
+            }
+        }
+    }
+}
+
+impl ComponentDef {
+    /// `Tag.propTypes = {...};` for the component this def points at, if it declares any props
+    pub fn prop_types_source(&self) -> Option<String> {
+        let mut names: Vec<&String> = self.prop_types.keys().chain(&self.required_props).collect();
+        names.sort();
+        names.dedup();
+        let entries: Vec<(&str, Option<&PropType>, bool)> = names
+            .into_iter()
+            .map(|name| (name.as_str(), self.prop_types.get(name), self.required_props.contains(name)))
+            .collect();
+        prop_types_assignment(&self.tag, &entries)
+    }
+}
+
+/// `Component.propTypes = {...};` from (name, type, required) entries; None when there are no entries
+pub(crate) fn prop_types_assignment(component: &str, entries: &[(&str, Option<&PropType>, bool)]) -> Option<String> {
+    if entries.is_empty() {
+        return None;
+    }
+    let mut output = format!("{}.propTypes = {{\n", component);
+    for (name, prop_type, required) in entries {
+        let validator = prop_type.map(PropType::prop_types_expr).unwrap_or_else(|| "PropTypes.any".to_string());
+        let required = if *required { ".isRequired" } else { "" };
+        output.push_str(&format!("  {}: {}{},\n", name, validator, required));
+    }
+    output.push_str("};\n");
+    Some(output)
+}
+
+impl PropValue {
+    /// Variant name, used in diagnostics
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            PropValue::Str(_) => "Str",
+            PropValue::Num(_) => "Num",
+            PropValue::Bool(_) => "Bool",
+            PropValue::Var(_) => "Var",
+            PropValue::Asset(_) => "Asset",
+            PropValue::Content(_) => "Content",
+            PropValue::ContentField(_) => "ContentField",
+            PropValue::Spread(_) => "Spread",
+            PropValue::Ref(_) => "Ref",
+            PropValue::Expr(_) => "Expr",
+            PropValue::Dim(..) => "Dim",
+            PropValue::Style(_) => "Style",
+            PropValue::Date { .. } => "Date",
+            PropValue::Color(_) => "Color",
+            PropValue::Handler(_) => "Handler",
+        }
+    }
 }
 
 /// Collection of component definitions
@@ -74,6 +601,10 @@ pub struct ComponentDefs {
 }
 
 impl ComponentDefs {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "ComponentDefs", path = %path.as_ref().display()))
+    )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let options = ron::Options::default()
@@ -82,6 +613,11 @@ impl ComponentDefs {
         Ok(defs)
     }
 
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
     pub fn get(&self, name: &str) -> Option<&ComponentDef> {
         self.components.iter().find(|c| c.name == name)
     }
@@ -101,10 +637,58 @@ pub enum AssetKind {
 pub struct AssetDef {
     pub name: String,
     pub kind: AssetKind,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,  // For images, local files
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,   // For youtube, external URLs
+    /// YouTube only - embed from youtube-nocookie.com
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub privacy_enhanced: bool,
+    /// Image only - source for the favicon set; one asset per project
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub app_icon: bool,
+}
+
+impl AssetDef {
+    /// Extract the video id from a Youtube asset's url
+    /// (watch?v=, youtu.be/, embed/ and shorts/ forms)
+    pub fn youtube_id(&self) -> Option<String> {
+        let url = self.url.as_deref()?;
+        let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+        let id = if host.ends_with("youtu.be") {
+            path
+        } else if let Some((_, query)) = path.split_once('?')
+            && path.starts_with("watch")
+        {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("v="))
+                .unwrap_or("")
+        } else {
+            path.strip_prefix("embed/")
+                .or_else(|| path.strip_prefix("shorts/"))
+                .or_else(|| path.strip_prefix("live/"))
+                .unwrap_or("")
+        };
+
+        let id: String = id
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if id.is_empty() { None } else { Some(id) }
+    }
+
+    /// Embeddable player URL for a Youtube asset
+    pub fn youtube_embed_url(&self) -> Option<String> {
+        let host = if self.privacy_enhanced {
+            "www.youtube-nocookie.com"
+        } else {
+            "www.youtube.com"
+        };
+        self.youtube_id().map(|id| format!("https://{}/embed/{}", host, id))
+    }
 }
 
 /// Collection of asset definitions
@@ -114,6 +698,10 @@ pub struct AssetDefs {
 }
 
 impl AssetDefs {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "AssetDefs", path = %path.as_ref().display()))
+    )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let options = ron::Options::default()
@@ -122,9 +710,80 @@ impl AssetDefs {
         Ok(defs)
     }
 
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
     pub fn get(&self, name: &str) -> Option<&AssetDef> {
         self.assets.iter().find(|a| a.name == name)
     }
+
+    /// A copy with each local image of at most `max_bytes` replaced by a base64 data URI, which
+    /// the generated JSX uses directly instead of importing the file. Files are found with
+    /// AliasDefs::asset_file from `project_root`; images of a type browsers can't be told about
+    /// stay files, as do images that can't be read, each reported as a warning.
+    pub fn inline_images<P: AsRef<Path>>(
+        &self,
+        project_root: P,
+        aliases: &AliasDefs,
+        max_bytes: u64,
+    ) -> (AssetDefs, Vec<Diagnostic>) {
+        let mut inlined = self.clone();
+        let mut diagnostics = Vec::new();
+        for asset in &mut inlined.assets {
+            let (AssetKind::Image, Some(path)) = (&asset.kind, asset.path.as_deref()) else {
+                continue;
+            };
+            let Some(mime) = image_mime_type(path).filter(|_| !is_remote_path(path)) else {
+                continue;
+            };
+            let file = project_root.as_ref().join(aliases.asset_file(path));
+            let bytes = match fs::metadata(&file) {
+                Ok(metadata) if metadata.len() > max_bytes => continue,
+                Ok(_) => fs::read(&file),
+                Err(e) => Err(e),
+            };
+            match bytes {
+                Ok(bytes) => asset.path = Some(format!("data:{};base64,{}", mime, BASE64_STANDARD.encode(bytes))),
+                Err(e) => diagnostics.push(Diagnostic::warning(
+                    format!("assets > {}", asset.name),
+                    format!("can't read {} ({}); it is left as a file", file.display(), e),
+                )),
+            }
+        }
+        (inlined, diagnostics)
+    }
+
+    /// The asset marked `app_icon`. Errors when none or several are marked.
+    pub fn app_icon(&self) -> Result<&AssetDef, Box<dyn std::error::Error>> {
+        let marked: Vec<&AssetDef> = self.assets.iter().filter(|a| a.app_icon).collect();
+        match marked.as_slice() {
+            [asset] => Ok(asset),
+            [] => Err("no asset is marked `app_icon`".into()),
+            _ => {
+                let names: Vec<&str> = marked.iter().map(|a| a.name.as_str()).collect();
+                Err(format!("only one asset can be marked `app_icon`, found: {}", names.join(", ")).into())
+            }
+        }
+    }
+}
+
+/// MIME type of an image file, from its extension
+pub(crate) fn image_mime_type(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        _ => return None,
+    };
+    Some(mime)
 }
 
 /// Import definition
@@ -132,11 +791,17 @@ impl AssetDefs {
 pub struct Import {
     pub name: String,
     pub path: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
     pub kind: ImportKind,
+    /// Hook only - names destructured from the call: `const { user } = useAuth();`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<String>,
+    /// Hook only - arguments passed to the call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<PropValue>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ImportKind {
     #[default]
@@ -149,14 +814,92 @@ pub enum ImportKind {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ViewProto {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub imports: Vec<Import>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub observer: bool,
+    /// Wrap the component in React.forwardRef; the forwarded ref is available as Ref("ref")
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub forward_ref: bool,
+    /// Export the component wrapped in React.memo (observer views are already memoized)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub memo: bool,
+    /// Hoist static subtrees into module-level constants so they aren't recreated on every render
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pure: bool,
+    /// Refs declared with useRef, usable as Ref(name) prop values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refs: Vec<String>,
+    /// State declared with useState, read as Var(name) and changed by Set and Toggle handlers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<StateDecl>,
+    /// Props the generated component accepts, readable in the tree as Var(name)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub props: Vec<PropDecl>,
+    /// Auxiliary components emitted above the view and usable from its tree via ComponentRef
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<LocalComponent>,
+    /// Name of a base view whose tree this view reuses; see ViewProto::resolve_extends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Replacement children for the base view's Region elements, by region name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub regions: HashMap<String, Vec<Box<Element>>>,
+    /// Optional when extending a base view
+    #[serde(default = "empty_tree")]
+    pub tree: Box<Element>,
+}
+
+fn empty_tree() -> Box<Element> {
+    Box::new(Element::Fragment { children: Vec::new() })
+}
+
+/// A sub-component declared inside a view proto
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LocalComponent {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub props: Vec<PropDecl>,
     pub tree: Box<Element>,
 }
 
+/// A piece of view state: `const [open, setOpen] = useState(false);`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StateDecl {
+    pub name: String,
+    pub initial: PropValue,
+}
+
+impl StateDecl {
+    /// `open` -> `setOpen`
+    pub fn setter(&self) -> String {
+        state_setter(&self.name)
+    }
+}
+
+pub(crate) fn state_setter(state: &str) -> String {
+    format!("set{}", capitalize(state))
+}
+
+/// A prop accepted by a generated component
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PropDecl {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prop_type: Option<PropType>,
+    /// Marked `.isRequired` in emitted propTypes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    /// Default used when the caller doesn't pass the prop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<PropValue>,
+}
+
 impl ViewProto {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "ViewProto", path = %path.as_ref().display()))
+    )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let options = ron::Options::default()
@@ -164,31 +907,827 @@ impl ViewProto {
         let proto: ViewProto = options.from_str(&content)?;
         Ok(proto)
     }
+
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
+    /// The element with editor id `id`, from the main tree, local components or region overrides
+    pub fn find_element(&self, id: &str) -> Option<&Element> {
+        self.element_trees().into_iter().find_map(|tree| find_by_id(tree, id))
+    }
+
+    /// find_element for editing in place; to_ron_string then writes the change back
+    pub fn find_element_mut(&mut self, id: &str) -> Option<&mut Element> {
+        self.element_trees_mut().into_iter().find_map(|tree| find_by_id_mut(tree, id))
+    }
+
+    /// Every editor id in the view, in tree order; ids used twice appear twice
+    pub fn element_ids(&self) -> Vec<&str> {
+        fn collect<'a>(element: &'a Element, ids: &mut Vec<&'a str>) {
+            ids.extend(element.id());
+            for child in element.child_elements() {
+                collect(child, ids);
+            }
+        }
+        let mut ids = Vec::new();
+        for tree in self.element_trees() {
+            collect(tree, &mut ids);
+        }
+        ids
+    }
+
+    /// Give every Node, ComponentRef, Link and Media without an id a new one (`e1`, `e2`, ...),
+    /// returning how many were assigned. Existing ids are kept, so saving the result keeps
+    /// them stable across edits.
+    pub fn assign_element_ids(&mut self) -> usize {
+        fn assign(element: &mut Element, taken: &mut HashSet<String>, next: &mut usize) -> usize {
+            let mut assigned = 0;
+            if let Some(id @ None) = element.id_mut() {
+                while taken.contains(&format!("e{}", next)) {
+                    *next += 1;
+                }
+                let new_id = format!("e{}", next);
+                taken.insert(new_id.clone());
+                *id = Some(new_id);
+                assigned += 1;
+            }
+            for child in element.child_elements_mut() {
+                assigned += assign(child, taken, next);
+            }
+            assigned
+        }
+        let mut taken: HashSet<String> = self.element_ids().into_iter().map(str::to_string).collect();
+        let mut next = 1;
+        self.element_trees_mut().into_iter().map(|tree| assign(tree, &mut taken, &mut next)).sum()
+    }
+
+    fn element_trees(&self) -> Vec<&Element> {
+        let mut region_names: Vec<&String> = self.regions.keys().collect();
+        region_names.sort();
+        std::iter::once(self.tree.as_ref())
+            .chain(self.components.iter().map(|c| c.tree.as_ref()))
+            .chain(region_names.into_iter().flat_map(|name| self.regions[name].iter().map(|e| e.as_ref())))
+            .collect()
+    }
+
+    fn element_trees_mut(&mut self) -> Vec<&mut Element> {
+        let mut regions: Vec<(&String, &mut Vec<Box<Element>>)> = self.regions.iter_mut().collect();
+        regions.sort_by_key(|(name, _)| *name);
+        std::iter::once(self.tree.as_mut())
+            .chain(self.components.iter_mut().map(|c| c.tree.as_mut()))
+            .chain(regions.into_iter().flat_map(|(_, elements)| elements.iter_mut().map(|e| e.as_mut())))
+            .collect()
+    }
+
+    /// Follow the `extends` chain and return a standalone view: the base tree with this
+    /// view's regions filled in. `find_base` looks up a view by name.
+    pub fn resolve_extends<F>(&self, find_base: F) -> Result<ViewProto, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<ViewProto>,
+    {
+        self.resolve_extends_with_depth(find_base, DEFAULT_MAX_EXPANSION_DEPTH)
+    }
+
+    /// resolve_extends, failing once the chain of base views is longer than `max_depth`
+    pub fn resolve_extends_with_depth<F>(&self, find_base: F, max_depth: usize) -> Result<ViewProto, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<ViewProto>,
+    {
+        let mut resolved = self.clone();
+        let mut chain = ExpansionStack::new("view inheritance", max_depth);
+        chain.enter(&self.name)?;
+
+        while let Some(base_name) = resolved.extends.take() {
+            chain.enter(&base_name)?;
+            let base = find_base(&base_name)
+                .ok_or_else(|| format!("view `{}` extends unknown view `{}`", resolved.name, base_name))?;
+            resolved = resolved.merge_into_base(base)?;
+        }
+
+        Ok(resolved)
+    }
+
+    /// Combine this (child) view with its base. The child keeps its name; its imports, refs,
+    /// states, props, and components are added to the base's, replacing same-named entries.
+    fn merge_into_base(self, base: ViewProto) -> Result<ViewProto, Box<dyn std::error::Error>> {
+        let mut tree = base.tree;
+        let mut filled = Vec::new();
+        fill_regions(&mut tree, &self, &mut filled);
+
+        let mut unknown: Vec<&String> = self.regions.keys().filter(|name| !filled.contains(*name)).collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(format!(
+                "view `{}` overrides regions not found in `{}`: {}",
+                self.name,
+                base.name,
+                unknown.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", ")
+            )
+            .into());
+        }
+
+        let mut imports = base.imports;
+        imports.retain(|i| !self.imports.iter().any(|own| own.name == i.name));
+        imports.extend(self.imports);
+
+        let mut refs = base.refs;
+        refs.retain(|r| !self.refs.contains(r));
+        refs.extend(self.refs);
+
+        let mut states = base.states;
+        states.retain(|s| !self.states.iter().any(|own| own.name == s.name));
+        states.extend(self.states);
+
+        let mut props = base.props;
+        props.retain(|p| !self.props.iter().any(|own| own.name == p.name));
+        props.extend(self.props);
+
+        let mut components = base.components;
+        components.retain(|c| !self.components.iter().any(|own| own.name == c.name));
+        components.extend(self.components);
+
+        Ok(ViewProto {
+            name: self.name,
+            imports,
+            observer: self.observer || base.observer,
+            forward_ref: self.forward_ref || base.forward_ref,
+            memo: self.memo || base.memo,
+            pure: self.pure || base.pure,
+            refs,
+            states,
+            props,
+            components,
+            extends: base.extends,
+            regions: base.regions,
+            tree,
+        })
+    }
+}
+
+fn find_by_id<'a>(element: &'a Element, id: &str) -> Option<&'a Element> {
+    if element.id() == Some(id) {
+        return Some(element);
+    }
+    element.child_elements().into_iter().find_map(|child| find_by_id(child, id))
+}
+
+fn find_by_id_mut<'a>(element: &'a mut Element, id: &str) -> Option<&'a mut Element> {
+    if element.id() == Some(id) {
+        return Some(element);
+    }
+    element.child_elements_mut().into_iter().find_map(|child| find_by_id_mut(child, id))
+}
+
+/// Replace the children of Region elements that have an override, recording which were filled.
+/// The Region itself is kept so a further extending view can override it again.
+fn fill_regions(element: &mut Element, child_view: &ViewProto, filled: &mut Vec<String>) {
+    if let Element::Region { name, children } = element
+        && let Some(replacement) = child_view.regions.get(name)
+    {
+        *children = replacement.clone();
+        filled.push(name.clone());
+        return;
+    }
+    for child in element.child_elements_mut() {
+        fill_regions(child, child_view, filled);
+    }
+}
+
+/// Theme tokens referenced from protos
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ThemeDefs {
+    /// Color tokens: "primary" is referenced as Color("color.primary")
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub colors: HashMap<String, String>,
+}
+
+impl ThemeDefs {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "ThemeDefs", path = %path.as_ref().display()))
+    )]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let defs: ThemeDefs = options.from_str(&content)?;
+        Ok(defs)
+    }
+
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
+    /// Resolve a Color value: `color.*` tokens are looked up, anything else is a CSS literal
+    pub fn resolve_color(&self, value: &str) -> Option<String> {
+        match value.strip_prefix("color.") {
+            Some(token) => self.colors.get(token).cloned(),
+            None => Some(value.to_string()),
+        }
+    }
+}
+
+/// Import path aliases: `"@components": "src/components"` lets protos import `@components/Button`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AliasDefs {
+    /// Alias -> directory relative to the project root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl AliasDefs {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "AliasDefs", path = %path.as_ref().display()))
+    )]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let defs: AliasDefs = options.from_str(&content)?;
+        Ok(defs)
+    }
+
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(to_ron_string(self)?)
+    }
+
+    /// The `"@" -> "src"` alias generated projects are set up with
+    pub fn project_default() -> Self {
+        Self {
+            aliases: BTreeMap::from([("@".to_string(), "src".to_string())]),
+        }
+    }
+
+    /// Filesystem path of an import path, relative to the project root: `@/assets/a.png` -> `src/assets/a.png`
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = normalize_path(path);
+        let path = path.as_str();
+        match self.alias_for(path) {
+            Some(alias) => {
+                let dir = self.aliases[alias].trim_start_matches("./").trim_end_matches('/');
+                let rest = path[alias.len()..].trim_start_matches('/');
+                Path::new(dir).join(rest)
+            }
+            None => PathBuf::from(path.trim_start_matches("./")),
+        }
+    }
+
+    /// File of a local asset path, relative to the project root: root paths (`/logo.png`) are
+    /// served from `public/`, aliased paths resolve through the aliases and other relative
+    /// paths are read from `src/`
+    pub fn asset_file(&self, path: &str) -> PathBuf {
+        let path = normalize_path(path);
+        if let Some(rest) = path.strip_prefix('/') {
+            return Path::new("public").join(rest);
+        }
+        match self.alias_for(&path) {
+            Some(_) => self.resolve_path(&path),
+            None => Path::new("src").join(path.trim_start_matches("./")),
+        }
+    }
+
+    /// The declared alias an import path starts with, if any
+    pub fn alias_for(&self, path: &str) -> Option<&str> {
+        self.aliases
+            .keys()
+            .find(|alias| path == alias.as_str() || path.starts_with(&format!("{}/", alias)))
+            .map(String::as_str)
+    }
+
+    /// jsconfig.json / tsconfig.json `paths` so editors and tsc resolve the aliases
+    pub fn jsconfig_json(&self) -> String {
+        let paths: serde_json::Map<String, serde_json::Value> = self
+            .aliases
+            .iter()
+            .map(|(alias, dir)| {
+                let dir = dir.trim_start_matches("./").trim_end_matches('/');
+                (format!("{}/*", alias), serde_json::json!([format!("{}/*", dir)]))
+            })
+            .collect();
+        let config = serde_json::json!({
+            "compilerOptions": {
+                "baseUrl": ".",
+                "paths": paths,
+            }
+        });
+        serde_json::to_string_pretty(&config).unwrap_or_default() + "\n"
+    }
 }
 
-/// A content value - can be a string, a record (key-value map), or a list
+/// A content value - can be a string, a record (key-value map), a list or rich text
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ContentValue {
     Str(String),
-    Record(HashMap<String, String>),
+    Record(#[serde(serialize_with = "crate::ron_format::sorted_map")] HashMap<String, String>),
     List(Vec<ContentValue>),
+    /// Structured text as exported from a headless CMS (portable-text style)
+    RichText(Vec<RichBlock>),
+}
+
+/// Levels of references resolve_relations follows, so cycles stop
+pub(crate) const MAX_RELATION_DEPTH: usize = 3;
+
+/// One block of rich text
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum RichBlock {
+    /// A paragraph, heading, quote or list item made of styled spans
+    Block {
+        #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
+        style: BlockStyle,
+        /// Set on list items; consecutive items of the same kind form one list
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        list: Option<ListKind>,
+        /// List nesting depth, starting at 1
+        #[serde(default = "default_list_level")]
+        level: usize,
+        children: Vec<RichSpan>,
+        /// Annotations spans point at through their marks
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        mark_defs: Vec<MarkDef>,
+    },
+    /// An image asset from AssetDefs
+    Image {
+        asset: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alt: Option<String>,
+    },
+}
+
+fn default_list_level() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlockStyle {
+    #[default]
+    Normal,
+    H1,
+    H2,
+    H3,
+    H4,
+    H5,
+    H6,
+    Blockquote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ListKind {
+    Bullet,
+    Number,
+}
+
+/// A run of text. Marks are decorators ("strong", "em", "code", "underline", "strike-through")
+/// or keys of the block's mark_defs.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RichSpan {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<String>,
+}
+
+/// A link annotation
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MarkDef {
+    pub key: String,
+    pub href: String,
+    /// Open in a new tab
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blank: bool,
 }
 
 /// Collection of content definitions
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ContentDefs {
+    #[serde(serialize_with = "crate::ron_format::sorted_map")]
     pub content: HashMap<String, ContentValue>,
+    /// Settings for List content, by content key
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "crate::ron_format::sorted_map")]
+    pub collections: HashMap<String, CollectionConfig>,
+}
+
+/// Settings for a List content value
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CollectionConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<PaginationConfig>,
+    /// Record fields holding markdown; a `text` prop reading one renders it as elements
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markdown: Vec<String>,
+    /// Fields the collection's records must have, by field name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schema: BTreeMap<String, FieldSchema>,
+    /// Field other collections' Ref fields match against; defaults to `id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Fields computed from other fields when the content loads, by field name.
+    /// Records that already set the field keep their value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub derived: BTreeMap<String, DerivedField>,
+    /// Publish the collection as RSS and Atom feeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed: Option<FeedConfig>,
+}
+
+impl CollectionConfig {
+    pub fn key_field(&self) -> &str {
+        self.key.as_deref().unwrap_or("id")
+    }
+}
+
+/// How a derived field is computed from a source field
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum DerivedField {
+    /// "Hello, World!" -> "hello-world"
+    Slugify(String),
+    /// First characters of the field, cut at a word boundary; markdown fields are read as plain text
+    Excerpt(String, usize),
+    /// Number formatted as a price, e.g. "1234.5" -> "$1,234.50"
+    Price { field: String, currency: String },
+    /// Date formatted with a pattern like "MMM D, YYYY"
+    Date { field: String, format: String },
+}
+
+impl DerivedField {
+    pub fn source(&self) -> &str {
+        match self {
+            DerivedField::Slugify(field) | DerivedField::Excerpt(field, _) => field,
+            DerivedField::Price { field, .. } | DerivedField::Date { field, .. } => field,
+        }
+    }
+
+    /// None when the source value can't be converted
+    fn compute(&self, value: &str, markdown: bool) -> Option<String> {
+        match self {
+            DerivedField::Slugify(_) => Some(slugify(value)),
+            DerivedField::Excerpt(_, length) => {
+                let text = if markdown { markdown_plain_text(value) } else { value.to_string() };
+                Some(excerpt(&text, *length))
+            }
+            DerivedField::Price { currency, .. } => format_price(value, currency),
+            DerivedField::Date { format, .. } => format_date(value, format),
+        }
+    }
+}
+
+fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+pub(crate) fn excerpt(text: &str, length: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= length {
+        return text;
+    }
+    let mut cut: String = text.chars().take(length).collect();
+    // Drop the partial word unless the cut landed on a boundary
+    if text.chars().nth(length).is_some_and(|c| c != ' ')
+        && let Some(space) = cut.rfind(' ')
+    {
+        cut.truncate(space);
+    }
+    format!("{}…", cut.trim_end_matches([' ', ',', '.', ';', ':']))
+}
+
+fn format_price(value: &str, currency: &str) -> Option<String> {
+    let amount: f64 = value.trim().parse().ok()?;
+    let decimals = if currency == "JPY" { 0 } else { 2 };
+    let fixed = format!("{:.*}", decimals, amount.abs());
+    let (whole, fraction) = fixed.split_once('.').map_or((fixed.as_str(), None), |(w, f)| (w, Some(f)));
+
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped = format!("{}.{}", grouped, fraction);
+    }
+
+    let sign = if amount < 0.0 { "-" } else { "" };
+    let symbol = match currency {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" => "¥",
+        _ => return Some(format!("{}{} {}", sign, grouped, currency)),
+    };
+    Some(format!("{}{}{}", sign, symbol, grouped))
+}
+
+/// Type of one record field. Fields are required unless marked optional.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FieldSchema {
+    #[serde(default, skip_serializing_if = "crate::ron_format::is_default")]
+    pub kind: FieldKind,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub enum FieldKind {
+    #[default]
+    String,
+    Number,
+    Bool,
+    /// ISO 8601 date or date-time
+    Date,
+    /// Absolute http(s) URL or root-relative path
+    Url,
+    Enum(Vec<String>),
+    /// Key of a record in another collection; its fields read as `field.other_field`
+    Ref(String),
+}
+
+impl FieldKind {
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            FieldKind::String => true,
+            FieldKind::Number => value.parse::<f64>().is_ok(),
+            FieldKind::Bool => matches!(value, "true" | "false"),
+            FieldKind::Date => is_iso_date(value),
+            FieldKind::Url => ["http://", "https://", "/"].iter().any(|prefix| value.starts_with(prefix)),
+            FieldKind::Enum(allowed) => allowed.iter().any(|a| a == value),
+            // Checked against the target collection by ContentDefs::validate
+            FieldKind::Ref(_) => true,
+        }
+    }
+}
+
+/// Splits a collection across routes: page 1 stays at the route's url, later pages use `url`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PaginationConfig {
+    pub page_size: usize,
+    /// Url pattern for pages after the first, with `:n` for the page number, e.g. "/blog/page/:n"
+    pub url: String,
+}
+
+/// Feed settings for a collection; record urls come from the route binding the collection
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FeedConfig {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Feed author; Atom readers show it on entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Directory under public/ for rss.xml and atom.xml; defaults to the collection name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Page the feed describes, e.g. "/blog"; defaults to the site root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(default = "default_title_field")]
+    pub title_field: String,
+    /// ISO 8601 date field; newest records come first
+    #[serde(default = "default_date_field")]
+    pub date_field: String,
+    #[serde(default = "default_summary_field")]
+    pub summary_field: String,
+    /// Most recent records included
+    #[serde(default = "default_feed_limit")]
+    pub limit: usize,
+}
+
+fn default_title_field() -> String {
+    "title".to_string()
+}
+
+fn default_date_field() -> String {
+    "date".to_string()
+}
+
+fn default_summary_field() -> String {
+    "summary".to_string()
+}
+
+fn default_feed_limit() -> usize {
+    20
 }
 
 impl ContentDefs {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "load", skip_all, err, fields(kind = "ContentDefs", path = %path.as_ref().display()))
+    )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let options = ron::Options::default()
             .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
-        let defs: ContentDefs = options.from_str(&content)?;
+        let mut defs: ContentDefs = options.from_str(&content)?;
+        defs.compute_derived();
+        let errors: Vec<String> = defs
+            .validate()
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.to_string())
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+        defs.resolve_relations();
         Ok(defs)
     }
 
+    /// Pretty RON with sorted maps and defaults left out, as from_file reads it. Values
+    /// from_file computes (related `field.sub` copies, derived fields matching their
+    /// computed value) are left out too.
+    pub fn to_ron_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut defs = self.clone();
+        for (name, config) in &self.collections {
+            let Some(ContentValue::List(list)) = defs.content.get_mut(name) else {
+                continue;
+            };
+            let relations: Vec<String> = config
+                .schema
+                .iter()
+                .filter(|(_, schema)| matches!(schema.kind, FieldKind::Ref(_)))
+                .map(|(field, _)| format!("{}.", field))
+                .collect();
+            for item in list {
+                let ContentValue::Record(record) = item else {
+                    continue;
+                };
+                record.retain(|key, _| !relations.iter().any(|prefix| key.starts_with(prefix.as_str())));
+                for (field, derived) in &config.derived {
+                    let markdown = config.markdown.iter().any(|f| f == derived.source());
+                    let computed = record.get(derived.source()).and_then(|v| derived.compute(v, markdown));
+                    if computed.is_some() && record.get(field) == computed.as_ref() {
+                        record.remove(field);
+                    }
+                }
+            }
+        }
+        Ok(to_ron_string(&defs)?)
+    }
+
+    /// Fill in each collection's derived fields on records that don't set them
+    pub fn compute_derived(&mut self) {
+        for (name, config) in &self.collections {
+            if config.derived.is_empty() {
+                continue;
+            }
+            let Some(ContentValue::List(list)) = self.content.get_mut(name) else {
+                continue;
+            };
+            for item in list {
+                let ContentValue::Record(record) = item else {
+                    continue;
+                };
+                for (field, derived) in &config.derived {
+                    if record.get(field).is_some_and(|value| !value.is_empty()) {
+                        continue;
+                    }
+                    let markdown = config.markdown.iter().any(|f| f == derived.source());
+                    if let Some(value) = record.get(derived.source()).and_then(|v| derived.compute(v, markdown)) {
+                        record.insert(field.clone(), value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copy the fields of referenced records into the referencing record under dotted names:
+    /// `author: "jane"` gains `author.name`, `author.avatar`, ... Refs inside the referenced
+    /// record are followed too, up to a few levels so cycles stop.
+    pub fn resolve_relations(&mut self) {
+        let resolved: Vec<(String, Vec<HashMap<String, String>>)> = self
+            .collections
+            .keys()
+            .filter_map(|name| {
+                let list = self.get_list(name)?;
+                let records = list
+                    .iter()
+                    .map(|item| match item {
+                        ContentValue::Record(record) => self.resolve_record(name, record, MAX_RELATION_DEPTH),
+                        _ => HashMap::new(),
+                    })
+                    .collect();
+                Some((name.clone(), records))
+            })
+            .collect();
+
+        for (name, records) in resolved {
+            if let Some(ContentValue::List(list)) = self.content.get_mut(&name) {
+                for (item, resolved) in list.iter_mut().zip(records) {
+                    if let ContentValue::Record(record) = item {
+                        *record = resolved;
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve_record(&self, collection: &str, record: &HashMap<String, String>, depth: usize) -> HashMap<String, String> {
+        let mut resolved = record.clone();
+        let Some(config) = self.collections.get(collection) else {
+            return resolved;
+        };
+        if depth == 0 {
+            return resolved;
+        }
+        for (field, schema) in &config.schema {
+            let FieldKind::Ref(target) = &schema.kind else {
+                continue;
+            };
+            let Some(key) = record.get(field) else {
+                continue;
+            };
+            if let Some(referenced) = self.find_record(target, key) {
+                for (name, value) in self.resolve_record(target, referenced, depth - 1) {
+                    resolved.insert(format!("{}.{}", field, name), value);
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Record of `collection` whose key field equals `key`
+    pub fn find_record(&self, collection: &str, key: &str) -> Option<&HashMap<String, String>> {
+        let key_field = self.collections.get(collection).map(|c| c.key_field()).unwrap_or("id");
+        self.get_list(collection)?.iter().find_map(|item| match item {
+            ContentValue::Record(record) if record.get(key_field).is_some_and(|k| k == key) => Some(record),
+            _ => None,
+        })
+    }
+
+    /// Check every record of a collection with a schema; paths are `collection[index]`
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "validate", skip_all, fields(kind = "ContentDefs")))]
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut names: Vec<&String> = self.collections.keys().collect();
+        names.sort();
+        for name in names {
+            let config = &self.collections[name];
+            let schema = &config.schema;
+            if schema.is_empty() {
+                continue;
+            }
+            let Some(list) = self.get_list(name) else {
+                diagnostics.push(Diagnostic::error(name, "collection with a schema is not a content list"));
+                continue;
+            };
+            for (i, item) in list.iter().enumerate() {
+                let path = format!("{}[{}]", name, i);
+                let ContentValue::Record(record) = item else {
+                    diagnostics.push(Diagnostic::error(&path, "item is not a record"));
+                    continue;
+                };
+                for (field, field_schema) in schema {
+                    match record.get(field).filter(|value| !value.is_empty()) {
+                        None if !field_schema.optional => {
+                            diagnostics.push(Diagnostic::error(&path, format!("missing required field `{}`", field)));
+                        }
+                        Some(value) if !field_schema.kind.accepts(value) => diagnostics.push(Diagnostic::error(
+                            &path,
+                            format!("field `{}` expects {:?} but got \"{}\"", field, field_schema.kind, value),
+                        )),
+                        Some(value) => {
+                            if let FieldKind::Ref(target) = &field_schema.kind
+                                && self.find_record(target, value).is_none()
+                            {
+                                diagnostics.push(Diagnostic::error(
+                                    &path,
+                                    format!("field `{}` references unknown `{}` record \"{}\"", field, target, value),
+                                ));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                // Dotted fields come from resolve_relations, derived ones from compute_derived
+                let is_related = |key: &str| {
+                    key.split_once('.')
+                        .and_then(|(field, _)| schema.get(field))
+                        .is_some_and(|f| matches!(f.kind, FieldKind::Ref(_)))
+                };
+                let mut extra: Vec<&String> = record
+                    .keys()
+                    .filter(|key| !schema.contains_key(*key) && !config.derived.contains_key(*key) && !is_related(key))
+                    .collect();
+                extra.sort();
+                for field in extra {
+                    diagnostics.push(Diagnostic::warning(&path, format!("field `{}` is not in the schema", field)));
+                }
+            }
+        }
+        diagnostics
+    }
+
     pub fn get(&self, name: &str) -> Option<&ContentValue> {
         self.content.get(name)
     }
@@ -201,6 +1740,14 @@ impl ContentDefs {
         }
     }
 
+    /// Get a rich text value by name
+    pub fn get_rich_text(&self, name: &str) -> Option<&Vec<RichBlock>> {
+        match self.content.get(name) {
+            Some(ContentValue::RichText(blocks)) => Some(blocks),
+            _ => None,
+        }
+    }
+
     /// Get a list value by name
     pub fn get_list(&self, name: &str) -> Option<&Vec<ContentValue>> {
         match self.content.get(name) {
@@ -208,4 +1755,271 @@ impl ContentDefs {
             _ => None,
         }
     }
+
+    /// Markdown fields of a collection's records
+    pub fn markdown_fields(&self, name: &str) -> &[String] {
+        self.collections.get(name).map(|c| c.markdown.as_slice()).unwrap_or_default()
+    }
+
+    /// Collections published as feeds, with their feed config
+    pub fn feeds(&self) -> impl Iterator<Item = (&String, &FeedConfig)> {
+        self.collections.iter().filter_map(|(name, c)| c.feed.as_ref().map(|feed| (name, feed)))
+    }
+
+    pub fn pagination(&self, name: &str) -> Option<&PaginationConfig> {
+        self.collections.get(name)?.pagination.as_ref()
+    }
+
+    /// Number of pages a paginated list splits into; an empty list still gets one page
+    pub fn page_count(&self, name: &str) -> Option<usize> {
+        let pagination = self.pagination(name)?;
+        let len = self.get_list(name)?.len();
+        Some(len.div_ceil(pagination.page_size.max(1)).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn youtube(url: &str, privacy_enhanced: bool) -> AssetDef {
+        AssetDef {
+            name: "intro".to_string(),
+            kind: AssetKind::Youtube,
+            path: None,
+            url: Some(url.to_string()),
+            privacy_enhanced,
+            app_icon: false,
+        }
+    }
+
+    #[test]
+    fn reads_youtube_ids_from_every_url_form() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "youtube.com/embed/dQw4w9WgXcQ",
+            "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(youtube(url, false).youtube_id().as_deref(), Some("dQw4w9WgXcQ"), "{}", url);
+        }
+        assert_eq!(youtube("https://www.youtube.com/channel/UC123", false).youtube_id(), None);
+    }
+
+    #[test]
+    fn embeds_privacy_enhanced_videos_from_nocookie() {
+        let url = "https://youtu.be/abc123";
+        assert_eq!(youtube(url, false).youtube_embed_url().as_deref(), Some("https://www.youtube.com/embed/abc123"));
+        assert_eq!(youtube(url, true).youtube_embed_url().as_deref(), Some("https://www.youtube-nocookie.com/embed/abc123"));
+    }
+
+    fn ron<T: serde::de::DeserializeOwned>(source: &str) -> T {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .unwrap()
+    }
+
+    fn proto(source: &str) -> ViewProto {
+        ron(source)
+    }
+
+    fn find_base(name: &str) -> Option<ViewProto> {
+        match name {
+            "Base" => Some(proto(
+                r#"(name: "Base", refs: ["heroRef"], tree: Node(tag: "main", children: [
+                    Region(name: "hero", children: [Text("Default hero")]),
+                    Region(name: "body"),
+                ]))"#,
+            )),
+            "Loop" => Some(proto(r#"(name: "Loop", extends: "Landing")"#)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fills_the_base_view_regions() {
+        let landing = proto(r#"(name: "Landing", extends: "Base", refs: ["formRef"], regions: {"body": [Text("Sign up")]})"#);
+        let resolved = landing.resolve_extends(find_base).unwrap();
+        assert_eq!(resolved.name, "Landing");
+        assert_eq!(resolved.refs, ["heroRef", "formRef"]);
+        assert_eq!(
+            ron::to_string(&resolved.tree).unwrap(),
+            r#"Node(tag:"main",children:[Region(name:"hero",children:[Text("Default hero")]),Region(name:"body",children:[Text("Sign up")])])"#
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_regions_and_cycles() {
+        let typo = proto(r#"(name: "Landing", extends: "Base", regions: {"footer": []})"#);
+        assert_eq!(typo.resolve_extends(find_base).unwrap_err().to_string(), "view `Landing` overrides regions not found in `Base`: footer");
+
+        let cycle = proto(r#"(name: "Landing", extends: "Loop")"#);
+        assert_eq!(cycle.resolve_extends(find_base).unwrap_err().to_string(), "view inheritance cycle: Landing → Loop → Landing");
+    }
+
+    #[test]
+    fn maps_aliases_for_editors_and_the_filesystem() {
+        let aliases: AliasDefs = ron(r#"(aliases: {"@": "src", "@components": "./src/components/"})"#);
+        let config: serde_json::Value = serde_json::from_str(&aliases.jsconfig_json()).unwrap();
+        assert_eq!(config["compilerOptions"]["baseUrl"], ".");
+        assert_eq!(config["compilerOptions"]["paths"]["@components/*"], serde_json::json!(["src/components/*"]));
+        assert_eq!(config["compilerOptions"]["paths"]["@/*"], serde_json::json!(["src/*"]));
+
+        assert_eq!(aliases.resolve_path("@components/Button"), Path::new("src/components/Button"));
+        assert_eq!(aliases.asset_file("/logo.png"), Path::new("public/logo.png"));
+        assert_eq!(aliases.asset_file("./img/a.png"), Path::new("src/img/a.png"));
+        assert_eq!(aliases.alias_for("@componentsX/a"), None);
+    }
+
+    #[test]
+    fn validates_records_against_the_collection_schema() {
+        let content: ContentDefs = ron(
+            r#"(
+                content: {"products": List([
+                    Record({"name": "Mug", "price": "12.5", "status": "live", "url": "/mug"}),
+                    Record({"price": "cheap", "status": "gone", "url": "mug", "color": "red"}),
+                ])},
+                collections: {"products": (schema: {
+                    "name": (),
+                    "price": (kind: Number),
+                    "status": (kind: Enum(["live", "draft"])),
+                    "url": (kind: Url, optional: true),
+                })},
+            )"#,
+        );
+        let messages: Vec<String> = content.validate().iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "error: missing required field `name` (at products[1])",
+                "error: field `price` expects Number but got \"cheap\" (at products[1])",
+                "error: field `status` expects Enum([\"live\", \"draft\"]) but got \"gone\" (at products[1])",
+                "error: field `url` expects Url but got \"mug\" (at products[1])",
+                "warning: field `color` is not in the schema (at products[1])",
+            ]
+        );
+    }
+
+    #[test]
+    fn copies_referenced_records_under_dotted_fields() {
+        let mut content: ContentDefs = ron(
+            r#"(
+                content: {
+                    "posts": List([Record({"title": "Hi", "author": "jane"}), Record({"title": "Lost", "author": "ghost"})]),
+                    "authors": List([Record({"handle": "jane", "name": "Jane", "team": "core"})]),
+                    "teams": List([Record({"id": "core", "label": "Core team"})]),
+                },
+                collections: {
+                    "posts": (schema: {"title": (), "author": (kind: Ref("authors"))}),
+                    "authors": (key: "handle", schema: {"handle": (), "name": (), "team": (kind: Ref("teams"))}),
+                    "teams": (),
+                },
+            )"#,
+        );
+        let messages: Vec<String> = content.validate().into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["field `author` references unknown `authors` record \"ghost\""]);
+
+        content.resolve_relations();
+        let ContentValue::Record(post) = &content.get_list("posts").unwrap()[0] else { panic!() };
+        assert_eq!(post["author.name"], "Jane");
+        assert_eq!(post["author.team.label"], "Core team");
+        assert!(content.validate().iter().all(|d| !d.message.contains("not in the schema")));
+    }
+
+    #[test]
+    fn computes_derived_fields_records_leave_unset() {
+        let mut content: ContentDefs = ron(
+            r#"(
+                content: {"products": List([
+                    Record({"name": "Hello, World!", "body": "**Big** news about our new mug today", "price": "1234.5", "added": "2024-03-09"}),
+                    Record({"name": "Kept", "slug": "custom", "body": "Short", "price": "-5", "added": "soon"}),
+                ])},
+                collections: {"products": (markdown: ["body"], derived: {
+                    "slug": Slugify("name"),
+                    "summary": Excerpt("body", 20),
+                    "cost": Price(field: "price", currency: "USD"),
+                    "added_on": Date(field: "added", format: "MMM D, YYYY"),
+                })},
+            )"#,
+        );
+        content.compute_derived();
+        let list = content.get_list("products").unwrap();
+        let (ContentValue::Record(first), ContentValue::Record(second)) = (&list[0], &list[1]) else { panic!() };
+        assert_eq!(first["slug"], "hello-world");
+        assert_eq!(first["summary"], "Big news about our…");
+        assert_eq!(first["cost"], "$1,234.50");
+        assert_eq!(first["added_on"], "Mar 9, 2024");
+        assert_eq!(second["slug"], "custom");
+        assert_eq!(second["summary"], "Short");
+        assert_eq!(second["cost"], "-$5.00");
+        assert!(!second.contains_key("added_on"));
+        assert_eq!(format_price("1000", "JPY").as_deref(), Some("¥1,000"));
+        assert_eq!(format_price("3", "CHF").as_deref(), Some("3.00 CHF"));
+    }
+
+
+    #[test]
+    fn writes_style_maps_in_key_order() {
+        let source = r#"(name: "Hero", tree: Node(tag: "div", props: {"style": Style({"zIndex": Num(2), "color": Str("red"), "margin": Num(0)})}))"#;
+        let ron = proto(source).to_ron_string().unwrap();
+        let color = ron.find("\"color\"").unwrap();
+        let margin = ron.find("\"margin\"").unwrap();
+        let z_index = ron.find("\"zIndex\"").unwrap();
+        assert!(color < margin && margin < z_index, "{}", ron);
+        assert_eq!(proto(&ron).to_ron_string().unwrap(), ron);
+    }
+
+
+    #[test]
+    fn inlines_small_local_images_as_data_uris() {
+        let root = std::env::temp_dir().join(format!("degenproto-inline-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/assets")).unwrap();
+        fs::write(root.join("src/assets/icon.svg"), "<svg/>").unwrap();
+        fs::write(root.join("src/assets/hero.png"), vec![0u8; 4096]).unwrap();
+        fs::write(root.join("src/assets/notes.txt"), "x").unwrap();
+        let assets: AssetDefs = ron(
+            r#"(assets: [
+                (name: "icon", kind: Image, path: "./assets/icon.svg"),
+                (name: "hero", kind: Image, path: "./assets/hero.png"),
+                (name: "notes", kind: Image, path: "./assets/notes.txt"),
+                (name: "remote", kind: Image, path: "https://example.com/a.png"),
+                (name: "missing", kind: Image, path: "./assets/missing.png"),
+            ])"#,
+        );
+        let (inlined, diagnostics) = assets.inline_images(&root, &AliasDefs::default(), 1024);
+        fs::remove_dir_all(&root).unwrap();
+
+        let path = |name| inlined.get(name).unwrap().path.clone().unwrap();
+        assert_eq!(path("icon"), "data:image/svg+xml;base64,PHN2Zy8+");
+        assert_eq!(path("hero"), "./assets/hero.png");
+        assert_eq!(path("notes"), "./assets/notes.txt");
+        assert_eq!(path("remote"), "https://example.com/a.png");
+        assert_eq!(path("missing"), "./assets/missing.png");
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].path, "assets > missing");
+        assert!(diagnostics[0].message.ends_with("it is left as a file"), "{}", diagnostics[0]);
+    }
+
+
+    #[test]
+    fn finds_and_assigns_element_ids() {
+        let mut view = proto(
+            r#"(name: "Home", tree: Node(tag: "main", id: "e1", children: [
+                Node(tag: "h1", children: [Text("Hi")]),
+                Fragment(children: [ComponentRef(component: "Card", id: "card")]),
+            ]))"#,
+        );
+        assert_eq!(view.element_ids(), ["e1", "card"]);
+        assert_eq!(view.assign_element_ids(), 1);
+        assert_eq!(view.element_ids(), ["e1", "e2", "card"]);
+        assert_eq!(view.assign_element_ids(), 0);
+
+        let Some(Element::Node { tag, .. }) = view.find_element_mut("e2") else { panic!() };
+        *tag = "h2".to_string();
+        assert!(matches!(view.find_element("e2"), Some(Element::Node { tag, .. }) if tag == "h2"));
+        assert!(view.find_element("missing").is_none());
+        assert!(view.to_ron_string().unwrap().contains("tag: \"h2\",\n"));
+    }
 }
@@ -0,0 +1,187 @@
+use crate::names::component_ident;
+use crate::view_jsx::{js_string, ViewJsx};
+use crate::view_proto::{Element, PropValue};
+use std::fmt;
+
+/// Generates a baseline `*.test.jsx` for a view (Vitest + React Testing Library)
+pub struct ViewTestJsx<'a> {
+    pub view: &'a ViewJsx,
+    // Module path the test imports the view from
+    pub import_path: String,
+}
+
+impl<'a> ViewTestJsx<'a> {
+    pub fn new(view: &'a ViewJsx) -> Self {
+        Self {
+            view,
+            import_path: format!("./{}", view.proto.name),
+        }
+    }
+
+    pub fn with_import_path(mut self, import_path: impl Into<String>) -> Self {
+        self.import_path = import_path.into();
+        self
+    }
+
+    /// File name the test is expected to be written to, next to the view
+    pub fn file_name(&self) -> String {
+        format!("{}.test.jsx", self.view.proto.name)
+    }
+
+    /// Static text that is always rendered by the view, in tree order
+    pub fn key_texts(&self) -> Vec<String> {
+        let mut texts = Vec::new();
+        self.collect_texts(&self.view.proto.tree, &mut texts);
+        texts
+    }
+
+    fn collect_texts(&self, element: &Element, texts: &mut Vec<String>) {
+        let text = match element {
+            Element::Text(text) => Some(text.clone()),
+            Element::Node { props, .. } => match props.get("text") {
+                Some(PropValue::Str(text)) => Some(text.clone()),
+                Some(PropValue::Content(key)) => self.view.content_defs.get_str(key).cloned(),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(text) = text {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() && !texts.contains(&text) {
+                texts.push(text);
+            }
+        }
+
+        match element {
+            // Only descend where children are rendered unconditionally and in place
+            Element::Node { children, .. }
+            | Element::Animated { children, .. }
+            | Element::Link { children, .. }
+            | Element::Fragment { children }
+            | Element::Region { children, .. }
+            | Element::Suspense { children, .. }
+            | Element::ErrorBoundary { children, .. } => {
+                for child in children {
+                    self.collect_texts(child, texts);
+                }
+            }
+            Element::ComponentRef { component, children, .. }
+                if self.view.proto.components.iter().any(|c| &c.name == component) =>
+            {
+                for child in children {
+                    self.collect_texts(child, texts);
+                }
+            }
+            Element::Experiment { name, variants } => {
+                if let Some(variant) = self.view.pinned_variant(name, variants) {
+                    self.collect_texts(&variant.template, texts);
+                }
+            }
+            Element::Flag { flag, children } if self.view.flag_enabled(flag) => {
+                for child in children {
+                    self.collect_texts(child, texts);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn needs_router(&self) -> bool {
+        !self.view.options.static_links && contains_link(&self.view.proto.tree)
+    }
+}
+
+fn contains_link(element: &Element) -> bool {
+    matches!(element, Element::Link { .. }) || element.child_elements().into_iter().any(contains_link)
+}
+
+impl fmt::Display for ViewTestJsx<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = component_ident(&self.view.proto.name);
+        let needs_router = self.needs_router();
+
+        writeln!(f, "import {{ describe, it, expect }} from 'vitest';")?;
+        writeln!(f, "import {{ render }} from '@testing-library/react';")?;
+        if needs_router {
+            writeln!(f, "import {{ MemoryRouter }} from 'react-router-dom';")?;
+        }
+        writeln!(f, "import {} from '{}';", name, self.import_path)?;
+        writeln!(f)?;
+
+        let element = if needs_router {
+            format!("<MemoryRouter><{} /></MemoryRouter>", name)
+        } else {
+            format!("<{} />", name)
+        };
+        writeln!(f, "function renderView() {{")?;
+        writeln!(f, "  return render({});", element)?;
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+
+        writeln!(f, "describe('{}', () => {{", name)?;
+        writeln!(f, "  it('renders without crashing', () => {{")?;
+        writeln!(f, "    renderView();")?;
+        writeln!(f, "  }});")?;
+
+        // Translated text depends on the loaded locale, so only markup is checked in i18n mode
+        let texts = if self.view.options.i18n { Vec::new() } else { self.key_texts() };
+        if !texts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "  it('renders key content', () => {{")?;
+            writeln!(f, "    const {{ container }} = renderView();")?;
+            for text in &texts {
+                writeln!(f, "    expect(container.textContent).toContain({});", js_string(text))?;
+            }
+            writeln!(f, "  }});")?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "  it('matches snapshot', () => {{")?;
+        writeln!(f, "    const {{ container }} = renderView();")?;
+        writeln!(f, "    expect(container).toMatchSnapshot();")?;
+        writeln!(f, "  }});")?;
+        writeln!(f, "}});")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(proto: &str) -> ViewJsx {
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        ViewJsx::new(
+            options.from_str(proto).unwrap(),
+            options.from_str("(components: [])").unwrap(),
+            options.from_str("(assets: [])").unwrap(),
+            options.from_str(r#"(content: {"hero.title": Str("Welcome  home")})"#).unwrap(),
+        )
+    }
+
+    #[test]
+    fn checks_only_unconditional_text() {
+        let view = view(
+            r#"(name: "Home", tree: Node(tag: "main", children: [
+                Node(tag: "h1", props: {"text": Content("hero.title")}),
+                Text("Always"),
+                Show(condition: Var("open"), children: [Text("Sometimes")]),
+                Text("Always"),
+            ]))"#,
+        );
+        let test = ViewTestJsx::new(&view);
+        assert_eq!(test.file_name(), "Home.test.jsx");
+        assert_eq!(test.key_texts(), ["Welcome home", "Always"]);
+        let source = test.to_string();
+        assert!(source.contains("import Home from './Home';\n"), "{}", source);
+        assert!(source.contains("    expect(container.textContent).toContain(\"Welcome home\");\n"), "{}", source);
+        assert!(!source.contains("MemoryRouter"), "{}", source);
+    }
+
+    #[test]
+    fn renders_views_with_links_inside_a_router() {
+        let view = view(r#"(name: "about page", tree: Link(route: "home", children: [Text("Back")]))"#);
+        let source = ViewTestJsx::new(&view).with_import_path("../views/AboutPage").to_string();
+        assert!(source.contains("import AboutPage from '../views/AboutPage';\n"), "{}", source);
+        assert!(source.contains("  return render(<MemoryRouter><AboutPage /></MemoryRouter>);\n"), "{}", source);
+    }
+}
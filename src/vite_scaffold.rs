@@ -0,0 +1,337 @@
+use crate::output::{html_escape, GeneratedFile};
+use crate::paths::{module_specifier, relative_specifier};
+use crate::{AliasDefs, AnalyticsConfig, FaviconScaffold, PackageJson, PwaScaffold, SeoConfig, ThemeSet};
+use serde_json::{json, Value};
+
+const VITE_DEV_DEPENDENCIES: &[(&str, &str)] = &[("@vitejs/plugin-react", "^4.3.1"), ("vite", "^5.4.0")];
+
+/// Runnable Vite + React project files around the generated sources.
+/// Expects the router at `src/router/index.jsx` unless told otherwise.
+#[derive(Debug, Clone)]
+pub struct ViteScaffold {
+    // npm package name, also used as the page title
+    pub name: String,
+    pub package: PackageJson,
+    // Aliases for vite.config.js and jsconfig.json; starts with "@" -> "src"
+    pub aliases: AliasDefs,
+    // Locales loaded by src/i18n.js when the views use react-i18next; the first is the fallback
+    pub locales: Vec<String>,
+    // Extra tags for index.html's <head>
+    pub head: Vec<String>,
+    // Register the service worker from src/registerServiceWorker.js in main.jsx
+    pub service_worker: bool,
+    // Tracking initialised from the entry through src/analytics.js
+    pub analytics: Option<AnalyticsConfig>,
+    // RouterJsx output location under src/, imported by main.jsx
+    pub router_path: String,
+    // The router module renders its own RouterProvider, so main.jsx adds no BrowserRouter
+    pub data_router: bool,
+}
+
+impl ViteScaffold {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            package: PackageJson::default(),
+            aliases: AliasDefs::project_default(),
+            locales: vec!["en".to_string()],
+            head: Vec::new(),
+            service_worker: false,
+            analytics: None,
+            router_path: "router/index.jsx".to_string(),
+            data_router: false,
+        }
+    }
+
+    /// Match RouterJsx::with_output_path
+    pub fn with_router_path(mut self, router_path: impl Into<String>) -> Self {
+        self.router_path = router_path.into();
+        self
+    }
+
+    /// Match RouterJsx::with_data_router
+    pub fn with_data_router(mut self) -> Self {
+        self.data_router = true;
+        self
+    }
+
+    pub fn with_package(mut self, package: PackageJson) -> Self {
+        self.package = package;
+        self
+    }
+
+    pub fn with_alias(mut self, alias: impl Into<String>, dir: impl Into<String>) -> Self {
+        self.aliases.aliases.insert(alias.into(), dir.into());
+        self
+    }
+
+    /// Add every alias from an AliasDefs file
+    pub fn with_aliases(mut self, alias_defs: &AliasDefs) -> Self {
+        self.aliases.aliases.extend(alias_defs.aliases.clone());
+        self
+    }
+
+    pub fn with_locales(mut self, locales: Vec<String>) -> Self {
+        self.locales = locales;
+        self
+    }
+
+    /// Link the PWA manifest and register its service worker; the PWA files come from PwaScaffold::files
+    pub fn with_pwa(mut self, pwa: &PwaScaffold) -> Self {
+        let has_touch_icon = self.head.iter().any(|tag| is_touch_icon(tag));
+        self.head.extend(pwa.head_tags().into_iter().filter(|tag| !(has_touch_icon && is_touch_icon(tag))));
+        self.service_worker = true;
+        self
+    }
+
+    /// Link the favicon set; its apple-touch-icon replaces the PWA one
+    pub fn with_favicon(mut self, favicon: &FaviconScaffold) -> Self {
+        self.head.retain(|tag| !is_touch_icon(tag));
+        self.head.extend(favicon.head_tags());
+        self
+    }
+
+    /// Add the project's default meta tags; robots.txt and sitemap.xml come from SeoConfig::files
+    pub fn with_seo(mut self, seo: &SeoConfig) -> Self {
+        self.head.extend(seo.head_tags());
+        self
+    }
+
+    /// Load the theme stylesheet and apply the saved theme; the files come from ThemeSet::files
+    pub fn with_themes(mut self, themes: &ThemeSet) -> Self {
+        self.head.extend(themes.head_tags());
+        self
+    }
+
+    /// Initialise analytics from the entry. Pair with RouterJsx::with_page_view_tracking.
+    pub fn with_analytics(mut self, analytics: &AnalyticsConfig) -> Self {
+        self.package.add_packages(analytics.npm_packages());
+        self.analytics = Some(analytics.clone());
+        self
+    }
+
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        let mut files = vec![
+            GeneratedFile::text("package.json", self.package_json()),
+            GeneratedFile::text("vite.config.js", self.vite_config()),
+            GeneratedFile::text("jsconfig.json", self.aliases.jsconfig_json()),
+            GeneratedFile::text("index.html", self.index_html()),
+            GeneratedFile::text("src/main.jsx", self.main_jsx()),
+        ];
+        if self.uses_i18n() {
+            files.push(GeneratedFile::text("src/i18n.js", self.i18n_js()));
+        }
+        if let Some(analytics) = &self.analytics {
+            files.push(GeneratedFile::text("src/analytics.js", analytics.analytics_js()));
+        }
+        files
+    }
+
+    fn uses_i18n(&self) -> bool {
+        self.package.dependencies.contains_key("react-i18next")
+    }
+
+    fn uses_vitest(&self) -> bool {
+        self.package.dev_dependencies.contains_key("vitest")
+    }
+
+    fn package_json(&self) -> String {
+        let mut package = self.package.clone();
+        package.add_packages(["react", "react-dom", "react-router-dom"].map(String::from));
+        for (name, version) in VITE_DEV_DEPENDENCIES {
+            package.dev_dependencies.entry(name.to_string()).or_insert(version.to_string());
+        }
+
+        let mut scripts = json!({
+            "dev": "vite",
+            "build": "vite build",
+            "preview": "vite preview",
+        });
+        if self.uses_vitest() {
+            scripts["test"] = json!("vitest");
+        }
+
+        let mut manifest = json!({
+            "name": self.name,
+            "private": true,
+            "version": "0.0.0",
+            "type": "module",
+            "scripts": scripts,
+        });
+        if let (Value::Object(manifest), Value::Object(sections)) = (&mut manifest, package.to_value()) {
+            manifest.extend(sections);
+        }
+        serde_json::to_string_pretty(&manifest).unwrap_or_default() + "\n"
+    }
+
+    fn vite_config(&self) -> String {
+        let mut output = String::new();
+        output.push_str("import { defineConfig } from 'vite';\n");
+        output.push_str("import react from '@vitejs/plugin-react';\n");
+        output.push_str("import { fileURLToPath, URL } from 'node:url';\n\n");
+        output.push_str("export default defineConfig({\n");
+        output.push_str("  plugins: [react()],\n");
+        output.push_str("  resolve: {\n    alias: {\n");
+        for (alias, dir) in &self.aliases.aliases {
+            output.push_str(&format!(
+                "      '{}': fileURLToPath(new URL('./{}', import.meta.url)),\n",
+                alias,
+                dir.trim_start_matches("./").trim_end_matches('/')
+            ));
+        }
+        output.push_str("    },\n  },\n");
+        if self.uses_vitest() {
+            output.push_str("  test: {\n    environment: 'jsdom',\n  },\n");
+        }
+        output.push_str("});\n");
+        output
+    }
+
+    fn index_html(&self) -> String {
+        format!(
+            r#"<!doctype html>
+<html lang="{}">
+  <head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <title>{}</title>
+{}  </head>
+  <body>
+    <div id="root"></div>
+    <script type="module" src="/src/main.jsx"></script>
+  </body>
+</html>
+"#,
+            self.locales.first().map(String::as_str).unwrap_or("en"),
+            html_escape(&self.name),
+            self.head.iter().map(|tag| format!("    {}\n", tag)).collect::<String>()
+        )
+    }
+
+    fn main_jsx(&self) -> String {
+        let mut imports = String::new();
+        if self.uses_i18n() {
+            imports.push_str("import './i18n';\n");
+        }
+        if self.service_worker {
+            imports.push_str("import { registerServiceWorker } from './registerServiceWorker';\n");
+        }
+        if self.analytics.is_some() {
+            imports.push_str("import { initAnalytics } from './analytics';\n");
+        }
+        let mut setup = String::new();
+        if self.service_worker {
+            setup.push_str("\nregisterServiceWorker();\n");
+        }
+        if self.analytics.is_some() {
+            setup.push_str("\ninitAnalytics();\n");
+        }
+        // A data router is its own router; nesting it in a BrowserRouter throws
+        let (router_import, router) = if self.data_router {
+            ("", "    <Router />\n")
+        } else {
+            ("import { BrowserRouter } from 'react-router-dom';\n", "    <BrowserRouter>\n      <Router />\n    </BrowserRouter>\n")
+        };
+        format!(
+            r#"import React from 'react';
+import ReactDOM from 'react-dom/client';
+{}{}import Router from '{}';
+
+ReactDOM.createRoot(document.getElementById('root')).render(
+  <React.StrictMode>
+{}  </React.StrictMode>
+);
+{}"#,
+            router_import,
+            imports,
+            router_specifier(&self.router_path),
+            router,
+            setup
+        )
+    }
+
+    fn i18n_js(&self) -> String {
+        let mut output = String::from("import i18n from 'i18next';\nimport { initReactI18next } from 'react-i18next';\n");
+        for locale in &self.locales {
+            output.push_str(&format!("import {} from './locales/{}.json';\n", locale_ident(locale), locale));
+        }
+        let resources: Vec<String> = self
+            .locales
+            .iter()
+            .map(|locale| format!("    '{}': {{ translation: {} }},\n", locale, locale_ident(locale)))
+            .collect();
+        let fallback = self.locales.first().map(String::as_str).unwrap_or("en");
+        output.push_str(&format!(
+            "\ni18n.use(initReactI18next).init({{\n  resources: {{\n{}  }},\n  lng: '{}',\n  fallbackLng: '{}',\n  interpolation: {{ escapeValue: false }},\n}});\n\nexport default i18n;\n",
+            resources.concat(),
+            fallback,
+            fallback
+        ));
+        output
+    }
+}
+
+// "router/index.jsx" -> "./router"; main.jsx sits at the source root
+fn router_specifier(router_path: &str) -> String {
+    let specifier = relative_specifier("main.jsx", &module_specifier(router_path, true));
+    specifier.strip_suffix("/index").map(str::to_string).unwrap_or(specifier)
+}
+
+/// `en-US` -> `enUS`, usable as an import binding
+fn locale_ident(locale: &str) -> String {
+    locale.chars().filter(|c| c.is_ascii_alphanumeric()).collect()
+}
+
+fn is_touch_icon(tag: &str) -> bool {
+    tag.starts_with(r#"<link rel="apple-touch-icon""#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(files: &[GeneratedFile], path: &str) -> String {
+        let file = files.iter().find(|f| f.path.to_str() == Some(path)).unwrap_or_else(|| panic!("no {}", path));
+        String::from_utf8(file.contents.clone()).unwrap()
+    }
+
+    #[test]
+    fn scaffolds_a_runnable_vite_project() {
+        let files = ViteScaffold::new("my <site>").with_alias("@components", "./src/components/").files();
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, ["package.json", "vite.config.js", "jsconfig.json", "index.html", "src/main.jsx"]);
+
+        let package: Value = serde_json::from_str(&file(&files, "package.json")).unwrap();
+        assert_eq!(package["scripts"]["dev"], "vite");
+        assert_eq!(package["dependencies"]["react-router-dom"], "^6.26.0");
+        assert_eq!(package["devDependencies"]["vite"], "^5.4.0");
+        assert!(file(&files, "vite.config.js").contains("      '@components': fileURLToPath(new URL('./src/components', import.meta.url)),\n"));
+        assert!(file(&files, "index.html").contains("<title>my &lt;site&gt;</title>"));
+
+        let main = file(&files, "src/main.jsx");
+        assert!(main.contains("import Router from './router';\n"), "{}", main);
+        assert!(main.contains("    <BrowserRouter>\n      <Router />\n    </BrowserRouter>\n"), "{}", main);
+    }
+
+    #[test]
+    fn loads_locales_when_views_translate() {
+        let mut package = PackageJson::default();
+        package.add_packages(["react-i18next".to_string(), "i18next".to_string()]);
+        package.add_test_dependencies();
+        let files = ViteScaffold::new("site")
+            .with_package(package)
+            .with_locales(vec!["en-US".to_string(), "de".to_string()])
+            .with_router_path("app/routes.jsx")
+            .with_data_router()
+            .files();
+
+        let i18n = file(&files, "src/i18n.js");
+        assert!(i18n.contains("import enUS from './locales/en-US.json';\n"), "{}", i18n);
+        assert!(i18n.contains("  lng: 'en-US',\n  fallbackLng: 'en-US',\n"), "{}", i18n);
+        let main = file(&files, "src/main.jsx");
+        assert!(main.contains("import './i18n';\nimport Router from './app/routes';\n"), "{}", main);
+        assert!(!main.contains("BrowserRouter"), "{}", main);
+        assert!(file(&files, "vite.config.js").contains("  test: {\n    environment: 'jsdom',\n  },\n"));
+        assert!(file(&files, "index.html").contains(r#"<html lang="en-US">"#));
+    }
+}